@@ -1,4 +1,21 @@
-use clap::{Arg, ArgAction, Command, command};
+use chrono::NaiveDate;
+use clap::{
+    Arg, ArgAction, Command,
+    builder::{PossibleValuesParser, TypedValueParser},
+    command, value_parser,
+};
+
+use crate::{
+    db::{
+        account::AccountType,
+        budget::parse_month,
+        expense::ShareMethod,
+        holding::{CostBasisMethod, TradeKind},
+        schedule::Frequency,
+        transaction::TransactionStatus,
+    },
+    money::{parse_amount, parse_currency},
+};
 
 fn general_user_queries() -> Vec<Arg> {
     vec![
@@ -41,10 +58,117 @@ fn general_account_options() -> Vec<Arg> {
             .short('m')
             .long("balance")
             .action(ArgAction::Set)
-            .help("The balance of the account"),
+            .value_parser(parse_amount)
+            .help("The opening balance of the account, before any transactions, or what was owed for a credit card, loan or other liability (e.g. 1,200.00)"),
+        Arg::new("type")
+            .short('t')
+            .long("type")
+            .action(ArgAction::Set)
+            .value_parser(
+                PossibleValuesParser::new(AccountType::ALL.map(|account_type| account_type.as_str()))
+                    .map(|account_type| account_type.parse::<AccountType>().unwrap()),
+            )
+            .help("The type of the account"),
+    ]
+}
+
+fn holder_option() -> Arg {
+    Arg::new("holders")
+        .short('u')
+        .long("holder")
+        .action(ArgAction::Append)
+        .help("A user holding the account, as id or name, optionally followed by =percentage owned (e.g. Alice=60), repeat for joint accounts which are split evenly without percentages")
+}
+
+fn net_worth_user_option() -> Arg {
+    Arg::new("user")
+        .short('u')
+        .long("user")
+        .action(ArgAction::Set)
+        .help("Only count the share of the accounts this user holds, by id or name")
+}
+
+fn card_options() -> Vec<Arg> {
+    vec![
+        Arg::new("closing_day")
+            .long("closing-day")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u32).range(1..=31))
+            .help("The day of the month the credit card statement closes"),
+        Arg::new("due_day")
+            .long("due-day")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u32).range(1..=31))
+            .help("The day of the month the credit card payment is due"),
+        Arg::new("limit")
+            .long("limit")
+            .action(ArgAction::Set)
+            .value_parser(parse_amount)
+            .help("The credit limit of the credit card (e.g. 5,000.00)"),
+        Arg::new("apr")
+            .long("apr")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(f64))
+            .help("The annual interest rate of the credit card in percent (e.g. 24.99)"),
+    ]
+}
+
+fn loan_options() -> Vec<Arg> {
+    vec![
+        Arg::new("principal")
+            .long("principal")
+            .action(ArgAction::Set)
+            .value_parser(parse_amount)
+            .help("How much was borrowed on the loan (e.g. 250,000.00)"),
+        Arg::new("rate")
+            .long("rate")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(f64))
+            .help("The annual interest rate of the loan in percent (e.g. 6.5)"),
+        Arg::new("term")
+            .long("term")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u32).range(1..))
+            .help("The number of monthly payments on the loan (e.g. 360)"),
+        Arg::new("start")
+            .long("start")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(NaiveDate))
+            .help("The day the loan money was borrowed (YYYY-MM-DD)"),
     ]
 }
 
+fn reporting_currency_option() -> Arg {
+    Arg::new("currency")
+        .short('r')
+        .long("currency")
+        .action(ArgAction::Set)
+        .value_parser(parse_currency)
+        .help("The currency to convert totals into (e.g. EUR), defaults to USD")
+}
+
+fn all_option(what: &str) -> Arg {
+    Arg::new("all")
+        .long("all")
+        .action(ArgAction::SetTrue)
+        .help(format!("Also list archived {}", what))
+}
+
+fn root_option() -> Arg {
+    Arg::new("root")
+        .long("root")
+        .action(ArgAction::SetTrue)
+        .conflicts_with("parent")
+        .help("Make it a top-level category without asking for a parent")
+}
+
+fn force_option(help: &'static str) -> Arg {
+    Arg::new("force")
+        .long("force")
+        .action(ArgAction::SetTrue)
+        .help(help)
+}
+
 fn general_category_options() -> Vec<Arg> {
     vec![
         Arg::new("id")
@@ -62,6 +186,11 @@ fn general_category_options() -> Vec<Arg> {
             .long("icon")
             .action(ArgAction::Set)
             .help("The icon of the category"),
+        Arg::new("parent")
+            .short('p')
+            .long("parent")
+            .action(ArgAction::Set)
+            .help("The id or name of the parent category"),
     ]
 }
 
@@ -81,12 +210,19 @@ fn general_transaction_options() -> Vec<Arg> {
             .short('m')
             .long("amount")
             .action(ArgAction::Set)
-            .help("The amount of the transaction (in cents)"),
+            .value_parser(parse_amount)
+            .help("The amount of the transaction (e.g. 12.34)"),
         Arg::new("type")
             .short('t')
             .long("type")
             .action(ArgAction::Set)
+            .value_parser(["credit", "debit"])
             .help("The type of the transaction (credit/debit)"),
+        Arg::new("payee")
+            .short('e')
+            .long("payee")
+            .action(ArgAction::Set)
+            .help("The id or name of the payee, new names are created"),
         Arg::new("description")
             .short('d')
             .long("description")
@@ -97,13 +233,220 @@ fn general_transaction_options() -> Vec<Arg> {
             .long("category-id")
             .action(ArgAction::Set)
             .help("The category id of the transaction"),
+        Arg::new("posted_on")
+            .short('p')
+            .long("posted-on")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(NaiveDate))
+            .help("The date the transaction happened (YYYY-MM-DD)"),
+        status_option(&TransactionStatus::SETTABLE)
+            .help("Whether the bank has cleared the transaction, defaults to pending"),
+        tag_option().help("A tag to label the transaction with, can be repeated"),
+        Arg::new("attach")
+            .short('f')
+            .long("attach")
+            .action(ArgAction::Append)
+            .help("A file such as a receipt to store with the transaction, can be repeated"),
+    ]
+}
+
+fn general_schedule_options() -> Vec<Arg> {
+    vec![
+        Arg::new("id")
+            .short('i')
+            .long("id")
+            .action(ArgAction::Set)
+            .help("The id of the schedule"),
+        Arg::new("account_id")
+            .short('a')
+            .long("account-id")
+            .action(ArgAction::Set)
+            .help("The id or name of the account to post to"),
+        Arg::new("amount")
+            .short('m')
+            .long("amount")
+            .action(ArgAction::Set)
+            .value_parser(parse_amount)
+            .help("The amount posted each time (e.g. 12.34)"),
+        Arg::new("type")
+            .short('t')
+            .long("type")
+            .action(ArgAction::Set)
+            .value_parser(["credit", "debit"])
+            .help("The type of the transactions (credit/debit)"),
+        Arg::new("payee")
+            .short('e')
+            .long("payee")
+            .action(ArgAction::Set)
+            .help("The id or name of the payee, new names are created"),
+        Arg::new("description")
+            .short('d')
+            .long("description")
+            .action(ArgAction::Set)
+            .help("The description of the transactions"),
+        Arg::new("category_id")
+            .short('c')
+            .long("category")
+            .action(ArgAction::Set)
+            .help("The id or name of the category"),
+        Arg::new("frequency")
+            .short('f')
+            .long("frequency")
+            .action(ArgAction::Set)
+            .value_parser(
+                PossibleValuesParser::new(Frequency::ALL.map(|frequency| frequency.as_str()))
+                    .map(|frequency| frequency.parse::<Frequency>().unwrap()),
+            )
+            .help("How often the transaction repeats"),
+        Arg::new("every")
+            .short('n')
+            .long("every")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(u32).range(1..))
+            .help("Repeat every this many days, weeks, months or years, defaults to 1"),
+        Arg::new("start")
+            .short('s')
+            .long("start")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(NaiveDate))
+            .help("The date of the first occurrence (YYYY-MM-DD)"),
+        Arg::new("until")
+            .short('u')
+            .long("until")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(NaiveDate))
+            .help("The last date an occurrence may fall on (YYYY-MM-DD)"),
+        Arg::new("skip")
+            .short('k')
+            .long("skip")
+            .action(ArgAction::Append)
+            .value_parser(value_parser!(NaiveDate))
+            .help("An occurrence not to post (YYYY-MM-DD), can be repeated"),
+    ]
+}
+
+fn general_goal_options() -> Vec<Arg> {
+    vec![
+        Arg::new("id")
+            .short('i')
+            .long("id")
+            .action(ArgAction::Set)
+            .help("The id or name of the goal"),
+        Arg::new("name")
+            .short('n')
+            .long("name")
+            .action(ArgAction::Set)
+            .help("What you are saving for"),
+        Arg::new("account_id")
+            .short('a')
+            .long("account-id")
+            .action(ArgAction::Set)
+            .help("The id or name of the account the money is saved in"),
+        Arg::new("amount")
+            .short('m')
+            .long("amount")
+            .action(ArgAction::Set)
+            .value_parser(parse_amount)
+            .help("How much you want to have saved (e.g. 5,000)"),
+        Arg::new("date")
+            .short('d')
+            .long("date")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(NaiveDate))
+            .help("The date you want to have the money by (YYYY-MM-DD)"),
+    ]
+}
+
+fn security_option() -> Arg {
+    Arg::new("security")
+        .short('s')
+        .long("security")
+        .action(ArgAction::Set)
+        .help("The id or symbol of the security")
+}
+
+fn investment_account_option() -> Arg {
+    Arg::new("account_id")
+        .short('a')
+        .long("account-id")
+        .action(ArgAction::Set)
+        .help("The id or name of the investment account")
+}
+
+fn general_trade_options() -> Vec<Arg> {
+    vec![
+        investment_account_option(),
+        security_option(),
+        Arg::new("date")
+            .short('d')
+            .long("date")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(NaiveDate))
+            .help("The day of the trade (YYYY-MM-DD)"),
+        Arg::new("quantity")
+            .short('q')
+            .long("quantity")
+            .action(ArgAction::Set)
+            .value_parser(value_parser!(f64))
+            .help("The number of units, or for a split how many units every unit became"),
+        Arg::new("amount")
+            .short('m')
+            .long("amount")
+            .action(ArgAction::Set)
+            .value_parser(parse_amount)
+            .help("The total paid with fees, received after fees, or paid out as a dividend (e.g. 1,250.00)"),
     ]
 }
 
+fn cost_basis_option() -> Arg {
+    Arg::new("method")
+        .short('b')
+        .long("basis")
+        .action(ArgAction::Set)
+        .value_parser(
+            PossibleValuesParser::new(CostBasisMethod::ALL.map(|method| method.as_str()))
+                .map(|method| method.parse::<CostBasisMethod>().unwrap()),
+        )
+        .help("How the cost of units sold is worked out, defaults to fifo")
+}
+
+fn month_option() -> Arg {
+    Arg::new("month")
+        .short('o')
+        .long("month")
+        .action(ArgAction::Set)
+        .value_parser(parse_month)
+}
+
+fn status_option(statuses: &[TransactionStatus]) -> Arg {
+    Arg::new("status")
+        .short('s')
+        .long("status")
+        .action(ArgAction::Set)
+        .value_parser(
+            PossibleValuesParser::new(statuses.iter().map(|status| status.as_str()))
+                .map(|status| status.parse::<TransactionStatus>().unwrap()),
+        )
+}
+
+fn tag_option() -> Arg {
+    Arg::new("tag")
+        .short('g')
+        .long("tag")
+        .action(ArgAction::Append)
+}
+
 pub fn setup_cli() -> Command {
     command!()
         .author("NoobScience <noobscience@duck.com>")
         .about("Advanced CLI for tracking your finances")
+        .arg(
+            Arg::new("as")
+                .long("as")
+                .global(true)
+                .action(ArgAction::Set)
+                .help("Act as this user for one command, by id or name, instead of the active user"),
+        )
         .subcommand(
             Command::new("user")
                 .about("Manage Users")
@@ -117,13 +460,16 @@ pub fn setup_cli() -> Command {
                     ),
                 )
                 .subcommand(
-                    Command::new("list").about("List all users").arg(
-                        Arg::new("id")
-                            .short('i')
-                            .long("id")
-                            .action(ArgAction::Set)
-                            .help("The id of the user"),
-                    ),
+                    Command::new("list")
+                        .about("List all users")
+                        .arg(
+                            Arg::new("id")
+                                .short('i')
+                                .long("id")
+                                .action(ArgAction::Set)
+                                .help("The id of the user"),
+                        )
+                        .arg(all_option("users")),
                 )
                 .subcommand(
                     Command::new("get")
@@ -135,10 +481,36 @@ pub fn setup_cli() -> Command {
                         .about("Delete a user")
                         .args(general_user_queries()),
                 )
+                .subcommand(
+                    Command::new("archive")
+                        .about("Archive a user, hiding them while keeping their history")
+                        .args(general_user_queries()),
+                )
+                .subcommand(
+                    Command::new("unarchive")
+                        .about("Bring back an archived user")
+                        .args(general_user_queries()),
+                )
                 .subcommand(
                     Command::new("update")
                         .about("Update a user")
                         .args(general_user_queries()),
+                )
+                .subcommand(
+                    Command::new("switch")
+                        .about("Switch the active user that listings and reports default to")
+                        .arg(
+                            Arg::new("user")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the user"),
+                        )
+                        .arg(
+                            Arg::new("clear")
+                                .long("clear")
+                                .action(ArgAction::SetTrue)
+                                .conflicts_with("user")
+                                .help("Stop acting as anyone, so commands cover every user"),
+                        ),
                 ),
         )
         .subcommand(
@@ -147,9 +519,33 @@ pub fn setup_cli() -> Command {
                 .subcommand(
                     Command::new("create")
                         .about("Create a new account")
-                        .args(&general_account_options()[1..]),
+                        .args(&general_account_options()[1..])
+                        .arg(
+                            Arg::new("currency")
+                                .short('c')
+                                .long("currency")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_currency)
+                                .help("The currency code of the account (e.g. EUR)"),
+                        )
+                        .arg(holder_option())
+                        .args(card_options())
+                        .args(loan_options()),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List all accounts")
+                        .arg(reporting_currency_option())
+                        .arg(all_option("accounts"))
+                        .arg(
+                            Arg::new("due_within")
+                                .short('w')
+                                .long("due-within")
+                                .action(ArgAction::Set)
+                                .value_parser(value_parser!(u32))
+                                .help("Warn about credit card payments due within this many days, defaults to 7"),
+                        ),
                 )
-                .subcommand(Command::new("list").about("List all accounts"))
                 .subcommand(
                     Command::new("get")
                         .about("Get an account")
@@ -158,12 +554,83 @@ pub fn setup_cli() -> Command {
                 .subcommand(
                     Command::new("delete")
                         .about("Delete an account")
+                        .args(general_account_options())
+                        .arg(force_option(
+                            "Delete even if transactions would be deleted along with it",
+                        )),
+                )
+                .subcommand(
+                    Command::new("archive")
+                        .about("Archive an account, hiding it while keeping its history")
+                        .args(general_account_options()),
+                )
+                .subcommand(
+                    Command::new("unarchive")
+                        .about("Bring back an archived account")
                         .args(general_account_options()),
                 )
                 .subcommand(
                     Command::new("update")
                         .about("Update an account")
-                        .args(general_account_options()),
+                        .args(general_account_options())
+                        .arg(holder_option())
+                        .args(card_options())
+                        .args(loan_options()),
+                )
+                .subcommand(
+                    Command::new("amortization")
+                        .about("Show the amortization schedule and payoff date of a loan")
+                        .args(&general_account_options()[..2])
+                        .arg(
+                            Arg::new("extra")
+                                .short('e')
+                                .long("extra")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_amount)
+                                .help("Pay this much on top of the monthly payment from now on (e.g. 200.00)"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("statement")
+                        .about("Show the last credit card statement and the current cycle")
+                        .args(&general_account_options()[..2])
+                        .arg(
+                            Arg::new("date")
+                                .short('d')
+                                .long("date")
+                                .action(ArgAction::Set)
+                                .value_parser(value_parser!(NaiveDate))
+                                .help("The day to show the statement as of (YYYY-MM-DD), defaults to today"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("recompute")
+                        .about("Rebuild balances from the opening balance and transaction history")
+                        .args(&general_account_options()[..2]),
+                )
+                .subcommand(
+                    Command::new("reconcile")
+                        .about("Match the transactions of an account against a bank statement")
+                        .args(&general_account_options()[..2])
+                        .args([
+                            Arg::new("statement_date")
+                                .short('d')
+                                .long("statement-date")
+                                .action(ArgAction::Set)
+                                .value_parser(value_parser!(NaiveDate))
+                                .help("The last day covered by the statement (YYYY-MM-DD)"),
+                            Arg::new("statement_balance")
+                                .short('m')
+                                .long("statement-balance")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_amount)
+                                .help("The closing balance on the statement, what is owed for liabilities (e.g. 1,200.00)"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("reconciliations")
+                        .about("List past reconciliations of an account")
+                        .args(&general_account_options()[..2]),
                 ),
         )
         .subcommand(
@@ -172,9 +639,19 @@ pub fn setup_cli() -> Command {
                 .subcommand(
                     Command::new("create")
                         .about("Create a new category")
-                        .args(&general_category_options()[1..]),
+                        .args(&general_category_options()[1..])
+                        .arg(root_option()),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List all categories")
+                        .arg(all_option("categories")),
+                )
+                .subcommand(
+                    Command::new("report")
+                        .about("Show income and expenses per category")
+                        .arg(reporting_currency_option()),
                 )
-                .subcommand(Command::new("list").about("List all categories"))
                 .subcommand(
                     Command::new("get")
                         .about("Get a category")
@@ -183,12 +660,26 @@ pub fn setup_cli() -> Command {
                 .subcommand(
                     Command::new("delete")
                         .about("Delete a category")
+                        .args(general_category_options())
+                        .arg(force_option(
+                            "Delete even if transactions are filed under it, moving them to its parent category",
+                        )),
+                )
+                .subcommand(
+                    Command::new("archive")
+                        .about("Archive a category, hiding it while keeping its history")
+                        .args(general_category_options()),
+                )
+                .subcommand(
+                    Command::new("unarchive")
+                        .about("Bring back an archived category")
                         .args(general_category_options()),
                 )
                 .subcommand(
                     Command::new("update")
                         .about("Update a category")
-                        .args(general_category_options()),
+                        .args(general_category_options())
+                        .arg(root_option()),
                 ),
         )
         .subcommand(
@@ -208,7 +699,16 @@ pub fn setup_cli() -> Command {
                                 .long("account-id")
                                 .action(ArgAction::Set)
                                 .help("Filter by account id"),
-                        ),
+                        )
+                        .arg(
+                            tag_option()
+                                .help("Only show transactions with this tag, can be repeated"),
+                        )
+                        .arg(
+                            status_option(&TransactionStatus::ALL)
+                                .help("Only show transactions with this status"),
+                        )
+                        .arg(reporting_currency_option()),
                 )
                 .subcommand(
                     Command::new("get")
@@ -235,13 +735,686 @@ pub fn setup_cli() -> Command {
                 .subcommand(
                     Command::new("update")
                         .about("Update a transaction")
+                        .args(general_transaction_options()),
+                )
+                .subcommand(
+                    Command::new("clear")
+                        .about("Mark transactions as cleared by the bank")
                         .arg(
+                            Arg::new("ids")
+                                .action(ArgAction::Append)
+                                .num_args(1..)
+                                .required(true)
+                                .help("The ids of the transactions"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("transfer")
+                        .about("Move money between two accounts")
+                        .args([
+                            Arg::new("from")
+                                .short('f')
+                                .long("from")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the account to take money from"),
+                            Arg::new("to")
+                                .short('t')
+                                .long("to")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the account to put money into"),
+                            Arg::new("amount")
+                                .short('m')
+                                .long("amount")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_amount)
+                                .help("The amount to transfer (e.g. 12.34)"),
+                            Arg::new("description")
+                                .short('d')
+                                .long("description")
+                                .action(ArgAction::Set)
+                                .help("The description of the transfer"),
+                            Arg::new("posted_on")
+                                .short('p')
+                                .long("posted-on")
+                                .action(ArgAction::Set)
+                                .value_parser(value_parser!(NaiveDate))
+                                .help("The date of the transfer (YYYY-MM-DD), defaults to today"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("attachments")
+                        .about("Manage files stored with transactions")
+                        .subcommand(
+                            Command::new("list")
+                                .about("List the attachments of a transaction")
+                                .arg(
+                                    Arg::new("id")
+                                        .short('i')
+                                        .long("id")
+                                        .action(ArgAction::Set)
+                                        .help("The id of the transaction"),
+                                ),
+                        )
+                        .subcommand(
+                            Command::new("extract")
+                                .about("Write an attachment back out to a file")
+                                .args([
+                                    Arg::new("id")
+                                        .short('i')
+                                        .long("id")
+                                        .action(ArgAction::Set)
+                                        .required(true)
+                                        .help("The id of the attachment"),
+                                    Arg::new("output")
+                                        .short('o')
+                                        .long("output")
+                                        .action(ArgAction::Set)
+                                        .help("The file or directory to write to, defaults to the original file name"),
+                                ]),
+                        )
+                        .subcommand(
+                            Command::new("remove").about("Delete an attachment").arg(
+                                Arg::new("id")
+                                    .short('i')
+                                    .long("id")
+                                    .action(ArgAction::Set)
+                                    .required(true)
+                                    .help("The id of the attachment"),
+                            ),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("rate")
+                .about("Manage Exchange Rates")
+                .subcommand(
+                    Command::new("import")
+                        .about("Import exchange rates from a CSV file of date,from,to,rate lines")
+                        .arg(
+                            Arg::new("file")
+                                .short('f')
+                                .long("file")
+                                .action(ArgAction::Set)
+                                .required(true)
+                                .help("The path of the CSV file"),
+                        ),
+                )
+                .subcommand(Command::new("list").about("List all exchange rates"))
+                .subcommand(
+                    Command::new("set")
+                        .about("Record a single exchange rate")
+                        .args([
+                        Arg::new("date")
+                            .short('d')
+                            .long("date")
+                            .action(ArgAction::Set)
+                            .value_parser(value_parser!(NaiveDate))
+                            .help("The date the rate applies from (YYYY-MM-DD), defaults to today"),
+                        Arg::new("from")
+                            .short('f')
+                            .long("from")
+                            .action(ArgAction::Set)
+                            .required(true)
+                            .value_parser(parse_currency)
+                            .help("The currency being converted from"),
+                        Arg::new("to")
+                            .short('t')
+                            .long("to")
+                            .action(ArgAction::Set)
+                            .required(true)
+                            .value_parser(parse_currency)
+                            .help("The currency being converted to"),
+                        Arg::new("rate")
+                            .short('r')
+                            .long("rate")
+                            .action(ArgAction::Set)
+                            .required(true)
+                            .value_parser(value_parser!(f64))
+                            .help("How many units of the target currency one unit buys"),
+                    ]),
+                ),
+        )
+        .subcommand(
+            Command::new("tag")
+                .about("Manage Tags")
+                .subcommand(Command::new("list").about("List all tags"))
+                .subcommand(
+                    Command::new("report")
+                        .about("Show income and expenses per tag")
+                        .arg(reporting_currency_option()),
+                )
+                .subcommand(
+                    Command::new("rename").about("Rename a tag").args([
+                        Arg::new("name")
+                            .short('n')
+                            .long("name")
+                            .action(ArgAction::Set)
+                            .help("The tag to rename"),
+                        Arg::new("to")
+                            .short('t')
+                            .long("to")
+                            .action(ArgAction::Set)
+                            .help("The new name of the tag"),
+                    ]),
+                )
+                .subcommand(
+                    Command::new("merge")
+                        .about(
+                            "Move every transaction from one tag to another and delete the first",
+                        )
+                        .args([
+                            Arg::new("from")
+                                .short('f')
+                                .long("from")
+                                .action(ArgAction::Set)
+                                .help("The tag to merge away"),
+                            Arg::new("into")
+                                .short('i')
+                                .long("into")
+                                .action(ArgAction::Set)
+                                .help("The tag to keep"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("delete")
+                        .about("Delete a tag, keeping the transactions it was on")
+                        .arg(
+                            Arg::new("name")
+                                .short('n')
+                                .long("name")
+                                .action(ArgAction::Set)
+                                .help("The tag to delete"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("payee")
+                .about("Manage Payees")
+                .subcommand(
+                    Command::new("create").about("Create a new payee").args([
+                        Arg::new("name")
+                            .short('n')
+                            .long("name")
+                            .action(ArgAction::Set)
+                            .help("The name of the payee"),
+                        Arg::new("category_id")
+                            .short('c')
+                            .long("category")
+                            .action(ArgAction::Set)
+                            .help("The id or name of the category to pre-select for this payee"),
+                    ]),
+                )
+                .subcommand(Command::new("list").about("List all payees"))
+                .subcommand(
+                    Command::new("update")
+                        .about("Rename a payee or change its default category")
+                        .args([
                             Arg::new("id")
                                 .short('i')
                                 .long("id")
                                 .action(ArgAction::Set)
-                                .help("The id of the transaction"),
+                                .help("The id or name of the payee"),
+                            Arg::new("name")
+                                .short('n')
+                                .long("name")
+                                .action(ArgAction::Set)
+                                .help("The new name of the payee"),
+                            Arg::new("category_id")
+                                .short('c')
+                                .long("category")
+                                .action(ArgAction::Set)
+                                .help(
+                                    "The id or name of the category to pre-select for this payee",
+                                ),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("merge")
+                        .about(
+                            "Move every transaction from one payee to another and delete the first",
+                        )
+                        .args([
+                            Arg::new("from")
+                                .short('f')
+                                .long("from")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the payee to merge away"),
+                            Arg::new("into")
+                                .short('i')
+                                .long("into")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the payee to keep"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("delete")
+                        .about("Delete a payee, keeping its transactions")
+                        .arg(
+                            Arg::new("id")
+                                .short('i')
+                                .long("id")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the payee"),
                         ),
                 ),
         )
+        .subcommand(
+            Command::new("schedule")
+                .about("Manage recurring transactions")
+                .subcommand(
+                    Command::new("create")
+                        .about("Create a new schedule")
+                        .args(&general_schedule_options()[1..]),
+                )
+                .subcommand(Command::new("list").about("List all schedules"))
+                .subcommand(
+                    Command::new("edit")
+                        .about("Edit a schedule")
+                        .args(general_schedule_options())
+                        .arg(
+                            Arg::new("unskip")
+                                .long("unskip")
+                                .action(ArgAction::Append)
+                                .value_parser(value_parser!(NaiveDate))
+                                .help("A skipped occurrence to post after all, can be repeated"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("delete")
+                        .about("Delete a schedule, keeping the transactions it posted")
+                        .arg(
+                            Arg::new("id")
+                                .short('i')
+                                .long("id")
+                                .action(ArgAction::Set)
+                                .help("The id of the schedule"),
+                        ),
+                )
+                .subcommand(
+                    Command::new("run")
+                        .about("Post every occurrence that is due, safe to run repeatedly")
+                        .arg(
+                            Arg::new("date")
+                                .short('d')
+                                .long("date")
+                                .action(ArgAction::Set)
+                                .value_parser(value_parser!(NaiveDate))
+                                .help("Post occurrences due by this date (YYYY-MM-DD), defaults to today"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("budget")
+                .about("Manage monthly budgets per category")
+                .subcommand(
+                    Command::new("set")
+                        .about("Set how much a category may spend in a month")
+                        .args([
+                            Arg::new("category_id")
+                                .short('c')
+                                .long("category")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the category"),
+                            Arg::new("amount")
+                                .short('m')
+                                .long("amount")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_amount)
+                                .help("How much may be spent in the month (e.g. 300)"),
+                            month_option().help("The month to budget (YYYY-MM), defaults to this month"),
+                            Arg::new("rollover")
+                                .long("rollover")
+                                .action(ArgAction::SetTrue)
+                                .help("Carry what is left over into next month"),
+                            Arg::new("currency")
+                                .short('r')
+                                .long("currency")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_currency)
+                                .help("The currency of the budget (e.g. EUR), defaults to USD"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("List budgets")
+                        .arg(month_option().help("Only list budgets of this month (YYYY-MM)")),
+                )
+                .subcommand(
+                    Command::new("copy-month")
+                        .about("Copy the budgets of one month into another")
+                        .args([
+                            Arg::new("from")
+                                .short('f')
+                                .long("from")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_month)
+                                .help("The month to copy (YYYY-MM), defaults to the month before --to"),
+                            Arg::new("to")
+                                .short('t')
+                                .long("to")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_month)
+                                .help("The month to copy into (YYYY-MM), defaults to this month"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("status")
+                        .about("Show budgeted, spent and remaining amounts per category")
+                        .arg(month_option().help("The month to report on (YYYY-MM), defaults to this month")),
+                ),
+        )
+        .subcommand(
+            Command::new("goal")
+                .about("Manage savings goals")
+                .subcommand(
+                    Command::new("create")
+                        .about("Create a new savings goal")
+                        .args(&general_goal_options()[1..]),
+                )
+                .subcommand(
+                    Command::new("list")
+                        .about("Show the progress of every goal and when it will be reached"),
+                )
+                .subcommand(
+                    Command::new("edit")
+                        .about("Edit a goal")
+                        .args(general_goal_options()),
+                )
+                .subcommand(
+                    Command::new("delete")
+                        .about("Delete a goal")
+                        .arg(&general_goal_options()[0]),
+                ),
+        )
+        .subcommand(
+            Command::new("envelope")
+                .about("Give every unit of income a job with zero-based envelopes")
+                .subcommand(
+                    Command::new("assign")
+                        .about("Set how much of the income goes into a category's envelope in a month")
+                        .args([
+                            Arg::new("category_id")
+                                .short('c')
+                                .long("category")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the category"),
+                            Arg::new("amount")
+                                .short('m')
+                                .long("amount")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_amount)
+                                .help("How much to put in the envelope (e.g. 300)"),
+                            month_option().help("The month to assign in (YYYY-MM), defaults to this month"),
+                            reporting_currency_option().help("The currency of the money (e.g. EUR), defaults to USD"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("move")
+                        .about("Move money from one envelope into another")
+                        .args([
+                            Arg::new("from")
+                                .short('f')
+                                .long("from")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the category to take the money from"),
+                            Arg::new("to")
+                                .short('t')
+                                .long("to")
+                                .action(ArgAction::Set)
+                                .help("The id or name of the category to put the money in"),
+                            Arg::new("amount")
+                                .short('m')
+                                .long("amount")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_amount)
+                                .help("How much to move (e.g. 50)"),
+                            month_option().help("The month to move in (YYYY-MM), defaults to this month"),
+                            reporting_currency_option().help("The currency of the money (e.g. EUR), defaults to USD"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("moves")
+                        .about("List money moved between envelopes")
+                        .arg(month_option().help("Only list moves of this month (YYYY-MM)")),
+                )
+                .subcommand(
+                    Command::new("status")
+                        .about("Show every envelope and what is left to assign")
+                        .args([
+                            month_option().help("The month to report on (YYYY-MM), defaults to this month"),
+                            reporting_currency_option(),
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("security")
+                .about("Manage securities and their prices")
+                .subcommand(
+                    Command::new("create")
+                        .about("Create a new security")
+                        .args([
+                            Arg::new("symbol")
+                                .short('s')
+                                .long("symbol")
+                                .action(ArgAction::Set)
+                                .help("The ticker symbol (e.g. VTI)"),
+                            Arg::new("name")
+                                .short('n')
+                                .long("name")
+                                .action(ArgAction::Set)
+                                .help("The name of the security"),
+                            Arg::new("currency")
+                                .short('c')
+                                .long("currency")
+                                .action(ArgAction::Set)
+                                .value_parser(parse_currency)
+                                .help("The currency it trades in (e.g. EUR)"),
+                        ]),
+                )
+                .subcommand(Command::new("list").about("List all securities with their latest price"))
+                .subcommand(
+                    Command::new("delete")
+                        .about("Delete a security along with its prices and trades")
+                        .arg(security_option()),
+                )
+                .subcommand(
+                    Command::new("price")
+                        .about("Record the price of a security on a day")
+                        .args([
+                            security_option(),
+                            Arg::new("price")
+                                .short('p')
+                                .long("price")
+                                .action(ArgAction::Set)
+                                .required(true)
+                                .value_parser(parse_amount)
+                                .help("What one unit was worth (e.g. 250.10)"),
+                            Arg::new("date")
+                                .short('d')
+                                .long("date")
+                                .action(ArgAction::Set)
+                                .value_parser(value_parser!(NaiveDate))
+                                .help("The day of the price (YYYY-MM-DD), defaults to today"),
+                        ]),
+                )
+                .subcommand(
+                    Command::new("import")
+                        .about("Import prices from a CSV file of date,symbol,price lines")
+                        .arg(
+                            Arg::new("file")
+                                .short('f')
+                                .long("file")
+                                .action(ArgAction::Set)
+                                .required(true)
+                                .help("The path of the CSV file"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("trade")
+                .about("Record buys, sells, dividends and splits in investment accounts")
+                .subcommands(TradeKind::ALL.map(|kind| {
+                    Command::new(kind.as_str())
+                        .about(match kind {
+                            TradeKind::Buy => "Record units bought",
+                            TradeKind::Sell => "Record units sold",
+                            TradeKind::Dividend => "Record a dividend paid out",
+                            TradeKind::Split => "Record a stock split",
+                        })
+                        .args(general_trade_options())
+                }))
+                .subcommand(
+                    Command::new("list")
+                        .about("List trades")
+                        .arg(investment_account_option().help("Only list trades of this account")),
+                )
+                .subcommand(
+                    Command::new("delete").about("Delete a trade").arg(
+                        Arg::new("id")
+                            .short('i')
+                            .long("id")
+                            .action(ArgAction::Set)
+                            .required(true)
+                            .help("The id of the trade"),
+                    ),
+                ),
+        )
+        .subcommand(
+            Command::new("holdings")
+                .about("Show quantity, cost, market value and unrealized gain of what is held")
+                .args([
+                    investment_account_option().help("Only show holdings of this account"),
+                    cost_basis_option(),
+                    Arg::new("date")
+                        .short('d')
+                        .long("date")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(NaiveDate))
+                        .help("The day to show holdings as of (YYYY-MM-DD), defaults to today"),
+                ]),
+        )
+        .subcommand(
+            Command::new("gains")
+                .about("Show realized gains and dividends per year")
+                .args([
+                    investment_account_option().help("Only show gains of this account"),
+                    cost_basis_option(),
+                ]),
+        )
+        .subcommand(
+            Command::new("networth")
+                .about("Add up every account into assets, liabilities and net worth")
+                .args([
+                    reporting_currency_option(),
+                    Arg::new("date")
+                        .short('d')
+                        .long("date")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(NaiveDate))
+                        .help("The day to work the net worth out for (YYYY-MM-DD), defaults to today"),
+                    Arg::new("snapshot")
+                        .short('s')
+                        .long("snapshot")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("user")
+                        .help("Record the net worth of everyone together as a dated snapshot"),
+                    net_worth_user_option(),
+                ])
+                .subcommand(
+                    Command::new("history")
+                        .about("Show the net worth month by month, from snapshots or rebuilt from transactions")
+                        .args([
+                            reporting_currency_option(),
+                            Arg::new("months")
+                                .short('m')
+                                .long("months")
+                                .action(ArgAction::Set)
+                                .value_parser(value_parser!(u32).range(1..))
+                                .help("How many months to show, defaults to 12"),
+                            net_worth_user_option(),
+                        ]),
+                ),
+        )
+        .subcommand(
+            Command::new("expense")
+                .about("Split what one user paid between several and keep track of who owes whom")
+                .subcommand(
+                    Command::new("create").about("Record a shared expense").args([
+                        Arg::new("paid_by")
+                            .short('p')
+                            .long("paid-by")
+                            .action(ArgAction::Set)
+                            .help("The id or name of the user who paid"),
+                        Arg::new("amount")
+                            .short('m')
+                            .long("amount")
+                            .action(ArgAction::Set)
+                            .value_parser(parse_amount)
+                            .help("The total that was paid (e.g. 90.00)"),
+                        Arg::new("currency")
+                            .short('c')
+                            .long("currency")
+                            .action(ArgAction::Set)
+                            .value_parser(parse_currency)
+                            .help("The currency it was paid in (e.g. EUR), defaults to USD"),
+                        Arg::new("description")
+                            .short('e')
+                            .long("description")
+                            .action(ArgAction::Set)
+                            .help("What the money was spent on"),
+                        Arg::new("date")
+                            .short('d')
+                            .long("date")
+                            .action(ArgAction::Set)
+                            .value_parser(value_parser!(NaiveDate))
+                            .help("The day it was spent (YYYY-MM-DD), defaults to today"),
+                        Arg::new("method")
+                            .short('s')
+                            .long("split")
+                            .action(ArgAction::Set)
+                            .value_parser(
+                                PossibleValuesParser::new(ShareMethod::ALL.map(|method| method.as_str()))
+                                    .map(|s| s.parse::<ShareMethod>().unwrap()),
+                            )
+                            .help("How the amount is divided, defaults to equal"),
+                        Arg::new("with")
+                            .short('w')
+                            .long("with")
+                            .action(ArgAction::Append)
+                            .help("A user who shares it, as id or name, followed by =amount or =percentage for exact and percentage splits (e.g. Bob=30.00)"),
+                    ]),
+                )
+                .subcommand(Command::new("list").about("List shared expenses"))
+                .subcommand(
+                    Command::new("delete").about("Delete a shared expense").arg(
+                        Arg::new("id")
+                            .short('i')
+                            .long("id")
+                            .action(ArgAction::Set)
+                            .required(true)
+                            .help("The id of the shared expense"),
+                    ),
+                )
+                .subcommand(Command::new("settlements").about("List the payments recorded to settle up"))
+                .subcommand(
+                    Command::new("balances")
+                        .about("Show who owes whom and the payments that would square everyone up"),
+                ),
+        )
+        .subcommand(
+            Command::new("settle")
+                .about("Record the fewest payments that square everyone up as transfers between their accounts")
+                .args([
+                    Arg::new("date")
+                        .short('d')
+                        .long("date")
+                        .action(ArgAction::Set)
+                        .value_parser(value_parser!(NaiveDate))
+                        .help("The day the payments are made (YYYY-MM-DD), defaults to today"),
+                    Arg::new("dry_run")
+                        .long("dry-run")
+                        .action(ArgAction::SetTrue)
+                        .help("Only show the payments without recording them"),
+                ]),
+        )
 }
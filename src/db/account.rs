@@ -1,12 +1,122 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::Row;
 use sea_query::{Expr, Query, SqliteQueryBuilder};
 use tabled::Tabled;
 
-use crate::{db::Db, utils::random_hash};
+use crate::{
+    db::{
+        Db, DbError,
+        holder::{AccountHolder, validate_holders},
+        transaction::TransactionFilter,
+    },
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{AccountHolders, Accounts};
+
+/// What kind of account it is, which decides whether its balance is money owned
+/// or money owed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AccountType {
+    Checking,
+    Savings,
+    Cash,
+    CreditCard,
+    Loan,
+    Investment,
+    OtherAsset,
+    OtherLiability,
+}
+
+impl AccountType {
+    pub const ALL: [AccountType; 8] = [
+        AccountType::Checking,
+        AccountType::Savings,
+        AccountType::Cash,
+        AccountType::CreditCard,
+        AccountType::Loan,
+        AccountType::Investment,
+        AccountType::OtherAsset,
+        AccountType::OtherLiability,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AccountType::Checking => "checking",
+            AccountType::Savings => "savings",
+            AccountType::Cash => "cash",
+            AccountType::CreditCard => "credit-card",
+            AccountType::Loan => "loan",
+            AccountType::Investment => "investment",
+            AccountType::OtherAsset => "other-asset",
+            AccountType::OtherLiability => "other-liability",
+        }
+    }
 
-use super::Accounts;
+    /// Whether the account holds money that is owed rather than owned.
+    pub fn is_liability(&self) -> bool {
+        matches!(
+            self,
+            AccountType::CreditCard | AccountType::Loan | AccountType::OtherLiability
+        )
+    }
+
+    /// Turns a ledger amount, where credits count up and debits count down, into
+    /// the way the account is usually talked about: what is owned for assets and
+    /// what is owed for liabilities. Converting back works the same way.
+    pub fn display_amount(&self, amount: &Money) -> Money {
+        if self.is_liability() {
+            Money::new(-amount.minor, &amount.currency)
+        } else {
+            amount.clone()
+        }
+    }
+}
+
+impl fmt::Display for AccountType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for AccountType {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        AccountType::ALL
+            .into_iter()
+            .find(|account_type| account_type.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("'{}' is not an account type", s))
+    }
+}
+
+/// The billing cycle and terms of a credit card.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CardTerms {
+    /// Day of the month the statement closes, the last day of shorter months
+    pub closing_day: u32,
+    /// Day of the month payment is due, in the month after closing when it
+    /// does not come later in the same month
+    pub due_day: u32,
+    pub credit_limit: Option<Money>,
+    /// Annual percentage rate, e.g. `24.99`
+    pub apr: Option<f64>,
+}
+
+/// What was borrowed on a loan and how it is paid back.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LoanTerms {
+    pub principal: Money,
+    /// Annual interest rate, e.g. `6.5`
+    pub rate: f64,
+    /// Number of monthly payments
+    pub term_months: u32,
+    /// The day the money was borrowed, a month before the first payment
+    pub start_date: NaiveDate,
+}
 
 #[derive(Debug, Clone)]
 pub struct Account {
@@ -14,10 +124,17 @@ pub struct Account {
     pub name: String,
     pub bank: String,
     pub account_number: Option<String>,
-    pub balance: f64,
-    pub holder_id: String,
-    pub created_at: chrono::DateTime<Local>,
-    pub updated_at: chrono::DateTime<Local>,
+    pub account_type: AccountType,
+    /// Ledger amount, negative for a liability that is owed
+    pub opening_balance: Money,
+    /// Ledger amount, negative for a liability that is owed
+    pub balance: Money,
+    /// Only set for credit cards with a known billing cycle
+    pub card: Option<CardTerms>,
+    /// Only set for loans with known terms
+    pub loan: Option<LoanTerms>,
+    /// Set once the account is archived, hiding it while keeping its history
+    pub archived_at: Option<DateTime<Local>>,
 }
 
 #[derive(Debug, Clone, Tabled)]
@@ -25,73 +142,161 @@ pub struct AccountResponse {
     pub id: String,
     pub name: String,
     pub bank: String,
-    pub balance: f64,
-    pub holder_id: String,
+    #[tabled(rename = "type")]
+    pub account_type: AccountType,
+    /// What is owned, or for liabilities what is owed
+    pub balance: Money,
+    /// The balance in the reporting currency, or `n/a` when no rate is known
+    pub converted: String,
+    /// Every holder with the share of the account they own
+    pub holders: String,
 }
 
 pub struct AccountRequest {
     pub name: String,
     pub bank: String,
     pub account_number: Option<String>,
-    pub balance: f64,
-    pub holder_id: String,
+    pub account_type: AccountType,
+    /// Ledger amount, negative for a liability that is owed
+    pub opening_balance: Money,
+    /// Who holds the account, owning all of it between them
+    pub holders: Vec<AccountHolder>,
+    /// Only set for credit cards with a known billing cycle
+    pub card: Option<CardTerms>,
+    /// Only set for loans with known terms
+    pub loan: Option<LoanTerms>,
+}
+
+fn account_columns() -> [Accounts; 19] {
+    [
+        Accounts::Id,
+        Accounts::Name,
+        Accounts::Bank,
+        Accounts::AccountNumber,
+        Accounts::Currency,
+        Accounts::OpeningBalance,
+        Accounts::Balance,
+        Accounts::CreatedAt,
+        Accounts::UpdatedAt,
+        Accounts::Type,
+        Accounts::ClosingDay,
+        Accounts::DueDay,
+        Accounts::CreditLimit,
+        Accounts::Apr,
+        Accounts::LoanPrincipal,
+        Accounts::LoanRate,
+        Accounts::LoanTerm,
+        Accounts::LoanStartDate,
+        Accounts::ArchivedAt,
+    ]
+}
+
+fn account_from_row(row: &Row) -> Result<Account, rusqlite::Error> {
+    let currency = row.get::<_, String>(4)?;
+
+    Ok(Account {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        bank: row.get(2)?,
+        account_number: row.get(3)?,
+        opening_balance: Money::new(row.get(5)?, &currency),
+        balance: Money::new(row.get(6)?, &currency),
+        account_type: AccountType::from_str(&row.get::<_, String>(9)?)
+            .unwrap_or(AccountType::Checking),
+        card: row
+            .get::<_, Option<u32>>(10)?
+            .zip(row.get::<_, Option<u32>>(11)?)
+            .map(
+                |(closing_day, due_day)| -> Result<CardTerms, rusqlite::Error> {
+                    Ok(CardTerms {
+                        closing_day,
+                        due_day,
+                        credit_limit: row
+                            .get::<_, Option<i64>>(12)?
+                            .map(|limit| Money::new(limit, &currency)),
+                        apr: row.get(13)?,
+                    })
+                },
+            )
+            .transpose()?,
+        loan: match (
+            row.get::<_, Option<i64>>(14)?,
+            row.get::<_, Option<f64>>(15)?,
+            row.get::<_, Option<u32>>(16)?,
+            row.get::<_, Option<String>>(17)?
+                .and_then(|start_date| NaiveDate::from_str(&start_date).ok()),
+        ) {
+            (Some(principal), Some(rate), Some(term_months), Some(start_date)) => Some(LoanTerms {
+                principal: Money::new(principal, &currency),
+                rate,
+                term_months,
+                start_date,
+            }),
+            _ => None,
+        },
+        archived_at: row
+            .get::<_, Option<String>>(18)?
+            .and_then(|archived_at| DateTime::from_str(&archived_at).ok()),
+    })
 }
 
 impl Db {
-    pub fn create_account(&self, account: &AccountRequest) -> Result<Account, rusqlite::Error> {
+    pub fn create_account(&self, account: &AccountRequest) -> Result<Account, DbError> {
+        validate_holders(&account.holders)?;
+
         let id = random_hash(8);
         let time_now = Local::now().to_string();
 
         let query = Query::insert()
             .into_table(Accounts::Table)
-            .columns([
-                Accounts::Id,
-                Accounts::Name,
-                Accounts::Bank,
-                Accounts::AccountNumber,
-                Accounts::Balance,
-                Accounts::HolderId,
-                Accounts::CreatedAt,
-                Accounts::UpdatedAt,
-            ])
+            .columns(account_columns())
             .values_panic(vec![
                 id.clone().into(),
                 account.name.clone().into(),
                 account.bank.clone().into(),
                 account.account_number.clone().into(),
-                account.balance.into(),
-                account.holder_id.clone().into(),
+                account.opening_balance.currency.clone().into(),
+                account.opening_balance.minor.into(),
+                account.opening_balance.minor.into(),
                 time_now.clone().into(),
                 time_now.into(),
+                account.account_type.as_str().into(),
+                Option::<u32>::None.into(),
+                Option::<u32>::None.into(),
+                Option::<i64>::None.into(),
+                Option::<f64>::None.into(),
+                Option::<i64>::None.into(),
+                Option::<f64>::None.into(),
+                Option::<u32>::None.into(),
+                Option::<String>::None.into(),
+                Option::<String>::None.into(),
             ])
             .to_string(SqliteQueryBuilder);
 
+        let tx = self.conn.unchecked_transaction()?;
         self.conn.execute(&query, ())?;
+        self.insert_account_holders(&id, &account.holders)?;
+        self.set_card_terms(&id, account.card.as_ref())?;
+        self.set_loan_terms(&id, account.loan.as_ref())?;
+        tx.commit()?;
 
         Ok(Account {
             id,
             name: account.name.clone(),
             bank: account.bank.clone(),
             account_number: account.account_number.clone(),
-            balance: account.balance,
-            holder_id: account.holder_id.clone(),
-            created_at: Local::now(),
-            updated_at: Local::now(),
+            account_type: account.account_type,
+            opening_balance: account.opening_balance.clone(),
+            balance: account.opening_balance.clone(),
+            card: account.card.clone(),
+            loan: account.loan.clone(),
+            archived_at: None,
         })
     }
 
-    pub fn get_account(&self, id: &str) -> Result<Account, rusqlite::Error> {
+    pub fn get_account(&self, id: &str) -> Result<Account, DbError> {
         let query = Query::select()
-            .columns([
-                Accounts::Id,
-                Accounts::Name,
-                Accounts::Bank,
-                Accounts::AccountNumber,
-                Accounts::Balance,
-                Accounts::HolderId,
-                Accounts::CreatedAt,
-                Accounts::UpdatedAt,
-            ])
+            .columns(account_columns())
             .from(Accounts::Table)
             .and_where(Expr::col(Accounts::Id).eq(id))
             .limit(1)
@@ -99,148 +304,73 @@ impl Db {
 
         let mut stmt = self.conn.prepare(&query)?;
 
-        let account = stmt.query_row((), |row| {
-            let created_at =
-                DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
-            let updated_at =
-                DateTime::from_str(row.get::<_, String>(7)?.as_str()).unwrap_or(Local::now());
-
-            Ok(Account {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                bank: row.get(2)?,
-                account_number: row.get(3)?,
-                balance: row.get(4)?,
-                holder_id: row.get(5)?,
-                created_at,
-                updated_at,
-            })
-        })?;
-
-        Ok(account)
+        Ok(stmt.query_row((), account_from_row)?)
     }
 
-    pub fn search_accounts_by_name(&self, name: &str) -> Result<Vec<Account>, rusqlite::Error> {
+    pub fn search_accounts_by_name(&self, name: &str) -> Result<Vec<Account>, DbError> {
         let query = Query::select()
-            .columns([
-                Accounts::Id,
-                Accounts::Name,
-                Accounts::Bank,
-                Accounts::AccountNumber,
-                Accounts::Balance,
-                Accounts::HolderId,
-                Accounts::CreatedAt,
-                Accounts::UpdatedAt,
-            ])
+            .columns(account_columns())
             .from(Accounts::Table)
             .and_where(Expr::col(Accounts::Name).like(format!("%{}%", name).as_str()))
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
 
-        let account = stmt.query_map((), |row| {
-            let created_at =
-                DateTime::from_str(row.get::<_, String>(5)?.as_str()).unwrap_or(Local::now());
-            let updated_at =
-                DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
-
-            Ok(Account {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                bank: row.get(2)?,
-                account_number: row.get(3)?,
-                balance: row.get(4)?,
-                holder_id: row.get(5)?,
-                created_at,
-                updated_at,
-            })
-        })?;
-
-        Ok(account
+        Ok(stmt
+            .query_map((), account_from_row)?
             .map(|account| account.unwrap())
             .collect::<Vec<Account>>())
     }
 
-    pub fn get_all_accounts(&self) -> Result<Vec<Account>, rusqlite::Error> {
+    pub fn get_all_accounts(&self) -> Result<Vec<Account>, DbError> {
         let query = Query::select()
-            .columns([
-                Accounts::Id,
-                Accounts::Name,
-                Accounts::Bank,
-                Accounts::AccountNumber,
-                Accounts::Balance,
-                Accounts::HolderId,
-                Accounts::CreatedAt,
-                Accounts::UpdatedAt,
-            ])
+            .columns(account_columns())
             .from(Accounts::Table)
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
 
         Ok(stmt
-            .query_map((), |row| -> Result<Account, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
-                let updated_at =
-                    DateTime::from_str(row.get::<_, String>(7)?.as_str()).unwrap_or(Local::now());
-
-                Ok(Account {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    bank: row.get(2)?,
-                    account_number: row.get(3)?,
-                    balance: row.get(4)?,
-                    holder_id: row.get(5)?,
-                    created_at,
-                    updated_at,
-                })
-            })?
+            .query_map((), account_from_row)?
             .map(|account| account.unwrap())
             .collect::<Vec<Account>>())
     }
 
-    pub fn get_accounts_by_holder(&self, holder_id: &str) -> Result<Vec<Account>, rusqlite::Error> {
+    /// Every account a user holds, alone or jointly.
+    pub fn get_accounts_by_holder(&self, user_id: &str) -> Result<Vec<Account>, DbError> {
         let query = Query::select()
-            .columns([
-                Accounts::Id,
-                Accounts::Name,
-                Accounts::Bank,
-                Accounts::AccountNumber,
-                Accounts::Balance,
-                Accounts::HolderId,
-                Accounts::CreatedAt,
-                Accounts::UpdatedAt,
-            ])
+            .columns(account_columns())
             .from(Accounts::Table)
-            .and_where(Expr::col(Accounts::HolderId).eq(holder_id))
+            .and_where(
+                Expr::col(Accounts::Id).in_subquery(
+                    Query::select()
+                        .column(AccountHolders::AccountId)
+                        .from(AccountHolders::Table)
+                        .and_where(Expr::col(AccountHolders::UserId).eq(user_id))
+                        .to_owned(),
+                ),
+            )
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
 
         Ok(stmt
-            .query_map((), |row| -> Result<Account, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
-                let updated_at =
-                    DateTime::from_str(row.get::<_, String>(7)?.as_str()).unwrap_or(Local::now());
-
-                Ok(Account {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    bank: row.get(2)?,
-                    account_number: row.get(3)?,
-                    balance: row.get(4)?,
-                    holder_id: row.get(5)?,
-                    created_at,
-                    updated_at,
-                })
-            })?
+            .query_map((), account_from_row)?
             .map(|account| account.unwrap())
             .collect::<Vec<Account>>())
     }
 
-    pub fn update_account(&self, id: &str, account: &Account) -> Result<(), rusqlite::Error> {
+    /// Updates the account details, its card and loan terms and who holds it, all
+    /// or nothing. The balance itself is derived from the opening balance and the
+    /// transaction history, so it is recomputed rather than written.
+    pub fn update_account(
+        &self,
+        id: &str,
+        account: &Account,
+        holders: &[AccountHolder],
+    ) -> Result<(), DbError> {
+        validate_holders(holders)?;
+
         let updated_at = Local::now().to_string();
 
         let query = Query::update()
@@ -253,32 +383,162 @@ impl Db {
                     Accounts::AccountNumber,
                     account.account_number.clone().into(),
                 ),
-                (Accounts::Balance, account.balance.into()),
-                (Accounts::HolderId, account.holder_id.clone().into()),
+                (
+                    Accounts::OpeningBalance,
+                    account.opening_balance.minor.into(),
+                ),
+                (Accounts::Type, account.account_type.as_str().into()),
                 (Accounts::UpdatedAt, updated_at.into()),
             ])
             .to_string(SqliteQueryBuilder);
 
+        let tx = self.conn.unchecked_transaction()?;
+        self.get_account(id)?;
+        self.conn.execute(&query, ())?;
+        self.insert_account_holders(id, holders)?;
+        self.set_card_terms(id, account.card.as_ref())?;
+        self.set_loan_terms(id, account.loan.as_ref())?;
+        self.recompute_account_balance(id)?;
+        Ok(tx.commit()?)
+    }
+
+    /// Sets or clears the billing cycle and terms of a credit card. Runs inside the
+    /// SQL transaction of its caller, if there is one.
+    pub fn set_card_terms(&self, id: &str, card: Option<&CardTerms>) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(Accounts::Table)
+            .and_where(Expr::col(Accounts::Id).eq(id))
+            .values(vec![
+                (
+                    Accounts::ClosingDay,
+                    card.map(|card| card.closing_day).into(),
+                ),
+                (Accounts::DueDay, card.map(|card| card.due_day).into()),
+                (
+                    Accounts::CreditLimit,
+                    card.and_then(|card| card.credit_limit.as_ref())
+                        .map(|limit| limit.minor)
+                        .into(),
+                ),
+                (Accounts::Apr, card.and_then(|card| card.apr).into()),
+            ])
+            .to_string(SqliteQueryBuilder);
+
         self.conn.execute(&query, ())?;
 
         Ok(())
     }
 
-    pub fn delete_account(&self, id: &str) -> Result<(), rusqlite::Error> {
-        let query = Query::delete()
-            .from_table(Accounts::Table)
+    /// Sets or clears the terms of a loan. Runs inside the SQL transaction of its
+    /// caller, if there is one.
+    pub fn set_loan_terms(&self, id: &str, loan: Option<&LoanTerms>) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(Accounts::Table)
             .and_where(Expr::col(Accounts::Id).eq(id))
+            .values(vec![
+                (
+                    Accounts::LoanPrincipal,
+                    loan.map(|loan| loan.principal.minor).into(),
+                ),
+                (Accounts::LoanRate, loan.map(|loan| loan.rate).into()),
+                (Accounts::LoanTerm, loan.map(|loan| loan.term_months).into()),
+                (
+                    Accounts::LoanStartDate,
+                    loan.map(|loan| loan.start_date.to_string()).into(),
+                ),
+            ])
             .to_string(SqliteQueryBuilder);
 
         self.conn.execute(&query, ())?;
 
         Ok(())
     }
+
+    /// Shifts the stored balance by `delta` minor units. Callers are expected to run this
+    /// inside the same SQL transaction as the change that caused it.
+    pub(super) fn adjust_account_balance(&self, id: &str, delta: i64) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(Accounts::Table)
+            .value(Accounts::Balance, Expr::col(Accounts::Balance).add(delta))
+            .and_where(Expr::col(Accounts::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// Rebuilds the balance of an account from its opening balance and every
+    /// transaction recorded against it, returning the new balance.
+    pub fn recompute_account_balance(&self, id: &str) -> Result<Money, DbError> {
+        let account = self.get_account(id)?;
+        let balance = Money::new(
+            account.opening_balance.minor + self.get_account_net(id)?,
+            &account.opening_balance.currency,
+        );
+
+        let query = Query::update()
+            .table(Accounts::Table)
+            .value(Accounts::Balance, balance.minor)
+            .and_where(Expr::col(Accounts::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(balance)
+    }
+
+    /// Archives an account, or brings it back with `archived` set to false.
+    pub fn set_account_archived(&self, id: &str, archived: bool) -> Result<(), DbError> {
+        Ok(self.set_archived(
+            Accounts::Table,
+            Accounts::Id,
+            Accounts::ArchivedAt,
+            id,
+            archived,
+        )?)
+    }
+
+    /// Deletes an account along with its transactions and the other side of its
+    /// transfers. Reconciled transactions, on either side, are refused.
+    pub fn delete_account(&self, id: &str) -> Result<(), DbError> {
+        let filter = TransactionFilter {
+            account_id: Some(id.to_string()),
+            ..Default::default()
+        };
+        let delete_holders = Query::delete()
+            .from_table(AccountHolders::Table)
+            .and_where(Expr::col(AccountHolders::AccountId).eq(id))
+            .to_string(SqliteQueryBuilder);
+        let query = Query::delete()
+            .from_table(Accounts::Table)
+            .and_where(Expr::col(Accounts::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        let tx = self.conn.unchecked_transaction()?;
+        // One by one, so the other side of a transfer goes along instead of
+        // being left behind on the other account
+        for transaction in self.get_filtered_transactions(&filter)? {
+            self.remove_transaction(&transaction.id)?;
+        }
+        self.conn.execute(&delete_holders, ())?;
+        self.conn.execute(&query, ())?;
+        Ok(tx.commit()?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::{db::users::UserRequest, utils::get_test_db_path};
+    use chrono::NaiveDate;
+
+    use crate::{
+        db::{
+            transaction::{TransactionStatus, TransferRequest},
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db, get_test_db_path},
+    };
 
     use super::*;
 
@@ -301,15 +561,21 @@ mod tests {
             name: "Checking Account".to_string(),
             bank: "Test Bank".to_string(),
             account_number: Some("123456789".to_string()),
-            balance: 1000.50,
-            holder_id: user.id.clone(),
+            account_type: AccountType::Checking,
+            opening_balance: Money::new(100050, DEFAULT_CURRENCY),
+            holders: vec![AccountHolder::sole(&user.id)],
+            card: None,
+            loan: None,
         };
 
         let created_account = db.create_account(&account_req).unwrap();
         assert_eq!(created_account.name, "Checking Account");
         assert_eq!(created_account.bank, "Test Bank");
-        assert_eq!(created_account.balance, 1000.50);
-        assert_eq!(created_account.holder_id, user.id);
+        assert_eq!(created_account.balance.minor, 100050);
+        assert_eq!(
+            db.get_account_holders(&created_account.id).unwrap()[0].user_id,
+            user.id
+        );
 
         // Get account by ID
         let retrieved_account = db.get_account(&created_account.id).unwrap();
@@ -323,22 +589,128 @@ mod tests {
         // Get accounts by holder
         let holder_accounts = db.get_accounts_by_holder(&user.id).unwrap();
         assert_eq!(holder_accounts.len(), 1);
-        assert_eq!(holder_accounts[0].holder_id, user.id);
+        assert_eq!(holder_accounts[0].id, created_account.id);
 
         // Update account
         let mut updated_account = retrieved_account.clone();
         updated_account.name = "Updated Checking Account".to_string();
-        updated_account.balance = 2000.75;
+        updated_account.opening_balance = Money::new(200075, DEFAULT_CURRENCY);
 
-        let update_result = db.update_account(&created_account.id, &updated_account);
+        let update_result = db.update_account(
+            &created_account.id,
+            &updated_account,
+            &[AccountHolder::sole(&user.id)],
+        );
         assert!(update_result.is_ok());
 
         let updated_retrieved = db.get_account(&created_account.id).unwrap();
         assert_eq!(updated_retrieved.name, "Updated Checking Account");
-        assert_eq!(updated_retrieved.balance, 2000.75);
+        assert_eq!(updated_retrieved.balance.minor, 200075);
+        assert_eq!(updated_retrieved.account_type, AccountType::Checking);
+
+        // A holder who does not exist fails the whole update, the account included
+        let mut renamed = updated_retrieved.clone();
+        renamed.name = "Renamed Checking Account".to_string();
+        assert!(
+            db.update_account(
+                &created_account.id,
+                &renamed,
+                &[AccountHolder::sole("missing")]
+            )
+            .is_err()
+        );
+        assert_eq!(
+            db.get_account(&created_account.id).unwrap().name,
+            "Updated Checking Account"
+        );
+
+        // Liabilities keep what is owed as a negative ledger amount
+        let card = db
+            .create_account(&AccountRequest {
+                name: "Credit Card".to_string(),
+                bank: "Test Bank".to_string(),
+                account_number: None,
+                account_type: AccountType::CreditCard,
+                opening_balance: AccountType::CreditCard
+                    .display_amount(&Money::new(50000, DEFAULT_CURRENCY)),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: Some(CardTerms {
+                    closing_day: 25,
+                    due_day: 20,
+                    credit_limit: None,
+                    apr: None,
+                }),
+                loan: None,
+            })
+            .unwrap();
+        let card = db.get_account(&card.id).unwrap();
+        assert_eq!(card.account_type, AccountType::CreditCard);
+        assert!(card.account_type.is_liability());
+        assert_eq!(card.balance.minor, -50000);
+        assert_eq!(card.card.map(|terms| terms.closing_day), Some(25));
+        assert_eq!(card.account_type.display_amount(&card.balance).minor, 50000);
+        assert_eq!(
+            "credit-card".parse::<AccountType>(),
+            Ok(AccountType::CreditCard)
+        );
+        assert!("mattress".parse::<AccountType>().is_err());
+
+        // Archiving hides an account without touching it
+        let before = db.get_account(&created_account.id).unwrap();
+        db.set_account_archived(&created_account.id, true).unwrap();
+        let archived = db.get_account(&created_account.id).unwrap();
+        assert!(archived.archived_at.is_some());
+        assert_eq!(archived.balance, before.balance);
+        db.set_account_archived(&created_account.id, false).unwrap();
+        assert!(
+            db.get_account(&created_account.id)
+                .unwrap()
+                .archived_at
+                .is_none()
+        );
+        assert!(db.set_account_archived("missing", true).is_err());
 
         // Delete account
         let delete_result = db.delete_account(&created_account.id);
         assert!(delete_result.is_ok());
     }
+
+    #[test]
+    fn test_delete_account_with_transfer() {
+        let db = get_test_db("delete_account_transfer");
+        let user = create_test_user(&db, "John Doe");
+        let checking = create_test_account(&db, &user.id, "Checking", 10000);
+        let savings = create_test_account(&db, &user.id, "Savings", 10000);
+        let (_, incoming) = db
+            .create_transfer(&TransferRequest {
+                from_account_id: checking.id.clone(),
+                to_account_id: savings.id.clone(),
+                amount: Money::new(2500, DEFAULT_CURRENCY),
+                description: "Saving up".to_string(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+            })
+            .unwrap();
+
+        // Not while the savings side is reconciled
+        db.set_transaction_status(
+            std::slice::from_ref(&incoming.id),
+            TransactionStatus::Reconciled,
+        )
+        .unwrap();
+        assert!(matches!(
+            db.delete_account(&checking.id),
+            Err(DbError::Invalid(_))
+        ));
+        assert_eq!(db.get_all_transactions().unwrap().len(), 2);
+        assert!(db.get_account(&checking.id).is_ok());
+
+        // Otherwise the savings side goes too, taking the money back out
+        let mut cleared = db.get_transaction(&incoming.id).unwrap();
+        cleared.status = TransactionStatus::Cleared;
+        db.update_transaction(&incoming.id, &cleared.into())
+            .unwrap();
+        db.delete_account(&checking.id).unwrap();
+        assert!(db.get_all_transactions().unwrap().is_empty());
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 10000);
+    }
 }
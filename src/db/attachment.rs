@@ -0,0 +1,305 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use rusqlite::Row;
+use sea_query::{Expr, Func, Order, Query, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    utils::random_hash,
+};
+
+use super::Attachments;
+
+/// A receipt, invoice or other document stored with a transaction. The file
+/// contents are only loaded when they are extracted.
+#[derive(Debug, Clone, Tabled)]
+pub struct Attachment {
+    pub id: String,
+    pub transaction_id: String,
+    pub file_name: String,
+    /// Size of the file in bytes
+    pub size: i64,
+    pub created_at: chrono::DateTime<Local>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttachmentRequest {
+    pub file_name: String,
+    pub content: Vec<u8>,
+}
+
+fn attachment_columns() -> [Attachments; 5] {
+    [
+        Attachments::Id,
+        Attachments::TransactionId,
+        Attachments::FileName,
+        Attachments::Size,
+        Attachments::CreatedAt,
+    ]
+}
+
+fn attachment_from_row(row: &Row) -> Result<Attachment, rusqlite::Error> {
+    let created_at = DateTime::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Attachment {
+        id: row.get(0)?,
+        transaction_id: row.get(1)?,
+        file_name: row.get(2)?,
+        size: row.get(3)?,
+        created_at,
+    })
+}
+
+impl Db {
+    /// Stores files with a transaction.
+    pub(super) fn insert_attachments(
+        &self,
+        transaction_id: &str,
+        attachments: &[AttachmentRequest],
+    ) -> Result<Vec<Attachment>, DbError> {
+        let mut created = vec![];
+
+        for attachment in attachments {
+            let id = random_hash(8);
+
+            let query = Query::insert()
+                .into_table(Attachments::Table)
+                .columns([
+                    Attachments::Id,
+                    Attachments::TransactionId,
+                    Attachments::FileName,
+                    Attachments::Size,
+                    Attachments::Content,
+                    Attachments::CreatedAt,
+                ])
+                .values_panic(vec![
+                    id.clone().into(),
+                    transaction_id.into(),
+                    attachment.file_name.clone().into(),
+                    (attachment.content.len() as i64).into(),
+                    attachment.content.clone().into(),
+                    Local::now().to_string().into(),
+                ])
+                .to_string(SqliteQueryBuilder);
+
+            self.conn.execute(&query, ())?;
+
+            created.push(Attachment {
+                id,
+                transaction_id: transaction_id.to_string(),
+                file_name: attachment.file_name.clone(),
+                size: attachment.content.len() as i64,
+                created_at: Local::now(),
+            });
+        }
+
+        Ok(created)
+    }
+
+    pub fn get_attachment(&self, id: &str) -> Result<Attachment, DbError> {
+        let query = Query::select()
+            .columns(attachment_columns())
+            .from(Attachments::Table)
+            .and_where(Expr::col(Attachments::Id).eq(id))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt.query_row((), attachment_from_row)?)
+    }
+
+    pub fn get_attachment_content(&self, id: &str) -> Result<Vec<u8>, DbError> {
+        let query = Query::select()
+            .column(Attachments::Content)
+            .from(Attachments::Table)
+            .and_where(Expr::col(Attachments::Id).eq(id))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        Ok(self.conn.query_row(&query, (), |row| row.get(0))?)
+    }
+
+    pub fn get_transaction_attachments(
+        &self,
+        transaction_id: &str,
+    ) -> Result<Vec<Attachment>, DbError> {
+        let query = Query::select()
+            .columns(attachment_columns())
+            .from(Attachments::Table)
+            .and_where(Expr::col(Attachments::TransactionId).eq(transaction_id))
+            .order_by(Attachments::CreatedAt, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), attachment_from_row)?
+            .map(|attachment| attachment.unwrap())
+            .collect::<Vec<Attachment>>())
+    }
+
+    pub fn count_transaction_attachments(&self, transaction_id: &str) -> Result<i64, DbError> {
+        let query = Query::select()
+            .expr(Func::count(Expr::col(Attachments::Id)))
+            .from(Attachments::Table)
+            .and_where(Expr::col(Attachments::TransactionId).eq(transaction_id))
+            .to_string(SqliteQueryBuilder);
+
+        Ok(self.conn.query_row(&query, (), |row| row.get(0))?)
+    }
+
+    pub fn delete_attachment(&self, id: &str) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(Attachments::Table)
+            .and_where(Expr::col(Attachments::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    pub(super) fn delete_transaction_attachments(
+        &self,
+        transaction_id: &str,
+    ) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(Attachments::Table)
+            .and_where(Expr::col(Attachments::TransactionId).eq(transaction_id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::AccountHolder,
+            transaction::{TransactionEdit, TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::{DEFAULT_CURRENCY, Money},
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_attachments() {
+        let _ = std::fs::remove_file(get_test_db_path("attachment"));
+
+        let db = Db::new(get_test_db_path("attachment").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Checking".to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(0, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Electronics".to_string(),
+                icon: "💻".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let receipt = AttachmentRequest {
+            file_name: "receipt.pdf".to_string(),
+            content: vec![0x25, 0x50, 0x44, 0x46, 0x00, 0xff],
+        };
+        let transaction = db
+            .create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(99900, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: "Laptop".to_string(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![receipt.clone()],
+            })
+            .unwrap();
+
+        // More files can be added when updating
+        db.update_transaction(
+            &transaction.id,
+            &TransactionEdit {
+                attachments: vec![AttachmentRequest {
+                    file_name: "warranty.png".to_string(),
+                    content: vec![1, 2, 3],
+                }],
+                ..transaction.clone().into()
+            },
+        )
+        .unwrap();
+        assert!(
+            db.update_transaction(
+                "missing",
+                &TransactionEdit {
+                    attachments: vec![receipt.clone()],
+                    ..transaction.clone().into()
+                },
+            )
+            .is_err()
+        );
+
+        let attachments = db.get_transaction_attachments(&transaction.id).unwrap();
+        assert_eq!(attachments.len(), 2);
+        assert_eq!(
+            db.count_transaction_attachments(&transaction.id).unwrap(),
+            2
+        );
+
+        // Binary contents come back byte for byte
+        let stored = attachments
+            .iter()
+            .find(|attachment| attachment.file_name == "receipt.pdf")
+            .unwrap();
+        assert_eq!(stored.size, 6);
+        assert_eq!(
+            db.get_attachment_content(&stored.id).unwrap(),
+            receipt.content
+        );
+
+        let warranty = attachments
+            .iter()
+            .find(|attachment| attachment.file_name == "warranty.png")
+            .unwrap();
+        db.delete_attachment(&warranty.id).unwrap();
+        assert_eq!(
+            db.count_transaction_attachments(&transaction.id).unwrap(),
+            1
+        );
+
+        // Attachments go away with their transaction
+        db.delete_transaction(&transaction.id).unwrap();
+        assert!(db.get_attachment(&stored.id).is_err());
+    }
+}
@@ -0,0 +1,450 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, Local, Months, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SqliteQueryBuilder};
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+};
+
+use super::Budgets;
+
+/// A spending cap for one category in one month.
+#[derive(Debug, Clone)]
+pub struct Budget {
+    pub category_id: String,
+    /// The first day of the month the budget applies to
+    pub month: NaiveDate,
+    pub amount: Money,
+    /// Whether whatever is left at the end of the month carries into the next one
+    pub rollover: bool,
+}
+
+pub struct BudgetRequest {
+    pub category_id: String,
+    pub month: NaiveDate,
+    pub amount: Money,
+    pub rollover: bool,
+}
+
+/// How a category is doing against its budget in a month.
+#[derive(Debug, Clone)]
+pub struct BudgetStatus {
+    pub category_id: String,
+    pub budgeted: Money,
+    /// Left over from previous months with rollover enabled
+    pub rolled_over: Money,
+    /// Expenses minus refunds, including those of child categories
+    pub spent: Money,
+}
+
+impl BudgetStatus {
+    pub fn available(&self) -> i64 {
+        self.budgeted.minor + self.rolled_over.minor
+    }
+
+    pub fn remaining(&self) -> Money {
+        Money::new(self.available() - self.spent.minor, &self.budgeted.currency)
+    }
+
+    /// Share of the available money that was spent, in percent.
+    pub fn percent_used(&self) -> f64 {
+        if self.available() == 0 {
+            return if self.spent.minor > 0 {
+                f64::INFINITY
+            } else {
+                0.0
+            };
+        }
+
+        self.spent.minor as f64 * 100.0 / self.available() as f64
+    }
+
+    pub fn is_overspent(&self) -> bool {
+        self.remaining().minor < 0
+    }
+}
+
+/// Parses a month such as `2026-03` into its first day.
+pub fn parse_month(input: &str) -> Result<NaiveDate, String> {
+    NaiveDate::parse_from_str(&format!("{}-01", input.trim()), "%Y-%m-%d")
+        .map_err(|_| format!("'{}' is not a month, use YYYY-MM", input))
+}
+
+/// The first day of the month `date` falls in.
+pub fn month_start(date: NaiveDate) -> NaiveDate {
+    date.with_day(1).unwrap_or(date)
+}
+
+/// The last day of the month starting on `month`.
+pub fn month_end(month: NaiveDate) -> NaiveDate {
+    month
+        .checked_add_months(Months::new(1))
+        .and_then(|next| next.pred_opt())
+        .unwrap_or(month)
+}
+
+fn budget_columns() -> [Budgets; 6] {
+    [
+        Budgets::CategoryId,
+        Budgets::Month,
+        Budgets::Amount,
+        Budgets::Currency,
+        Budgets::Rollover,
+        Budgets::CreatedAt,
+    ]
+}
+
+fn budget_from_row(row: &Row) -> Result<Budget, rusqlite::Error> {
+    let month =
+        NaiveDate::from_str(row.get::<_, String>(1)?.as_str()).unwrap_or(Local::now().date_naive());
+
+    Ok(Budget {
+        category_id: row.get(0)?,
+        month,
+        amount: Money::new(row.get(2)?, &row.get::<_, String>(3)?),
+        rollover: row.get(4)?,
+    })
+}
+
+impl Db {
+    /// Sets the budget of a category for a month, replacing any earlier one.
+    pub fn set_budget(&self, budget: &BudgetRequest) -> Result<Budget, DbError> {
+        let month = month_start(budget.month);
+
+        let query = Query::insert()
+            .into_table(Budgets::Table)
+            .columns(budget_columns())
+            .values_panic(vec![
+                budget.category_id.clone().into(),
+                month.to_string().into(),
+                budget.amount.minor.into(),
+                budget.amount.currency.clone().into(),
+                budget.rollover.into(),
+                Local::now().to_string().into(),
+            ])
+            .on_conflict(
+                sea_query::OnConflict::columns([Budgets::CategoryId, Budgets::Month])
+                    .update_columns([Budgets::Amount, Budgets::Currency, Budgets::Rollover])
+                    .to_owned(),
+            )
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(Budget {
+            category_id: budget.category_id.clone(),
+            month,
+            amount: budget.amount.clone(),
+            rollover: budget.rollover,
+        })
+    }
+
+    pub fn get_budget(
+        &self,
+        category_id: &str,
+        month: NaiveDate,
+    ) -> Result<Option<Budget>, DbError> {
+        let query = Query::select()
+            .columns(budget_columns())
+            .from(Budgets::Table)
+            .and_where(Expr::col(Budgets::CategoryId).eq(category_id))
+            .and_where(Expr::col(Budgets::Month).eq(month_start(month).to_string()))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query_map((), budget_from_row)?;
+
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Budgets of one month, or of every month with the latest first.
+    pub fn get_budgets(&self, month: Option<NaiveDate>) -> Result<Vec<Budget>, DbError> {
+        let mut query = Query::select();
+        query
+            .columns(budget_columns())
+            .from(Budgets::Table)
+            .order_by(Budgets::Month, Order::Desc);
+
+        if let Some(month) = month {
+            query.and_where(Expr::col(Budgets::Month).eq(month_start(month).to_string()));
+        }
+
+        let mut stmt = self.conn.prepare(&query.to_string(SqliteQueryBuilder))?;
+
+        Ok(stmt
+            .query_map((), budget_from_row)?
+            .map(|budget| budget.unwrap())
+            .collect::<Vec<Budget>>())
+    }
+
+    /// Copies every budget of `from` into `to`, leaving budgets that `to`
+    /// already has alone. Returns how many were copied.
+    pub fn copy_budgets(&self, from: NaiveDate, to: NaiveDate) -> Result<usize, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let mut copied = 0;
+        for budget in self.get_budgets(Some(from))? {
+            let query = Query::insert()
+                .into_table(Budgets::Table)
+                .columns(budget_columns())
+                .values_panic(vec![
+                    budget.category_id.into(),
+                    month_start(to).to_string().into(),
+                    budget.amount.minor.into(),
+                    budget.amount.currency.into(),
+                    budget.rollover.into(),
+                    Local::now().to_string().into(),
+                ])
+                .on_conflict(
+                    sea_query::OnConflict::columns([Budgets::CategoryId, Budgets::Month])
+                        .do_nothing()
+                        .to_owned(),
+                )
+                .to_string(SqliteQueryBuilder);
+
+            copied += self.conn.execute(&query, ())?;
+        }
+
+        tx.commit()?;
+
+        Ok(copied)
+    }
+
+    pub(super) fn delete_category_budgets(&self, category_id: &str) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(Budgets::Table)
+            .and_where(Expr::col(Budgets::CategoryId).eq(category_id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// Budgeted, rolled over and spent amounts of every category with a budget in
+    /// `month`, in the currency of each budget.
+    pub fn get_budget_status(&self, month: NaiveDate) -> Result<Vec<BudgetStatus>, DbError> {
+        self.get_budgets(Some(month))?
+            .into_iter()
+            .map(|budget| {
+                Ok(BudgetStatus {
+                    rolled_over: self.get_budget_carry(&budget)?,
+                    spent: self.get_budget_spent(&budget)?,
+                    category_id: budget.category_id,
+                    budgeted: budget.amount,
+                })
+            })
+            .collect()
+    }
+
+    fn get_budget_spent(&self, budget: &Budget) -> Result<Money, DbError> {
+        let spent = self
+            .get_category_rollups(
+                &budget.amount.currency,
+                Some((budget.month, month_end(budget.month))),
+                None,
+            )?
+            .into_iter()
+            .find(|total| total.category_id == budget.category_id)
+            .map_or(0, |total| total.expenses.minor - total.income.minor);
+
+        Ok(Money::new(spent, &budget.amount.currency))
+    }
+
+    /// What the months before `budget` leave for it. A month only passes on
+    /// what it did not spend, and only when it has rollover enabled.
+    fn get_budget_carry(&self, budget: &Budget) -> Result<Money, DbError> {
+        let currency = &budget.amount.currency;
+
+        let mut previous_months = vec![];
+        let mut previous = budget.month.checked_sub_months(Months::new(1));
+        while let Some(month) = previous {
+            match self.get_budget(&budget.category_id, month)? {
+                Some(previous_budget) if previous_budget.rollover => {
+                    previous_months.push(previous_budget)
+                }
+                _ => break,
+            }
+            previous = month.checked_sub_months(Months::new(1));
+        }
+
+        // Walk forwards from the earliest month in the chain
+        let mut carry = 0;
+        for previous_budget in previous_months.iter().rev() {
+            let spent = self.get_budget_spent(previous_budget)?;
+            let amount = self.convert(&previous_budget.amount, currency, previous_budget.month)?;
+            let spent = self.convert(&spent, currency, previous_budget.month)?;
+            carry = (carry + amount.minor - spent.minor).max(0);
+        }
+
+        Ok(Money::new(carry, currency))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{
+            category::{Category, CategoryRequest},
+            transaction::{TransactionRequest, TransactionStatus},
+        },
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db},
+    };
+
+    use super::*;
+
+    /// A Food category with Restaurants under it, where 100.00 was spent in
+    /// January and 350.00 in February.
+    fn setup(name: &str) -> (Db, Category, Category) {
+        let db = get_test_db(name);
+        let user = create_test_user(&db, "John Doe");
+        let account = create_test_account(&db, &user.id, "Checking", 0);
+        let food = db
+            .create_category(&CategoryRequest {
+                name: "Food".to_string(),
+                icon: "🍽️".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        let restaurants = db
+            .create_category(&CategoryRequest {
+                name: "Restaurants".to_string(),
+                icon: "🍝".to_string(),
+                parent_id: Some(food.id.clone()),
+            })
+            .unwrap();
+
+        for (amount, posted_on) in [(10000, "2024-01-10"), (35000, "2024-02-14")] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(amount, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: "Dinner".to_string(),
+                category_id: restaurants.id.clone(),
+                posted_on: NaiveDate::from_str(posted_on).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        (db, food, restaurants)
+    }
+
+    fn month(input: &str) -> NaiveDate {
+        parse_month(input).unwrap()
+    }
+
+    fn budget(category: &Category, month: NaiveDate, amount: i64, rollover: bool) -> BudgetRequest {
+        BudgetRequest {
+            category_id: category.id.clone(),
+            month,
+            amount: Money::new(amount, DEFAULT_CURRENCY),
+            rollover,
+        }
+    }
+
+    #[test]
+    fn test_parse_month() {
+        assert_eq!(
+            month_end(month("2024-02")),
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        );
+        assert!(parse_month("2024-13").is_err());
+    }
+
+    #[test]
+    fn test_copy_budgets() {
+        let (db, food, restaurants) = setup("budget_copy");
+        let (january, february) = (month("2024-01"), month("2024-02"));
+
+        for category in [&restaurants, &food] {
+            db.set_budget(&budget(category, january, 30000, true))
+                .unwrap();
+        }
+        assert_eq!(db.copy_budgets(january, february).unwrap(), 2);
+        assert_eq!(db.copy_budgets(january, february).unwrap(), 0);
+
+        // Setting again replaces the amount
+        db.set_budget(&budget(&food, february, 50000, false))
+            .unwrap();
+        assert_eq!(db.get_budgets(Some(february)).unwrap().len(), 2);
+        assert_eq!(
+            db.get_budget(&food.id, february)
+                .unwrap()
+                .unwrap()
+                .amount
+                .minor,
+            50000
+        );
+    }
+
+    #[test]
+    fn test_budget_status() {
+        let (db, food, restaurants) = setup("budget_status");
+        let (january, february) = (month("2024-01"), month("2024-02"));
+
+        db.set_budget(&budget(&restaurants, january, 30000, true))
+            .unwrap();
+        db.set_budget(&budget(&food, february, 50000, false))
+            .unwrap();
+        db.copy_budgets(january, february).unwrap();
+
+        // January leaves 200 for February, which spends 350 of 500
+        let status = db.get_budget_status(february).unwrap();
+        let dining = status
+            .iter()
+            .find(|status| status.category_id == restaurants.id)
+            .unwrap();
+        assert_eq!(dining.rolled_over.minor, 20000);
+        assert_eq!(dining.spent.minor, 35000);
+        assert_eq!(dining.remaining().minor, 15000);
+        assert!(!dining.is_overspent());
+        assert_eq!(dining.percent_used(), 70.0);
+
+        // Parents include their children
+        let total = status
+            .iter()
+            .find(|status| status.category_id == food.id)
+            .unwrap();
+        assert_eq!(total.spent.minor, 35000);
+    }
+
+    #[test]
+    fn test_overspent_budget() {
+        let (db, _, restaurants) = setup("budget_overspent");
+        let (february, march) = (month("2024-02"), month("2024-03"));
+
+        db.set_budget(&budget(&restaurants, february, 0, true))
+            .unwrap();
+        db.copy_budgets(february, march).unwrap();
+
+        let status = db.get_budget_status(february).unwrap();
+        assert!(status[0].is_overspent());
+
+        // Overspending is never carried over as a debt
+        let status = db.get_budget_status(march).unwrap();
+        assert!(status.iter().all(|status| status.rolled_over.minor == 0));
+    }
+
+    #[test]
+    fn test_delete_budgeted_category() {
+        let (db, _, restaurants) = setup("budget_category_delete");
+        let march = month("2024-03");
+
+        db.set_budget(&budget(&restaurants, march, 30000, true))
+            .unwrap();
+
+        // Budgets go away with their category
+        db.delete_category(&restaurants.id).unwrap();
+        assert!(db.get_budget(&restaurants.id, march).unwrap().is_none());
+    }
+}
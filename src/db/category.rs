@@ -1,44 +1,193 @@
 use std::str::FromStr;
 
-use chrono::{DateTime, Local};
-use sea_query::{Expr, Query, SqliteQueryBuilder};
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Alias, Expr, Func, Query, SqliteQueryBuilder};
 use tabled::Tabled;
 
-use crate::{db::Db, utils::random_hash};
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
 
-use super::Categories;
+use super::{
+    Accounts, Categories, TransactionSplits, Transactions,
+    holder::{held_by, owned_amount},
+    transaction::{TransactionFilter, not_void},
+};
 
 #[derive(Debug, Clone, Tabled)]
 pub struct Category {
     pub id: String,
     pub name: String,
     pub icon: String,
+    #[tabled(skip)]
+    pub parent_id: Option<String>,
     pub created_at: chrono::DateTime<Local>,
+    /// Set once the category is archived, hiding it while keeping its history
+    #[tabled(skip)]
+    pub archived_at: Option<DateTime<Local>>,
 }
 
 pub struct CategoryRequest {
     pub name: String,
     pub icon: String,
+    pub parent_id: Option<String>,
+}
+
+pub const TRANSFER_CATEGORY: &str = "Transfer";
+
+pub const UNCATEGORIZED_CATEGORY: &str = "Uncategorized";
+
+/// Money that came in and went out under one category.
+#[derive(Debug, Clone)]
+pub struct CategoryTotal {
+    pub category_id: String,
+    pub income: Money,
+    pub expenses: Money,
+}
+
+/// Raised when a category would end up as its own ancestor.
+#[derive(Debug)]
+pub struct CategoryCycleError {
+    pub category_id: String,
+    pub parent_id: String,
+}
+
+impl std::fmt::Display for CategoryCycleError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Category {} cannot be moved under {}, which is itself or one of its children",
+            self.category_id, self.parent_id
+        )
+    }
+}
+
+impl std::error::Error for CategoryCycleError {}
+
+/// The ids of `id` and all of its ancestors, starting with `id` itself.
+pub fn category_ancestors(categories: &[Category], id: &str) -> Vec<String> {
+    let mut ancestors = vec![];
+    let mut current = Some(id.to_string());
+
+    while let Some(id) = current {
+        // Guards against loops left behind by hand edited databases
+        if ancestors.contains(&id) {
+            break;
+        }
+
+        current = categories
+            .iter()
+            .find(|category| category.id == id)
+            .and_then(|category| category.parent_id.clone());
+        ancestors.push(id);
+    }
+
+    ancestors
+}
+
+/// The full name of a category, such as `Food > Groceries`.
+pub fn category_path(categories: &[Category], id: &str) -> String {
+    category_ancestors(categories, id)
+        .iter()
+        .rev()
+        .map(|id| {
+            categories
+                .iter()
+                .find(|category| &category.id == id)
+                .map_or(id.clone(), |category| category.name.clone())
+        })
+        .collect::<Vec<String>>()
+        .join(" > ")
+}
+
+/// Categories in depth first order, each with its depth below the top level.
+/// Siblings are sorted by name, and orphans whose parent is gone are shown at the top level.
+pub fn category_tree(categories: &[Category]) -> Vec<(usize, Category)> {
+    fn visit(
+        categories: &[Category],
+        parent_id: &str,
+        depth: usize,
+        tree: &mut Vec<(usize, Category)>,
+    ) {
+        let mut children = categories
+            .iter()
+            .filter(|category| category.parent_id.as_deref() == Some(parent_id))
+            .collect::<Vec<&Category>>();
+        children.sort_by(|a, b| a.name.cmp(&b.name));
+
+        for child in children {
+            if tree.iter().any(|(_, category)| category.id == child.id) {
+                continue;
+            }
+
+            tree.push((depth, child.clone()));
+            visit(categories, &child.id, depth + 1, tree);
+        }
+    }
+
+    let mut roots = categories
+        .iter()
+        .filter(|category| {
+            category.parent_id.as_ref().is_none_or(|parent_id| {
+                !categories.iter().any(|category| &category.id == parent_id)
+            })
+        })
+        .collect::<Vec<&Category>>();
+    roots.sort_by(|a, b| a.name.cmp(&b.name));
+
+    let mut tree = vec![];
+    for root in roots {
+        tree.push((0, root.clone()));
+        visit(categories, &root.id, 1, &mut tree);
+    }
+
+    tree
+}
+
+fn category_columns() -> [Categories; 6] {
+    [
+        Categories::Id,
+        Categories::Name,
+        Categories::Icon,
+        Categories::ParentId,
+        Categories::CreatedAt,
+        Categories::ArchivedAt,
+    ]
+}
+
+fn category_from_row(row: &Row) -> Result<Category, rusqlite::Error> {
+    let created_at = DateTime::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Category {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        icon: row.get(2)?,
+        parent_id: row.get(3)?,
+        created_at,
+        archived_at: row
+            .get::<_, Option<String>>(5)?
+            .and_then(|archived_at| DateTime::from_str(&archived_at).ok()),
+    })
 }
 
 impl Db {
-    pub fn create_category(&self, category: &CategoryRequest) -> Result<Category, rusqlite::Error> {
+    pub fn create_category(&self, category: &CategoryRequest) -> Result<Category, DbError> {
         let id = random_hash(8);
         let time_now = Local::now().to_string();
 
         let query = Query::insert()
             .into_table(Categories::Table)
-            .columns([
-                Categories::Id,
-                Categories::Name,
-                Categories::Icon,
-                Categories::CreatedAt,
-            ])
+            .columns(category_columns())
             .values_panic(vec![
                 id.clone().into(),
                 category.name.clone().into(),
                 category.icon.clone().into(),
+                category.parent_id.clone().into(),
                 time_now.into(),
+                Option::<String>::None.into(),
             ])
             .to_string(SqliteQueryBuilder);
 
@@ -48,18 +197,15 @@ impl Db {
             id,
             name: category.name.clone(),
             icon: category.icon.clone(),
+            parent_id: category.parent_id.clone(),
             created_at: Local::now(),
+            archived_at: None,
         })
     }
 
-    pub fn get_category(&self, id: &str) -> Result<Category, rusqlite::Error> {
+    pub fn get_category(&self, id: &str) -> Result<Category, DbError> {
         let query = Query::select()
-            .columns([
-                Categories::Id,
-                Categories::Name,
-                Categories::Icon,
-                Categories::CreatedAt,
-            ])
+            .columns(category_columns())
             .from(Categories::Table)
             .and_where(Expr::col(Categories::Id).eq(id))
             .limit(1)
@@ -67,58 +213,28 @@ impl Db {
 
         let mut stmt = self.conn.prepare(&query)?;
 
-        let category = stmt.query_row((), |row| {
-            let created_at =
-                DateTime::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or(Local::now());
-
-            Ok(Category {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                icon: row.get(2)?,
-                created_at,
-            })
-        })?;
+        let category = stmt.query_row((), category_from_row)?;
 
         Ok(category)
     }
 
-    pub fn get_all_categories(&self) -> Result<Vec<Category>, rusqlite::Error> {
+    pub fn get_all_categories(&self) -> Result<Vec<Category>, DbError> {
         let query = Query::select()
-            .columns([
-                Categories::Id,
-                Categories::Name,
-                Categories::Icon,
-                Categories::CreatedAt,
-            ])
+            .columns(category_columns())
             .from(Categories::Table)
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
 
         Ok(stmt
-            .query_map((), |row| -> Result<Category, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or(Local::now());
-
-                Ok(Category {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    created_at,
-                })
-            })?
+            .query_map((), category_from_row)?
             .map(|category| category.unwrap())
             .collect::<Vec<Category>>())
     }
 
-    pub fn get_categories_by_name(&self, name: &str) -> Result<Vec<Category>, rusqlite::Error> {
+    pub fn get_categories_by_name(&self, name: &str) -> Result<Vec<Category>, DbError> {
         let query = Query::select()
-            .columns([
-                Categories::Id,
-                Categories::Name,
-                Categories::Icon,
-                Categories::CreatedAt,
-            ])
+            .columns(category_columns())
             .from(Categories::Table)
             .and_where(Expr::col(Categories::Name).like(format!("%{}%", name)))
             .to_string(SqliteQueryBuilder);
@@ -126,28 +242,203 @@ impl Db {
         let mut stmt = self.conn.prepare(&query)?;
 
         Ok(stmt
-            .query_map((), |row| -> Result<Category, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or(Local::now());
-
-                Ok(Category {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    icon: row.get(2)?,
-                    created_at,
-                })
-            })?
+            .query_map((), category_from_row)?
             .map(|category| category.unwrap())
             .collect::<Vec<Category>>())
     }
 
-    pub fn update_category(&self, id: &str, category: &Category) -> Result<(), rusqlite::Error> {
+    /// The category both sides of a transfer are filed under, created on first use.
+    pub fn get_transfer_category(&self) -> Result<Category, DbError> {
+        let existing = self
+            .get_categories_by_name(TRANSFER_CATEGORY)?
+            .into_iter()
+            .find(|category| category.name == TRANSFER_CATEGORY);
+
+        match existing {
+            Some(category) => Ok(category),
+            None => self.create_category(&CategoryRequest {
+                name: TRANSFER_CATEGORY.to_string(),
+                icon: "🔁".to_string(),
+                parent_id: None,
+            }),
+        }
+    }
+
+    /// The category split lines of deleted top level categories are moved to,
+    /// created on first use.
+    pub fn get_uncategorized_category(&self) -> Result<Category, DbError> {
+        let existing = self
+            .get_categories_by_name(UNCATEGORIZED_CATEGORY)?
+            .into_iter()
+            .find(|category| category.name == UNCATEGORIZED_CATEGORY);
+
+        match existing {
+            Some(category) => Ok(category),
+            None => self.create_category(&CategoryRequest {
+                name: UNCATEGORIZED_CATEGORY.to_string(),
+                icon: "❔".to_string(),
+                parent_id: None,
+            }),
+        }
+    }
+
+    /// Income and expenses per category converted into `currency`, excluding
+    /// transfers and void transactions, optionally only between two dates
+    /// (inclusive) and on accounts `holder_id` holds, weighted by their share of
+    /// each account. A split transaction counts each of its lines under that
+    /// line's category.
+    pub fn get_category_totals(
+        &self,
+        currency: &str,
+        between: Option<(NaiveDate, NaiveDate)>,
+        holder_id: Option<&str>,
+    ) -> Result<Vec<CategoryTotal>, DbError> {
+        let category = Func::coalesce([
+            Expr::col((TransactionSplits::Table, TransactionSplits::CategoryId)).into(),
+            Expr::col((Transactions::Table, Transactions::CategoryId)).into(),
+        ]);
+        let amount = Func::coalesce([
+            Expr::col((TransactionSplits::Table, TransactionSplits::Amount)).into(),
+            Expr::col((Transactions::Table, Transactions::Amount)).into(),
+        ]);
+        let is_credit = Expr::col((Transactions::Table, Transactions::Type)).eq("credit");
+
+        let mut query = Query::select();
+        query
+            .expr_as(category.clone(), Alias::new("category"))
+            .column((Accounts::Table, Accounts::Currency))
+            .column((Transactions::Table, Transactions::PostedOn))
+            .column((Transactions::Table, Transactions::AccountId))
+            .expr(Func::sum(
+                Expr::case(is_credit.clone(), amount.clone()).finally(0),
+            ))
+            .expr(Func::sum(Expr::case(is_credit, 0).finally(amount)))
+            .from(Transactions::Table)
+            .left_join(
+                TransactionSplits::Table,
+                Expr::col((TransactionSplits::Table, TransactionSplits::TransactionId))
+                    .equals((Transactions::Table, Transactions::Id)),
+            )
+            .left_join(
+                Accounts::Table,
+                Expr::col((Accounts::Table, Accounts::Id))
+                    .equals((Transactions::Table, Transactions::AccountId)),
+            )
+            .and_where(Expr::col((Transactions::Table, Transactions::TransferId)).is_null())
+            .and_where(not_void())
+            .add_group_by([category.into()])
+            .group_by_col((Accounts::Table, Accounts::Currency))
+            .group_by_col((Transactions::Table, Transactions::PostedOn))
+            .group_by_col((Transactions::Table, Transactions::AccountId));
+
+        if let Some((from, to)) = between {
+            query.and_where(
+                Expr::col((Transactions::Table, Transactions::PostedOn))
+                    .between(from.to_string(), to.to_string()),
+            );
+        }
+        if let Some(holder_id) = holder_id {
+            query.and_where(held_by(holder_id));
+        }
+
+        let mut stmt = self.conn.prepare(&query.to_string(SqliteQueryBuilder))?;
+        let rows = stmt
+            .query_map((), |row| {
+                let native = row.get::<_, String>(1)?;
+                let posted_on = NaiveDate::from_str(row.get::<_, String>(2)?.as_str())
+                    .unwrap_or(Local::now().date_naive());
+
+                Ok((
+                    row.get::<_, String>(0)?,
+                    posted_on,
+                    row.get::<_, String>(3)?,
+                    Money::new(row.get(4)?, &native),
+                    Money::new(row.get(5)?, &native),
+                ))
+            })?
+            .collect::<Result<Vec<(String, NaiveDate, String, Money, Money)>, rusqlite::Error>>()?;
+
+        let ownership = holder_id
+            .map(|user_id| self.get_ownership_by_user(user_id))
+            .transpose()?;
+
+        let mut totals: Vec<CategoryTotal> = vec![];
+        for (category_id, posted_on, account_id, income, expenses) in rows {
+            let mut income = self.convert(&income, currency, posted_on)?;
+            let mut expenses = self.convert(&expenses, currency, posted_on)?;
+            income.minor = owned_amount(ownership.as_ref(), &account_id, income.minor);
+            expenses.minor = owned_amount(ownership.as_ref(), &account_id, expenses.minor);
+
+            match totals
+                .iter_mut()
+                .find(|total| total.category_id == category_id)
+            {
+                Some(total) => {
+                    total.income.minor += income.minor;
+                    total.expenses.minor += expenses.minor;
+                }
+                None => totals.push(CategoryTotal {
+                    category_id,
+                    income,
+                    expenses,
+                }),
+            }
+        }
+
+        Ok(totals)
+    }
+
+    /// Like [`Db::get_category_totals`], but every category also includes the
+    /// income and expenses of all of its descendants.
+    pub fn get_category_rollups(
+        &self,
+        currency: &str,
+        between: Option<(NaiveDate, NaiveDate)>,
+        holder_id: Option<&str>,
+    ) -> Result<Vec<CategoryTotal>, DbError> {
+        let categories = self.get_all_categories()?;
+
+        let mut rollups: Vec<CategoryTotal> = vec![];
+        for total in self.get_category_totals(currency, between, holder_id)? {
+            for category_id in category_ancestors(&categories, &total.category_id) {
+                match rollups
+                    .iter_mut()
+                    .find(|rollup| rollup.category_id == category_id)
+                {
+                    Some(rollup) => {
+                        rollup.income.minor += total.income.minor;
+                        rollup.expenses.minor += total.expenses.minor;
+                    }
+                    None => rollups.push(CategoryTotal {
+                        category_id,
+                        income: total.income.clone(),
+                        expenses: total.expenses.clone(),
+                    }),
+                }
+            }
+        }
+
+        Ok(rollups)
+    }
+
+    pub fn update_category(&self, id: &str, category: &Category) -> Result<(), DbError> {
+        if let Some(parent_id) = &category.parent_id {
+            let categories = self.get_all_categories()?;
+            if category_ancestors(&categories, parent_id).contains(&id.to_string()) {
+                return Err(DbError::invalid(CategoryCycleError {
+                    category_id: id.to_string(),
+                    parent_id: parent_id.clone(),
+                }));
+            }
+        }
+
         let query = Query::update()
             .table(Categories::Table)
             .and_where(Expr::col(Categories::Id).eq(id))
             .values(vec![
                 (Categories::Name, category.name.clone().into()),
                 (Categories::Icon, category.icon.clone().into()),
+                (Categories::ParentId, category.parent_id.clone().into()),
             ])
             .to_string(SqliteQueryBuilder);
 
@@ -156,21 +447,84 @@ impl Db {
         Ok(())
     }
 
-    pub fn delete_category(&self, id: &str) -> Result<(), rusqlite::Error> {
+    /// Archives a category, or brings it back with `archived` set to false.
+    pub fn set_category_archived(&self, id: &str, archived: bool) -> Result<(), DbError> {
+        Ok(self.set_archived(
+            Categories::Table,
+            Categories::Id,
+            Categories::ArchivedAt,
+            id,
+            archived,
+        )?)
+    }
+
+    /// Deletes a category, moving its children up to its own parent. The
+    /// transactions, split lines and schedules filed under it are moved to its
+    /// parent as well, or to [`UNCATEGORIZED_CATEGORY`] for a top level
+    /// category, so no history is lost with it.
+    pub fn delete_category(&self, id: &str) -> Result<(), DbError> {
+        let category = self.get_category(id)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+
+        let filter = TransactionFilter {
+            category_id: Some(id.to_string()),
+            ..Default::default()
+        };
+        let has_transactions = !self.get_filtered_transactions(&filter)?.is_empty();
+        let has_schedules = self
+            .get_all_schedules()?
+            .iter()
+            .any(|schedule| schedule.category_id == id);
+        if has_transactions || has_schedules {
+            let replacement = match &category.parent_id {
+                Some(parent_id) => parent_id.clone(),
+                None => self.get_uncategorized_category()?.id,
+            };
+
+            self.refile_category_schedules(id, &replacement)?;
+            self.refile_category_splits(id, &replacement)?;
+            let refile = Query::update()
+                .table(Transactions::Table)
+                .value(Transactions::CategoryId, replacement)
+                .and_where(Expr::col(Transactions::CategoryId).eq(id))
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&refile, ())?;
+        }
+
+        let reparent = Query::update()
+            .table(Categories::Table)
+            .value(Categories::ParentId, category.parent_id)
+            .and_where(Expr::col(Categories::ParentId).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&reparent, ())?;
+        self.delete_category_budgets(id)?;
+        self.delete_category_envelopes(id)?;
+
         let query = Query::delete()
             .from_table(Categories::Table)
             .and_where(Expr::col(Categories::Id).eq(id))
             .to_string(SqliteQueryBuilder);
-
         self.conn.execute(&query, ())?;
 
-        Ok(())
+        Ok(tx.commit()?)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use crate::utils::get_test_db_path;
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            holder::AccountHolder,
+            schedule::{Frequency, ScheduleRequest},
+            split::SplitRequest,
+            transaction::{TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
+        utils::get_test_db_path,
+    };
 
     use super::*;
 
@@ -186,6 +540,7 @@ mod tests {
         let category_req = CategoryRequest {
             name: "Food & Dining".to_string(),
             icon: "🍽️".to_string(),
+            parent_id: None,
         };
 
         let created_category = db.create_category(&category_req).unwrap();
@@ -202,6 +557,7 @@ mod tests {
         let category_req2 = CategoryRequest {
             name: "Transportation".to_string(),
             icon: "🚗".to_string(),
+            parent_id: None,
         };
         let _created_category2 = db.create_category(&category_req2).unwrap();
 
@@ -235,6 +591,23 @@ mod tests {
         assert_eq!(updated_retrieved.icon, "🍴");
 
         // Delete category
+        db.set_category_archived(&created_category.id, true)
+            .unwrap();
+        assert!(
+            db.get_category(&created_category.id)
+                .unwrap()
+                .archived_at
+                .is_some()
+        );
+        db.set_category_archived(&created_category.id, false)
+            .unwrap();
+        assert!(
+            db.get_category(&created_category.id)
+                .unwrap()
+                .archived_at
+                .is_none()
+        );
+
         let delete_result = db.delete_category(&created_category.id);
         assert!(delete_result.is_ok());
 
@@ -261,18 +634,22 @@ mod tests {
             CategoryRequest {
                 name: "Food".to_string(),
                 icon: "🍎".to_string(),
+                parent_id: None,
             },
             CategoryRequest {
                 name: "Fast Food".to_string(),
                 icon: "🍟".to_string(),
+                parent_id: None,
             },
             CategoryRequest {
                 name: "Food Delivery".to_string(),
                 icon: "🚚".to_string(),
+                parent_id: None,
             },
             CategoryRequest {
                 name: "Pet Food".to_string(),
                 icon: "🐕".to_string(),
+                parent_id: None,
             },
         ];
 
@@ -301,4 +678,248 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_file("test_search.db");
     }
+
+    #[test]
+    fn test_category_hierarchy() {
+        let _ = std::fs::remove_file(get_test_db_path("category_hierarchy"));
+
+        let db = Db::new(get_test_db_path("category_hierarchy").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let category = |name: &str, parent_id: Option<&str>| {
+            db.create_category(&CategoryRequest {
+                name: name.to_string(),
+                icon: "🏷️".to_string(),
+                parent_id: parent_id.map(str::to_string),
+            })
+            .unwrap()
+        };
+        let food = category("Food", None);
+        let groceries = category("Groceries", Some(&food.id));
+        let restaurants = category("Restaurants", Some(&food.id));
+        let fast_food = category("Fast Food", Some(&restaurants.id));
+        let rent = category("Rent", None);
+
+        let categories = db.get_all_categories().unwrap();
+        assert_eq!(
+            category_path(&categories, &fast_food.id),
+            "Food > Restaurants > Fast Food"
+        );
+
+        let tree = category_tree(&categories)
+            .into_iter()
+            .map(|(depth, category)| (depth, category.name))
+            .collect::<Vec<(usize, String)>>();
+        assert_eq!(
+            tree,
+            vec![
+                (0, "Food".to_string()),
+                (1, "Groceries".to_string()),
+                (1, "Restaurants".to_string()),
+                (2, "Fast Food".to_string()),
+                (0, "Rent".to_string()),
+            ]
+        );
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Checking".to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(0, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        for (category_id, minor) in [
+            (&groceries.id, 4000),
+            (&fast_food.id, 1500),
+            (&rent.id, 90000),
+        ] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(minor, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: String::new(),
+                category_id: category_id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        // Parents include everything spent in their children
+        let rollups = db
+            .get_category_rollups(DEFAULT_CURRENCY, None, None)
+            .unwrap();
+        let expenses_of = |category_id: &str| {
+            rollups
+                .iter()
+                .find(|rollup| rollup.category_id == category_id)
+                .map(|rollup| rollup.expenses.minor)
+        };
+        assert_eq!(expenses_of(&food.id), Some(5500));
+        assert_eq!(expenses_of(&restaurants.id), Some(1500));
+        assert_eq!(expenses_of(&groceries.id), Some(4000));
+        assert_eq!(expenses_of(&rent.id), Some(90000));
+
+        // A category cannot be moved underneath itself
+        let mut moved = food.clone();
+        moved.parent_id = Some(fast_food.id.clone());
+        assert!(db.update_category(&food.id, &moved).is_err());
+
+        let schedule = db
+            .create_schedule(&ScheduleRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(2500, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: "Pizza night".to_string(),
+                category_id: restaurants.id.clone(),
+                payee_id: None,
+                frequency: Frequency::Weekly,
+                interval: 1,
+                start_date: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                end_date: None,
+                skips: vec![],
+            })
+            .unwrap();
+
+        // Deleting a category hands its children and schedules to its parent
+        db.delete_category(&restaurants.id).unwrap();
+        assert_eq!(
+            db.get_category(&fast_food.id).unwrap().parent_id,
+            Some(food.id.clone())
+        );
+        assert_eq!(db.get_schedule(&schedule.id).unwrap().category_id, food.id);
+    }
+
+    #[test]
+    fn test_delete_category_with_transactions() {
+        let _ = std::fs::remove_file(get_test_db_path("category_delete"));
+
+        let db = Db::new(get_test_db_path("category_delete").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = |name: &str, account_type: AccountType, opening: i64| {
+            db.create_account(&AccountRequest {
+                name: name.to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type,
+                opening_balance: Money::new(opening, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap()
+        };
+        let checking = account("Checking", AccountType::Checking, 15000);
+        let card = account("Card", AccountType::CreditCard, 0);
+        let dining = db
+            .create_category(&CategoryRequest {
+                name: "Dining".to_string(),
+                icon: "🍽️".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        for (account_id, minor) in [(&checking.id, 2000), (&card.id, 30000)] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account_id.clone(),
+                amount: Money::new(minor, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: String::new(),
+                category_id: dining.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        // Only one line of this transaction is filed under the category
+        let groceries = db
+            .create_category(&CategoryRequest {
+                name: "Groceries".to_string(),
+                icon: "🛒".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        let split = |category_id: &str| SplitRequest {
+            category_id: category_id.to_string(),
+            amount: Money::new(1000, DEFAULT_CURRENCY),
+            memo: String::new(),
+        };
+        let shared = db
+            .create_transaction(&TransactionRequest {
+                account_id: checking.id.clone(),
+                amount: Money::new(2000, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: String::new(),
+                category_id: groceries.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![split(&groceries.id), split(&dining.id)],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        assert_eq!(db.get_account(&checking.id).unwrap().balance.minor, 11000);
+        assert_eq!(db.get_account(&card.id).unwrap().balance.minor, -30000);
+
+        // Split lines are never dropped on their own, which would leave the
+        // transaction with lines that no longer add up
+        let delete = format!("DELETE FROM categories WHERE id = '{}'", dining.id);
+        assert!(db.conn.execute(&delete, ()).is_err());
+        assert_eq!(db.get_transaction_splits(&shared.id).unwrap().len(), 2);
+        let filter = TransactionFilter {
+            category_id: Some(dining.id.clone()),
+            ..Default::default()
+        };
+        assert_eq!(db.get_filtered_transactions(&filter).unwrap().len(), 3);
+
+        // Nothing is deleted with the category, so the balances stay as they were
+        db.delete_category(&dining.id).unwrap();
+        assert_eq!(db.get_all_transactions().unwrap().len(), 3);
+        assert_eq!(db.get_account(&checking.id).unwrap().balance.minor, 11000);
+        assert_eq!(db.get_account(&card.id).unwrap().balance.minor, -30000);
+
+        // What was filed under the deleted top level category is uncategorized now
+        let uncategorized = db.get_uncategorized_category().unwrap();
+        let filter = TransactionFilter {
+            category_id: Some(uncategorized.id.clone()),
+            ..Default::default()
+        };
+        assert_eq!(db.get_filtered_transactions(&filter).unwrap().len(), 3);
+        let mut categories = db
+            .get_transaction_splits(&shared.id)
+            .unwrap()
+            .into_iter()
+            .map(|split| (split.category_id, split.amount.minor))
+            .collect::<Vec<(String, i64)>>();
+        categories.sort();
+        let mut expected = vec![(groceries.id.clone(), 1000), (uncategorized.id, 1000)];
+        expected.sort();
+        assert_eq!(categories, expected);
+    }
 }
@@ -0,0 +1,617 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, Months, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Cond, Expr, Order, Query, SqliteQueryBuilder};
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{
+    EnvelopeAssignments, EnvelopeMoves,
+    budget::{month_end, month_start},
+};
+
+/// Money given to the envelope of one category in one month.
+#[derive(Debug, Clone)]
+pub struct EnvelopeAssignment {
+    pub category_id: String,
+    /// The first day of the month the money was assigned in
+    pub month: NaiveDate,
+    pub amount: Money,
+}
+
+pub struct EnvelopeAssignmentRequest {
+    pub category_id: String,
+    pub month: NaiveDate,
+    pub amount: Money,
+}
+
+/// Money taken out of one envelope and put into another.
+#[derive(Debug, Clone)]
+pub struct EnvelopeMove {
+    pub id: String,
+    pub from_category_id: String,
+    pub to_category_id: String,
+    pub month: NaiveDate,
+    pub amount: Money,
+    pub created_at: DateTime<Local>,
+}
+
+pub struct EnvelopeMoveRequest {
+    pub from_category_id: String,
+    pub to_category_id: String,
+    pub month: NaiveDate,
+    pub amount: Money,
+}
+
+/// Raised when money would be moved from an envelope into itself.
+#[derive(Debug)]
+pub struct SameEnvelopeError {
+    pub category_id: String,
+}
+
+impl std::fmt::Display for SameEnvelopeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Cannot move money from the envelope of category {} into itself",
+            self.category_id
+        )
+    }
+}
+
+impl std::error::Error for SameEnvelopeError {}
+
+/// How the envelope of a category stands in a month.
+#[derive(Debug, Clone)]
+pub struct EnvelopeStatus {
+    pub category_id: String,
+    /// What the envelope held at the end of the month before, which is
+    /// negative when it was overspent
+    pub carried: Money,
+    pub assigned: Money,
+    /// Moved in minus moved out
+    pub moved: Money,
+    /// Expenses posted directly against the category
+    pub spent: Money,
+}
+
+impl EnvelopeStatus {
+    /// What is left in the envelope at the end of the month.
+    pub fn available(&self) -> Money {
+        Money::new(
+            self.carried.minor + self.assigned.minor + self.moved.minor - self.spent.minor,
+            &self.assigned.currency,
+        )
+    }
+
+    pub fn is_overspent(&self) -> bool {
+        self.available().minor < 0
+    }
+}
+
+/// The envelopes of a month and the pool they are filled from.
+#[derive(Debug, Clone)]
+pub struct EnvelopeReport {
+    pub month: NaiveDate,
+    /// Income received up to the end of the month
+    pub income: Money,
+    /// Assigned to envelopes up to the end of the month
+    pub assigned: Money,
+    pub envelopes: Vec<EnvelopeStatus>,
+}
+
+impl EnvelopeReport {
+    /// Income that has not been given to an envelope yet. Zero-based budgeting
+    /// aims to bring this to exactly zero.
+    pub fn to_be_assigned(&self) -> Money {
+        Money::new(
+            self.income.minor - self.assigned.minor,
+            &self.income.currency,
+        )
+    }
+}
+
+fn assignment_columns() -> [EnvelopeAssignments; 5] {
+    [
+        EnvelopeAssignments::CategoryId,
+        EnvelopeAssignments::Month,
+        EnvelopeAssignments::Amount,
+        EnvelopeAssignments::Currency,
+        EnvelopeAssignments::CreatedAt,
+    ]
+}
+
+fn assignment_from_row(row: &Row) -> Result<EnvelopeAssignment, rusqlite::Error> {
+    let month =
+        NaiveDate::from_str(row.get::<_, String>(1)?.as_str()).unwrap_or(Local::now().date_naive());
+
+    Ok(EnvelopeAssignment {
+        category_id: row.get(0)?,
+        month,
+        amount: Money::new(row.get(2)?, &row.get::<_, String>(3)?),
+    })
+}
+
+fn move_columns() -> [EnvelopeMoves; 7] {
+    [
+        EnvelopeMoves::Id,
+        EnvelopeMoves::FromCategoryId,
+        EnvelopeMoves::ToCategoryId,
+        EnvelopeMoves::Month,
+        EnvelopeMoves::Amount,
+        EnvelopeMoves::Currency,
+        EnvelopeMoves::CreatedAt,
+    ]
+}
+
+fn move_from_row(row: &Row) -> Result<EnvelopeMove, rusqlite::Error> {
+    let month =
+        NaiveDate::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or(Local::now().date_naive());
+    let created_at = DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
+
+    Ok(EnvelopeMove {
+        id: row.get(0)?,
+        from_category_id: row.get(1)?,
+        to_category_id: row.get(2)?,
+        month,
+        amount: Money::new(row.get(4)?, &row.get::<_, String>(5)?),
+        created_at,
+    })
+}
+
+impl Db {
+    /// Sets how much is assigned to the envelope of a category in a month,
+    /// replacing any earlier assignment.
+    pub fn assign_envelope(
+        &self,
+        assignment: &EnvelopeAssignmentRequest,
+    ) -> Result<EnvelopeAssignment, DbError> {
+        let month = month_start(assignment.month);
+
+        let query = Query::insert()
+            .into_table(EnvelopeAssignments::Table)
+            .columns(assignment_columns())
+            .values_panic(vec![
+                assignment.category_id.clone().into(),
+                month.to_string().into(),
+                assignment.amount.minor.into(),
+                assignment.amount.currency.clone().into(),
+                Local::now().to_string().into(),
+            ])
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    EnvelopeAssignments::CategoryId,
+                    EnvelopeAssignments::Month,
+                ])
+                .update_columns([EnvelopeAssignments::Amount, EnvelopeAssignments::Currency])
+                .to_owned(),
+            )
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(EnvelopeAssignment {
+            category_id: assignment.category_id.clone(),
+            month,
+            amount: assignment.amount.clone(),
+        })
+    }
+
+    pub fn get_envelope_assignment(
+        &self,
+        category_id: &str,
+        month: NaiveDate,
+    ) -> Result<Option<EnvelopeAssignment>, DbError> {
+        let query = Query::select()
+            .columns(assignment_columns())
+            .from(EnvelopeAssignments::Table)
+            .and_where(Expr::col(EnvelopeAssignments::CategoryId).eq(category_id))
+            .and_where(Expr::col(EnvelopeAssignments::Month).eq(month_start(month).to_string()))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query_map((), assignment_from_row)?;
+
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Assignments up to the end of `until`, or all of them, earliest first.
+    pub fn get_envelope_assignments(
+        &self,
+        until: Option<NaiveDate>,
+    ) -> Result<Vec<EnvelopeAssignment>, DbError> {
+        let mut query = Query::select();
+        query
+            .columns(assignment_columns())
+            .from(EnvelopeAssignments::Table)
+            .order_by(EnvelopeAssignments::Month, Order::Asc);
+
+        if let Some(until) = until {
+            query.and_where(
+                Expr::col(EnvelopeAssignments::Month).lte(month_start(until).to_string()),
+            );
+        }
+
+        let mut stmt = self.conn.prepare(&query.to_string(SqliteQueryBuilder))?;
+
+        Ok(stmt
+            .query_map((), assignment_from_row)?
+            .map(|assignment| assignment.unwrap())
+            .collect::<Vec<EnvelopeAssignment>>())
+    }
+
+    /// Moves money from one envelope into another within a month. The source
+    /// envelope may go negative, in which case it carries the debt forward.
+    pub fn move_envelope_money(
+        &self,
+        envelope_move: &EnvelopeMoveRequest,
+    ) -> Result<EnvelopeMove, DbError> {
+        if envelope_move.from_category_id == envelope_move.to_category_id {
+            return Err(DbError::invalid(SameEnvelopeError {
+                category_id: envelope_move.from_category_id.clone(),
+            }));
+        }
+
+        let id = random_hash(8);
+        let month = month_start(envelope_move.month);
+        let query = Query::insert()
+            .into_table(EnvelopeMoves::Table)
+            .columns(move_columns())
+            .values_panic(vec![
+                id.clone().into(),
+                envelope_move.from_category_id.clone().into(),
+                envelope_move.to_category_id.clone().into(),
+                month.to_string().into(),
+                envelope_move.amount.minor.into(),
+                envelope_move.amount.currency.clone().into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(EnvelopeMove {
+            id,
+            from_category_id: envelope_move.from_category_id.clone(),
+            to_category_id: envelope_move.to_category_id.clone(),
+            month,
+            amount: envelope_move.amount.clone(),
+            created_at: Local::now(),
+        })
+    }
+
+    /// Moves up to the end of `until`, or all of them, earliest first.
+    pub fn get_envelope_moves(
+        &self,
+        until: Option<NaiveDate>,
+    ) -> Result<Vec<EnvelopeMove>, DbError> {
+        let mut query = Query::select();
+        query
+            .columns(move_columns())
+            .from(EnvelopeMoves::Table)
+            .order_by(EnvelopeMoves::Month, Order::Asc)
+            .order_by(EnvelopeMoves::CreatedAt, Order::Asc);
+
+        if let Some(until) = until {
+            query.and_where(Expr::col(EnvelopeMoves::Month).lte(month_start(until).to_string()));
+        }
+
+        let mut stmt = self.conn.prepare(&query.to_string(SqliteQueryBuilder))?;
+
+        Ok(stmt
+            .query_map((), move_from_row)?
+            .map(|envelope_move| envelope_move.unwrap())
+            .collect::<Vec<EnvelopeMove>>())
+    }
+
+    pub(super) fn delete_category_envelopes(&self, category_id: &str) -> Result<(), DbError> {
+        let assignments = Query::delete()
+            .from_table(EnvelopeAssignments::Table)
+            .and_where(Expr::col(EnvelopeAssignments::CategoryId).eq(category_id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&assignments, ())?;
+
+        let moves = Query::delete()
+            .from_table(EnvelopeMoves::Table)
+            .cond_where(
+                Cond::any()
+                    .add(Expr::col(EnvelopeMoves::FromCategoryId).eq(category_id))
+                    .add(Expr::col(EnvelopeMoves::ToCategoryId).eq(category_id)),
+            )
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&moves, ())?;
+
+        Ok(())
+    }
+
+    /// The pool and every envelope in `month`, converted into `currency`.
+    ///
+    /// Every credit that is not a transfer feeds the pool, and every debit is
+    /// taken from the envelope of its own category, so refunds go back to the
+    /// pool rather than to an envelope. An envelope opens in the first month it
+    /// is assigned or moved money, and from then on carries whatever it holds,
+    /// including a negative balance, into the next month.
+    pub fn get_envelope_report(
+        &self,
+        month: NaiveDate,
+        currency: &str,
+    ) -> Result<EnvelopeReport, DbError> {
+        let month = month_start(month);
+        let assignments = self.get_envelope_assignments(Some(month))?;
+        let moves = self.get_envelope_moves(Some(month))?;
+
+        let income = self
+            .get_category_totals(currency, Some((NaiveDate::MIN, month_end(month))), None)?
+            .iter()
+            .map(|total| total.income.minor)
+            .sum();
+
+        let mut assigned = 0;
+        for assignment in &assignments {
+            assigned += self
+                .convert(&assignment.amount, currency, assignment.month)?
+                .minor;
+        }
+
+        let first_month = assignments
+            .iter()
+            .map(|assignment| assignment.month)
+            .chain(moves.iter().map(|envelope_move| envelope_move.month))
+            .min();
+
+        let mut envelopes: Vec<EnvelopeStatus> = vec![];
+        let mut current = first_month;
+        while let Some(current_month) = current.filter(|current_month| *current_month <= month) {
+            let totals = self.get_category_totals(
+                currency,
+                Some((current_month, month_end(current_month))),
+                None,
+            )?;
+
+            // Open the envelopes that start this month
+            let opened = assignments
+                .iter()
+                .filter(|assignment| assignment.month == current_month)
+                .map(|assignment| &assignment.category_id)
+                .chain(
+                    moves
+                        .iter()
+                        .filter(|envelope_move| envelope_move.month == current_month)
+                        .flat_map(|envelope_move| {
+                            [
+                                &envelope_move.from_category_id,
+                                &envelope_move.to_category_id,
+                            ]
+                        }),
+                );
+            for category_id in opened {
+                if !envelopes
+                    .iter()
+                    .any(|envelope| &envelope.category_id == category_id)
+                {
+                    envelopes.push(EnvelopeStatus {
+                        category_id: category_id.clone(),
+                        carried: Money::new(0, currency),
+                        assigned: Money::new(0, currency),
+                        moved: Money::new(0, currency),
+                        spent: Money::new(0, currency),
+                    });
+                }
+            }
+
+            for envelope in envelopes.iter_mut() {
+                let mut status = EnvelopeStatus {
+                    category_id: envelope.category_id.clone(),
+                    carried: envelope.available(),
+                    assigned: Money::new(0, currency),
+                    moved: Money::new(0, currency),
+                    spent: Money::new(0, currency),
+                };
+
+                for assignment in assignments.iter().filter(|assignment| {
+                    assignment.month == current_month
+                        && assignment.category_id == status.category_id
+                }) {
+                    status.assigned.minor += self
+                        .convert(&assignment.amount, currency, current_month)?
+                        .minor;
+                }
+                for envelope_move in moves
+                    .iter()
+                    .filter(|envelope_move| envelope_move.month == current_month)
+                {
+                    let amount = self
+                        .convert(&envelope_move.amount, currency, current_month)?
+                        .minor;
+                    if envelope_move.to_category_id == status.category_id {
+                        status.moved.minor += amount;
+                    }
+                    if envelope_move.from_category_id == status.category_id {
+                        status.moved.minor -= amount;
+                    }
+                }
+                status.spent.minor = totals
+                    .iter()
+                    .find(|total| total.category_id == status.category_id)
+                    .map_or(0, |total| total.expenses.minor);
+
+                *envelope = status;
+            }
+
+            current = current_month.checked_add_months(Months::new(1));
+        }
+
+        Ok(EnvelopeReport {
+            month,
+            income: Money::new(income, currency),
+            assigned: Money::new(assigned, currency),
+            envelopes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{
+            budget::parse_month,
+            category::{Category, CategoryRequest},
+            transaction::{TransactionRequest, TransactionStatus},
+        },
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db},
+    };
+
+    use super::*;
+
+    /// Salary, Groceries and Rent categories. January brings in 3,000.00 and
+    /// a 20.00 refund, spends 500.00 on groceries and assigns 400.00 to them
+    /// and 1,500.00 to rent.
+    fn setup(name: &str) -> (Db, Category, Category) {
+        let db = get_test_db(name);
+        let user = create_test_user(&db, "John Doe");
+        let account = create_test_account(&db, &user.id, "Checking", 0);
+        let mut categories = vec![];
+        for (name, icon) in [("Salary", "💼"), ("Groceries", "🛒"), ("Rent", "🏠")] {
+            categories.push(
+                db.create_category(&CategoryRequest {
+                    name: name.to_string(),
+                    icon: icon.to_string(),
+                    parent_id: None,
+                })
+                .unwrap(),
+            );
+        }
+        let (salary, groceries, rent) = (&categories[0], &categories[1], &categories[2]);
+
+        for (amount, transaction_type, category_id, posted_on) in [
+            (300000, "credit", &salary.id, "2024-01-01"),
+            (50000, "debit", &groceries.id, "2024-01-20"),
+            (2000, "credit", &groceries.id, "2024-01-22"),
+        ] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(amount, DEFAULT_CURRENCY),
+                transaction_type: transaction_type.to_string(),
+                description: "Envelope test".to_string(),
+                category_id: category_id.clone(),
+                posted_on: NaiveDate::from_str(posted_on).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        for (category_id, amount) in [(&groceries.id, 40000), (&rent.id, 150000)] {
+            db.assign_envelope(&assignment(category_id, "2024-01", amount))
+                .unwrap();
+        }
+
+        (db, groceries.clone(), rent.clone())
+    }
+
+    fn assignment(category_id: &str, month: &str, amount: i64) -> EnvelopeAssignmentRequest {
+        EnvelopeAssignmentRequest {
+            category_id: category_id.to_string(),
+            month: parse_month(month).unwrap(),
+            amount: Money::new(amount, DEFAULT_CURRENCY),
+        }
+    }
+
+    fn envelope<'a>(report: &'a EnvelopeReport, category: &Category) -> &'a EnvelopeStatus {
+        report
+            .envelopes
+            .iter()
+            .find(|envelope| envelope.category_id == category.id)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_assign_envelope() {
+        let (db, groceries, _) = setup("envelope_assign");
+        let january = parse_month("2024-01").unwrap();
+
+        // Assigning again replaces the amount
+        db.assign_envelope(&assignment(&groceries.id, "2024-01", 30000))
+            .unwrap();
+        assert_eq!(
+            db.get_envelope_assignment(&groceries.id, january)
+                .unwrap()
+                .unwrap()
+                .amount
+                .minor,
+            30000
+        );
+        assert_eq!(db.get_envelope_assignments(Some(january)).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_envelope_report() {
+        let (db, groceries, _) = setup("envelope_report");
+        let january = parse_month("2024-01").unwrap();
+
+        // The refund goes back to the pool, not to the envelope
+        let report = db.get_envelope_report(january, DEFAULT_CURRENCY).unwrap();
+        assert_eq!(report.income.minor, 302000);
+        assert_eq!(report.assigned.minor, 190000);
+        assert_eq!(report.to_be_assigned().minor, 112000);
+        let groceries_status = envelope(&report, &groceries);
+        assert_eq!(groceries_status.available().minor, -10000);
+        assert!(groceries_status.is_overspent());
+    }
+
+    #[test]
+    fn test_move_envelope_money() {
+        let (db, groceries, rent) = setup("envelope_move");
+        let february = parse_month("2024-02").unwrap();
+        let move_to = |category: &Category| EnvelopeMoveRequest {
+            from_category_id: rent.id.clone(),
+            to_category_id: category.id.clone(),
+            month: february,
+            amount: Money::new(5000, DEFAULT_CURRENCY),
+        };
+
+        assert!(db.move_envelope_money(&move_to(&rent)).is_err());
+        db.move_envelope_money(&move_to(&groceries)).unwrap();
+        db.assign_envelope(&assignment(&groceries.id, "2024-02", 40000))
+            .unwrap();
+
+        // The overspent envelope carries its debt into February
+        let report = db.get_envelope_report(february, DEFAULT_CURRENCY).unwrap();
+        assert_eq!(report.to_be_assigned().minor, 72000);
+        let groceries_status = envelope(&report, &groceries);
+        assert_eq!(groceries_status.carried.minor, -10000);
+        assert_eq!(groceries_status.moved.minor, 5000);
+        assert_eq!(groceries_status.available().minor, 35000);
+        let rent_status = envelope(&report, &rent);
+        assert_eq!(rent_status.carried.minor, 150000);
+        assert_eq!(rent_status.available().minor, 145000);
+    }
+
+    #[test]
+    fn test_delete_envelope_category() {
+        let (db, groceries, rent) = setup("envelope_category_delete");
+
+        db.move_envelope_money(&EnvelopeMoveRequest {
+            from_category_id: rent.id.clone(),
+            to_category_id: groceries.id.clone(),
+            month: parse_month("2024-02").unwrap(),
+            amount: Money::new(5000, DEFAULT_CURRENCY),
+        })
+        .unwrap();
+
+        // Envelopes go away with their category
+        db.delete_category(&groceries.id).unwrap();
+        assert_eq!(db.get_envelope_assignments(None).unwrap().len(), 1);
+        assert!(db.get_envelope_moves(None).unwrap().is_empty());
+    }
+}
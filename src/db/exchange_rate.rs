@@ -0,0 +1,208 @@
+use std::str::FromStr;
+
+use chrono::{Local, NaiveDate};
+use sea_query::{Expr, Order, Query, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+};
+
+use super::ExchangeRates;
+
+/// How many units of `to_currency` one unit of `from_currency` bought on `date`.
+#[derive(Debug, Clone, Tabled)]
+pub struct ExchangeRate {
+    pub date: NaiveDate,
+    pub from_currency: String,
+    pub to_currency: String,
+    pub rate: f64,
+}
+
+/// Raised when an amount has to be converted but no rate is known for the pair.
+#[derive(Debug)]
+pub struct MissingRateError {
+    pub from_currency: String,
+    pub to_currency: String,
+    pub on: NaiveDate,
+}
+
+impl std::fmt::Display for MissingRateError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "No exchange rate from {} to {} on or before {}",
+            self.from_currency, self.to_currency, self.on
+        )
+    }
+}
+
+impl std::error::Error for MissingRateError {}
+
+impl Db {
+    /// Stores rates, replacing any already recorded for the same day and pair.
+    pub fn set_exchange_rates(&self, rates: &[ExchangeRate]) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        for rate in rates {
+            let delete = Query::delete()
+                .from_table(ExchangeRates::Table)
+                .and_where(Expr::col(ExchangeRates::Date).eq(rate.date.to_string()))
+                .and_where(Expr::col(ExchangeRates::FromCurrency).eq(rate.from_currency.as_str()))
+                .and_where(Expr::col(ExchangeRates::ToCurrency).eq(rate.to_currency.as_str()))
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&delete, ())?;
+
+            let insert = Query::insert()
+                .into_table(ExchangeRates::Table)
+                .columns([
+                    ExchangeRates::Date,
+                    ExchangeRates::FromCurrency,
+                    ExchangeRates::ToCurrency,
+                    ExchangeRates::Rate,
+                ])
+                .values_panic(vec![
+                    rate.date.to_string().into(),
+                    rate.from_currency.clone().into(),
+                    rate.to_currency.clone().into(),
+                    rate.rate.into(),
+                ])
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&insert, ())?;
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    pub fn get_all_exchange_rates(&self) -> Result<Vec<ExchangeRate>, DbError> {
+        let query = Query::select()
+            .columns([
+                ExchangeRates::Date,
+                ExchangeRates::FromCurrency,
+                ExchangeRates::ToCurrency,
+                ExchangeRates::Rate,
+            ])
+            .from(ExchangeRates::Table)
+            .order_by(ExchangeRates::Date, Order::Asc)
+            .order_by(ExchangeRates::FromCurrency, Order::Asc)
+            .order_by(ExchangeRates::ToCurrency, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), |row| {
+                let date = NaiveDate::from_str(row.get::<_, String>(0)?.as_str())
+                    .unwrap_or(Local::now().date_naive());
+
+                Ok(ExchangeRate {
+                    date,
+                    from_currency: row.get(1)?,
+                    to_currency: row.get(2)?,
+                    rate: row.get(3)?,
+                })
+            })?
+            .map(|rate| rate.unwrap())
+            .collect::<Vec<ExchangeRate>>())
+    }
+
+    /// The most recent rate for the pair on or before `on`, falling back to the
+    /// inverse of the opposite pair.
+    pub fn get_exchange_rate(
+        &self,
+        from_currency: &str,
+        to_currency: &str,
+        on: NaiveDate,
+    ) -> Result<Option<f64>, DbError> {
+        if from_currency == to_currency {
+            return Ok(Some(1.0));
+        }
+
+        let latest_rate = |from: &str, to: &str| -> Result<Option<f64>, DbError> {
+            let query = Query::select()
+                .column(ExchangeRates::Rate)
+                .from(ExchangeRates::Table)
+                .and_where(Expr::col(ExchangeRates::FromCurrency).eq(from))
+                .and_where(Expr::col(ExchangeRates::ToCurrency).eq(to))
+                .and_where(Expr::col(ExchangeRates::Date).lte(on.to_string()))
+                .order_by(ExchangeRates::Date, Order::Desc)
+                .limit(1)
+                .to_string(SqliteQueryBuilder);
+
+            let mut stmt = self.conn.prepare(&query)?;
+            let mut rows = stmt.query_map((), |row| row.get::<_, f64>(0))?;
+
+            Ok(rows.next().transpose()?)
+        };
+
+        if let Some(rate) = latest_rate(from_currency, to_currency)? {
+            return Ok(Some(rate));
+        }
+
+        Ok(latest_rate(to_currency, from_currency)?
+            .filter(|rate| *rate != 0.0)
+            .map(|rate| 1.0 / rate))
+    }
+
+    /// Converts `money` into `currency` using the rate in effect on `on`.
+    pub fn convert(&self, money: &Money, currency: &str, on: NaiveDate) -> Result<Money, DbError> {
+        match self.get_exchange_rate(&money.currency, currency, on)? {
+            Some(rate) => Ok(money.exchange(rate, currency)),
+            None => Err(DbError::invalid(MissingRateError {
+                from_currency: money.currency.clone(),
+                to_currency: currency.to_string(),
+                on,
+            })),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::get_test_db_path;
+
+    use super::*;
+
+    #[test]
+    fn test_exchange_rates() {
+        let _ = std::fs::remove_file(get_test_db_path("exchange_rate"));
+
+        let db = Db::new(get_test_db_path("exchange_rate").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let rate = |date: NaiveDate, from: &str, to: &str, rate: f64| ExchangeRate {
+            date,
+            from_currency: from.to_string(),
+            to_currency: to.to_string(),
+            rate,
+        };
+
+        db.set_exchange_rates(&[
+            rate(day(1), "EUR", "USD", 1.10),
+            rate(day(10), "EUR", "USD", 1.20),
+            rate(day(1), "USD", "INR", 80.0),
+        ])
+        .unwrap();
+
+        // Setting the same day and pair again replaces the old rate
+        db.set_exchange_rates(&[rate(day(10), "EUR", "USD", 1.25)])
+            .unwrap();
+        assert_eq!(db.get_all_exchange_rates().unwrap().len(), 3);
+
+        let euros = Money::new(1000, "EUR");
+        assert_eq!(db.convert(&euros, "USD", day(5)).unwrap().minor, 1100);
+        assert_eq!(db.convert(&euros, "USD", day(15)).unwrap().minor, 1250);
+        assert_eq!(db.convert(&euros, "EUR", day(15)).unwrap().minor, 1000);
+
+        // The inverse pair is used when only the opposite direction is known
+        let rupees = Money::new(8000, "INR");
+        assert_eq!(db.convert(&rupees, "USD", day(2)).unwrap().minor, 100);
+
+        // Nothing is known before the first rate was recorded, or for unrelated pairs
+        let before = NaiveDate::from_ymd_opt(2024, 2, 28).unwrap();
+        assert!(db.convert(&euros, "USD", before).is_err());
+        assert!(db.convert(&euros, "INR", day(5)).is_err());
+    }
+}
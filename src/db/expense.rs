@@ -0,0 +1,836 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use chrono::{Local, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SqliteQueryBuilder};
+
+use crate::{
+    db::{Db, DbError, holder::format_ownership},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{ExpenseShares, Settlements, SharedExpenses, transaction::TransferRequest};
+
+/// How the amount of a shared expense is divided between the people in it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShareMethod {
+    /// Everyone pays the same, leftover cents go to the first people
+    Equal,
+    /// Everyone pays the amount given, which must add up to the expense
+    Exact,
+    /// Everyone pays a percentage, which must add up to 100
+    Percentage,
+}
+
+impl ShareMethod {
+    pub const ALL: [ShareMethod; 3] = [
+        ShareMethod::Equal,
+        ShareMethod::Exact,
+        ShareMethod::Percentage,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShareMethod::Equal => "equal",
+            ShareMethod::Exact => "exact",
+            ShareMethod::Percentage => "percentage",
+        }
+    }
+}
+
+impl fmt::Display for ShareMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for ShareMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        ShareMethod::ALL
+            .into_iter()
+            .find(|method| method.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("'{}' is not a share method", s))
+    }
+}
+
+/// What one person owes of a shared expense.
+#[derive(Debug, Clone)]
+pub struct Share {
+    pub user_id: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone)]
+pub struct SharedExpense {
+    pub id: String,
+    /// The user who paid the whole amount
+    pub paid_by: String,
+    pub description: String,
+    pub amount: Money,
+    pub method: ShareMethod,
+    pub spent_on: NaiveDate,
+    pub shares: Vec<Share>,
+}
+
+/// Someone taking part in a shared expense. The value is ignored for equal
+/// shares, is the amount in minor units for exact shares and the percentage in
+/// hundredths for percentage shares, so `3333` is 33.33%.
+#[derive(Debug, Clone)]
+pub struct ShareRequest {
+    pub user_id: String,
+    pub value: i64,
+}
+
+pub struct SharedExpenseRequest {
+    pub paid_by: String,
+    pub description: String,
+    pub amount: Money,
+    pub method: ShareMethod,
+    pub spent_on: NaiveDate,
+    pub shares: Vec<ShareRequest>,
+}
+
+/// Raised when the shares of an expense cannot be worked out.
+#[derive(Debug)]
+pub struct InvalidSharesError {
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidSharesError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid shares: {}", self.reason)
+    }
+}
+
+impl std::error::Error for InvalidSharesError {}
+
+/// Raised when deleting a user would take shared expenses and settlements that
+/// other people are part of along with them.
+#[derive(Debug)]
+pub struct UserHasSharedHistoryError {
+    pub user_id: String,
+    pub expenses: usize,
+    pub settlements: usize,
+}
+
+impl fmt::Display for UserHasSharedHistoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "User {} is part of {} shared expenses and {} settlements, archive them with `user archive` to keep that history",
+            self.user_id, self.expenses, self.settlements
+        )
+    }
+}
+
+impl std::error::Error for UserHasSharedHistoryError {}
+
+/// How much a user is owed, or owes when negative, across shared expenses and
+/// settlements in one currency.
+#[derive(Debug, Clone)]
+pub struct SharedBalance {
+    pub user_id: String,
+    pub balance: Money,
+}
+
+/// A payment from one user to another that squares them up.
+#[derive(Debug, Clone)]
+pub struct Payment {
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone)]
+pub struct Settlement {
+    pub id: String,
+    pub from_user_id: String,
+    pub to_user_id: String,
+    pub amount: Money,
+    pub settled_on: NaiveDate,
+    /// The transfer that moved the money, when it went between accounts
+    pub transfer_id: Option<String>,
+}
+
+pub struct SettlementRequest {
+    pub payment: Payment,
+    pub settled_on: NaiveDate,
+    /// The accounts the money left and arrived in, both in the currency of the
+    /// payment
+    pub from_account_id: String,
+    pub to_account_id: String,
+}
+
+fn invalid_shares(reason: String) -> DbError {
+    DbError::invalid(InvalidSharesError { reason })
+}
+
+/// Hands `remainder` cents out one at a time from the first of `amounts`
+/// onwards, so the shares add up to the whole amount.
+fn spread_remainder<'a>(amounts: impl Iterator<Item = &'a mut i64>, remainder: i64) {
+    for amount in amounts.take(remainder as usize) {
+        *amount += 1;
+    }
+}
+
+/// Divides `amount` between the people in `shares` following `method`.
+pub fn compute_shares(
+    amount: &Money,
+    method: ShareMethod,
+    shares: &[ShareRequest],
+) -> Result<Vec<Share>, DbError> {
+    if amount.minor <= 0 {
+        return Err(invalid_shares("the amount must be positive".to_string()));
+    }
+    if shares.is_empty() {
+        return Err(invalid_shares("nobody shares the expense".to_string()));
+    }
+    for (index, share) in shares.iter().enumerate() {
+        if shares[..index]
+            .iter()
+            .any(|other| other.user_id == share.user_id)
+        {
+            return Err(invalid_shares(format!(
+                "user {} is listed more than once",
+                share.user_id
+            )));
+        }
+    }
+    if method != ShareMethod::Equal && shares.iter().any(|share| share.value < 0) {
+        return Err(invalid_shares("shares cannot be negative".to_string()));
+    }
+
+    let count = shares.len() as i64;
+    let amounts = match method {
+        ShareMethod::Equal => {
+            let mut amounts = vec![amount.minor / count; shares.len()];
+            spread_remainder(amounts.iter_mut(), amount.minor % count);
+            amounts
+        }
+        ShareMethod::Exact => {
+            let total = shares.iter().map(|share| share.value).sum::<i64>();
+            if total != amount.minor {
+                return Err(invalid_shares(format!(
+                    "they add up to {} but the expense is {}",
+                    Money::new(total, &amount.currency),
+                    amount
+                )));
+            }
+
+            shares.iter().map(|share| share.value).collect()
+        }
+        ShareMethod::Percentage => {
+            let total = shares.iter().map(|share| share.value).sum::<i64>();
+            if total != 10000 {
+                return Err(invalid_shares(format!(
+                    "percentages add up to {} instead of 100%",
+                    format_ownership(total)
+                )));
+            }
+
+            let mut amounts = shares
+                .iter()
+                .map(|share| amount.minor * share.value / 10000)
+                .collect::<Vec<i64>>();
+            let remainder = amount.minor - amounts.iter().sum::<i64>();
+            // Someone with a 0% share never owes anything, not even a cent
+            spread_remainder(
+                amounts
+                    .iter_mut()
+                    .zip(shares)
+                    .filter(|(_, share)| share.value > 0)
+                    .map(|(minor, _)| minor),
+                remainder,
+            );
+            amounts
+        }
+    };
+
+    Ok(shares
+        .iter()
+        .zip(amounts)
+        .map(|(share, minor)| Share {
+            user_id: share.user_id.clone(),
+            amount: Money::new(minor, &amount.currency),
+        })
+        .collect())
+}
+
+/// The payments that square everyone up. The biggest debt is always paid to
+/// the biggest creditor, so there is at most one payment fewer than there are
+/// people with a balance in a currency.
+pub fn settle_up(balances: &[SharedBalance]) -> Vec<Payment> {
+    let mut by_currency = BTreeMap::<String, Vec<(String, i64)>>::new();
+    for balance in balances {
+        if balance.balance.minor != 0 {
+            by_currency
+                .entry(balance.balance.currency.clone())
+                .or_default()
+                .push((balance.user_id.clone(), balance.balance.minor));
+        }
+    }
+
+    let mut payments = vec![];
+    for (currency, mut balances) in by_currency {
+        loop {
+            balances.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+            let (Some(debtor), Some(creditor)) = (balances.first(), balances.last()) else {
+                break;
+            };
+            if debtor.1 >= 0 || creditor.1 <= 0 {
+                break;
+            }
+
+            let amount = (-debtor.1).min(creditor.1);
+            payments.push(Payment {
+                from_user_id: debtor.0.clone(),
+                to_user_id: creditor.0.clone(),
+                amount: Money::new(amount, &currency),
+            });
+
+            let last = balances.len() - 1;
+            balances[0].1 += amount;
+            balances[last].1 -= amount;
+            balances.retain(|(_, balance)| *balance != 0);
+        }
+    }
+
+    payments
+}
+
+fn shared_expense_columns() -> [SharedExpenses; 7] {
+    [
+        SharedExpenses::Id,
+        SharedExpenses::PaidBy,
+        SharedExpenses::Description,
+        SharedExpenses::Amount,
+        SharedExpenses::Currency,
+        SharedExpenses::Method,
+        SharedExpenses::SpentOn,
+    ]
+}
+
+fn shared_expense_from_row(row: &Row) -> Result<SharedExpense, rusqlite::Error> {
+    Ok(SharedExpense {
+        id: row.get(0)?,
+        paid_by: row.get(1)?,
+        description: row.get(2)?,
+        amount: Money::new(row.get(3)?, &row.get::<_, String>(4)?),
+        method: row
+            .get::<_, String>(5)?
+            .parse::<ShareMethod>()
+            .unwrap_or(ShareMethod::Equal),
+        spent_on: NaiveDate::from_str(row.get::<_, String>(6)?.as_str())
+            .unwrap_or(Local::now().date_naive()),
+        shares: vec![],
+    })
+}
+
+fn settlement_columns() -> [Settlements; 7] {
+    [
+        Settlements::Id,
+        Settlements::FromUserId,
+        Settlements::ToUserId,
+        Settlements::Amount,
+        Settlements::Currency,
+        Settlements::SettledOn,
+        Settlements::TransferId,
+    ]
+}
+
+fn settlement_from_row(row: &Row) -> Result<Settlement, rusqlite::Error> {
+    Ok(Settlement {
+        id: row.get(0)?,
+        from_user_id: row.get(1)?,
+        to_user_id: row.get(2)?,
+        amount: Money::new(row.get(3)?, &row.get::<_, String>(4)?),
+        settled_on: NaiveDate::from_str(row.get::<_, String>(5)?.as_str())
+            .unwrap_or(Local::now().date_naive()),
+        transfer_id: row.get(6)?,
+    })
+}
+
+impl Db {
+    pub fn create_shared_expense(
+        &self,
+        expense: &SharedExpenseRequest,
+    ) -> Result<SharedExpense, DbError> {
+        let shares = compute_shares(&expense.amount, expense.method, &expense.shares)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+
+        let id = random_hash(8);
+        let query = Query::insert()
+            .into_table(SharedExpenses::Table)
+            .columns([
+                SharedExpenses::Id,
+                SharedExpenses::PaidBy,
+                SharedExpenses::Description,
+                SharedExpenses::Amount,
+                SharedExpenses::Currency,
+                SharedExpenses::Method,
+                SharedExpenses::SpentOn,
+                SharedExpenses::CreatedAt,
+            ])
+            .values_panic(vec![
+                id.clone().into(),
+                expense.paid_by.clone().into(),
+                expense.description.clone().into(),
+                expense.amount.minor.into(),
+                expense.amount.currency.clone().into(),
+                expense.method.as_str().into(),
+                expense.spent_on.to_string().into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&query, ())?;
+
+        for share in &shares {
+            let query = Query::insert()
+                .into_table(ExpenseShares::Table)
+                .columns([
+                    ExpenseShares::ExpenseId,
+                    ExpenseShares::UserId,
+                    ExpenseShares::Amount,
+                ])
+                .values_panic(vec![
+                    id.clone().into(),
+                    share.user_id.clone().into(),
+                    share.amount.minor.into(),
+                ])
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&query, ())?;
+        }
+
+        tx.commit()?;
+
+        Ok(SharedExpense {
+            id,
+            paid_by: expense.paid_by.clone(),
+            description: expense.description.clone(),
+            amount: expense.amount.clone(),
+            method: expense.method,
+            spent_on: expense.spent_on,
+            shares,
+        })
+    }
+
+    fn get_expense_shares(&self, expense: &SharedExpense) -> Result<Vec<Share>, DbError> {
+        let query = Query::select()
+            .columns([ExpenseShares::UserId, ExpenseShares::Amount])
+            .from(ExpenseShares::Table)
+            .and_where(Expr::col(ExpenseShares::ExpenseId).eq(expense.id.as_str()))
+            // In the order they were entered
+            .order_by_expr(Expr::cust("rowid"), Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), |row| {
+                Ok(Share {
+                    user_id: row.get(0)?,
+                    amount: Money::new(row.get(1)?, &expense.amount.currency),
+                })
+            })?
+            .map(|share| share.unwrap())
+            .collect::<Vec<Share>>())
+    }
+
+    /// Every shared expense with its shares, oldest first.
+    pub fn get_shared_expenses(&self) -> Result<Vec<SharedExpense>, DbError> {
+        let query = Query::select()
+            .columns(shared_expense_columns())
+            .from(SharedExpenses::Table)
+            .order_by(SharedExpenses::SpentOn, Order::Asc)
+            .order_by(SharedExpenses::CreatedAt, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let expenses = stmt
+            .query_map((), shared_expense_from_row)?
+            .map(|expense| expense.unwrap())
+            .collect::<Vec<SharedExpense>>();
+
+        expenses
+            .into_iter()
+            .map(|mut expense| {
+                expense.shares = self.get_expense_shares(&expense)?;
+                Ok(expense)
+            })
+            .collect()
+    }
+
+    pub fn delete_shared_expense(&self, id: &str) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let delete_shares = Query::delete()
+            .from_table(ExpenseShares::Table)
+            .and_where(Expr::col(ExpenseShares::ExpenseId).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&delete_shares, ())?;
+
+        let delete_expense = Query::delete()
+            .from_table(SharedExpenses::Table)
+            .and_where(Expr::col(SharedExpenses::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+        if self.conn.execute(&delete_expense, ())? == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows.into());
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    /// Every settlement, oldest first.
+    pub fn get_settlements(&self) -> Result<Vec<Settlement>, DbError> {
+        let query = Query::select()
+            .columns(settlement_columns())
+            .from(Settlements::Table)
+            .order_by(Settlements::SettledOn, Order::Asc)
+            .order_by(Settlements::CreatedAt, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), settlement_from_row)?
+            .map(|settlement| settlement.unwrap())
+            .collect::<Vec<Settlement>>())
+    }
+
+    /// What everyone is owed, or owes when negative, per currency. The payer
+    /// of an expense is owed the whole amount and everyone owes their share;
+    /// settlements move the difference from the one who paid to the one who
+    /// received. Users who are square are left out.
+    pub fn get_shared_balances(&self) -> Result<Vec<SharedBalance>, DbError> {
+        let mut balances = BTreeMap::<(String, String), i64>::new();
+        let mut add = |user_id: &str, amount: &Money, minor: i64| {
+            *balances
+                .entry((amount.currency.clone(), user_id.to_string()))
+                .or_insert(0) += minor;
+        };
+
+        for expense in self.get_shared_expenses()? {
+            add(&expense.paid_by, &expense.amount, expense.amount.minor);
+            for share in &expense.shares {
+                add(&share.user_id, &share.amount, -share.amount.minor);
+            }
+        }
+        for settlement in self.get_settlements()? {
+            add(
+                &settlement.from_user_id,
+                &settlement.amount,
+                settlement.amount.minor,
+            );
+            add(
+                &settlement.to_user_id,
+                &settlement.amount,
+                -settlement.amount.minor,
+            );
+        }
+
+        Ok(balances
+            .into_iter()
+            .filter(|(_, minor)| *minor != 0)
+            .map(|((currency, user_id), minor)| SharedBalance {
+                user_id,
+                balance: Money::new(minor, &currency),
+            })
+            .collect())
+    }
+
+    /// Records a payment between two users along with the transfer between
+    /// their accounts that carried it.
+    pub fn create_settlement(&self, settlement: &SettlementRequest) -> Result<Settlement, DbError> {
+        let payment = &settlement.payment;
+
+        let tx = self.conn.unchecked_transaction()?;
+
+        let to_user = self.get_user(&payment.to_user_id)?;
+        let (outgoing, _) = self.insert_transfer(&TransferRequest {
+            from_account_id: settlement.from_account_id.clone(),
+            to_account_id: settlement.to_account_id.clone(),
+            amount: payment.amount.clone(),
+            description: format!("Settle up with {}", to_user.name),
+            posted_on: settlement.settled_on,
+        })?;
+
+        let id = random_hash(8);
+        let query = Query::insert()
+            .into_table(Settlements::Table)
+            .columns([
+                Settlements::Id,
+                Settlements::FromUserId,
+                Settlements::ToUserId,
+                Settlements::Amount,
+                Settlements::Currency,
+                Settlements::SettledOn,
+                Settlements::TransferId,
+                Settlements::CreatedAt,
+            ])
+            .values_panic(vec![
+                id.clone().into(),
+                payment.from_user_id.clone().into(),
+                payment.to_user_id.clone().into(),
+                payment.amount.minor.into(),
+                payment.amount.currency.clone().into(),
+                settlement.settled_on.to_string().into(),
+                outgoing.transfer_id.clone().into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&query, ())?;
+
+        tx.commit()?;
+
+        Ok(Settlement {
+            id,
+            from_user_id: payment.from_user_id.clone(),
+            to_user_id: payment.to_user_id.clone(),
+            amount: payment.amount.clone(),
+            settled_on: settlement.settled_on,
+            transfer_id: outgoing.transfer_id,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::users::User,
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db},
+    };
+
+    use super::*;
+
+    fn usd(minor: i64) -> Money {
+        Money::new(minor, DEFAULT_CURRENCY)
+    }
+
+    fn share(user_id: &str, value: i64) -> ShareRequest {
+        ShareRequest {
+            user_id: user_id.to_string(),
+            value,
+        }
+    }
+
+    fn amounts(shares: Vec<Share>) -> Vec<i64> {
+        shares
+            .into_iter()
+            .map(|share| share.amount.minor)
+            .collect::<Vec<i64>>()
+    }
+
+    fn balance(balances: &[SharedBalance], user: &User) -> i64 {
+        balances
+            .iter()
+            .find(|balance| balance.user_id == user.id)
+            .map_or(0, |balance| balance.balance.minor)
+    }
+
+    fn date() -> NaiveDate {
+        NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+    }
+
+    /// Alice, Bob and Carol. Alice paid 90.00 for dinner split three ways and
+    /// Bob paid 60.00 for a taxi, 40.00 of it Carol's. Returns the taxi.
+    fn setup(name: &str) -> (Db, [User; 3], SharedExpense) {
+        let db = get_test_db(name);
+        let users = ["Alice", "Bob", "Carol"].map(|name| create_test_user(&db, name));
+        let [alice, bob, carol] = &users;
+
+        db.create_shared_expense(&SharedExpenseRequest {
+            paid_by: alice.id.clone(),
+            description: "Dinner".to_string(),
+            amount: usd(9000),
+            method: ShareMethod::Equal,
+            spent_on: date(),
+            shares: users.iter().map(|user| share(&user.id, 0)).collect(),
+        })
+        .unwrap();
+        let taxi = db
+            .create_shared_expense(&SharedExpenseRequest {
+                paid_by: bob.id.clone(),
+                description: "Taxi".to_string(),
+                amount: usd(6000),
+                method: ShareMethod::Exact,
+                spent_on: date(),
+                shares: vec![share(&bob.id, 2000), share(&carol.id, 4000)],
+            })
+            .unwrap();
+
+        (db, users, taxi)
+    }
+
+    #[test]
+    fn test_equal_shares() {
+        // Leftover cents go to the first people
+        let everyone = [share("alice", 0), share("bob", 0), share("carol", 0)];
+        assert_eq!(
+            amounts(compute_shares(&usd(10000), ShareMethod::Equal, &everyone).unwrap()),
+            vec![3334, 3333, 3333]
+        );
+    }
+
+    #[test]
+    fn test_percentage_shares() {
+        assert_eq!(
+            amounts(
+                compute_shares(
+                    &usd(10001),
+                    ShareMethod::Percentage,
+                    &[share("alice", 5000), share("bob", 5000)]
+                )
+                .unwrap()
+            ),
+            vec![5001, 5000]
+        );
+
+        // Nobody with a 0% share is left owing a cent
+        assert_eq!(
+            amounts(
+                compute_shares(
+                    &usd(10001),
+                    ShareMethod::Percentage,
+                    &[share("alice", 0), share("bob", 5000), share("carol", 5000)]
+                )
+                .unwrap()
+            ),
+            vec![0, 5001, 5000]
+        );
+
+        let uneven = compute_shares(
+            &Money::new(10000, "JPY"),
+            ShareMethod::Percentage,
+            &[share("alice", 5000), share("bob", 4000)],
+        );
+        assert!(
+            matches!(uneven, Err(DbError::Invalid(error)) if error.to_string().contains("90%"))
+        );
+    }
+
+    #[test]
+    fn test_invalid_shares() {
+        assert!(
+            compute_shares(
+                &usd(10000),
+                ShareMethod::Exact,
+                &[share("alice", 6000), share("bob", 3000)]
+            )
+            .is_err()
+        );
+        assert!(compute_shares(&usd(10000), ShareMethod::Equal, &[]).is_err());
+        assert!(
+            compute_shares(
+                &usd(10000),
+                ShareMethod::Equal,
+                &[share("alice", 0), share("alice", 0)]
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_shared_expense_shares() {
+        let (db, [_, bob, carol], taxi) = setup("expense_shares");
+
+        // Shares are listed in the order they were entered
+        let expenses = db.get_shared_expenses().unwrap();
+        let shares = &expenses
+            .iter()
+            .find(|expense| expense.id == taxi.id)
+            .unwrap()
+            .shares;
+        let users = shares
+            .iter()
+            .map(|share| share.user_id.clone())
+            .collect::<Vec<String>>();
+        assert_eq!(users, vec![bob.id, carol.id]);
+        assert_eq!(amounts(shares.clone()), vec![2000, 4000]);
+    }
+
+    #[test]
+    fn test_shared_balances() {
+        let (db, [alice, bob, carol], _) = setup("expense_balances");
+
+        let balances = db.get_shared_balances().unwrap();
+        assert_eq!(balance(&balances, &alice), 6000);
+        assert_eq!(balance(&balances, &bob), 1000);
+        assert_eq!(balance(&balances, &carol), -7000);
+    }
+
+    #[test]
+    fn test_settle_up() {
+        let (db, [alice, bob, carol], _) = setup("expense_settle_up");
+
+        // Carol pays both of them back, and nobody else has to pay anyone
+        let payments = settle_up(&db.get_shared_balances().unwrap());
+        assert_eq!(payments.len(), 2);
+        assert!(
+            payments
+                .iter()
+                .all(|payment| payment.from_user_id == carol.id)
+        );
+        assert_eq!(payments[0].to_user_id, alice.id);
+        assert_eq!(payments[0].amount.minor, 6000);
+        assert_eq!(payments[1].to_user_id, bob.id);
+        assert_eq!(payments[1].amount.minor, 1000);
+    }
+
+    #[test]
+    fn test_create_settlement() {
+        let (db, [alice, _, carol], _) = setup("expense_settlement");
+        let alice_account = create_test_account(&db, &alice.id, "Checking", 100000);
+        let carol_account = create_test_account(&db, &carol.id, "Checking", 100000);
+
+        let payments = settle_up(&db.get_shared_balances().unwrap());
+        let settlement = db
+            .create_settlement(&SettlementRequest {
+                payment: payments[0].clone(),
+                settled_on: date(),
+                from_account_id: carol_account.id.clone(),
+                to_account_id: alice_account.id.clone(),
+            })
+            .unwrap();
+        assert!(settlement.transfer_id.is_some());
+        assert_eq!(
+            db.get_account(&carol_account.id).unwrap().balance.minor,
+            94000
+        );
+        assert_eq!(
+            db.get_account(&alice_account.id).unwrap().balance.minor,
+            106000
+        );
+
+        let balances = db.get_shared_balances().unwrap();
+        assert_eq!(balances.len(), 2);
+        assert_eq!(balance(&balances, &alice), 0);
+        assert_eq!(balance(&balances, &carol), -1000);
+    }
+
+    #[test]
+    fn test_delete_shared_expense() {
+        let (db, [alice, bob, carol], taxi) = setup("expense_delete");
+
+        // Only the dinner is left to pay back
+        db.delete_shared_expense(&taxi.id).unwrap();
+        assert!(db.delete_shared_expense(&taxi.id).is_err());
+        let balances = db.get_shared_balances().unwrap();
+        assert_eq!(balance(&balances, &alice), 6000);
+        assert_eq!(balance(&balances, &bob), -3000);
+        assert_eq!(balance(&balances, &carol), -3000);
+    }
+
+    #[test]
+    fn test_delete_user_with_shared_expenses() {
+        let (db, [_, bob, _], _) = setup("expense_delete_user");
+
+        // Bob holds no account, but deleting him would take his expenses with him
+        assert!(db.delete_user(&bob.id).is_err());
+        assert_eq!(db.get_shared_expenses().unwrap().len(), 2);
+    }
+}
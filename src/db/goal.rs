@@ -0,0 +1,407 @@
+use std::str::FromStr;
+
+use chrono::{Local, Months, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SelectStatement, SqliteQueryBuilder};
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{
+    Accounts, Goals,
+    budget::month_start,
+    transaction::{Transaction, TransactionStatus},
+};
+
+/// How many calendar months of an account's history are averaged to estimate
+/// its monthly contribution.
+pub const CONTRIBUTION_HISTORY_MONTHS: u32 = 6;
+
+/// An amount to save up in an account by a date.
+#[derive(Debug, Clone)]
+pub struct Goal {
+    pub id: String,
+    pub name: String,
+    /// The account the money is saved in, whose balance counts as saved
+    pub account_id: String,
+    pub target: Money,
+    pub target_date: NaiveDate,
+}
+
+pub struct GoalRequest {
+    pub name: String,
+    pub account_id: String,
+    pub target: Money,
+    pub target_date: NaiveDate,
+}
+
+/// Where a goal stands on a given day.
+#[derive(Debug, Clone)]
+pub struct GoalProgress {
+    pub goal: Goal,
+    /// The balance of the linked account
+    pub saved: Money,
+    /// What the linked account grew by per month on average, recently
+    pub monthly_contribution: Money,
+    /// The day the progress was worked out for
+    pub on: NaiveDate,
+}
+
+impl GoalProgress {
+    pub fn remaining(&self) -> Money {
+        Money::new(
+            (self.goal.target.minor - self.saved.minor).max(0),
+            &self.goal.target.currency,
+        )
+    }
+
+    pub fn is_reached(&self) -> bool {
+        self.remaining().minor == 0
+    }
+
+    /// Share of the target that was saved, in percent.
+    pub fn percent(&self) -> f64 {
+        if self.goal.target.minor <= 0 {
+            return 100.0;
+        }
+
+        self.saved.minor.max(0) as f64 * 100.0 / self.goal.target.minor as f64
+    }
+
+    /// What has to go in every month from now on to reach the target on time.
+    /// Once the target date has passed, everything that is left is due now.
+    pub fn required_monthly(&self) -> Money {
+        let months = months_until(self.on, self.goal.target_date).max(1) as i64;
+
+        Money::new(
+            (self.remaining().minor + months - 1) / months,
+            &self.goal.target.currency,
+        )
+    }
+
+    /// When the target is reached if the account keeps growing at its recent
+    /// pace, or `None` when it is not growing at all.
+    pub fn projected_completion(&self) -> Option<NaiveDate> {
+        if self.is_reached() {
+            return Some(self.on);
+        }
+        if self.monthly_contribution.minor <= 0 {
+            return None;
+        }
+
+        let contribution = self.monthly_contribution.minor;
+        let months = (self.remaining().minor + contribution - 1) / contribution;
+
+        u32::try_from(months)
+            .ok()
+            .and_then(|months| self.on.checked_add_months(Months::new(months)))
+    }
+}
+
+/// Whole months from `from` until `to`, rounded up.
+fn months_until(from: NaiveDate, to: NaiveDate) -> u32 {
+    let mut months = 0;
+    while from
+        .checked_add_months(Months::new(months))
+        .is_some_and(|date| date < to)
+    {
+        months += 1;
+    }
+
+    months
+}
+
+fn goal_columns() -> [Goals; 6] {
+    [
+        Goals::Id,
+        Goals::Name,
+        Goals::AccountId,
+        Goals::TargetAmount,
+        Goals::TargetDate,
+        Goals::CreatedAt,
+    ]
+}
+
+fn select_goals() -> SelectStatement {
+    Query::select()
+        .columns(
+            goal_columns()
+                .into_iter()
+                .map(|column| (Goals::Table, column)),
+        )
+        .column((Accounts::Table, Accounts::Currency))
+        .from(Goals::Table)
+        .left_join(
+            Accounts::Table,
+            Expr::col((Accounts::Table, Accounts::Id)).equals((Goals::Table, Goals::AccountId)),
+        )
+        .to_owned()
+}
+
+fn goal_from_row(row: &Row) -> Result<Goal, rusqlite::Error> {
+    let target_date =
+        NaiveDate::from_str(row.get::<_, String>(4)?.as_str()).unwrap_or(Local::now().date_naive());
+
+    Ok(Goal {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        account_id: row.get(2)?,
+        target: Money::new(row.get(3)?, &row.get::<_, String>(6)?),
+        target_date,
+    })
+}
+
+impl Db {
+    pub fn create_goal(&self, goal: &GoalRequest) -> Result<Goal, DbError> {
+        let id = random_hash(8);
+
+        let query = Query::insert()
+            .into_table(Goals::Table)
+            .columns(goal_columns())
+            .values_panic(vec![
+                id.clone().into(),
+                goal.name.clone().into(),
+                goal.account_id.clone().into(),
+                goal.target.minor.into(),
+                goal.target_date.to_string().into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(Goal {
+            id,
+            name: goal.name.clone(),
+            account_id: goal.account_id.clone(),
+            target: goal.target.clone(),
+            target_date: goal.target_date,
+        })
+    }
+
+    pub fn get_goal(&self, id: &str) -> Result<Goal, DbError> {
+        let query = select_goals()
+            .and_where(Expr::col((Goals::Table, Goals::Id)).eq(id))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt.query_row((), goal_from_row)?)
+    }
+
+    /// Every goal, the one due first at the top.
+    pub fn get_all_goals(&self) -> Result<Vec<Goal>, DbError> {
+        let query = select_goals()
+            .order_by((Goals::Table, Goals::TargetDate), Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), goal_from_row)?
+            .map(|goal| goal.unwrap())
+            .collect::<Vec<Goal>>())
+    }
+
+    pub fn update_goal(&self, id: &str, goal: &Goal) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(Goals::Table)
+            .and_where(Expr::col(Goals::Id).eq(id))
+            .values(vec![
+                (Goals::Name, goal.name.clone().into()),
+                (Goals::AccountId, goal.account_id.clone().into()),
+                (Goals::TargetAmount, goal.target.minor.into()),
+                (Goals::TargetDate, goal.target_date.to_string().into()),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    pub fn delete_goal(&self, id: &str) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(Goals::Table)
+            .and_where(Expr::col(Goals::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// How far a goal has come by `on`. The monthly contribution averages the
+    /// net change of the linked account over the last
+    /// [`CONTRIBUTION_HISTORY_MONTHS`] calendar months up to and including the
+    /// month of `on`, but never from before its first transaction.
+    pub fn get_goal_progress(&self, goal: &Goal, on: NaiveDate) -> Result<GoalProgress, DbError> {
+        let account = self.get_account(&goal.account_id)?;
+        let transactions = self
+            .get_transactions_by_account(&goal.account_id)?
+            .into_iter()
+            .filter(|transaction| {
+                transaction.status != TransactionStatus::Void && transaction.posted_on <= on
+            })
+            .collect::<Vec<Transaction>>();
+
+        let earliest = on
+            .checked_sub_months(Months::new(CONTRIBUTION_HISTORY_MONTHS - 1))
+            .map(month_start)
+            .unwrap_or(on);
+        let first = transactions
+            .iter()
+            .map(|transaction| month_start(transaction.posted_on))
+            .min();
+
+        let monthly_contribution = match first {
+            Some(first) => {
+                let from = first.max(earliest);
+                let months = months_until(from, month_start(on)) as i64 + 1;
+                let net = transactions
+                    .iter()
+                    .filter(|transaction| transaction.posted_on >= from)
+                    .map(|transaction| transaction.signed_amount())
+                    .sum::<i64>();
+
+                (net as f64 / months as f64).round() as i64
+            }
+            None => 0,
+        };
+
+        Ok(GoalProgress {
+            goal: goal.clone(),
+            monthly_contribution: Money::new(monthly_contribution, &account.balance.currency),
+            saved: account.balance,
+            on,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{category::CategoryRequest, transaction::TransactionRequest},
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db},
+    };
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// A goal of saving 3,000.00 by the end of 2024. With `history`, 1,400.00
+    /// is saved over the first three months, not counting a pending deposit
+    /// and a void one.
+    fn setup(name: &str, history: bool) -> (Db, Goal) {
+        let db = get_test_db(name);
+        let user = create_test_user(&db, "John Doe");
+        let account = create_test_account(&db, &user.id, "Savings", 0);
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Savings".to_string(),
+                icon: "🐷".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let goal = db
+            .create_goal(&GoalRequest {
+                name: "Vacation".to_string(),
+                account_id: account.id.clone(),
+                target: Money::new(300000, DEFAULT_CURRENCY),
+                target_date: date(2024, 12, 31),
+            })
+            .unwrap();
+
+        if !history {
+            return (db, goal);
+        }
+
+        for (amount, transaction_type, posted_on, status) in [
+            (50000, "credit", "2024-01-05", TransactionStatus::Cleared),
+            (50000, "credit", "2024-02-05", TransactionStatus::Cleared),
+            (10000, "debit", "2024-02-20", TransactionStatus::Cleared),
+            (50000, "credit", "2024-03-05", TransactionStatus::Pending),
+            (90000, "credit", "2024-03-06", TransactionStatus::Void),
+        ] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(amount, DEFAULT_CURRENCY),
+                transaction_type: transaction_type.to_string(),
+                description: "Deposit".to_string(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_str(posted_on).unwrap(),
+                status,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        (db, goal)
+    }
+
+    #[test]
+    fn test_goal_without_history() {
+        let (db, goal) = setup("goal_without_history", false);
+
+        // Without any history the account is not growing
+        let progress = db.get_goal_progress(&goal, date(2024, 3, 15)).unwrap();
+        assert_eq!(progress.monthly_contribution.minor, 0);
+        assert!(progress.projected_completion().is_none());
+    }
+
+    #[test]
+    fn test_goal_progress() {
+        let (db, goal) = setup("goal_progress", true);
+
+        // 1,400 saved over three months, 1,600 to go in ten months
+        let progress = db.get_goal_progress(&goal, date(2024, 3, 15)).unwrap();
+        assert_eq!(progress.saved.minor, 140000);
+        assert_eq!(progress.remaining().minor, 160000);
+        assert_eq!(progress.monthly_contribution.minor, 46667);
+        assert_eq!(progress.required_monthly().minor, 16000);
+        assert_eq!(progress.projected_completion(), Some(date(2024, 7, 15)));
+    }
+
+    #[test]
+    fn test_goal_past_target_date() {
+        let (db, goal) = setup("goal_past_target_date", true);
+
+        // Everything left is due at once
+        let late = db.get_goal_progress(&goal, date(2025, 2, 1)).unwrap();
+        assert_eq!(late.required_monthly().minor, 160000);
+    }
+
+    #[test]
+    fn test_reached_goal() {
+        let (db, mut goal) = setup("goal_reached", true);
+        let on = date(2024, 3, 15);
+
+        goal.target = Money::new(100000, DEFAULT_CURRENCY);
+        db.update_goal(&goal.id, &goal).unwrap();
+        let progress = db
+            .get_goal_progress(&db.get_goal(&goal.id).unwrap(), on)
+            .unwrap();
+        assert!(progress.is_reached());
+        assert_eq!(progress.projected_completion(), Some(on));
+        assert_eq!(progress.percent(), 140.0);
+    }
+
+    #[test]
+    fn test_delete_goal() {
+        let (db, goal) = setup("goal_delete", false);
+
+        db.delete_goal(&goal.id).unwrap();
+        assert!(db.get_all_goals().unwrap().is_empty());
+    }
+}
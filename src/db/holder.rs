@@ -0,0 +1,307 @@
+use std::{collections::HashMap, fmt};
+
+use sea_query::{Expr, Order, Query, SimpleExpr, SqliteQueryBuilder};
+
+use crate::db::{Db, DbError};
+
+use super::{AccountHolders, Transactions};
+
+/// Ownership is kept in hundredths of a percent, so this is all of an account.
+pub const WHOLE_OWNERSHIP: i64 = 10000;
+
+/// A user holding an account and how much of it they own.
+#[derive(Debug, Clone)]
+pub struct AccountHolder {
+    pub user_id: String,
+    /// In hundredths of a percent, so `5000` is half of the account
+    pub share: i64,
+}
+
+impl AccountHolder {
+    /// The only holder of an account, owning all of it.
+    pub fn sole(user_id: &str) -> Self {
+        Self {
+            user_id: user_id.to_string(),
+            share: WHOLE_OWNERSHIP,
+        }
+    }
+}
+
+/// The part of `minor` that `share` hundredths of a percent stand for, rounded
+/// to the nearest minor unit.
+pub fn owned_part(minor: i64, share: i64) -> i64 {
+    (minor as f64 * share as f64 / WHOLE_OWNERSHIP as f64).round() as i64
+}
+
+/// The part of `minor` on `account_id` that counts for a user owning accounts
+/// as in `ownership`, which comes from [`Db::get_ownership_by_user`]. Without
+/// ownership all of it counts, and nothing of accounts the user does not hold.
+pub fn owned_amount(ownership: Option<&HashMap<String, i64>>, account_id: &str, minor: i64) -> i64 {
+    match ownership {
+        Some(ownership) => ownership
+            .get(account_id)
+            .map_or(0, |share| owned_part(minor, *share)),
+        None => minor,
+    }
+}
+
+/// Shows a share as a percentage without trailing zeros, so `5000` reads as
+/// `50%` and `3333` as `33.33%`.
+pub fn format_ownership(share: i64) -> String {
+    let formatted = format!("{}.{:02}", share / 100, share % 100);
+
+    format!("{}%", formatted.trim_end_matches('0').trim_end_matches('.'))
+}
+
+/// Raised when the holders of an account do not own exactly all of it.
+#[derive(Debug)]
+pub struct InvalidOwnershipError {
+    pub reason: String,
+}
+
+impl fmt::Display for InvalidOwnershipError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid ownership: {}", self.reason)
+    }
+}
+
+impl std::error::Error for InvalidOwnershipError {}
+
+/// Raised when deleting a user who still holds accounts, which would leave
+/// them without an owner.
+#[derive(Debug)]
+pub struct UserHoldsAccountsError {
+    pub user_id: String,
+    pub accounts: Vec<String>,
+}
+
+impl fmt::Display for UserHoldsAccountsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "User {} still holds {}, change the holders of those accounts first or archive them with `user archive`",
+            self.user_id,
+            self.accounts.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for UserHoldsAccountsError {}
+
+/// Matches transactions on accounts `user_id` holds, alone or jointly.
+pub(super) fn held_by(user_id: &str) -> SimpleExpr {
+    Expr::col((Transactions::Table, Transactions::AccountId)).in_subquery(
+        Query::select()
+            .column(AccountHolders::AccountId)
+            .from(AccountHolders::Table)
+            .and_where(Expr::col(AccountHolders::UserId).eq(user_id))
+            .take(),
+    )
+}
+
+/// Checks that every holder is listed once, owns part of the account and that
+/// together they own all of it.
+pub fn validate_holders(holders: &[AccountHolder]) -> Result<(), DbError> {
+    let invalid = |reason: String| Err(DbError::invalid(InvalidOwnershipError { reason }));
+
+    if holders.is_empty() {
+        return invalid("an account needs at least one holder".to_string());
+    }
+    for (index, holder) in holders.iter().enumerate() {
+        if holders[..index]
+            .iter()
+            .any(|other| other.user_id == holder.user_id)
+        {
+            return invalid(format!("user {} is listed more than once", holder.user_id));
+        }
+        if holder.share <= 0 {
+            return invalid(format!(
+                "user {} must own part of the account",
+                holder.user_id
+            ));
+        }
+    }
+
+    let total = holders.iter().map(|holder| holder.share).sum::<i64>();
+    if total != WHOLE_OWNERSHIP {
+        return invalid(format!(
+            "shares add up to {} instead of 100%",
+            format_ownership(total)
+        ));
+    }
+
+    Ok(())
+}
+
+impl Db {
+    /// Writes the holders of an account without checking them. Callers are
+    /// expected to validate them and run this inside their own SQL transaction.
+    pub(super) fn insert_account_holders(
+        &self,
+        account_id: &str,
+        holders: &[AccountHolder],
+    ) -> Result<(), DbError> {
+        let delete = Query::delete()
+            .from_table(AccountHolders::Table)
+            .and_where(Expr::col(AccountHolders::AccountId).eq(account_id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&delete, ())?;
+
+        for holder in holders {
+            let insert = Query::insert()
+                .into_table(AccountHolders::Table)
+                .columns([
+                    AccountHolders::AccountId,
+                    AccountHolders::UserId,
+                    AccountHolders::Share,
+                ])
+                .values_panic(vec![
+                    account_id.into(),
+                    holder.user_id.clone().into(),
+                    holder.share.into(),
+                ])
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&insert, ())?;
+        }
+
+        Ok(())
+    }
+
+    /// The holders of an account, the biggest owner first.
+    pub fn get_account_holders(&self, account_id: &str) -> Result<Vec<AccountHolder>, DbError> {
+        let query = Query::select()
+            .columns([AccountHolders::UserId, AccountHolders::Share])
+            .from(AccountHolders::Table)
+            .and_where(Expr::col(AccountHolders::AccountId).eq(account_id))
+            .order_by(AccountHolders::Share, Order::Desc)
+            .order_by(AccountHolders::UserId, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), |row| {
+                Ok(AccountHolder {
+                    user_id: row.get(0)?,
+                    share: row.get(1)?,
+                })
+            })?
+            .map(|holder| holder.unwrap())
+            .collect::<Vec<AccountHolder>>())
+    }
+
+    /// How much of each account a user owns, by account id. Accounts they do
+    /// not hold are left out.
+    pub fn get_ownership_by_user(&self, user_id: &str) -> Result<HashMap<String, i64>, DbError> {
+        let query = Query::select()
+            .columns([AccountHolders::AccountId, AccountHolders::Share])
+            .from(AccountHolders::Table)
+            .and_where(Expr::col(AccountHolders::UserId).eq(user_id))
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<Result<HashMap<String, i64>, rusqlite::Error>>()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            users::UserRequest,
+        },
+        money::{DEFAULT_CURRENCY, Money},
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_account_holders() {
+        let _ = std::fs::remove_file(get_test_db_path("holder"));
+
+        let db = Db::new(get_test_db_path("holder").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = |name: &str| {
+            db.create_user(&UserRequest {
+                name: name.to_string(),
+            })
+            .unwrap()
+        };
+        let (alice, bob) = (user("Alice"), user("Bob"));
+        let holder = |user_id: &str, share| AccountHolder {
+            user_id: user_id.to_string(),
+            share,
+        };
+        let request = |holders: Vec<AccountHolder>| AccountRequest {
+            name: "Joint Checking".to_string(),
+            bank: "Chase".to_string(),
+            account_number: None,
+            account_type: AccountType::Checking,
+            opening_balance: Money::new(100000, DEFAULT_CURRENCY),
+            holders,
+            card: None,
+            loan: None,
+        };
+
+        // Holders must own all of the account between them
+        assert!(db.create_account(&request(vec![])).is_err());
+        assert!(
+            db.create_account(&request(vec![
+                holder(&alice.id, 6000),
+                holder(&bob.id, 3000)
+            ]))
+            .is_err()
+        );
+        assert!(
+            db.create_account(&request(vec![
+                holder(&alice.id, 5000),
+                holder(&alice.id, 5000)
+            ]))
+            .is_err()
+        );
+        assert!(db.get_all_accounts().unwrap().is_empty());
+
+        let joint = db
+            .create_account(&request(vec![
+                holder(&alice.id, 4000),
+                holder(&bob.id, 6000),
+            ]))
+            .unwrap();
+        let holders = db.get_account_holders(&joint.id).unwrap();
+        assert_eq!(holders.len(), 2);
+        assert_eq!(holders[0].user_id, bob.id);
+        assert_eq!(holders[0].share, 6000);
+        assert_eq!(db.get_accounts_by_holder(&alice.id).unwrap().len(), 1);
+        assert_eq!(db.get_accounts_by_holder(&bob.id).unwrap().len(), 1);
+        assert_eq!(
+            db.get_ownership_by_user(&alice.id).unwrap().get(&joint.id),
+            Some(&4000)
+        );
+
+        // A user holding accounts cannot be deleted out from under them
+        assert!(db.delete_user(&alice.id).is_err());
+        db.update_account(
+            &joint.id,
+            &db.get_account(&joint.id).unwrap(),
+            &[AccountHolder::sole(&bob.id)],
+        )
+        .unwrap();
+        assert!(db.get_accounts_by_holder(&alice.id).unwrap().is_empty());
+        db.delete_user(&alice.id).unwrap();
+
+        db.delete_account(&joint.id).unwrap();
+        assert!(db.get_account_holders(&joint.id).unwrap().is_empty());
+        db.delete_user(&bob.id).unwrap();
+
+        assert_eq!(owned_part(10001, 5000), 5001);
+        assert_eq!(format_ownership(5000), "50%");
+        assert_eq!(format_ownership(3333), "33.33%");
+        assert_eq!(format_ownership(1250), "12.5%");
+    }
+}
@@ -0,0 +1,712 @@
+use std::{collections::BTreeMap, fmt, str::FromStr};
+
+use chrono::{Datelike, Local, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SqliteQueryBuilder};
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{
+    Trades,
+    security::{Security, SecurityPrice},
+};
+
+/// Quantities closer to zero than this count as nothing left.
+const QUANTITY_EPSILON: f64 = 1e-9;
+
+/// What happened in a trade.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TradeKind {
+    /// Units bought for the amount, fees included
+    Buy,
+    /// Units sold for the amount, after fees
+    Sell,
+    /// The amount paid out, the quantity is not used
+    Dividend,
+    /// Every unit held became `quantity` units, the amount is not used
+    Split,
+}
+
+impl TradeKind {
+    pub const ALL: [TradeKind; 4] = [
+        TradeKind::Buy,
+        TradeKind::Sell,
+        TradeKind::Dividend,
+        TradeKind::Split,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TradeKind::Buy => "buy",
+            TradeKind::Sell => "sell",
+            TradeKind::Dividend => "dividend",
+            TradeKind::Split => "split",
+        }
+    }
+}
+
+impl fmt::Display for TradeKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TradeKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TradeKind::ALL
+            .into_iter()
+            .find(|kind| kind.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("'{}' is not a trade kind", s))
+    }
+}
+
+/// Which units a sale is taken from when working out what they cost.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CostBasisMethod {
+    /// The oldest lots are sold first
+    Fifo,
+    /// Every unit cost the same, the average of everything bought
+    Average,
+}
+
+impl CostBasisMethod {
+    pub const ALL: [CostBasisMethod; 2] = [CostBasisMethod::Fifo, CostBasisMethod::Average];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CostBasisMethod::Fifo => "fifo",
+            CostBasisMethod::Average => "average",
+        }
+    }
+}
+
+impl fmt::Display for CostBasisMethod {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for CostBasisMethod {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        CostBasisMethod::ALL
+            .into_iter()
+            .find(|method| method.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("'{}' is not a cost basis method", s))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Trade {
+    pub id: String,
+    pub account_id: String,
+    pub security_id: String,
+    pub kind: TradeKind,
+    pub traded_on: NaiveDate,
+    pub quantity: f64,
+    /// In the currency of the security
+    pub amount: Money,
+}
+
+pub struct TradeRequest {
+    pub account_id: String,
+    pub security_id: String,
+    pub kind: TradeKind,
+    pub traded_on: NaiveDate,
+    pub quantity: f64,
+    pub amount: Money,
+}
+
+/// Raised when a trade would sell more units than are held at the time.
+#[derive(Debug)]
+pub struct InsufficientUnitsError {
+    pub security_id: String,
+    pub sold_on: NaiveDate,
+    pub held: f64,
+    pub selling: f64,
+}
+
+impl fmt::Display for InsufficientUnitsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Cannot sell {} units of security {} on {}, only {} are held",
+            self.selling, self.security_id, self.sold_on, self.held
+        )
+    }
+}
+
+impl std::error::Error for InsufficientUnitsError {}
+
+/// Units bought together, and what they cost.
+#[derive(Debug, Clone)]
+pub struct Lot {
+    pub bought_on: NaiveDate,
+    pub quantity: f64,
+    pub cost: Money,
+}
+
+/// The gain or loss locked in by a sale.
+#[derive(Debug, Clone)]
+pub struct RealizedGain {
+    pub sold_on: NaiveDate,
+    pub proceeds: Money,
+    /// What the units sold cost
+    pub cost: Money,
+}
+
+/// What an account holds of a security on a day.
+#[derive(Debug, Clone)]
+pub struct Holding {
+    pub account_id: String,
+    pub security: Security,
+    pub lots: Vec<Lot>,
+    /// The latest known price, `None` when none was imported yet
+    pub price: Option<SecurityPrice>,
+}
+
+impl Holding {
+    pub fn quantity(&self) -> f64 {
+        self.lots.iter().map(|lot| lot.quantity).sum()
+    }
+
+    pub fn cost(&self) -> Money {
+        Money::new(
+            self.lots.iter().map(|lot| lot.cost.minor).sum(),
+            &self.security.currency,
+        )
+    }
+
+    pub fn market_value(&self) -> Option<Money> {
+        self.price.as_ref().map(|price| {
+            Money::new(
+                (price.price.minor as f64 * self.quantity()).round() as i64,
+                &self.security.currency,
+            )
+        })
+    }
+
+    pub fn unrealized_gain(&self) -> Option<Money> {
+        self.market_value()
+            .map(|value| Money::new(value.minor - self.cost().minor, &self.security.currency))
+    }
+}
+
+/// Realized gains and dividends of one calendar year in one currency.
+#[derive(Debug, Clone)]
+pub struct YearlyGains {
+    pub year: i32,
+    pub proceeds: Money,
+    pub cost: Money,
+    pub dividends: Money,
+}
+
+impl YearlyGains {
+    pub fn realized(&self) -> Money {
+        Money::new(
+            self.proceeds.minor - self.cost.minor,
+            &self.proceeds.currency,
+        )
+    }
+}
+
+/// Where a replay of the trades in one security of one account ends up.
+struct Position {
+    lots: Vec<Lot>,
+    realized: Vec<RealizedGain>,
+    dividends: Vec<(NaiveDate, Money)>,
+}
+
+/// Replays the trades of one security in one account, oldest first, into the
+/// lots left over and the gains realized along the way.
+fn replay(
+    trades: &[Trade],
+    currency: &str,
+    method: CostBasisMethod,
+) -> Result<Position, InsufficientUnitsError> {
+    let mut position = Position {
+        lots: vec![],
+        realized: vec![],
+        dividends: vec![],
+    };
+
+    for trade in trades {
+        match trade.kind {
+            TradeKind::Buy => {
+                let lot = Lot {
+                    bought_on: trade.traded_on,
+                    quantity: trade.quantity,
+                    cost: Money::new(trade.amount.minor, currency),
+                };
+
+                match (method, position.lots.first_mut()) {
+                    // The average method keeps a single pooled lot
+                    (CostBasisMethod::Average, Some(pool)) => {
+                        pool.quantity += lot.quantity;
+                        pool.cost.minor += lot.cost.minor;
+                    }
+                    _ => position.lots.push(lot),
+                }
+            }
+            TradeKind::Sell => {
+                let held = position.lots.iter().map(|lot| lot.quantity).sum::<f64>();
+                if trade.quantity > held + QUANTITY_EPSILON {
+                    return Err(InsufficientUnitsError {
+                        security_id: trade.security_id.clone(),
+                        sold_on: trade.traded_on,
+                        held,
+                        selling: trade.quantity,
+                    });
+                }
+
+                let mut left = trade.quantity;
+                let mut cost = 0;
+                for lot in position.lots.iter_mut() {
+                    if left <= QUANTITY_EPSILON {
+                        break;
+                    }
+
+                    let taken = left.min(lot.quantity);
+                    let taken_cost = (lot.cost.minor as f64 * taken / lot.quantity).round() as i64;
+                    lot.quantity -= taken;
+                    lot.cost.minor -= taken_cost;
+                    left -= taken;
+                    cost += taken_cost;
+                }
+                position.lots.retain(|lot| lot.quantity > QUANTITY_EPSILON);
+
+                position.realized.push(RealizedGain {
+                    sold_on: trade.traded_on,
+                    proceeds: Money::new(trade.amount.minor, currency),
+                    cost: Money::new(cost, currency),
+                });
+            }
+            TradeKind::Dividend => position
+                .dividends
+                .push((trade.traded_on, Money::new(trade.amount.minor, currency))),
+            TradeKind::Split => {
+                for lot in position.lots.iter_mut() {
+                    lot.quantity *= trade.quantity;
+                }
+            }
+        }
+    }
+
+    Ok(position)
+}
+
+fn trade_columns() -> [Trades; 7] {
+    [
+        Trades::Id,
+        Trades::AccountId,
+        Trades::SecurityId,
+        Trades::Kind,
+        Trades::TradedOn,
+        Trades::Quantity,
+        Trades::Amount,
+    ]
+}
+
+fn trade_from_row(row: &Row, currency: &str) -> Result<Trade, rusqlite::Error> {
+    Ok(Trade {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        security_id: row.get(2)?,
+        kind: TradeKind::from_str(&row.get::<_, String>(3)?).unwrap_or(TradeKind::Buy),
+        traded_on: NaiveDate::from_str(row.get::<_, String>(4)?.as_str())
+            .unwrap_or(Local::now().date_naive()),
+        quantity: row.get(5)?,
+        amount: Money::new(row.get(6)?, currency),
+    })
+}
+
+impl Db {
+    /// Records a trade, refusing it when it would sell units that are not held.
+    pub fn create_trade(&self, trade: &TradeRequest) -> Result<Trade, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let id = random_hash(8);
+        let query = Query::insert()
+            .into_table(Trades::Table)
+            .columns([
+                Trades::Id,
+                Trades::AccountId,
+                Trades::SecurityId,
+                Trades::Kind,
+                Trades::TradedOn,
+                Trades::Quantity,
+                Trades::Amount,
+                Trades::CreatedAt,
+            ])
+            .values_panic(vec![
+                id.clone().into(),
+                trade.account_id.clone().into(),
+                trade.security_id.clone().into(),
+                trade.kind.as_str().into(),
+                trade.traded_on.to_string().into(),
+                trade.quantity.into(),
+                trade.amount.minor.into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&query, ())?;
+
+        self.check_position(&trade.account_id, &trade.security_id)?;
+        tx.commit()?;
+
+        Ok(Trade {
+            id,
+            account_id: trade.account_id.clone(),
+            security_id: trade.security_id.clone(),
+            kind: trade.kind,
+            traded_on: trade.traded_on,
+            quantity: trade.quantity,
+            amount: trade.amount.clone(),
+        })
+    }
+
+    /// Every trade, or those of one account, oldest first.
+    pub fn get_trades(&self, account_id: Option<&str>) -> Result<Vec<Trade>, DbError> {
+        let mut query = Query::select();
+        query
+            .columns(trade_columns())
+            .from(Trades::Table)
+            .order_by(Trades::TradedOn, Order::Asc)
+            .order_by(Trades::CreatedAt, Order::Asc);
+        if let Some(account_id) = account_id {
+            query.and_where(Expr::col(Trades::AccountId).eq(account_id));
+        }
+        let query = query.to_string(SqliteQueryBuilder);
+
+        let securities = self
+            .get_all_securities()?
+            .into_iter()
+            .map(|security| (security.id, security.currency))
+            .collect::<BTreeMap<String, String>>();
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), |row| {
+                let security_id = row.get::<_, String>(2)?;
+                let currency = securities
+                    .get(&security_id)
+                    .map_or(crate::money::DEFAULT_CURRENCY, String::as_str);
+
+                trade_from_row(row, currency)
+            })?
+            .map(|trade| trade.unwrap())
+            .collect::<Vec<Trade>>())
+    }
+
+    /// Deletes a trade, refusing when a later sale would then sell units that
+    /// are no longer held.
+    pub fn delete_trade(&self, id: &str) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let trade = self
+            .get_trades(None)?
+            .into_iter()
+            .find(|trade| trade.id == id)
+            .ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+
+        let query = Query::delete()
+            .from_table(Trades::Table)
+            .and_where(Expr::col(Trades::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&query, ())?;
+
+        self.check_position(&trade.account_id, &trade.security_id)?;
+        Ok(tx.commit()?)
+    }
+
+    /// Fails when the trades of a security in an account sell more than they
+    /// buy at any point.
+    fn check_position(&self, account_id: &str, security_id: &str) -> Result<(), DbError> {
+        let security = self.get_security(security_id)?;
+        let trades = self
+            .get_trades(Some(account_id))?
+            .into_iter()
+            .filter(|trade| trade.security_id == security_id)
+            .collect::<Vec<Trade>>();
+
+        replay(&trades, &security.currency, CostBasisMethod::Fifo)
+            .map(|_| ())
+            .map_err(DbError::invalid)
+    }
+
+    /// Replays the trades up to `on` of every security in every account, or in
+    /// one account, grouped by account and security.
+    fn get_positions(
+        &self,
+        account_id: Option<&str>,
+        method: CostBasisMethod,
+        on: NaiveDate,
+    ) -> Result<Vec<(String, Security, Position)>, DbError> {
+        let mut grouped = BTreeMap::<(String, String), Vec<Trade>>::new();
+        for trade in self.get_trades(account_id)? {
+            if trade.traded_on <= on {
+                grouped
+                    .entry((trade.account_id.clone(), trade.security_id.clone()))
+                    .or_default()
+                    .push(trade);
+            }
+        }
+
+        let mut positions = vec![];
+        for ((account_id, security_id), trades) in grouped {
+            let security = self.get_security(&security_id)?;
+            let position = replay(&trades, &security.currency, method).map_err(DbError::invalid)?;
+
+            positions.push((account_id, security, position));
+        }
+
+        Ok(positions)
+    }
+
+    /// What is held on `on`, priced at the latest price known by then.
+    pub fn get_holdings(
+        &self,
+        account_id: Option<&str>,
+        method: CostBasisMethod,
+        on: NaiveDate,
+    ) -> Result<Vec<Holding>, DbError> {
+        let mut holdings = vec![];
+        for (account_id, security, position) in self.get_positions(account_id, method, on)? {
+            if position.lots.is_empty() {
+                continue;
+            }
+
+            holdings.push(Holding {
+                price: self.get_security_price(&security, on)?,
+                account_id,
+                security,
+                lots: position.lots,
+            });
+        }
+        holdings.sort_by(|a, b| {
+            (&a.account_id, &a.security.symbol).cmp(&(&b.account_id, &b.security.symbol))
+        });
+
+        Ok(holdings)
+    }
+
+    /// Realized gains and dividends per calendar year and currency, oldest
+    /// year first.
+    pub fn get_yearly_gains(
+        &self,
+        account_id: Option<&str>,
+        method: CostBasisMethod,
+    ) -> Result<Vec<YearlyGains>, DbError> {
+        let mut years = BTreeMap::<(i32, String), YearlyGains>::new();
+        let positions = self.get_positions(account_id, method, NaiveDate::MAX)?;
+
+        for (_, security, position) in positions {
+            // Sales add proceeds and cost, dividends add to what was paid out
+            let sales = position
+                .realized
+                .into_iter()
+                .map(|gain| (gain.sold_on, gain.proceeds.minor, gain.cost.minor, 0));
+            let dividends = position
+                .dividends
+                .into_iter()
+                .map(|(paid_on, dividend)| (paid_on, 0, 0, dividend.minor));
+
+            for (date, proceeds, cost, dividend) in sales.chain(dividends) {
+                let year = years
+                    .entry((date.year(), security.currency.clone()))
+                    .or_insert_with(|| YearlyGains {
+                        year: date.year(),
+                        proceeds: Money::new(0, &security.currency),
+                        cost: Money::new(0, &security.currency),
+                        dividends: Money::new(0, &security.currency),
+                    });
+                year.proceeds.minor += proceeds;
+                year.cost.minor += cost;
+                year.dividends.minor += dividend;
+            }
+        }
+
+        Ok(years.into_values().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{account::Account, security::SecurityRequest},
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db},
+    };
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn trade(
+        account: &Account,
+        security: &Security,
+        kind: TradeKind,
+        traded_on: NaiveDate,
+        quantity: f64,
+        amount: i64,
+    ) -> TradeRequest {
+        TradeRequest {
+            account_id: account.id.clone(),
+            security_id: security.id.clone(),
+            kind,
+            traded_on,
+            quantity,
+            amount: Money::new(amount, DEFAULT_CURRENCY),
+        }
+    }
+
+    /// A brokerage account without any trades yet.
+    fn setup(name: &str) -> (Db, Account, Security) {
+        let db = get_test_db(name);
+        let user = create_test_user(&db, "John Doe");
+        let account = create_test_account(&db, &user.id, "Brokerage", 0);
+        let security = db
+            .create_security(&SecurityRequest {
+                symbol: "VTI".to_string(),
+                name: "Vanguard Total Stock Market".to_string(),
+                currency: DEFAULT_CURRENCY.to_string(),
+            })
+            .unwrap();
+
+        (db, account, security)
+    }
+
+    /// Ten units bought for 1,000.00 and ten for 2,000.00 in 2023, with a
+    /// dividend of 50.00. Fifteen are sold for 3,000.00 in February 2024 before
+    /// a 2 for 1 split and a price of 120.00 in March. Returns the first trade.
+    fn setup_trades(name: &str) -> (Db, Account, Trade) {
+        let (db, account, security) = setup(name);
+
+        let first = db
+            .create_trade(&trade(
+                &account,
+                &security,
+                TradeKind::Buy,
+                date(2023, 1, 10),
+                10.0,
+                100000,
+            ))
+            .unwrap();
+        for (kind, traded_on, quantity, amount) in [
+            (TradeKind::Buy, date(2023, 6, 10), 10.0, 200000),
+            (TradeKind::Dividend, date(2023, 12, 20), 0.0, 5000),
+            (TradeKind::Sell, date(2024, 2, 1), 15.0, 300000),
+            (TradeKind::Split, date(2024, 3, 1), 2.0, 0),
+        ] {
+            db.create_trade(&trade(
+                &account, &security, kind, traded_on, quantity, amount,
+            ))
+            .unwrap();
+        }
+        db.set_security_prices(&[SecurityPrice {
+            security_id: security.id.clone(),
+            priced_on: date(2024, 3, 1),
+            price: Money::new(12000, DEFAULT_CURRENCY),
+        }])
+        .unwrap();
+
+        (db, account, first)
+    }
+
+    #[test]
+    fn test_sell_before_buy() {
+        let (db, account, security) = setup("holding_sell_before_buy");
+
+        // Nothing can be sold before it is bought
+        assert!(
+            db.create_trade(&trade(
+                &account,
+                &security,
+                TradeKind::Sell,
+                date(2023, 1, 1),
+                1.0,
+                10000
+            ))
+            .is_err()
+        );
+        assert!(db.get_trades(None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_fifo_holdings() {
+        let (db, account, _) = setup_trades("holding_fifo");
+
+        // FIFO sells all of the first lot and half of the second
+        let holdings = db
+            .get_holdings(Some(&account.id), CostBasisMethod::Fifo, date(2024, 3, 31))
+            .unwrap();
+        assert_eq!(holdings.len(), 1);
+        assert_eq!(holdings[0].quantity(), 10.0);
+        assert_eq!(holdings[0].cost().minor, 100000);
+        assert_eq!(holdings[0].market_value().unwrap().minor, 120000);
+        assert_eq!(holdings[0].unrealized_gain().unwrap().minor, 20000);
+    }
+
+    #[test]
+    fn test_average_holdings() {
+        let (db, account, _) = setup_trades("holding_average");
+
+        // Averaging sells units that all cost 150 each
+        let holdings = db
+            .get_holdings(
+                Some(&account.id),
+                CostBasisMethod::Average,
+                date(2024, 3, 31),
+            )
+            .unwrap();
+        assert_eq!(holdings[0].quantity(), 10.0);
+        assert_eq!(holdings[0].cost().minor, 75000);
+    }
+
+    #[test]
+    fn test_holdings_without_price() {
+        let (db, _, _) = setup_trades("holding_without_price");
+
+        // Before the sale there was no price to value the units at
+        let holdings = db
+            .get_holdings(None, CostBasisMethod::Fifo, date(2024, 1, 31))
+            .unwrap();
+        assert_eq!(holdings[0].quantity(), 20.0);
+        assert!(holdings[0].market_value().is_none());
+    }
+
+    #[test]
+    fn test_yearly_gains() {
+        let (db, _, _) = setup_trades("holding_yearly_gains");
+
+        let gains = db.get_yearly_gains(None, CostBasisMethod::Fifo).unwrap();
+        assert_eq!(gains.len(), 2);
+        assert_eq!(gains[0].year, 2023);
+        assert_eq!(gains[0].realized().minor, 0);
+        assert_eq!(gains[0].dividends.minor, 5000);
+        assert_eq!(gains[1].year, 2024);
+        assert_eq!(gains[1].proceeds.minor, 300000);
+        assert_eq!(gains[1].realized().minor, 100000);
+        let gains = db.get_yearly_gains(None, CostBasisMethod::Average).unwrap();
+        assert_eq!(gains[1].realized().minor, 75000);
+    }
+
+    #[test]
+    fn test_delete_sold_trade() {
+        let (db, account, first) = setup_trades("holding_delete_sold_trade");
+
+        // Deleting the first purchase would leave the sale short
+        assert!(db.delete_trade(&first.id).is_err());
+        assert_eq!(db.get_trades(Some(&account.id)).unwrap().len(), 5);
+    }
+}
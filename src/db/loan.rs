@@ -0,0 +1,324 @@
+use chrono::{Months, NaiveDate};
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+};
+
+use super::account::LoanTerms;
+
+/// How many payments a projection runs for at most, so that a payment that
+/// never covers the interest does not go on forever.
+const MAX_PAYMENTS: u32 = 1200;
+
+#[derive(Debug)]
+pub struct NotALoanError {
+    pub account_id: String,
+}
+
+impl std::fmt::Display for NotALoanError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Account {} is not a loan with a principal, rate, term and start date",
+            self.account_id
+        )
+    }
+}
+
+impl std::error::Error for NotALoanError {}
+
+/// One payment on a loan, either recorded or still to come.
+#[derive(Debug, Clone)]
+pub struct AmortizationRow {
+    pub number: u32,
+    pub date: NaiveDate,
+    pub payment: Money,
+    pub interest: Money,
+    pub principal: Money,
+    /// The principal left after the payment
+    pub remaining: Money,
+    /// Whether the payment was recorded rather than projected
+    pub paid: bool,
+}
+
+/// The payments recorded on a loan followed by the ones it takes to pay off
+/// the rest.
+#[derive(Debug, Clone)]
+pub struct LoanSchedule {
+    pub terms: LoanTerms,
+    /// The scheduled payment that pays the loan off over its term
+    pub monthly_payment: Money,
+    /// Paid on top of the monthly payment from now on
+    pub extra: Money,
+    pub rows: Vec<AmortizationRow>,
+}
+
+impl LoanSchedule {
+    /// The principal left after the recorded payments.
+    pub fn remaining_principal(&self) -> Money {
+        self.rows
+            .iter()
+            .rfind(|row| row.paid)
+            .map_or(self.terms.principal.clone(), |row| row.remaining.clone())
+    }
+
+    /// The day of the last payment, or `None` when the payments never pay the
+    /// loan off.
+    pub fn payoff_date(&self) -> Option<NaiveDate> {
+        match self.rows.last() {
+            Some(row) if row.remaining.minor == 0 => Some(row.date),
+            Some(_) => None,
+            None => Some(self.terms.start_date),
+        }
+    }
+
+    pub fn interest_paid(&self) -> Money {
+        self.interest(true)
+    }
+
+    pub fn interest_remaining(&self) -> Money {
+        self.interest(false)
+    }
+
+    fn interest(&self, paid: bool) -> Money {
+        Money::new(
+            self.rows
+                .iter()
+                .filter(|row| row.paid == paid)
+                .map(|row| row.interest.minor)
+                .sum(),
+            &self.terms.principal.currency,
+        )
+    }
+}
+
+fn monthly_rate(terms: &LoanTerms) -> f64 {
+    terms.rate / 100.0 / 12.0
+}
+
+/// The fixed monthly payment that pays `terms` off over its term, rounded up
+/// to the next minor unit.
+pub fn monthly_payment(terms: &LoanTerms) -> Money {
+    let principal = terms.principal.minor as f64;
+    let months = terms.term_months.max(1) as f64;
+    let rate = monthly_rate(terms);
+
+    let payment = if rate == 0.0 {
+        principal / months
+    } else {
+        principal * rate / (1.0 - (1.0 + rate).powf(-months))
+    };
+
+    Money::new(payment.ceil() as i64, &terms.principal.currency)
+}
+
+/// Splits `payment` into a month of interest on `remaining` and the principal
+/// it pays back, which never goes beyond what is left.
+fn split_payment(remaining: i64, payment: i64, rate: f64) -> (i64, i64) {
+    let interest = ((remaining as f64 * rate).round() as i64).min(payment);
+    let principal = (payment - interest).min(remaining);
+
+    (interest, principal)
+}
+
+impl Db {
+    /// Builds the amortization schedule of a loan account. Every payment
+    /// recorded on the account since the loan started is split into a month of
+    /// interest and principal, after which the monthly payment plus `extra`
+    /// is projected until the loan is paid off.
+    pub fn get_loan_schedule(
+        &self,
+        account_id: &str,
+        extra: &Money,
+    ) -> Result<LoanSchedule, DbError> {
+        let account = self.get_account(account_id)?;
+        let terms = account.loan.clone().ok_or_else(|| {
+            DbError::invalid(NotALoanError {
+                account_id: account.id.clone(),
+            })
+        })?;
+        let currency = terms.principal.currency.clone();
+        let rate = monthly_rate(&terms);
+        let monthly = monthly_payment(&terms);
+
+        let mut payments = self
+            .get_transactions_by_account(&account.id)?
+            .into_iter()
+            .filter(|transaction| {
+                transaction.posted_on >= terms.start_date && transaction.signed_amount() > 0
+            })
+            .collect::<Vec<_>>();
+        payments.sort_by_key(|transaction| transaction.posted_on);
+
+        let mut rows = vec![];
+        let mut remaining = terms.principal.minor;
+        for transaction in payments {
+            if remaining == 0 {
+                break;
+            }
+
+            let payment = transaction.signed_amount();
+            let (interest, principal) = split_payment(remaining, payment, rate);
+            remaining -= principal;
+            rows.push(AmortizationRow {
+                number: rows.len() as u32 + 1,
+                date: transaction.posted_on,
+                payment: Money::new(payment, &currency),
+                interest: Money::new(interest, &currency),
+                principal: Money::new(principal, &currency),
+                remaining: Money::new(remaining, &currency),
+                paid: true,
+            });
+        }
+
+        while remaining > 0 && rows.len() < MAX_PAYMENTS as usize {
+            let number = rows.len() as u32 + 1;
+            let (interest, principal) = split_payment(remaining, monthly.minor + extra.minor, rate);
+            if principal <= 0 {
+                break;
+            }
+
+            remaining -= principal;
+            rows.push(AmortizationRow {
+                number,
+                date: terms.start_date + Months::new(number),
+                payment: Money::new(interest + principal, &currency),
+                interest: Money::new(interest, &currency),
+                principal: Money::new(principal, &currency),
+                remaining: Money::new(remaining, &currency),
+                paid: false,
+            });
+        }
+
+        Ok(LoanSchedule {
+            terms,
+            monthly_payment: monthly,
+            extra: extra.clone(),
+            rows,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::AccountHolder,
+            transaction::{TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_loan_schedule() {
+        let _ = std::fs::remove_file(get_test_db_path("loan"));
+
+        let db = Db::new(get_test_db_path("loan").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Car Loan".to_string(),
+                bank: "Chase".to_string(),
+                account_number: None,
+                account_type: AccountType::Loan,
+                opening_balance: Money::new(-1000000, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Loan".to_string(),
+                icon: "🚗".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        let no_extra = Money::new(0, DEFAULT_CURRENCY);
+
+        // Without terms there is nothing to amortize
+        assert!(db.get_loan_schedule(&account.id, &no_extra).is_err());
+
+        let terms = LoanTerms {
+            principal: Money::new(1000000, DEFAULT_CURRENCY),
+            rate: 12.0,
+            term_months: 12,
+            start_date: NaiveDate::from_ymd_opt(2024, 1, 15).unwrap(),
+        };
+        db.set_loan_terms(&account.id, Some(&terms)).unwrap();
+        assert_eq!(db.get_account(&account.id).unwrap().loan, Some(terms));
+
+        // 1% a month over a year
+        let schedule = db.get_loan_schedule(&account.id, &no_extra).unwrap();
+        assert_eq!(schedule.monthly_payment.minor, 88849);
+        assert_eq!(schedule.rows.len(), 12);
+        assert_eq!(schedule.rows[0].interest.minor, 10000);
+        assert_eq!(schedule.rows[0].principal.minor, 78849);
+        assert_eq!(
+            schedule.rows[0].date,
+            NaiveDate::from_ymd_opt(2024, 2, 15).unwrap()
+        );
+        assert_eq!(schedule.payoff_date(), NaiveDate::from_ymd_opt(2025, 1, 15));
+        assert_eq!(schedule.rows[11].payment.minor, 88847);
+        assert_eq!(schedule.interest_remaining().minor, 66186);
+
+        for (amount, posted_on, status) in [
+            (88849, "2024-02-15", TransactionStatus::Cleared),
+            (188849, "2024-03-15", TransactionStatus::Cleared),
+            (88849, "2024-04-15", TransactionStatus::Void),
+        ] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(amount, DEFAULT_CURRENCY),
+                transaction_type: "credit".to_string(),
+                description: "Payment".to_string(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_str(posted_on).unwrap(),
+                status,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        // The extra thousand goes entirely into principal
+        let schedule = db.get_loan_schedule(&account.id, &no_extra).unwrap();
+        assert!(schedule.rows[1].paid && !schedule.rows[2].paid);
+        assert_eq!(schedule.rows[1].interest.minor, 9212);
+        assert_eq!(schedule.rows[1].principal.minor, 179637);
+        assert_eq!(schedule.remaining_principal().minor, 741514);
+        assert_eq!(schedule.interest_paid().minor, 19212);
+        assert_eq!(
+            schedule.payoff_date(),
+            NaiveDate::from_ymd_opt(2024, 12, 15)
+        );
+
+        // Paying more every month pays the loan off sooner
+        let faster = db
+            .get_loan_schedule(&account.id, &Money::new(50000, DEFAULT_CURRENCY))
+            .unwrap();
+        assert_eq!(faster.remaining_principal().minor, 741514);
+        assert!(faster.payoff_date() < schedule.payoff_date());
+        assert!(faster.interest_remaining().minor < schedule.interest_remaining().minor);
+
+        db.set_loan_terms(&account.id, None).unwrap();
+        assert!(db.get_account(&account.id).unwrap().loan.is_none());
+    }
+}
@@ -1,8 +1,32 @@
+use chrono::Local;
 use rusqlite::{Connection, Result};
-use sea_query::{ColumnDef, ForeignKey, ForeignKeyAction, Iden, SqliteQueryBuilder, Table};
+use sea_query::{
+    Alias, ColumnDef, Expr, ForeignKey, ForeignKeyAction, Iden, Index, IntoIden, Query,
+    SqliteQueryBuilder, Table,
+};
+
+use crate::{db::holder::WHOLE_OWNERSHIP, money::DEFAULT_CURRENCY};
 
 pub mod account;
+pub mod attachment;
+pub mod budget;
 pub mod category;
+pub mod envelope;
+pub mod exchange_rate;
+pub mod expense;
+pub mod goal;
+pub mod holder;
+pub mod holding;
+pub mod loan;
+pub mod networth;
+pub mod payee;
+pub mod profile;
+pub mod reconciliation;
+pub mod schedule;
+pub mod security;
+pub mod split;
+pub mod statement;
+pub mod tag;
 pub mod transaction;
 pub mod users;
 
@@ -10,12 +34,65 @@ pub struct Db {
     conn: Connection,
 }
 
+/// Why a database call failed: either SQLite itself, or a request that breaks
+/// one of the rules of the ledger, like splits that do not add up.
+#[derive(Debug)]
+pub enum DbError {
+    Sqlite(rusqlite::Error),
+    Invalid(Box<dyn std::error::Error + Send + Sync>),
+}
+
+impl DbError {
+    pub fn invalid(error: impl std::error::Error + Send + Sync + 'static) -> Self {
+        DbError::Invalid(Box::new(error))
+    }
+}
+
+impl std::fmt::Display for DbError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DbError::Sqlite(error) => error.fmt(f),
+            DbError::Invalid(error) => error.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for DbError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            DbError::Sqlite(error) => Some(error),
+            DbError::Invalid(error) => Some(error.as_ref()),
+        }
+    }
+}
+
+impl From<rusqlite::Error> for DbError {
+    fn from(error: rusqlite::Error) -> Self {
+        DbError::Sqlite(error)
+    }
+}
+
+/// Rule violations are reported as invalid input and missing rows as not
+/// found, like the checks the handlers make themselves.
+impl From<DbError> for std::io::Error {
+    fn from(error: DbError) -> Self {
+        let kind = match &error {
+            DbError::Sqlite(rusqlite::Error::QueryReturnedNoRows) => std::io::ErrorKind::NotFound,
+            DbError::Sqlite(_) => std::io::ErrorKind::Other,
+            DbError::Invalid(_) => std::io::ErrorKind::InvalidInput,
+        };
+
+        std::io::Error::new(kind, error)
+    }
+}
+
 #[derive(Iden)]
 enum Users {
     Table,
     Id,
     Name,
     CreatedAt,
+    ArchivedAt,
 }
 
 #[derive(Iden)]
@@ -25,10 +102,30 @@ enum Accounts {
     Name,
     Bank,
     AccountNumber,
+    Currency,
+    OpeningBalance,
     Balance,
     HolderId,
+    Type,
+    ClosingDay,
+    DueDay,
+    CreditLimit,
+    Apr,
+    LoanPrincipal,
+    LoanRate,
+    LoanTerm,
+    LoanStartDate,
     CreatedAt,
     UpdatedAt,
+    ArchivedAt,
+}
+
+#[derive(Iden)]
+enum AccountHolders {
+    Table,
+    AccountId,
+    UserId,
+    Share,
 }
 
 #[derive(Iden)]
@@ -37,7 +134,9 @@ enum Categories {
     Id,
     Name,
     Icon,
+    ParentId,
     CreatedAt,
+    ArchivedAt,
 }
 
 #[derive(Iden)]
@@ -49,10 +148,369 @@ enum Transactions {
     Type,
     Description,
     CategoryId,
+    PostedOn,
+    TransferId,
+    PayeeId,
+    Status,
     CreatedAt,
     UpdatedAt,
 }
 
+#[derive(Iden)]
+enum TransactionSplits {
+    Table,
+    Id,
+    TransactionId,
+    CategoryId,
+    Amount,
+    Memo,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Attachments {
+    Table,
+    Id,
+    TransactionId,
+    FileName,
+    Size,
+    Content,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Payees {
+    Table,
+    Id,
+    Name,
+    DefaultCategoryId,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Tags {
+    Table,
+    Id,
+    Name,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum TransactionTags {
+    Table,
+    TransactionId,
+    TagId,
+}
+
+#[derive(Iden)]
+enum Reconciliations {
+    Table,
+    Id,
+    AccountId,
+    StatementDate,
+    StatementBalance,
+    TransactionCount,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Schedules {
+    Table,
+    Id,
+    AccountId,
+    Amount,
+    Type,
+    Description,
+    CategoryId,
+    PayeeId,
+    Frequency,
+    Interval,
+    StartDate,
+    EndDate,
+    LastPostedOn,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum ScheduleSkips {
+    Table,
+    ScheduleId,
+    Date,
+}
+
+#[derive(Iden)]
+enum Budgets {
+    Table,
+    CategoryId,
+    Month,
+    Amount,
+    Currency,
+    Rollover,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum EnvelopeAssignments {
+    Table,
+    CategoryId,
+    Month,
+    Amount,
+    Currency,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum EnvelopeMoves {
+    Table,
+    Id,
+    FromCategoryId,
+    ToCategoryId,
+    Month,
+    Amount,
+    Currency,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Goals {
+    Table,
+    Id,
+    Name,
+    AccountId,
+    TargetAmount,
+    TargetDate,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Securities {
+    Table,
+    Id,
+    Symbol,
+    Name,
+    Currency,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum SecurityPrices {
+    Table,
+    SecurityId,
+    PricedOn,
+    Price,
+}
+
+#[derive(Iden)]
+enum Trades {
+    Table,
+    Id,
+    AccountId,
+    SecurityId,
+    Kind,
+    TradedOn,
+    Quantity,
+    Amount,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum NetWorthSnapshots {
+    Table,
+    TakenOn,
+    Currency,
+    Assets,
+    Liabilities,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum SharedExpenses {
+    Table,
+    Id,
+    PaidBy,
+    Description,
+    Amount,
+    Currency,
+    Method,
+    SpentOn,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum ExpenseShares {
+    Table,
+    ExpenseId,
+    UserId,
+    Amount,
+}
+
+#[derive(Iden)]
+enum Settlements {
+    Table,
+    Id,
+    FromUserId,
+    ToUserId,
+    Amount,
+    Currency,
+    SettledOn,
+    TransferId,
+    CreatedAt,
+}
+
+#[derive(Iden)]
+enum Settings {
+    Table,
+    Key,
+    Value,
+}
+
+#[derive(Iden)]
+enum ExchangeRates {
+    Table,
+    Date,
+    FromCurrency,
+    ToCurrency,
+    Rate,
+}
+
+/// Creates a table as it is today under the given name, see [`Db::rebuild_table`].
+type CreateTable = fn(Alias) -> String;
+
+/// The accounts table as it is today, created under `table` so that
+/// migrations can rebuild it.
+fn accounts_table(table: impl IntoIden + 'static) -> String {
+    Table::create()
+        .table(table)
+        .if_not_exists()
+        .col(ColumnDef::new(Accounts::Id).text().not_null().primary_key())
+        .col(ColumnDef::new(Accounts::Name).text().not_null())
+        .col(ColumnDef::new(Accounts::Bank).text().not_null())
+        .col(ColumnDef::new(Accounts::AccountNumber).text())
+        .col(ColumnDef::new(Accounts::Currency).text().not_null())
+        .col(
+            ColumnDef::new(Accounts::OpeningBalance)
+                .integer()
+                .not_null(),
+        )
+        .col(ColumnDef::new(Accounts::Balance).integer().not_null())
+        .col(
+            ColumnDef::new(Accounts::Type)
+                .text()
+                .not_null()
+                .default("checking"),
+        )
+        .col(ColumnDef::new(Accounts::ClosingDay).integer())
+        .col(ColumnDef::new(Accounts::DueDay).integer())
+        .col(ColumnDef::new(Accounts::CreditLimit).integer())
+        .col(ColumnDef::new(Accounts::Apr).double())
+        .col(ColumnDef::new(Accounts::LoanPrincipal).integer())
+        .col(ColumnDef::new(Accounts::LoanRate).double())
+        .col(ColumnDef::new(Accounts::LoanTerm).integer())
+        .col(ColumnDef::new(Accounts::LoanStartDate).date())
+        .col(ColumnDef::new(Accounts::CreatedAt).timestamp().not_null())
+        .col(ColumnDef::new(Accounts::UpdatedAt).timestamp().not_null())
+        .col(ColumnDef::new(Accounts::ArchivedAt).timestamp())
+        .build(SqliteQueryBuilder)
+}
+
+/// Split lines keep their category: deleting a category that a split line is
+/// filed under is refused rather than silently dropping the line.
+fn transaction_splits_table(table: impl IntoIden + 'static) -> String {
+    Table::create()
+        .table(table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(TransactionSplits::Id)
+                .text()
+                .not_null()
+                .primary_key(),
+        )
+        .col(
+            ColumnDef::new(TransactionSplits::TransactionId)
+                .text()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(TransactionSplits::CategoryId)
+                .text()
+                .not_null(),
+        )
+        .col(
+            ColumnDef::new(TransactionSplits::Amount)
+                .integer()
+                .not_null(),
+        )
+        .col(ColumnDef::new(TransactionSplits::Memo).text().not_null())
+        .col(
+            ColumnDef::new(TransactionSplits::CreatedAt)
+                .timestamp()
+                .not_null(),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_transaction_splits_transactions")
+                .from(TransactionSplits::Table, TransactionSplits::TransactionId)
+                .to(Transactions::Table, Transactions::Id)
+                .on_delete(ForeignKeyAction::Cascade),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_transaction_splits_categories")
+                .from(TransactionSplits::Table, TransactionSplits::CategoryId)
+                .to(Categories::Table, Categories::Id)
+                .on_delete(ForeignKeyAction::Restrict),
+        )
+        .build(SqliteQueryBuilder)
+}
+
+/// Schedules keep their category: deleting a category a schedule is filed
+/// under is refused rather than silently dropping the schedule.
+fn schedules_table(table: impl IntoIden + 'static) -> String {
+    Table::create()
+        .table(table)
+        .if_not_exists()
+        .col(
+            ColumnDef::new(Schedules::Id)
+                .text()
+                .not_null()
+                .primary_key(),
+        )
+        .col(ColumnDef::new(Schedules::AccountId).text().not_null())
+        .col(ColumnDef::new(Schedules::Amount).integer().not_null())
+        .col(ColumnDef::new(Schedules::Type).text().not_null())
+        .col(ColumnDef::new(Schedules::Description).text().not_null())
+        .col(ColumnDef::new(Schedules::CategoryId).text().not_null())
+        .col(ColumnDef::new(Schedules::PayeeId).text())
+        .col(ColumnDef::new(Schedules::Frequency).text().not_null())
+        .col(ColumnDef::new(Schedules::Interval).integer().not_null())
+        .col(ColumnDef::new(Schedules::StartDate).date().not_null())
+        .col(ColumnDef::new(Schedules::EndDate).date())
+        .col(ColumnDef::new(Schedules::LastPostedOn).date())
+        .col(ColumnDef::new(Schedules::CreatedAt).timestamp().not_null())
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_schedules_accounts")
+                .from(Schedules::Table, Schedules::AccountId)
+                .to(Accounts::Table, Accounts::Id)
+                .on_delete(ForeignKeyAction::Cascade),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_schedules_categories")
+                .from(Schedules::Table, Schedules::CategoryId)
+                .to(Categories::Table, Categories::Id)
+                .on_delete(ForeignKeyAction::Restrict),
+        )
+        .foreign_key(
+            ForeignKey::create()
+                .name("fk_schedules_payees")
+                .from(Schedules::Table, Schedules::PayeeId)
+                .to(Payees::Table, Payees::Id)
+                .on_delete(ForeignKeyAction::SetNull),
+        )
+        .build(SqliteQueryBuilder)
+}
+
 impl Db {
     pub fn new(path: &str) -> Result<Self> {
         let conn = Connection::open(path)?;
@@ -66,26 +524,35 @@ impl Db {
             .col(ColumnDef::new(Users::Id).text().not_null().primary_key())
             .col(ColumnDef::new(Users::Name).text().not_null())
             .col(ColumnDef::new(Users::CreatedAt).timestamp().not_null())
+            .col(ColumnDef::new(Users::ArchivedAt).timestamp())
             .build(SqliteQueryBuilder);
 
-        let create_accounts = Table::create()
-            .table(Accounts::Table)
+        let create_accounts = accounts_table(Accounts::Table);
+
+        let create_account_holders = Table::create()
+            .table(AccountHolders::Table)
             .if_not_exists()
-            .col(ColumnDef::new(Accounts::Id).text().not_null().primary_key())
-            .col(ColumnDef::new(Accounts::Name).text().not_null())
-            .col(ColumnDef::new(Accounts::Bank).text().not_null())
-            .col(ColumnDef::new(Accounts::AccountNumber).text())
-            .col(ColumnDef::new(Accounts::Balance).integer().not_null())
-            .col(ColumnDef::new(Accounts::HolderId).text().not_null())
-            .col(ColumnDef::new(Accounts::CreatedAt).timestamp().not_null())
-            .col(ColumnDef::new(Accounts::UpdatedAt).timestamp().not_null())
+            .col(ColumnDef::new(AccountHolders::AccountId).text().not_null())
+            .col(ColumnDef::new(AccountHolders::UserId).text().not_null())
+            .col(ColumnDef::new(AccountHolders::Share).integer().not_null())
+            .primary_key(
+                Index::create()
+                    .col(AccountHolders::AccountId)
+                    .col(AccountHolders::UserId),
+            )
             .foreign_key(
                 ForeignKey::create()
-                    .name("fk_accounts_users")
-                    .from(Accounts::Table, Accounts::HolderId)
-                    .to(Users::Table, Users::Id)
+                    .name("fk_account_holders_accounts")
+                    .from(AccountHolders::Table, AccountHolders::AccountId)
+                    .to(Accounts::Table, Accounts::Id)
                     .on_delete(ForeignKeyAction::Cascade),
             )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_account_holders_users")
+                    .from(AccountHolders::Table, AccountHolders::UserId)
+                    .to(Users::Table, Users::Id),
+            )
             .build(SqliteQueryBuilder);
 
         let create_categories = Table::create()
@@ -99,7 +566,32 @@ impl Db {
             )
             .col(ColumnDef::new(Categories::Name).text().not_null())
             .col(ColumnDef::new(Categories::Icon).text().not_null())
+            .col(ColumnDef::new(Categories::ParentId).text())
             .col(ColumnDef::new(Categories::CreatedAt).timestamp().not_null())
+            .col(ColumnDef::new(Categories::ArchivedAt).timestamp())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_categories_parent")
+                    .from(Categories::Table, Categories::ParentId)
+                    .to(Categories::Table, Categories::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_payees = Table::create()
+            .table(Payees::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Payees::Id).text().not_null().primary_key())
+            .col(ColumnDef::new(Payees::Name).text().not_null().unique_key())
+            .col(ColumnDef::new(Payees::DefaultCategoryId).text())
+            .col(ColumnDef::new(Payees::CreatedAt).timestamp().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_payees_categories")
+                    .from(Payees::Table, Payees::DefaultCategoryId)
+                    .to(Categories::Table, Categories::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            )
             .build(SqliteQueryBuilder);
 
         let create_transactions = Table::create()
@@ -116,6 +608,15 @@ impl Db {
             .col(ColumnDef::new(Transactions::Type).text().not_null())
             .col(ColumnDef::new(Transactions::Description).text().not_null())
             .col(ColumnDef::new(Transactions::CategoryId).text().not_null())
+            .col(ColumnDef::new(Transactions::PostedOn).date().not_null())
+            .col(ColumnDef::new(Transactions::TransferId).text())
+            .col(ColumnDef::new(Transactions::PayeeId).text())
+            .col(
+                ColumnDef::new(Transactions::Status)
+                    .text()
+                    .not_null()
+                    .default("pending"),
+            )
             .col(
                 ColumnDef::new(Transactions::CreatedAt)
                     .timestamp()
@@ -140,18 +641,869 @@ impl Db {
                     .to(Categories::Table, Categories::Id)
                     .on_delete(ForeignKeyAction::Cascade),
             )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_transactions_payees")
+                    .from(Transactions::Table, Transactions::PayeeId)
+                    .to(Payees::Table, Payees::Id)
+                    .on_delete(ForeignKeyAction::SetNull),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_transaction_splits = transaction_splits_table(TransactionSplits::Table);
+
+        let create_attachments = Table::create()
+            .table(Attachments::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(Attachments::Id)
+                    .text()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(Attachments::TransactionId).text().not_null())
+            .col(ColumnDef::new(Attachments::FileName).text().not_null())
+            .col(ColumnDef::new(Attachments::Size).integer().not_null())
+            .col(ColumnDef::new(Attachments::Content).blob().not_null())
+            .col(
+                ColumnDef::new(Attachments::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_attachments_transactions")
+                    .from(Attachments::Table, Attachments::TransactionId)
+                    .to(Transactions::Table, Transactions::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
             .build(SqliteQueryBuilder);
 
-        let create_tables = vec![
-            create_users,
-            create_accounts,
-            create_categories,
-            create_transactions,
-        ];
-        for table in create_tables {
-            self.conn.execute(&table, ())?;
-        }
+        let create_tags = Table::create()
+            .table(Tags::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Tags::Id).text().not_null().primary_key())
+            .col(ColumnDef::new(Tags::Name).text().not_null().unique_key())
+            .col(ColumnDef::new(Tags::CreatedAt).timestamp().not_null())
+            .build(SqliteQueryBuilder);
 
-        Ok(())
+        let create_transaction_tags = Table::create()
+            .table(TransactionTags::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(TransactionTags::TransactionId)
+                    .text()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(TransactionTags::TagId).text().not_null())
+            .primary_key(
+                Index::create()
+                    .col(TransactionTags::TransactionId)
+                    .col(TransactionTags::TagId),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_transaction_tags_transactions")
+                    .from(TransactionTags::Table, TransactionTags::TransactionId)
+                    .to(Transactions::Table, Transactions::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_transaction_tags_tags")
+                    .from(TransactionTags::Table, TransactionTags::TagId)
+                    .to(Tags::Table, Tags::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_reconciliations = Table::create()
+            .table(Reconciliations::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(Reconciliations::Id)
+                    .text()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(Reconciliations::AccountId).text().not_null())
+            .col(
+                ColumnDef::new(Reconciliations::StatementDate)
+                    .date()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(Reconciliations::StatementBalance)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(Reconciliations::TransactionCount)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(Reconciliations::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_reconciliations_accounts")
+                    .from(Reconciliations::Table, Reconciliations::AccountId)
+                    .to(Accounts::Table, Accounts::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_schedules = schedules_table(Schedules::Table);
+
+        let create_schedule_skips = Table::create()
+            .table(ScheduleSkips::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(ScheduleSkips::ScheduleId).text().not_null())
+            .col(ColumnDef::new(ScheduleSkips::Date).date().not_null())
+            .primary_key(
+                Index::create()
+                    .col(ScheduleSkips::ScheduleId)
+                    .col(ScheduleSkips::Date),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_schedule_skips_schedules")
+                    .from(ScheduleSkips::Table, ScheduleSkips::ScheduleId)
+                    .to(Schedules::Table, Schedules::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_budgets = Table::create()
+            .table(Budgets::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Budgets::CategoryId).text().not_null())
+            .col(ColumnDef::new(Budgets::Month).date().not_null())
+            .col(ColumnDef::new(Budgets::Amount).integer().not_null())
+            .col(ColumnDef::new(Budgets::Currency).text().not_null())
+            .col(
+                ColumnDef::new(Budgets::Rollover)
+                    .boolean()
+                    .not_null()
+                    .default(false),
+            )
+            .col(ColumnDef::new(Budgets::CreatedAt).timestamp().not_null())
+            .primary_key(Index::create().col(Budgets::CategoryId).col(Budgets::Month))
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_budgets_categories")
+                    .from(Budgets::Table, Budgets::CategoryId)
+                    .to(Categories::Table, Categories::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_envelope_assignments = Table::create()
+            .table(EnvelopeAssignments::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(EnvelopeAssignments::CategoryId)
+                    .text()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(EnvelopeAssignments::Month).date().not_null())
+            .col(
+                ColumnDef::new(EnvelopeAssignments::Amount)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(EnvelopeAssignments::Currency)
+                    .text()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(EnvelopeAssignments::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .primary_key(
+                Index::create()
+                    .col(EnvelopeAssignments::CategoryId)
+                    .col(EnvelopeAssignments::Month),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_envelope_assignments_categories")
+                    .from(EnvelopeAssignments::Table, EnvelopeAssignments::CategoryId)
+                    .to(Categories::Table, Categories::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_envelope_moves = Table::create()
+            .table(EnvelopeMoves::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(EnvelopeMoves::Id)
+                    .text()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(EnvelopeMoves::FromCategoryId)
+                    .text()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(EnvelopeMoves::ToCategoryId)
+                    .text()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(EnvelopeMoves::Month).date().not_null())
+            .col(ColumnDef::new(EnvelopeMoves::Amount).integer().not_null())
+            .col(ColumnDef::new(EnvelopeMoves::Currency).text().not_null())
+            .col(
+                ColumnDef::new(EnvelopeMoves::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_envelope_moves_from_categories")
+                    .from(EnvelopeMoves::Table, EnvelopeMoves::FromCategoryId)
+                    .to(Categories::Table, Categories::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_envelope_moves_to_categories")
+                    .from(EnvelopeMoves::Table, EnvelopeMoves::ToCategoryId)
+                    .to(Categories::Table, Categories::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_goals = Table::create()
+            .table(Goals::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Goals::Id).text().not_null().primary_key())
+            .col(ColumnDef::new(Goals::Name).text().not_null())
+            .col(ColumnDef::new(Goals::AccountId).text().not_null())
+            .col(ColumnDef::new(Goals::TargetAmount).integer().not_null())
+            .col(ColumnDef::new(Goals::TargetDate).date().not_null())
+            .col(ColumnDef::new(Goals::CreatedAt).timestamp().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_goals_accounts")
+                    .from(Goals::Table, Goals::AccountId)
+                    .to(Accounts::Table, Accounts::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_exchange_rates = Table::create()
+            .table(ExchangeRates::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(ExchangeRates::Date).date().not_null())
+            .col(
+                ColumnDef::new(ExchangeRates::FromCurrency)
+                    .text()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(ExchangeRates::ToCurrency).text().not_null())
+            .col(ColumnDef::new(ExchangeRates::Rate).double().not_null())
+            .primary_key(
+                Index::create()
+                    .col(ExchangeRates::Date)
+                    .col(ExchangeRates::FromCurrency)
+                    .col(ExchangeRates::ToCurrency),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_securities = Table::create()
+            .table(Securities::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(Securities::Id)
+                    .text()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(
+                ColumnDef::new(Securities::Symbol)
+                    .text()
+                    .not_null()
+                    .unique_key(),
+            )
+            .col(ColumnDef::new(Securities::Name).text().not_null())
+            .col(ColumnDef::new(Securities::Currency).text().not_null())
+            .col(ColumnDef::new(Securities::CreatedAt).timestamp().not_null())
+            .build(SqliteQueryBuilder);
+
+        let create_security_prices = Table::create()
+            .table(SecurityPrices::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(SecurityPrices::SecurityId).text().not_null())
+            .col(ColumnDef::new(SecurityPrices::PricedOn).date().not_null())
+            .col(ColumnDef::new(SecurityPrices::Price).integer().not_null())
+            .primary_key(
+                Index::create()
+                    .col(SecurityPrices::SecurityId)
+                    .col(SecurityPrices::PricedOn),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_security_prices_securities")
+                    .from(SecurityPrices::Table, SecurityPrices::SecurityId)
+                    .to(Securities::Table, Securities::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_trades = Table::create()
+            .table(Trades::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(Trades::Id).text().not_null().primary_key())
+            .col(ColumnDef::new(Trades::AccountId).text().not_null())
+            .col(ColumnDef::new(Trades::SecurityId).text().not_null())
+            .col(ColumnDef::new(Trades::Kind).text().not_null())
+            .col(ColumnDef::new(Trades::TradedOn).date().not_null())
+            .col(ColumnDef::new(Trades::Quantity).double().not_null())
+            .col(ColumnDef::new(Trades::Amount).integer().not_null())
+            .col(ColumnDef::new(Trades::CreatedAt).timestamp().not_null())
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_trades_accounts")
+                    .from(Trades::Table, Trades::AccountId)
+                    .to(Accounts::Table, Accounts::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_trades_securities")
+                    .from(Trades::Table, Trades::SecurityId)
+                    .to(Securities::Table, Securities::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_net_worth_snapshots = Table::create()
+            .table(NetWorthSnapshots::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(NetWorthSnapshots::TakenOn).date().not_null())
+            .col(
+                ColumnDef::new(NetWorthSnapshots::Currency)
+                    .text()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(NetWorthSnapshots::Assets)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(NetWorthSnapshots::Liabilities)
+                    .integer()
+                    .not_null(),
+            )
+            .col(
+                ColumnDef::new(NetWorthSnapshots::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .primary_key(
+                Index::create()
+                    .col(NetWorthSnapshots::TakenOn)
+                    .col(NetWorthSnapshots::Currency),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_shared_expenses = Table::create()
+            .table(SharedExpenses::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SharedExpenses::Id)
+                    .text()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SharedExpenses::PaidBy).text().not_null())
+            .col(
+                ColumnDef::new(SharedExpenses::Description)
+                    .text()
+                    .not_null(),
+            )
+            .col(ColumnDef::new(SharedExpenses::Amount).integer().not_null())
+            .col(ColumnDef::new(SharedExpenses::Currency).text().not_null())
+            .col(ColumnDef::new(SharedExpenses::Method).text().not_null())
+            .col(ColumnDef::new(SharedExpenses::SpentOn).date().not_null())
+            .col(
+                ColumnDef::new(SharedExpenses::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_shared_expenses_users")
+                    .from(SharedExpenses::Table, SharedExpenses::PaidBy)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_expense_shares = Table::create()
+            .table(ExpenseShares::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(ExpenseShares::ExpenseId).text().not_null())
+            .col(ColumnDef::new(ExpenseShares::UserId).text().not_null())
+            .col(ColumnDef::new(ExpenseShares::Amount).integer().not_null())
+            .primary_key(
+                Index::create()
+                    .col(ExpenseShares::ExpenseId)
+                    .col(ExpenseShares::UserId),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_expense_shares_shared_expenses")
+                    .from(ExpenseShares::Table, ExpenseShares::ExpenseId)
+                    .to(SharedExpenses::Table, SharedExpenses::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_expense_shares_users")
+                    .from(ExpenseShares::Table, ExpenseShares::UserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_settlements = Table::create()
+            .table(Settlements::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(Settlements::Id)
+                    .text()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(Settlements::FromUserId).text().not_null())
+            .col(ColumnDef::new(Settlements::ToUserId).text().not_null())
+            .col(ColumnDef::new(Settlements::Amount).integer().not_null())
+            .col(ColumnDef::new(Settlements::Currency).text().not_null())
+            .col(ColumnDef::new(Settlements::SettledOn).date().not_null())
+            .col(ColumnDef::new(Settlements::TransferId).text())
+            .col(
+                ColumnDef::new(Settlements::CreatedAt)
+                    .timestamp()
+                    .not_null(),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_settlements_from_users")
+                    .from(Settlements::Table, Settlements::FromUserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .foreign_key(
+                ForeignKey::create()
+                    .name("fk_settlements_to_users")
+                    .from(Settlements::Table, Settlements::ToUserId)
+                    .to(Users::Table, Users::Id)
+                    .on_delete(ForeignKeyAction::Cascade),
+            )
+            .build(SqliteQueryBuilder);
+
+        let create_settings = Table::create()
+            .table(Settings::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(Settings::Key)
+                    .text()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(Settings::Value).text().not_null())
+            .build(SqliteQueryBuilder);
+
+        let create_tables = vec![
+            create_users,
+            create_accounts,
+            create_account_holders,
+            create_categories,
+            create_payees,
+            create_transactions,
+            create_transaction_splits,
+            create_attachments,
+            create_tags,
+            create_transaction_tags,
+            create_reconciliations,
+            create_schedules,
+            create_schedule_skips,
+            create_budgets,
+            create_envelope_assignments,
+            create_envelope_moves,
+            create_goals,
+            create_exchange_rates,
+            create_securities,
+            create_security_prices,
+            create_trades,
+            create_net_worth_snapshots,
+            create_shared_expenses,
+            create_expense_shares,
+            create_settlements,
+            create_settings,
+        ];
+        for table in create_tables {
+            self.conn.execute(&table, ())?;
+        }
+
+        self.migrate()
+    }
+
+    /// Brings databases created by older versions up to the current schema.
+    fn migrate(&self) -> Result<()> {
+        if !self.has_column(Transactions::Table, Transactions::PostedOn)? {
+            let add_posted_on = Table::alter()
+                .table(Transactions::Table)
+                .add_column(ColumnDef::new(Transactions::PostedOn).date())
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_posted_on, ())?;
+
+            // Older rows never recorded a spend date, the best guess is the day they were entered
+            let backfill = Query::update()
+                .table(Transactions::Table)
+                .value(
+                    Transactions::PostedOn,
+                    Expr::cust("substr(created_at, 1, 10)"),
+                )
+                .and_where(Expr::col(Transactions::PostedOn).is_null())
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&backfill, ())?;
+        }
+
+        if !self.has_column(Accounts::Table, Accounts::OpeningBalance)? {
+            let add_opening_balance = Table::alter()
+                .table(Accounts::Table)
+                .add_column(
+                    ColumnDef::new(Accounts::OpeningBalance)
+                        .integer()
+                        .not_null()
+                        .default(0),
+                )
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_opening_balance, ())?;
+
+            // Work backwards from the balance people see today so that nothing shifts on upgrade
+            let backfill = Query::update()
+                .table(Accounts::Table)
+                .value(
+                    Accounts::OpeningBalance,
+                    Expr::cust(
+                        "balance - COALESCE((SELECT SUM(CASE WHEN type = 'credit' THEN amount \
+                         ELSE -amount END) FROM transactions \
+                         WHERE transactions.account_id = accounts.id), 0) / 100.0",
+                    ),
+                )
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&backfill, ())?;
+        }
+
+        if !self.has_column(Transactions::Table, Transactions::TransferId)? {
+            let add_transfer_id = Table::alter()
+                .table(Transactions::Table)
+                .add_column(ColumnDef::new(Transactions::TransferId).text())
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_transfer_id, ())?;
+        }
+
+        if !self.has_column(Accounts::Table, Accounts::Currency)? {
+            let add_currency = Table::alter()
+                .table(Accounts::Table)
+                .add_column(
+                    ColumnDef::new(Accounts::Currency)
+                        .text()
+                        .not_null()
+                        .default(DEFAULT_CURRENCY),
+                )
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_currency, ())?;
+        }
+
+        if !self.has_column(Categories::Table, Categories::ParentId)? {
+            let add_parent_id = Table::alter()
+                .table(Categories::Table)
+                .add_column(ColumnDef::new(Categories::ParentId).text())
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_parent_id, ())?;
+        }
+
+        if !self.has_column(Transactions::Table, Transactions::PayeeId)? {
+            let add_payee_id = Table::alter()
+                .table(Transactions::Table)
+                .add_column(ColumnDef::new(Transactions::PayeeId).text())
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_payee_id, ())?;
+        }
+
+        if !self.has_column(Transactions::Table, Transactions::Status)? {
+            // Everything entered before statuses existed already counts towards the balance
+            let add_status = Table::alter()
+                .table(Transactions::Table)
+                .add_column(
+                    ColumnDef::new(Transactions::Status)
+                        .text()
+                        .not_null()
+                        .default("cleared"),
+                )
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_status, ())?;
+        }
+
+        if !self.has_column(Accounts::Table, Accounts::Type)? {
+            let add_type = Table::alter()
+                .table(Accounts::Table)
+                .add_column(
+                    ColumnDef::new(Accounts::Type)
+                        .text()
+                        .not_null()
+                        .default("checking"),
+                )
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_type, ())?;
+        }
+
+        if !self.has_column(Accounts::Table, Accounts::ClosingDay)? {
+            for column in [
+                ColumnDef::new(Accounts::ClosingDay).integer().to_owned(),
+                ColumnDef::new(Accounts::DueDay).integer().to_owned(),
+                ColumnDef::new(Accounts::CreditLimit).integer().to_owned(),
+                ColumnDef::new(Accounts::Apr).double().to_owned(),
+            ] {
+                let add_column = Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(column)
+                    .build(SqliteQueryBuilder);
+                self.conn.execute(&add_column, ())?;
+            }
+        }
+
+        if !self.has_column(Accounts::Table, Accounts::LoanPrincipal)? {
+            for column in [
+                ColumnDef::new(Accounts::LoanPrincipal).integer().to_owned(),
+                ColumnDef::new(Accounts::LoanRate).double().to_owned(),
+                ColumnDef::new(Accounts::LoanTerm).integer().to_owned(),
+                ColumnDef::new(Accounts::LoanStartDate).date().to_owned(),
+            ] {
+                let add_column = Table::alter()
+                    .table(Accounts::Table)
+                    .add_column(column)
+                    .build(SqliteQueryBuilder);
+                self.conn.execute(&add_column, ())?;
+            }
+        }
+
+        // Added before the accounts table can be rebuilt below, which copies
+        // every column the new table has
+        if !self.has_column(Accounts::Table, Accounts::ArchivedAt)? {
+            let add_archived_at = Table::alter()
+                .table(Accounts::Table)
+                .add_column(ColumnDef::new(Accounts::ArchivedAt).timestamp())
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_archived_at, ())?;
+        }
+
+        if !self.has_column(Categories::Table, Categories::ArchivedAt)? {
+            let add_archived_at = Table::alter()
+                .table(Categories::Table)
+                .add_column(ColumnDef::new(Categories::ArchivedAt).timestamp())
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_archived_at, ())?;
+        }
+
+        if !self.has_column(Users::Table, Users::ArchivedAt)? {
+            let add_archived_at = Table::alter()
+                .table(Users::Table)
+                .add_column(ColumnDef::new(Users::ArchivedAt).timestamp())
+                .build(SqliteQueryBuilder);
+            self.conn.execute(&add_archived_at, ())?;
+        }
+
+        if self.has_column(Accounts::Table, Accounts::HolderId)? {
+            // Dropping the old table would otherwise cascade into everything
+            // that references accounts. The pragma has no effect inside a
+            // transaction, so it is switched around it.
+            let foreign_keys: bool = self
+                .conn
+                .query_row("PRAGMA foreign_keys", (), |row| row.get(0))?;
+            self.conn.execute("PRAGMA foreign_keys = OFF", ())?;
+
+            let tx = self.conn.unchecked_transaction()?;
+
+            // Every account used to have one holder who owned all of it
+            let backfill = Query::insert()
+                .into_table(AccountHolders::Table)
+                .columns([
+                    AccountHolders::AccountId,
+                    AccountHolders::UserId,
+                    AccountHolders::Share,
+                ])
+                .select_from(
+                    Query::select()
+                        .columns([Accounts::Id, Accounts::HolderId])
+                        .expr(WHOLE_OWNERSHIP)
+                        .from(Accounts::Table)
+                        .to_owned(),
+                )
+                .unwrap()
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&backfill, ())?;
+
+            // SQLite cannot drop a column used by a foreign key, so the table
+            // is rebuilt without it
+            self.rebuild_table(Accounts::Table, accounts_table)?;
+
+            tx.commit()?;
+            if foreign_keys {
+                self.conn.execute("PRAGMA foreign_keys = ON", ())?;
+            }
+        }
+
+        // Deleting a category used to silently drop the split lines and
+        // schedules filed under it
+        let restricted: [(Alias, CreateTable); 2] = [
+            (
+                Alias::new(TransactionSplits::Table.to_string()),
+                transaction_splits_table,
+            ),
+            (Alias::new(Schedules::Table.to_string()), schedules_table),
+        ];
+        for (table, create) in restricted {
+            if !self.cascades_from_categories(table.clone())? {
+                continue;
+            }
+
+            // Foreign keys cannot be altered in place, so the table is
+            // rebuilt, with the pragma switched around it as above
+            let foreign_keys: bool = self
+                .conn
+                .query_row("PRAGMA foreign_keys", (), |row| row.get(0))?;
+            self.conn.execute("PRAGMA foreign_keys = OFF", ())?;
+
+            let tx = self.conn.unchecked_transaction()?;
+            self.rebuild_table(table, create)?;
+            tx.commit()?;
+
+            if foreign_keys {
+                self.conn.execute("PRAGMA foreign_keys = ON", ())?;
+            }
+        }
+
+        let version: i64 = self
+            .conn
+            .query_row("PRAGMA user_version", (), |row| row.get(0))?;
+
+        if version < 1 {
+            // Balances used to be stored as REAL amounts in major units
+            let tx = self.conn.unchecked_transaction()?;
+            let to_minor_units = Query::update()
+                .table(Accounts::Table)
+                .values(vec![
+                    (
+                        Accounts::OpeningBalance,
+                        Expr::cust("CAST(ROUND(opening_balance * 100) AS INTEGER)"),
+                    ),
+                    (
+                        Accounts::Balance,
+                        Expr::cust("CAST(ROUND(balance * 100) AS INTEGER)"),
+                    ),
+                ])
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&to_minor_units, ())?;
+            self.conn.execute("PRAGMA user_version = 1", ())?;
+            tx.commit()?;
+        }
+
+        Ok(())
+    }
+
+    /// Replaces `table` with a copy created by `create`, keeping every row and
+    /// every column the new table has. Callers switch foreign keys off and run
+    /// this inside their own SQL transaction.
+    fn rebuild_table(&self, table: impl Iden, create: CreateTable) -> Result<()> {
+        let table = Alias::new(table.to_string());
+        let rebuilt = Alias::new(format!("{}_new", table.to_string()));
+
+        self.conn.execute(&create(rebuilt.clone()), ())?;
+        let columns = self.table_columns(rebuilt.clone())?.join(", ");
+        self.conn.execute(
+            &format!(
+                "INSERT INTO {} ({}) SELECT {} FROM {}",
+                rebuilt.to_string(),
+                columns,
+                columns,
+                table.to_string()
+            ),
+            (),
+        )?;
+        let drop = Table::drop().table(table.clone()).build(SqliteQueryBuilder);
+        self.conn.execute(&drop, ())?;
+        let rename = Table::rename()
+            .table(rebuilt, table)
+            .build(SqliteQueryBuilder);
+        self.conn.execute(&rename, ())?;
+
+        Ok(())
+    }
+
+    /// Whether rows of `table` are deleted along with the category they point to.
+    fn cascades_from_categories(&self, table: impl Iden) -> Result<bool> {
+        Ok(self
+            .conn
+            .prepare(&format!("PRAGMA foreign_key_list({})", table.to_string()))?
+            .query_map((), |row| {
+                Ok((row.get::<_, String>(2)?, row.get::<_, String>(6)?))
+            })?
+            .collect::<Result<Vec<(String, String)>>>()?
+            .into_iter()
+            .any(|(table, on_delete)| {
+                table == Categories::Table.to_string() && on_delete == "CASCADE"
+            }))
+    }
+
+    fn table_columns(&self, table: impl Iden) -> Result<Vec<String>> {
+        let mut stmt = self
+            .conn
+            .prepare(&format!("PRAGMA table_info({})", table.to_string()))?;
+
+        stmt.query_map((), |row| row.get::<_, String>(1))?
+            .collect::<Result<Vec<String>>>()
+    }
+
+    fn has_column(&self, table: impl Iden, column: impl Iden) -> Result<bool> {
+        Ok(self.table_columns(table)?.contains(&column.to_string()))
+    }
+
+    /// Stamps a row as archived now, or clears the stamp. Archived rows keep
+    /// their history but are hidden from listings and selectors.
+    fn set_archived(
+        &self,
+        table: impl IntoIden + 'static,
+        id_column: impl IntoIden + 'static,
+        archived_at_column: impl IntoIden + 'static,
+        id: &str,
+        archived: bool,
+    ) -> Result<()> {
+        let archived_at = archived.then(|| Local::now().to_string());
+
+        let query = Query::update()
+            .table(table)
+            .value(archived_at_column, archived_at)
+            .and_where(Expr::col(id_column).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        match self.conn.execute(&query, ())? {
+            0 => Err(rusqlite::Error::QueryReturnedNoRows),
+            _ => Ok(()),
+        }
     }
 }
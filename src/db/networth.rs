@@ -0,0 +1,409 @@
+use std::{collections::HashMap, str::FromStr};
+
+use chrono::{Local, Months, NaiveDate};
+use sea_query::{Expr, Order, Query, SqliteQueryBuilder};
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+};
+
+use super::{
+    NetWorthSnapshots,
+    account::Account,
+    budget::{month_end, month_start},
+    holder::owned_amount,
+    holding::CostBasisMethod,
+    transaction::Transaction,
+};
+
+/// What was owned and owed on a day, in one currency.
+#[derive(Debug, Clone)]
+pub struct NetWorth {
+    pub on: NaiveDate,
+    pub assets: Money,
+    /// What is owed, as a positive amount
+    pub liabilities: Money,
+}
+
+impl NetWorth {
+    pub fn net(&self) -> Money {
+        Money::new(
+            self.assets.minor - self.liabilities.minor,
+            &self.assets.currency,
+        )
+    }
+}
+
+/// The net worth at the end of a month, or on the day it was worked out for
+/// the month that is still running.
+#[derive(Debug, Clone)]
+pub struct NetWorthPoint {
+    pub month: NaiveDate,
+    pub net_worth: NetWorth,
+    /// Whether it comes from a recorded snapshot rather than the transaction
+    /// history
+    pub snapshot: bool,
+}
+
+impl Db {
+    /// How much of each account counts towards the net worth of a user, by
+    /// account id, or `None` when every account counts in full.
+    fn ownership(&self, user_id: Option<&str>) -> Result<Option<HashMap<String, i64>>, DbError> {
+        user_id
+            .map(|user_id| self.get_ownership_by_user(user_id))
+            .transpose()
+    }
+
+    /// Adds up every account as it stood at the end of `on`, converted into
+    /// `currency`. Balances are rebuilt from the opening balance and the
+    /// transactions posted by then, and investment accounts add what their
+    /// holdings were worth, or what they cost when no price was known. With
+    /// `ownership`, only the share owned of the accounts held counts.
+    fn net_worth_on(
+        &self,
+        accounts: &[Account],
+        transactions: &[Transaction],
+        ownership: Option<&HashMap<String, i64>>,
+        currency: &str,
+        on: NaiveDate,
+    ) -> Result<NetWorth, DbError> {
+        let mut assets = 0;
+        let mut liabilities = 0;
+
+        for account in accounts {
+            if ownership.is_some_and(|ownership| !ownership.contains_key(&account.id)) {
+                continue;
+            }
+
+            let balance = account.opening_balance.minor
+                + transactions
+                    .iter()
+                    .filter(|transaction| {
+                        transaction.account_id == account.id && transaction.posted_on <= on
+                    })
+                    .map(Transaction::signed_amount)
+                    .sum::<i64>();
+            let converted = self
+                .convert(
+                    &Money::new(balance, &account.balance.currency),
+                    currency,
+                    on,
+                )?
+                .minor;
+            let converted = owned_amount(ownership, &account.id, converted);
+
+            // Ledger balances of liabilities are negative, so what is owed is
+            // the other way round
+            if account.account_type.is_liability() {
+                liabilities -= converted;
+            } else {
+                assets += converted;
+            }
+        }
+
+        for holding in self.get_holdings(None, CostBasisMethod::Fifo, on)? {
+            let value = holding.market_value().unwrap_or(holding.cost());
+            assets += owned_amount(
+                ownership,
+                &holding.account_id,
+                self.convert(&value, currency, on)?.minor,
+            );
+        }
+
+        Ok(NetWorth {
+            on,
+            assets: Money::new(assets, currency),
+            liabilities: Money::new(liabilities, currency),
+        })
+    }
+
+    /// The net worth of everyone together, or of one user when `user_id` is
+    /// given, counting only their share of the accounts they hold.
+    pub fn get_net_worth(
+        &self,
+        currency: &str,
+        on: NaiveDate,
+        user_id: Option<&str>,
+    ) -> Result<NetWorth, DbError> {
+        let accounts = self.get_all_accounts()?;
+        let transactions = self.get_all_transactions()?;
+        let ownership = self.ownership(user_id)?;
+
+        self.net_worth_on(&accounts, &transactions, ownership.as_ref(), currency, on)
+    }
+
+    /// Works out the net worth on `on` and stores it, replacing a snapshot
+    /// taken the same day in the same currency.
+    pub fn record_net_worth_snapshot(
+        &self,
+        currency: &str,
+        on: NaiveDate,
+    ) -> Result<NetWorth, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let net_worth = self.get_net_worth(currency, on, None)?;
+
+        let delete = Query::delete()
+            .from_table(NetWorthSnapshots::Table)
+            .and_where(Expr::col(NetWorthSnapshots::TakenOn).eq(on.to_string()))
+            .and_where(Expr::col(NetWorthSnapshots::Currency).eq(currency))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&delete, ())?;
+
+        let insert = Query::insert()
+            .into_table(NetWorthSnapshots::Table)
+            .columns([
+                NetWorthSnapshots::TakenOn,
+                NetWorthSnapshots::Currency,
+                NetWorthSnapshots::Assets,
+                NetWorthSnapshots::Liabilities,
+                NetWorthSnapshots::CreatedAt,
+            ])
+            .values_panic(vec![
+                on.to_string().into(),
+                currency.into(),
+                net_worth.assets.minor.into(),
+                net_worth.liabilities.minor.into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&insert, ())?;
+
+        tx.commit()?;
+
+        Ok(net_worth)
+    }
+
+    /// Every snapshot taken in `currency`, oldest first.
+    pub fn get_net_worth_snapshots(&self, currency: &str) -> Result<Vec<NetWorth>, DbError> {
+        let query = Query::select()
+            .columns([
+                NetWorthSnapshots::TakenOn,
+                NetWorthSnapshots::Assets,
+                NetWorthSnapshots::Liabilities,
+            ])
+            .from(NetWorthSnapshots::Table)
+            .and_where(Expr::col(NetWorthSnapshots::Currency).eq(currency))
+            .order_by(NetWorthSnapshots::TakenOn, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), |row| {
+                Ok(NetWorth {
+                    on: NaiveDate::from_str(row.get::<_, String>(0)?.as_str())
+                        .unwrap_or(Local::now().date_naive()),
+                    assets: Money::new(row.get(1)?, currency),
+                    liabilities: Money::new(row.get(2)?, currency),
+                })
+            })?
+            .map(|snapshot| snapshot.unwrap())
+            .collect::<Vec<NetWorth>>())
+    }
+
+    /// The net worth month by month over the `months` months up to the one
+    /// `on` falls in. The latest snapshot of a month is used when there is
+    /// one, otherwise the value is rebuilt for the end of the month, or for
+    /// `on` in its own month. Snapshots cover everyone together, so the
+    /// history of one user is always rebuilt.
+    pub fn get_net_worth_history(
+        &self,
+        currency: &str,
+        months: u32,
+        on: NaiveDate,
+        user_id: Option<&str>,
+    ) -> Result<Vec<NetWorthPoint>, DbError> {
+        let accounts = self.get_all_accounts()?;
+        let transactions = self.get_all_transactions()?;
+        let ownership = self.ownership(user_id)?;
+        let snapshots = match user_id {
+            Some(_) => vec![],
+            None => self.get_net_worth_snapshots(currency)?,
+        };
+
+        let last_month = month_start(on);
+        let mut points = vec![];
+        for offset in (0..months.max(1)).rev() {
+            let month = last_month - Months::new(offset);
+            let until = month_end(month).min(on);
+
+            let snapshot = snapshots
+                .iter()
+                .rfind(|snapshot| month_start(snapshot.on) == month && snapshot.on <= on);
+            let (net_worth, snapshot) = match snapshot {
+                Some(snapshot) => (snapshot.clone(), true),
+                None => (
+                    self.net_worth_on(
+                        &accounts,
+                        &transactions,
+                        ownership.as_ref(),
+                        currency,
+                        until,
+                    )?,
+                    false,
+                ),
+            };
+
+            points.push(NetWorthPoint {
+                month,
+                net_worth,
+                snapshot,
+            });
+        }
+
+        Ok(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::AccountHolder,
+            transaction::{TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_net_worth() {
+        let _ = std::fs::remove_file(get_test_db_path("networth"));
+
+        let db = Db::new(get_test_db_path("networth").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = |name: &str, account_type, opening| {
+            db.create_account(&AccountRequest {
+                name: name.to_string(),
+                bank: "Chase".to_string(),
+                account_number: None,
+                account_type,
+                opening_balance: Money::new(opening, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap()
+        };
+        let checking = account("Checking", AccountType::Checking, 100000);
+        let card = account("Visa", AccountType::CreditCard, -20000);
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Shopping".to_string(),
+                icon: "🛍️".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        for (account_id, amount, transaction_type, posted_on) in [
+            (&checking.id, 50000, "credit", date(2024, 1, 15)),
+            (&card.id, 30000, "debit", date(2024, 2, 10)),
+            (&checking.id, 10000, "debit", date(2024, 3, 5)),
+        ] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account_id.clone(),
+                amount: Money::new(amount, DEFAULT_CURRENCY),
+                transaction_type: transaction_type.to_string(),
+                description: "Purchase".to_string(),
+                category_id: category.id.clone(),
+                posted_on,
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        // Liabilities count against what is owned
+        let net_worth = db
+            .get_net_worth(DEFAULT_CURRENCY, date(2024, 2, 29), None)
+            .unwrap();
+        assert_eq!(net_worth.assets.minor, 150000);
+        assert_eq!(net_worth.liabilities.minor, 50000);
+        assert_eq!(net_worth.net().minor, 100000);
+
+        // Without a rate nothing can be added up in another currency
+        assert!(db.get_net_worth("EUR", date(2024, 2, 29), None).is_err());
+
+        // January is rebuilt, February comes from the snapshot
+        let snapshot = db
+            .record_net_worth_snapshot(DEFAULT_CURRENCY, date(2024, 2, 20))
+            .unwrap();
+        assert_eq!(snapshot.net().minor, 100000);
+        db.record_net_worth_snapshot(DEFAULT_CURRENCY, date(2024, 2, 20))
+            .unwrap();
+        assert_eq!(
+            db.get_net_worth_snapshots(DEFAULT_CURRENCY).unwrap().len(),
+            1
+        );
+
+        let history = db
+            .get_net_worth_history(DEFAULT_CURRENCY, 4, date(2024, 3, 10), None)
+            .unwrap();
+        assert_eq!(history.len(), 4);
+        assert_eq!(history[0].month, date(2023, 12, 1));
+        assert_eq!(history[0].net_worth.net().minor, 80000);
+        assert!(!history[1].snapshot);
+        assert_eq!(history[1].net_worth.on, date(2024, 1, 31));
+        assert_eq!(history[1].net_worth.net().minor, 130000);
+        assert!(history[2].snapshot);
+        assert_eq!(history[2].net_worth.on, date(2024, 2, 20));
+        assert_eq!(history[3].net_worth.on, date(2024, 3, 10));
+        assert_eq!(history[3].net_worth.net().minor, 90000);
+
+        // Holders only count their share of a joint account
+        let partner = db
+            .create_user(&UserRequest {
+                name: "Jane Doe".to_string(),
+            })
+            .unwrap();
+        db.update_account(
+            &checking.id,
+            &db.get_account(&checking.id).unwrap(),
+            &[
+                AccountHolder {
+                    user_id: user.id.clone(),
+                    share: 7500,
+                },
+                AccountHolder {
+                    user_id: partner.id.clone(),
+                    share: 2500,
+                },
+            ],
+        )
+        .unwrap();
+
+        let own = db
+            .get_net_worth(DEFAULT_CURRENCY, date(2024, 2, 29), Some(&user.id))
+            .unwrap();
+        assert_eq!(own.assets.minor, 112500);
+        assert_eq!(own.liabilities.minor, 50000);
+        let partners = db
+            .get_net_worth(DEFAULT_CURRENCY, date(2024, 2, 29), Some(&partner.id))
+            .unwrap();
+        assert_eq!(partners.assets.minor, 37500);
+        assert_eq!(partners.liabilities.minor, 0);
+
+        // Snapshots are for everyone, so a user's history is rebuilt
+        let history = db
+            .get_net_worth_history(DEFAULT_CURRENCY, 2, date(2024, 2, 29), Some(&partner.id))
+            .unwrap();
+        assert!(history.iter().all(|point| !point.snapshot));
+        assert_eq!(history[1].net_worth.net().minor, 37500);
+    }
+}
@@ -0,0 +1,300 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use rusqlite::Row;
+use sea_query::{Expr, Func, Order, Query, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    utils::random_hash,
+};
+
+use super::{Payees, Transactions};
+
+/// Someone money is paid to or received from, such as a shop or an employer.
+#[derive(Debug, Clone, Tabled)]
+pub struct Payee {
+    pub id: String,
+    pub name: String,
+    /// Pre-selected when entering a transaction for this payee
+    #[tabled(skip)]
+    pub default_category_id: Option<String>,
+    pub created_at: chrono::DateTime<Local>,
+}
+
+pub struct PayeeRequest {
+    pub name: String,
+    pub default_category_id: Option<String>,
+}
+
+fn payee_columns() -> [Payees; 4] {
+    [
+        Payees::Id,
+        Payees::Name,
+        Payees::DefaultCategoryId,
+        Payees::CreatedAt,
+    ]
+}
+
+fn payee_from_row(row: &Row) -> Result<Payee, rusqlite::Error> {
+    let created_at = DateTime::from_str(row.get::<_, String>(3)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Payee {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        default_category_id: row.get(2)?,
+        created_at,
+    })
+}
+
+impl Db {
+    pub fn create_payee(&self, payee: &PayeeRequest) -> Result<Payee, DbError> {
+        let id = random_hash(8);
+        let name = payee.name.trim().to_string();
+
+        let query = Query::insert()
+            .into_table(Payees::Table)
+            .columns(payee_columns())
+            .values_panic(vec![
+                id.clone().into(),
+                name.clone().into(),
+                payee.default_category_id.clone().into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(Payee {
+            id,
+            name,
+            default_category_id: payee.default_category_id.clone(),
+            created_at: Local::now(),
+        })
+    }
+
+    pub fn get_payee(&self, id: &str) -> Result<Payee, DbError> {
+        let query = Query::select()
+            .columns(payee_columns())
+            .from(Payees::Table)
+            .and_where(Expr::col(Payees::Id).eq(id))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt.query_row((), payee_from_row)?)
+    }
+
+    /// Looks a payee up by its exact name, ignoring case.
+    pub fn get_payee_by_name(&self, name: &str) -> Result<Option<Payee>, DbError> {
+        let query = Query::select()
+            .columns(payee_columns())
+            .from(Payees::Table)
+            .and_where(
+                Expr::expr(Func::lower(Expr::col(Payees::Name))).eq(name.trim().to_lowercase()),
+            )
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query_map((), payee_from_row)?;
+
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn get_all_payees(&self) -> Result<Vec<Payee>, DbError> {
+        let query = Query::select()
+            .columns(payee_columns())
+            .from(Payees::Table)
+            .order_by(Payees::Name, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), payee_from_row)?
+            .map(|payee| payee.unwrap())
+            .collect::<Vec<Payee>>())
+    }
+
+    pub fn update_payee(&self, id: &str, payee: &Payee) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(Payees::Table)
+            .and_where(Expr::col(Payees::Id).eq(id))
+            .values(vec![
+                (Payees::Name, payee.name.trim().into()),
+                (
+                    Payees::DefaultCategoryId,
+                    payee.default_category_id.clone().into(),
+                ),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// Points every transaction of `from_id` at `into_id` and deletes `from_id`,
+    /// for when the same merchant was entered under different names.
+    pub fn merge_payees(&self, from_id: &str, into_id: &str) -> Result<(), DbError> {
+        if from_id == into_id {
+            return Ok(());
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+        self.reassign_payee(from_id, Some(into_id))?;
+        self.remove_payee(from_id)?;
+        Ok(tx.commit()?)
+    }
+
+    /// Deletes a payee. Its transactions stay, without a payee.
+    pub fn delete_payee(&self, id: &str) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.reassign_payee(id, None)?;
+        self.remove_payee(id)?;
+        Ok(tx.commit()?)
+    }
+
+    fn reassign_payee(&self, from_id: &str, into_id: Option<&str>) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(Transactions::Table)
+            .value(Transactions::PayeeId, into_id.map(|id| id.to_string()))
+            .and_where(Expr::col(Transactions::PayeeId).eq(from_id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    fn remove_payee(&self, id: &str) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(Payees::Table)
+            .and_where(Expr::col(Payees::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::AccountHolder,
+            transaction::{TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::{DEFAULT_CURRENCY, Money},
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_payees() {
+        let _ = std::fs::remove_file(get_test_db_path("payee"));
+
+        let db = Db::new(get_test_db_path("payee").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Checking".to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(0, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let shopping = db
+            .create_category(&CategoryRequest {
+                name: "Shopping".to_string(),
+                icon: "🛍️".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let amazon = db
+            .create_payee(&PayeeRequest {
+                name: " Amazon ".to_string(),
+                default_category_id: Some(shopping.id.clone()),
+            })
+            .unwrap();
+        let marketplace = db
+            .create_payee(&PayeeRequest {
+                name: "AMZN Mktp".to_string(),
+                default_category_id: None,
+            })
+            .unwrap();
+        assert_eq!(amazon.name, "Amazon");
+        assert_eq!(
+            db.get_payee_by_name("amazon").unwrap().unwrap().id,
+            amazon.id
+        );
+        assert!(db.get_payee_by_name("Amazon Prime").unwrap().is_none());
+
+        let transaction = db
+            .create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(2599, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: "Headphones".to_string(),
+                category_id: shopping.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: Some(marketplace.id.clone()),
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        assert_eq!(
+            db.get_transaction(&transaction.id).unwrap().payee_id,
+            Some(marketplace.id.clone())
+        );
+
+        // Merging moves the transactions over to the payee that is kept
+        db.merge_payees(&marketplace.id, &amazon.id).unwrap();
+        assert_eq!(db.get_all_payees().unwrap().len(), 1);
+        assert_eq!(
+            db.get_transaction(&transaction.id).unwrap().payee_id,
+            Some(amazon.id.clone())
+        );
+
+        let mut renamed = amazon.clone();
+        renamed.name = "Amazon.com".to_string();
+        renamed.default_category_id = None;
+        db.update_payee(&amazon.id, &renamed).unwrap();
+        let updated = db.get_payee(&amazon.id).unwrap();
+        assert_eq!(updated.name, "Amazon.com");
+        assert!(updated.default_category_id.is_none());
+
+        // Deleting a payee keeps its transactions
+        db.delete_payee(&amazon.id).unwrap();
+        assert!(db.get_all_payees().unwrap().is_empty());
+        assert!(
+            db.get_transaction(&transaction.id)
+                .unwrap()
+                .payee_id
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,293 @@
+use sea_query::{Expr, OnConflict, Query, SqliteQueryBuilder};
+
+use crate::db::{Db, DbError, users::User};
+
+use super::Settings;
+
+const ACTIVE_USER: &str = "active_user";
+
+impl Db {
+    fn get_setting(&self, key: &str) -> Result<Option<String>, DbError> {
+        let query = Query::select()
+            .column(Settings::Value)
+            .from(Settings::Table)
+            .and_where(Expr::col(Settings::Key).eq(key))
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query_map((), |row| row.get(0))?;
+
+        Ok(rows.next().transpose()?)
+    }
+
+    fn set_setting(&self, key: &str, value: Option<&str>) -> Result<(), DbError> {
+        let query = match value {
+            Some(value) => Query::insert()
+                .into_table(Settings::Table)
+                .columns([Settings::Key, Settings::Value])
+                .values_panic(vec![key.into(), value.into()])
+                .on_conflict(
+                    OnConflict::column(Settings::Key)
+                        .update_column(Settings::Value)
+                        .to_owned(),
+                )
+                .to_string(SqliteQueryBuilder),
+            None => Query::delete()
+                .from_table(Settings::Table)
+                .and_where(Expr::col(Settings::Key).eq(key))
+                .to_string(SqliteQueryBuilder),
+        };
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// The user commands act as by default, if one was switched to and still
+    /// exists.
+    pub fn get_active_user(&self) -> Result<Option<User>, DbError> {
+        let Some(user_id) = self.get_setting(ACTIVE_USER)? else {
+            return Ok(None);
+        };
+
+        match self.get_user(&user_id) {
+            Ok(user) => Ok(Some(user)),
+            Err(DbError::Sqlite(rusqlite::Error::QueryReturnedNoRows)) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Switches the active user, or clears it with `None` so commands cover
+    /// every user again.
+    pub fn set_active_user(&self, user_id: Option<&str>) -> Result<(), DbError> {
+        if let Some(user_id) = user_id {
+            self.get_user(user_id)?;
+        }
+
+        self.set_setting(ACTIVE_USER, user_id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{Local, NaiveDate};
+
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::{AccountHolder, owned_amount},
+            transaction::{TransactionFilter, TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::{DEFAULT_CURRENCY, Money},
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_active_user() {
+        let _ = std::fs::remove_file(get_test_db_path("profile"));
+
+        let db = Db::new(get_test_db_path("profile").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = |name: &str| {
+            db.create_user(&UserRequest {
+                name: name.to_string(),
+            })
+            .unwrap()
+        };
+        let (alice, bob) = (user("Alice"), user("Bob"));
+
+        assert!(db.get_active_user().unwrap().is_none());
+        assert!(db.set_active_user(Some("missing")).is_err());
+
+        db.set_active_user(Some(&alice.id)).unwrap();
+        assert_eq!(db.get_active_user().unwrap().unwrap().id, alice.id);
+        db.set_active_user(Some(&bob.id)).unwrap();
+        assert_eq!(db.get_active_user().unwrap().unwrap().id, bob.id);
+
+        // Transactions can be narrowed down to the accounts of one holder
+        let account = |user_id: &str| {
+            db.create_account(&AccountRequest {
+                name: "Checking".to_string(),
+                bank: "Chase".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(0, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(user_id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap()
+        };
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Groceries".to_string(),
+                icon: "🛒".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        for account in [account(&alice.id), account(&bob.id)] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id,
+                amount: Money::new(1000, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: String::new(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2026, 7, 1).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+        let filter = TransactionFilter {
+            holder_id: Some(alice.id.clone()),
+            ..Default::default()
+        };
+        assert_eq!(db.get_filtered_transactions(&filter).unwrap().len(), 1);
+
+        db.set_active_user(None).unwrap();
+        assert!(db.get_active_user().unwrap().is_none());
+
+        // A deleted user is no longer active
+        db.set_active_user(Some(&alice.id)).unwrap();
+        for account in db.get_accounts_by_holder(&alice.id).unwrap() {
+            db.delete_account(&account.id).unwrap();
+        }
+        db.delete_user(&alice.id).unwrap();
+        assert!(db.get_active_user().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_profile_totals_with_joint_account() {
+        let _ = std::fs::remove_file(get_test_db_path("profile_joint"));
+
+        let db = Db::new(get_test_db_path("profile_joint").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = |name: &str| {
+            db.create_user(&UserRequest {
+                name: name.to_string(),
+            })
+            .unwrap()
+        };
+        let (alice, bob) = (user("Alice"), user("Bob"));
+
+        let account = |name: &str, account_type: AccountType, minor: i64, holders| {
+            db.create_account(&AccountRequest {
+                name: name.to_string(),
+                bank: "Chase".to_string(),
+                account_number: None,
+                account_type,
+                opening_balance: Money::new(minor, DEFAULT_CURRENCY),
+                holders,
+                card: None,
+                loan: None,
+            })
+            .unwrap()
+        };
+        let joint = account(
+            "Joint",
+            AccountType::Checking,
+            100000,
+            vec![
+                AccountHolder {
+                    user_id: alice.id.clone(),
+                    share: 6000,
+                },
+                AccountHolder {
+                    user_id: bob.id.clone(),
+                    share: 4000,
+                },
+            ],
+        );
+        account(
+            "Savings",
+            AccountType::Savings,
+            50000,
+            vec![AccountHolder::sole(&alice.id)],
+        );
+        account(
+            "Card",
+            AccountType::CreditCard,
+            -20000,
+            vec![AccountHolder::sole(&alice.id)],
+        );
+        account(
+            "Bob's",
+            AccountType::Checking,
+            70000,
+            vec![AccountHolder::sole(&bob.id)],
+        );
+
+        // The totals of a profile's accounts count the joint account with the
+        // profile's share, the same way its net worth does
+        let today = Local::now().date_naive();
+        for user in [&alice, &bob] {
+            let ownership = db.get_ownership_by_user(&user.id).unwrap();
+            let total: i64 = db
+                .get_accounts_by_holder(&user.id)
+                .unwrap()
+                .iter()
+                .map(|account| owned_amount(Some(&ownership), &account.id, account.balance.minor))
+                .sum();
+            let net_worth = db
+                .get_net_worth(DEFAULT_CURRENCY, today, Some(&user.id))
+                .unwrap();
+            assert_eq!(total, net_worth.net().minor);
+        }
+        let ownership = db.get_ownership_by_user(&alice.id).unwrap();
+        assert_eq!(owned_amount(Some(&ownership), &joint.id, 100000), 60000);
+        assert_eq!(owned_amount(None, &joint.id, 100000), 100000);
+        assert_eq!(owned_amount(Some(&ownership), "missing", 100000), 0);
+
+        // Spending from the joint account counts with the same share in the
+        // reports of each holder
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Groceries".to_string(),
+                icon: "🛒".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        db.create_transaction(&TransactionRequest {
+            account_id: joint.id.clone(),
+            amount: Money::new(1000, DEFAULT_CURRENCY),
+            transaction_type: "debit".to_string(),
+            description: String::new(),
+            category_id: category.id.clone(),
+            posted_on: today,
+            status: TransactionStatus::Cleared,
+            payee_id: None,
+            splits: vec![],
+            tags: vec!["household".to_string()],
+            attachments: vec![],
+        })
+        .unwrap();
+        for (user_id, expected) in [(&alice.id, 600), (&bob.id, 400)] {
+            let categories = db
+                .get_category_totals(DEFAULT_CURRENCY, None, Some(user_id))
+                .unwrap();
+            assert_eq!(categories[0].expenses.minor, expected);
+
+            let tags = db.get_tag_totals(DEFAULT_CURRENCY, Some(user_id)).unwrap();
+            assert_eq!(tags[0].count, 1);
+            assert_eq!(tags[0].expenses.minor, expected);
+
+            let filter = TransactionFilter {
+                holder_id: Some(user_id.clone()),
+                ..Default::default()
+            };
+            let (_, expenses) = db
+                .get_income_and_expenses(&filter, DEFAULT_CURRENCY)
+                .unwrap();
+            assert_eq!(expenses.minor, expected);
+        }
+    }
+}
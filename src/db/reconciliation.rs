@@ -0,0 +1,345 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{
+    Accounts, Reconciliations,
+    transaction::{Transaction, TransactionStatus},
+};
+
+/// A bank statement that was matched against the ledger of an account.
+#[derive(Debug, Clone, Tabled)]
+pub struct Reconciliation {
+    pub id: String,
+    pub account_id: String,
+    pub statement_date: NaiveDate,
+    /// Closing balance printed on the statement
+    pub statement_balance: Money,
+    /// How many transactions were reconciled against the statement
+    pub transactions: i64,
+    pub created_at: chrono::DateTime<Local>,
+}
+
+pub struct ReconciliationRequest {
+    pub account_id: String,
+    pub statement_date: NaiveDate,
+    pub statement_balance: Money,
+    /// The transactions that appear on the statement
+    pub transaction_ids: Vec<String>,
+}
+
+/// Raised when the selected transactions do not bring the ledger to the
+/// closing balance of the statement.
+#[derive(Debug)]
+pub struct ReconciliationMismatchError {
+    pub statement: Money,
+    pub ledger: Money,
+}
+
+impl std::fmt::Display for ReconciliationMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "The statement closes at {} but the reconciled ledger is at {}",
+            self.statement, self.ledger
+        )
+    }
+}
+
+impl std::error::Error for ReconciliationMismatchError {}
+
+/// Raised when a transaction cannot be reconciled against the given account.
+#[derive(Debug)]
+pub struct NotReconcilableError {
+    pub transaction_id: String,
+}
+
+impl std::fmt::Display for NotReconcilableError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Transaction {} is void, already reconciled or belongs to another account",
+            self.transaction_id
+        )
+    }
+}
+
+impl std::error::Error for NotReconcilableError {}
+
+fn reconciliation_columns() -> [Reconciliations; 6] {
+    [
+        Reconciliations::Id,
+        Reconciliations::AccountId,
+        Reconciliations::StatementDate,
+        Reconciliations::StatementBalance,
+        Reconciliations::TransactionCount,
+        Reconciliations::CreatedAt,
+    ]
+}
+
+fn reconciliation_from_row(row: &Row) -> Result<Reconciliation, rusqlite::Error> {
+    let statement_date =
+        NaiveDate::from_str(row.get::<_, String>(2)?.as_str()).unwrap_or(Local::now().date_naive());
+    let created_at = DateTime::from_str(row.get::<_, String>(5)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Reconciliation {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        statement_date,
+        statement_balance: Money::new(row.get(3)?, &row.get::<_, String>(6)?),
+        transactions: row.get(4)?,
+        created_at,
+    })
+}
+
+impl Db {
+    /// The balance of an account counting only reconciled transactions, which is
+    /// where the next statement picks up from.
+    pub fn get_reconciled_balance(&self, account_id: &str) -> Result<Money, DbError> {
+        let account = self.get_account(account_id)?;
+        let net = self.get_account_reconciled_net(account_id)?;
+
+        Ok(Money::new(
+            account.opening_balance.minor + net,
+            &account.balance.currency,
+        ))
+    }
+
+    /// Marks the transactions on a statement as reconciled and records the
+    /// statement. Nothing changes unless they bring the reconciled balance of the
+    /// account exactly to the closing balance of the statement.
+    pub fn reconcile_account(
+        &self,
+        reconciliation: &ReconciliationRequest,
+    ) -> Result<Reconciliation, DbError> {
+        let mut ledger = self.get_reconciled_balance(&reconciliation.account_id)?;
+
+        let transactions = reconciliation
+            .transaction_ids
+            .iter()
+            .map(|id| self.get_transaction(id))
+            .collect::<Result<Vec<Transaction>, DbError>>()?;
+        for transaction in &transactions {
+            if transaction.account_id != reconciliation.account_id
+                || matches!(
+                    transaction.status,
+                    TransactionStatus::Void | TransactionStatus::Reconciled
+                )
+            {
+                return Err(DbError::invalid(NotReconcilableError {
+                    transaction_id: transaction.id.clone(),
+                }));
+            }
+
+            ledger.minor += transaction.signed_amount();
+        }
+
+        if ledger.minor != reconciliation.statement_balance.minor {
+            return Err(DbError::invalid(ReconciliationMismatchError {
+                statement: reconciliation.statement_balance.clone(),
+                ledger,
+            }));
+        }
+
+        let id = random_hash(8);
+        let query = Query::insert()
+            .into_table(Reconciliations::Table)
+            .columns(reconciliation_columns())
+            .values_panic(vec![
+                id.clone().into(),
+                reconciliation.account_id.clone().into(),
+                reconciliation.statement_date.to_string().into(),
+                reconciliation.statement_balance.minor.into(),
+                (transactions.len() as i64).into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        let tx = self.conn.unchecked_transaction()?;
+        self.write_transaction_status(
+            &reconciliation.transaction_ids,
+            TransactionStatus::Reconciled,
+        )?;
+        self.conn.execute(&query, ())?;
+        tx.commit()?;
+
+        Ok(Reconciliation {
+            id,
+            account_id: reconciliation.account_id.clone(),
+            statement_date: reconciliation.statement_date,
+            statement_balance: reconciliation.statement_balance.clone(),
+            transactions: transactions.len() as i64,
+            created_at: Local::now(),
+        })
+    }
+
+    /// Past reconciliations of an account, latest statement first.
+    pub fn get_account_reconciliations(
+        &self,
+        account_id: &str,
+    ) -> Result<Vec<Reconciliation>, DbError> {
+        let query = Query::select()
+            .columns(
+                reconciliation_columns()
+                    .into_iter()
+                    .map(|column| (Reconciliations::Table, column)),
+            )
+            .column((Accounts::Table, Accounts::Currency))
+            .from(Reconciliations::Table)
+            .left_join(
+                Accounts::Table,
+                Expr::col((Accounts::Table, Accounts::Id))
+                    .equals((Reconciliations::Table, Reconciliations::AccountId)),
+            )
+            .and_where(
+                Expr::col((Reconciliations::Table, Reconciliations::AccountId)).eq(account_id),
+            )
+            .order_by(
+                (Reconciliations::Table, Reconciliations::StatementDate),
+                Order::Desc,
+            )
+            .order_by(
+                (Reconciliations::Table, Reconciliations::CreatedAt),
+                Order::Desc,
+            )
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), reconciliation_from_row)?
+            .map(|reconciliation| reconciliation.unwrap())
+            .collect::<Vec<Reconciliation>>())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{account::Account, category::CategoryRequest, transaction::TransactionRequest},
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db},
+    };
+
+    use super::*;
+
+    /// An account opened with 100.00 and a cleared, a pending and a void
+    /// transaction on it, in that order.
+    fn setup(name: &str) -> (Db, Account, Vec<String>) {
+        let db = get_test_db(name);
+        let user = create_test_user(&db, "John Doe");
+        let account = create_test_account(&db, &user.id, "Checking", 10000);
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Groceries".to_string(),
+                icon: "🛒".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let ids = [
+            (1500, TransactionStatus::Cleared),
+            (2500, TransactionStatus::Pending),
+            (9900, TransactionStatus::Void),
+        ]
+        .into_iter()
+        .map(|(amount, status)| {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(amount, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: "Groceries".to_string(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                status,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap()
+            .id
+        })
+        .collect();
+
+        (db, account, ids)
+    }
+
+    fn statement(account: &Account, transaction_ids: &[String]) -> ReconciliationRequest {
+        ReconciliationRequest {
+            account_id: account.id.clone(),
+            statement_date: NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(),
+            statement_balance: Money::new(8500, DEFAULT_CURRENCY),
+            transaction_ids: transaction_ids.to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_reconcile_with_difference() {
+        let (db, account, ids) = setup("reconciliation_difference");
+
+        // Nothing is reconciled while the difference is not zero
+        assert!(
+            db.reconcile_account(&statement(&account, &ids[..2]))
+                .is_err()
+        );
+        assert!(
+            db.reconcile_account(&statement(&account, &ids[2..]))
+                .is_err()
+        );
+        assert_eq!(
+            db.get_transaction(&ids[0]).unwrap().status,
+            TransactionStatus::Cleared
+        );
+        assert!(
+            db.get_account_reconciliations(&account.id)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_reconcile_account() {
+        let (db, account, ids) = setup("reconciliation");
+
+        let reconciliation = db
+            .reconcile_account(&statement(&account, &ids[..1]))
+            .unwrap();
+        assert_eq!(reconciliation.transactions, 1);
+        assert_eq!(
+            db.get_transaction(&ids[0]).unwrap().status,
+            TransactionStatus::Reconciled
+        );
+        assert_eq!(db.get_reconciled_balance(&account.id).unwrap().minor, 8500);
+
+        let history = db.get_account_reconciliations(&account.id).unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].id, reconciliation.id);
+        assert_eq!(history[0].statement_balance.minor, 8500);
+    }
+
+    #[test]
+    fn test_reconcile_twice() {
+        let (db, account, ids) = setup("reconciliation_twice");
+
+        db.reconcile_account(&statement(&account, &ids[..1]))
+            .unwrap();
+        assert!(
+            db.reconcile_account(&statement(&account, &ids[..1]))
+                .is_err()
+        );
+        assert_eq!(
+            db.get_account_reconciliations(&account.id).unwrap().len(),
+            1
+        );
+    }
+}
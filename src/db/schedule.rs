@@ -0,0 +1,639 @@
+use std::{fmt, str::FromStr};
+
+use chrono::{DateTime, Days, Local, Months, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SelectStatement, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{
+    Accounts, ScheduleSkips, Schedules,
+    transaction::{Transaction, TransactionRequest, TransactionStatus},
+};
+
+/// How often a scheduled transaction repeats, in steps of its interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    pub const ALL: [Frequency; 4] = [
+        Frequency::Daily,
+        Frequency::Weekly,
+        Frequency::Monthly,
+        Frequency::Yearly,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Frequency::Daily => "daily",
+            Frequency::Weekly => "weekly",
+            Frequency::Monthly => "monthly",
+            Frequency::Yearly => "yearly",
+        }
+    }
+
+    fn unit(&self) -> &'static str {
+        match self {
+            Frequency::Daily => "day",
+            Frequency::Weekly => "week",
+            Frequency::Monthly => "month",
+            Frequency::Yearly => "year",
+        }
+    }
+}
+
+impl fmt::Display for Frequency {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for Frequency {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Frequency::ALL
+            .into_iter()
+            .find(|frequency| frequency.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("'{}' is not a frequency", s))
+    }
+}
+
+/// Raised when a schedule would never get past its first occurrence.
+#[derive(Debug)]
+pub struct ZeroIntervalError;
+
+impl fmt::Display for ZeroIntervalError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "A schedule has to repeat every 1 or more days, weeks, months or years"
+        )
+    }
+}
+
+impl std::error::Error for ZeroIntervalError {}
+
+fn validate_interval(interval: u32) -> Result<(), DbError> {
+    match interval {
+        0 => Err(DbError::invalid(ZeroIntervalError)),
+        _ => Ok(()),
+    }
+}
+
+/// A transaction that repeats, such as rent or a salary.
+#[derive(Debug, Clone, Tabled)]
+pub struct Schedule {
+    pub id: String,
+    pub account_id: String,
+    pub amount: Money,
+    pub transaction_type: String,
+    pub description: String,
+    pub category_id: String,
+    #[tabled(skip)]
+    pub payee_id: Option<String>,
+    pub frequency: Frequency,
+    /// Repeats every this many days, weeks, months or years
+    pub interval: u32,
+    pub start_date: NaiveDate,
+    #[tabled(skip)]
+    pub end_date: Option<NaiveDate>,
+    /// The last occurrence that was posted or skipped, later ones are still due
+    #[tabled(skip)]
+    pub last_posted_on: Option<NaiveDate>,
+    pub created_at: chrono::DateTime<Local>,
+}
+
+pub struct ScheduleRequest {
+    pub account_id: String,
+    pub amount: Money,
+    pub transaction_type: String,
+    pub description: String,
+    pub category_id: String,
+    pub payee_id: Option<String>,
+    pub frequency: Frequency,
+    pub interval: u32,
+    pub start_date: NaiveDate,
+    pub end_date: Option<NaiveDate>,
+    /// Occurrences that should not be posted
+    pub skips: Vec<NaiveDate>,
+}
+
+impl Schedule {
+    /// A readable version of the rule, such as `every 2 weeks`.
+    pub fn rule(&self) -> String {
+        if self.interval == 1 {
+            self.frequency.to_string()
+        } else {
+            format!("every {} {}s", self.interval, self.frequency.unit())
+        }
+    }
+
+    /// The `n`th occurrence counting from the start date. Monthly and yearly
+    /// schedules starting at the end of a month stick to the last day of shorter
+    /// months.
+    fn occurrence(&self, n: u32) -> Option<NaiveDate> {
+        let steps = n.checked_mul(self.interval)?;
+
+        match self.frequency {
+            Frequency::Daily => self.start_date.checked_add_days(Days::new(steps.into())),
+            Frequency::Weekly => self
+                .start_date
+                .checked_add_days(Days::new(u64::from(steps) * 7)),
+            Frequency::Monthly => self.start_date.checked_add_months(Months::new(steps)),
+            Frequency::Yearly => self
+                .start_date
+                .checked_add_months(Months::new(steps.checked_mul(12)?)),
+        }
+    }
+
+    /// Every occurrence after the last posted one, up to and including `until`
+    /// and never past the end date. Skipped dates are included.
+    fn pending_occurrences(&self, until: NaiveDate) -> Vec<NaiveDate> {
+        let until = self.end_date.map_or(until, |end_date| end_date.min(until));
+
+        (0..)
+            .map_while(|n| self.occurrence(n))
+            .take_while(|date| *date <= until)
+            .filter(|date| self.last_posted_on.is_none_or(|last| *date > last))
+            .collect()
+    }
+
+    /// The next occurrence that will be posted, if the schedule has not ended.
+    pub fn next_occurrence(&self, skips: &[NaiveDate]) -> Option<NaiveDate> {
+        (0..)
+            .map_while(|n| self.occurrence(n))
+            .take_while(|date| self.end_date.is_none_or(|end_date| *date <= end_date))
+            .find(|date| {
+                self.last_posted_on.is_none_or(|last| *date > last) && !skips.contains(date)
+            })
+    }
+}
+
+fn schedule_columns() -> [Schedules; 13] {
+    [
+        Schedules::Id,
+        Schedules::AccountId,
+        Schedules::Amount,
+        Schedules::Type,
+        Schedules::Description,
+        Schedules::CategoryId,
+        Schedules::PayeeId,
+        Schedules::Frequency,
+        Schedules::Interval,
+        Schedules::StartDate,
+        Schedules::EndDate,
+        Schedules::LastPostedOn,
+        Schedules::CreatedAt,
+    ]
+}
+
+fn select_schedules() -> SelectStatement {
+    Query::select()
+        .columns(
+            schedule_columns()
+                .into_iter()
+                .map(|column| (Schedules::Table, column)),
+        )
+        .column((Accounts::Table, Accounts::Currency))
+        .from(Schedules::Table)
+        .left_join(
+            Accounts::Table,
+            Expr::col((Accounts::Table, Accounts::Id))
+                .equals((Schedules::Table, Schedules::AccountId)),
+        )
+        .to_owned()
+}
+
+fn parse_date(value: Option<String>) -> Option<NaiveDate> {
+    value.and_then(|value| NaiveDate::from_str(&value).ok())
+}
+
+fn schedule_from_row(row: &Row) -> Result<Schedule, rusqlite::Error> {
+    let created_at = DateTime::from_str(row.get::<_, String>(12)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Schedule {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        amount: Money::new(row.get(2)?, &row.get::<_, String>(13)?),
+        transaction_type: row.get(3)?,
+        description: row.get(4)?,
+        category_id: row.get(5)?,
+        payee_id: row.get(6)?,
+        frequency: Frequency::from_str(&row.get::<_, String>(7)?).unwrap_or(Frequency::Monthly),
+        interval: row.get(8)?,
+        start_date: parse_date(row.get(9)?).unwrap_or(Local::now().date_naive()),
+        end_date: parse_date(row.get(10)?),
+        last_posted_on: parse_date(row.get(11)?),
+        created_at,
+    })
+}
+
+impl Db {
+    pub fn create_schedule(&self, schedule: &ScheduleRequest) -> Result<Schedule, DbError> {
+        validate_interval(schedule.interval)?;
+
+        let id = random_hash(8);
+
+        let query = Query::insert()
+            .into_table(Schedules::Table)
+            .columns(schedule_columns())
+            .values_panic(vec![
+                id.clone().into(),
+                schedule.account_id.clone().into(),
+                schedule.amount.minor.into(),
+                schedule.transaction_type.clone().into(),
+                schedule.description.clone().into(),
+                schedule.category_id.clone().into(),
+                schedule.payee_id.clone().into(),
+                schedule.frequency.as_str().into(),
+                schedule.interval.into(),
+                schedule.start_date.to_string().into(),
+                schedule.end_date.map(|date| date.to_string()).into(),
+                None::<String>.into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        let tx = self.conn.unchecked_transaction()?;
+        self.conn.execute(&query, ())?;
+        self.write_schedule_skips(&id, &schedule.skips)?;
+        tx.commit()?;
+
+        Ok(Schedule {
+            id,
+            account_id: schedule.account_id.clone(),
+            amount: schedule.amount.clone(),
+            transaction_type: schedule.transaction_type.clone(),
+            description: schedule.description.clone(),
+            category_id: schedule.category_id.clone(),
+            payee_id: schedule.payee_id.clone(),
+            frequency: schedule.frequency,
+            interval: schedule.interval,
+            start_date: schedule.start_date,
+            end_date: schedule.end_date,
+            last_posted_on: None,
+            created_at: Local::now(),
+        })
+    }
+
+    pub fn get_schedule(&self, id: &str) -> Result<Schedule, DbError> {
+        let query = select_schedules()
+            .and_where(Expr::col((Schedules::Table, Schedules::Id)).eq(id))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt.query_row((), schedule_from_row)?)
+    }
+
+    pub fn get_all_schedules(&self) -> Result<Vec<Schedule>, DbError> {
+        let query = select_schedules()
+            .order_by((Schedules::Table, Schedules::StartDate), Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), schedule_from_row)?
+            .map(|schedule| schedule.unwrap())
+            .collect::<Vec<Schedule>>())
+    }
+
+    pub fn update_schedule(&self, id: &str, schedule: &Schedule) -> Result<(), DbError> {
+        validate_interval(schedule.interval)?;
+
+        let query = Query::update()
+            .table(Schedules::Table)
+            .and_where(Expr::col(Schedules::Id).eq(id))
+            .values(vec![
+                (Schedules::AccountId, schedule.account_id.clone().into()),
+                (Schedules::Amount, schedule.amount.minor.into()),
+                (Schedules::Type, schedule.transaction_type.clone().into()),
+                (Schedules::Description, schedule.description.clone().into()),
+                (Schedules::CategoryId, schedule.category_id.clone().into()),
+                (Schedules::PayeeId, schedule.payee_id.clone().into()),
+                (Schedules::Frequency, schedule.frequency.as_str().into()),
+                (Schedules::Interval, schedule.interval.into()),
+                (Schedules::StartDate, schedule.start_date.to_string().into()),
+                (
+                    Schedules::EndDate,
+                    schedule.end_date.map(|date| date.to_string()).into(),
+                ),
+                (
+                    Schedules::LastPostedOn,
+                    schedule.last_posted_on.map(|date| date.to_string()).into(),
+                ),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// Moves every schedule filed under `from_category_id` to `to_category_id`.
+    pub(super) fn refile_category_schedules(
+        &self,
+        from_category_id: &str,
+        to_category_id: &str,
+    ) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(Schedules::Table)
+            .value(Schedules::CategoryId, to_category_id)
+            .and_where(Expr::col(Schedules::CategoryId).eq(from_category_id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// Deletes a schedule. Transactions it already posted are kept.
+    pub fn delete_schedule(&self, id: &str) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(Schedules::Table)
+            .and_where(Expr::col(Schedules::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        let tx = self.conn.unchecked_transaction()?;
+        self.write_schedule_skips(id, &[])?;
+        self.conn.execute(&query, ())?;
+        Ok(tx.commit()?)
+    }
+
+    pub fn get_schedule_skips(&self, schedule_id: &str) -> Result<Vec<NaiveDate>, DbError> {
+        let query = Query::select()
+            .column(ScheduleSkips::Date)
+            .from(ScheduleSkips::Table)
+            .and_where(Expr::col(ScheduleSkips::ScheduleId).eq(schedule_id))
+            .order_by(ScheduleSkips::Date, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), |row| row.get::<_, String>(0))?
+            .filter_map(|date| parse_date(date.ok()))
+            .collect::<Vec<NaiveDate>>())
+    }
+
+    /// Replaces the dates a schedule skips.
+    pub fn set_schedule_skips(
+        &self,
+        schedule_id: &str,
+        skips: &[NaiveDate],
+    ) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.write_schedule_skips(schedule_id, skips)?;
+        Ok(tx.commit()?)
+    }
+
+    fn write_schedule_skips(&self, schedule_id: &str, skips: &[NaiveDate]) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(ScheduleSkips::Table)
+            .and_where(Expr::col(ScheduleSkips::ScheduleId).eq(schedule_id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&query, ())?;
+
+        for skip in skips {
+            let query = Query::insert()
+                .into_table(ScheduleSkips::Table)
+                .columns([ScheduleSkips::ScheduleId, ScheduleSkips::Date])
+                .values_panic(vec![schedule_id.into(), skip.to_string().into()])
+                .on_conflict(
+                    sea_query::OnConflict::columns([
+                        ScheduleSkips::ScheduleId,
+                        ScheduleSkips::Date,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&query, ())?;
+        }
+
+        Ok(())
+    }
+
+    /// Posts every occurrence of every schedule that is due by `until` as a
+    /// pending transaction. Each schedule remembers the last occurrence it
+    /// handled in the same database transaction, so running this again never
+    /// posts an occurrence twice.
+    pub fn run_schedules(&self, until: NaiveDate) -> Result<Vec<Transaction>, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let mut posted = vec![];
+        for mut schedule in self.get_all_schedules()? {
+            let occurrences = schedule.pending_occurrences(until);
+            let Some(last) = occurrences.last().cloned() else {
+                continue;
+            };
+            let skips = self.get_schedule_skips(&schedule.id)?;
+
+            for posted_on in occurrences.into_iter().filter(|date| !skips.contains(date)) {
+                posted.push(self.insert_transaction(
+                    &TransactionRequest {
+                        account_id: schedule.account_id.clone(),
+                        amount: schedule.amount.clone(),
+                        transaction_type: schedule.transaction_type.clone(),
+                        description: schedule.description.clone(),
+                        category_id: schedule.category_id.clone(),
+                        posted_on,
+                        status: TransactionStatus::Pending,
+                        payee_id: schedule.payee_id.clone(),
+                        splits: vec![],
+                        tags: vec![],
+                        attachments: vec![],
+                    },
+                    None,
+                )?);
+            }
+
+            schedule.last_posted_on = Some(last);
+            self.update_schedule(&schedule.id, &schedule)?;
+        }
+
+        tx.commit()?;
+
+        Ok(posted)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::category::CategoryRequest,
+        money::DEFAULT_CURRENCY,
+        utils::{create_test_account, create_test_user, get_test_db},
+    };
+
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    /// Monthly rent at the end of January to June 2024, skipping March, and a
+    /// fortnightly gym fee from the start of 2024. Returns the rent first.
+    fn setup(name: &str) -> (Db, Schedule, Schedule) {
+        let db = get_test_db(name);
+        let user = create_test_user(&db, "John Doe");
+        let account = create_test_account(&db, &user.id, "Checking", 0);
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Rent".to_string(),
+                icon: "🏠".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        let schedule =
+            |description: &str, amount, frequency, interval, start_date, end_date, skips| {
+                ScheduleRequest {
+                    account_id: account.id.clone(),
+                    amount: Money::new(amount, DEFAULT_CURRENCY),
+                    transaction_type: "debit".to_string(),
+                    description: description.to_string(),
+                    category_id: category.id.clone(),
+                    payee_id: None,
+                    frequency,
+                    interval,
+                    start_date,
+                    end_date,
+                    skips,
+                }
+            };
+
+        let rent = db
+            .create_schedule(&schedule(
+                "Rent",
+                120000,
+                Frequency::Monthly,
+                1,
+                date(2024, 1, 31),
+                Some(date(2024, 6, 30)),
+                vec![date(2024, 3, 31)],
+            ))
+            .unwrap();
+        let gym = db
+            .create_schedule(&schedule(
+                "Gym",
+                1500,
+                Frequency::Weekly,
+                2,
+                date(2024, 1, 1),
+                None,
+                vec![],
+            ))
+            .unwrap();
+
+        (db, rent, gym)
+    }
+
+    #[test]
+    fn test_schedule_occurrences() {
+        let (_, rent, gym) = setup("schedule_occurrences");
+
+        assert_eq!(gym.rule(), "every 2 weeks");
+        // Month ends stick to the last day of shorter months
+        assert_eq!(
+            rent.pending_occurrences(date(2024, 3, 1)),
+            vec![date(2024, 1, 31), date(2024, 2, 29)]
+        );
+    }
+
+    #[test]
+    fn test_run_schedules() {
+        let (db, _, _) = setup("schedule_run");
+
+        // Two rent payments and five fortnightly gym fees
+        let posted = db.run_schedules(date(2024, 3, 1)).unwrap();
+        assert_eq!(posted.len(), 7);
+        assert!(
+            posted
+                .iter()
+                .all(|transaction| transaction.status == TransactionStatus::Pending)
+        );
+
+        // Running again posts nothing new
+        assert!(db.run_schedules(date(2024, 3, 1)).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_run_schedules_with_skips_and_end() {
+        let (db, rent, _) = setup("schedule_skips_and_end");
+
+        // The skipped March rent is passed over, the schedule ends in June
+        let posted = db.run_schedules(date(2024, 12, 31)).unwrap();
+        let rent_dates = posted
+            .iter()
+            .filter(|transaction| transaction.description == "Rent")
+            .map(|transaction| transaction.posted_on)
+            .collect::<Vec<NaiveDate>>();
+        assert_eq!(
+            rent_dates,
+            vec![
+                date(2024, 1, 31),
+                date(2024, 2, 29),
+                date(2024, 4, 30),
+                date(2024, 5, 31),
+                date(2024, 6, 30)
+            ]
+        );
+        assert_eq!(
+            db.get_schedule(&rent.id).unwrap().next_occurrence(&[]),
+            None
+        );
+    }
+
+    #[test]
+    fn test_zero_interval() {
+        let (db, rent, _) = setup("schedule_zero_interval");
+
+        // It would never get past its first occurrence
+        let mut stuck = rent.clone();
+        stuck.interval = 0;
+        assert!(matches!(
+            db.update_schedule(&rent.id, &stuck),
+            Err(DbError::Invalid(_))
+        ));
+        assert_eq!(db.get_schedule(&rent.id).unwrap().interval, 1);
+        assert!(matches!(
+            db.create_schedule(&ScheduleRequest {
+                account_id: rent.account_id.clone(),
+                amount: rent.amount.clone(),
+                transaction_type: rent.transaction_type.clone(),
+                description: rent.description.clone(),
+                category_id: rent.category_id.clone(),
+                payee_id: None,
+                frequency: Frequency::Monthly,
+                interval: 0,
+                start_date: rent.start_date,
+                end_date: None,
+                skips: vec![],
+            }),
+            Err(DbError::Invalid(_))
+        ));
+        assert_eq!(db.get_all_schedules().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_delete_schedule() {
+        let (db, _, gym) = setup("schedule_delete");
+
+        db.delete_schedule(&gym.id).unwrap();
+        assert_eq!(db.get_all_schedules().unwrap().len(), 1);
+        assert!(db.get_schedule_skips(&gym.id).unwrap().is_empty());
+    }
+}
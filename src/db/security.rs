@@ -0,0 +1,283 @@
+use std::str::FromStr;
+
+use chrono::{Local, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{Securities, SecurityPrices, Trades};
+
+/// A stock, fund or anything else that is held in units and has a price.
+#[derive(Debug, Clone, Tabled)]
+pub struct Security {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+    /// The currency it is priced and traded in
+    pub currency: String,
+}
+
+pub struct SecurityRequest {
+    pub symbol: String,
+    pub name: String,
+    pub currency: String,
+}
+
+/// What one unit of a security was worth at the end of a day.
+#[derive(Debug, Clone)]
+pub struct SecurityPrice {
+    pub security_id: String,
+    pub priced_on: NaiveDate,
+    pub price: Money,
+}
+
+fn security_columns() -> [Securities; 4] {
+    [
+        Securities::Id,
+        Securities::Symbol,
+        Securities::Name,
+        Securities::Currency,
+    ]
+}
+
+fn security_from_row(row: &Row) -> Result<Security, rusqlite::Error> {
+    Ok(Security {
+        id: row.get(0)?,
+        symbol: row.get(1)?,
+        name: row.get(2)?,
+        currency: row.get(3)?,
+    })
+}
+
+impl Db {
+    /// Symbols are kept in upper case so that `aapl` and `AAPL` are the same.
+    pub fn create_security(&self, security: &SecurityRequest) -> Result<Security, DbError> {
+        let id = random_hash(8);
+        let symbol = security.symbol.trim().to_uppercase();
+
+        let query = Query::insert()
+            .into_table(Securities::Table)
+            .columns([
+                Securities::Id,
+                Securities::Symbol,
+                Securities::Name,
+                Securities::Currency,
+                Securities::CreatedAt,
+            ])
+            .values_panic(vec![
+                id.clone().into(),
+                symbol.clone().into(),
+                security.name.clone().into(),
+                security.currency.clone().into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(Security {
+            id,
+            symbol,
+            name: security.name.clone(),
+            currency: security.currency.clone(),
+        })
+    }
+
+    pub fn get_security(&self, id: &str) -> Result<Security, DbError> {
+        let query = Query::select()
+            .columns(security_columns())
+            .from(Securities::Table)
+            .and_where(Expr::col(Securities::Id).eq(id))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt.query_row((), security_from_row)?)
+    }
+
+    pub fn get_security_by_symbol(&self, symbol: &str) -> Result<Security, DbError> {
+        let query = Query::select()
+            .columns(security_columns())
+            .from(Securities::Table)
+            .and_where(Expr::col(Securities::Symbol).eq(symbol.trim().to_uppercase()))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt.query_row((), security_from_row)?)
+    }
+
+    pub fn get_all_securities(&self) -> Result<Vec<Security>, DbError> {
+        let query = Query::select()
+            .columns(security_columns())
+            .from(Securities::Table)
+            .order_by(Securities::Symbol, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), security_from_row)?
+            .map(|security| security.unwrap())
+            .collect::<Vec<Security>>())
+    }
+
+    /// Deletes a security along with its prices and every trade in it.
+    pub fn delete_security(&self, id: &str) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        let delete_prices = Query::delete()
+            .from_table(SecurityPrices::Table)
+            .and_where(Expr::col(SecurityPrices::SecurityId).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&delete_prices, ())?;
+
+        let delete_trades = Query::delete()
+            .from_table(Trades::Table)
+            .and_where(Expr::col(Trades::SecurityId).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&delete_trades, ())?;
+
+        let delete_security = Query::delete()
+            .from_table(Securities::Table)
+            .and_where(Expr::col(Securities::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&delete_security, ())?;
+
+        Ok(tx.commit()?)
+    }
+
+    /// Stores prices, replacing any already recorded for the same day and
+    /// security.
+    pub fn set_security_prices(&self, prices: &[SecurityPrice]) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+
+        for price in prices {
+            let delete = Query::delete()
+                .from_table(SecurityPrices::Table)
+                .and_where(Expr::col(SecurityPrices::SecurityId).eq(price.security_id.as_str()))
+                .and_where(Expr::col(SecurityPrices::PricedOn).eq(price.priced_on.to_string()))
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&delete, ())?;
+
+            let insert = Query::insert()
+                .into_table(SecurityPrices::Table)
+                .columns([
+                    SecurityPrices::SecurityId,
+                    SecurityPrices::PricedOn,
+                    SecurityPrices::Price,
+                ])
+                .values_panic(vec![
+                    price.security_id.clone().into(),
+                    price.priced_on.to_string().into(),
+                    price.price.minor.into(),
+                ])
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&insert, ())?;
+        }
+
+        Ok(tx.commit()?)
+    }
+
+    /// The most recent price of a security on or before `on`.
+    pub fn get_security_price(
+        &self,
+        security: &Security,
+        on: NaiveDate,
+    ) -> Result<Option<SecurityPrice>, DbError> {
+        let query = Query::select()
+            .columns([SecurityPrices::PricedOn, SecurityPrices::Price])
+            .from(SecurityPrices::Table)
+            .and_where(Expr::col(SecurityPrices::SecurityId).eq(security.id.as_str()))
+            .and_where(Expr::col(SecurityPrices::PricedOn).lte(on.to_string()))
+            .order_by(SecurityPrices::PricedOn, Order::Desc)
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query_map((), |row| {
+            Ok(SecurityPrice {
+                security_id: security.id.clone(),
+                priced_on: NaiveDate::from_str(row.get::<_, String>(0)?.as_str()).unwrap_or(on),
+                price: Money::new(row.get(1)?, &security.currency),
+            })
+        })?;
+
+        Ok(rows.next().transpose()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{money::DEFAULT_CURRENCY, utils::get_test_db_path};
+
+    use super::*;
+
+    #[test]
+    fn test_securities() {
+        let _ = std::fs::remove_file(get_test_db_path("security"));
+
+        let db = Db::new(get_test_db_path("security").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let security = db
+            .create_security(&SecurityRequest {
+                symbol: " vti ".to_string(),
+                name: "Vanguard Total Stock Market".to_string(),
+                currency: DEFAULT_CURRENCY.to_string(),
+            })
+            .unwrap();
+        assert_eq!(security.symbol, "VTI");
+        assert_eq!(db.get_security_by_symbol("vti").unwrap().id, security.id);
+
+        // Symbols are unique
+        assert!(
+            db.create_security(&SecurityRequest {
+                symbol: "VTI".to_string(),
+                name: "Duplicate".to_string(),
+                currency: DEFAULT_CURRENCY.to_string(),
+            })
+            .is_err()
+        );
+
+        let day = |d: u32| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let price = |priced_on: NaiveDate, minor: i64| SecurityPrice {
+            security_id: security.id.clone(),
+            priced_on,
+            price: Money::new(minor, DEFAULT_CURRENCY),
+        };
+        db.set_security_prices(&[price(day(1), 25000), price(day(10), 26000)])
+            .unwrap();
+        // Setting the same day again replaces the old price
+        db.set_security_prices(&[price(day(10), 26500)]).unwrap();
+
+        assert!(
+            db.get_security_price(&security, day(1).pred_opt().unwrap())
+                .unwrap()
+                .is_none()
+        );
+        let latest = db.get_security_price(&security, day(15)).unwrap().unwrap();
+        assert_eq!(latest.priced_on, day(10));
+        assert_eq!(latest.price.minor, 26500);
+        assert_eq!(
+            db.get_security_price(&security, day(9))
+                .unwrap()
+                .unwrap()
+                .price
+                .minor,
+            25000
+        );
+
+        db.delete_security(&security.id).unwrap();
+        assert!(db.get_all_securities().unwrap().is_empty());
+        assert!(db.get_security_price(&security, day(15)).unwrap().is_none());
+    }
+}
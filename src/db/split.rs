@@ -0,0 +1,332 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local};
+use rusqlite::Row;
+use sea_query::{Expr, Query, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{Accounts, TransactionSplits, Transactions};
+
+/// A slice of a transaction filed under its own category.
+#[derive(Debug, Clone, Tabled)]
+pub struct Split {
+    pub id: String,
+    pub transaction_id: String,
+    pub category_id: String,
+    pub amount: Money,
+    pub memo: String,
+    pub created_at: chrono::DateTime<Local>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SplitRequest {
+    pub category_id: String,
+    pub amount: Money,
+    pub memo: String,
+}
+
+impl From<Split> for SplitRequest {
+    fn from(split: Split) -> Self {
+        Self {
+            category_id: split.category_id,
+            amount: split.amount,
+            memo: split.memo,
+        }
+    }
+}
+
+/// Raised when split lines do not add up to the amount of their transaction.
+#[derive(Debug)]
+pub struct SplitMismatchError {
+    pub expected: Money,
+    pub actual: Money,
+}
+
+impl std::fmt::Display for SplitMismatchError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Splits add up to {} but the transaction is {}",
+            self.actual, self.expected
+        )
+    }
+}
+
+impl std::error::Error for SplitMismatchError {}
+
+/// Checks that `splits` add up to `amount`. No splits at all is always valid.
+pub fn validate_splits(amount: &Money, splits: &[SplitRequest]) -> Result<(), DbError> {
+    if splits.is_empty() {
+        return Ok(());
+    }
+
+    let total = splits.iter().map(|split| split.amount.minor).sum::<i64>();
+    if total != amount.minor {
+        return Err(DbError::invalid(SplitMismatchError {
+            expected: amount.clone(),
+            actual: Money::new(total, &amount.currency),
+        }));
+    }
+
+    Ok(())
+}
+
+fn split_columns() -> [TransactionSplits; 6] {
+    [
+        TransactionSplits::Id,
+        TransactionSplits::TransactionId,
+        TransactionSplits::CategoryId,
+        TransactionSplits::Amount,
+        TransactionSplits::Memo,
+        TransactionSplits::CreatedAt,
+    ]
+}
+
+fn split_from_row(row: &Row) -> Result<Split, rusqlite::Error> {
+    let created_at = DateTime::from_str(row.get::<_, String>(5)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Split {
+        id: row.get(0)?,
+        transaction_id: row.get(1)?,
+        category_id: row.get(2)?,
+        amount: Money::new(row.get(3)?, &row.get::<_, String>(6)?),
+        memo: row.get(4)?,
+        created_at,
+    })
+}
+
+impl Db {
+    pub fn get_transaction_splits(&self, transaction_id: &str) -> Result<Vec<Split>, DbError> {
+        // Splits are in the currency of the account their transaction belongs to
+        let query = Query::select()
+            .columns(
+                split_columns()
+                    .into_iter()
+                    .map(|column| (TransactionSplits::Table, column)),
+            )
+            .column((Accounts::Table, Accounts::Currency))
+            .from(TransactionSplits::Table)
+            .left_join(
+                Transactions::Table,
+                Expr::col((Transactions::Table, Transactions::Id))
+                    .equals((TransactionSplits::Table, TransactionSplits::TransactionId)),
+            )
+            .left_join(
+                Accounts::Table,
+                Expr::col((Accounts::Table, Accounts::Id))
+                    .equals((Transactions::Table, Transactions::AccountId)),
+            )
+            .and_where(
+                Expr::col((TransactionSplits::Table, TransactionSplits::TransactionId))
+                    .eq(transaction_id),
+            )
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), split_from_row)?
+            .map(|split| split.unwrap())
+            .collect::<Vec<Split>>())
+    }
+
+    pub(super) fn insert_transaction_splits(
+        &self,
+        transaction_id: &str,
+        splits: &[SplitRequest],
+    ) -> Result<(), DbError> {
+        let time_now = Local::now().to_string();
+
+        for split in splits {
+            let query = Query::insert()
+                .into_table(TransactionSplits::Table)
+                .columns(split_columns())
+                .values_panic(vec![
+                    random_hash(8).into(),
+                    transaction_id.into(),
+                    split.category_id.clone().into(),
+                    split.amount.minor.into(),
+                    split.memo.clone().into(),
+                    time_now.clone().into(),
+                ])
+                .to_string(SqliteQueryBuilder);
+
+            self.conn.execute(&query, ())?;
+        }
+
+        Ok(())
+    }
+
+    /// Moves every split line filed under `from_category_id` to `to_category_id`.
+    pub(super) fn refile_category_splits(
+        &self,
+        from_category_id: &str,
+        to_category_id: &str,
+    ) -> Result<(), DbError> {
+        let query = Query::update()
+            .table(TransactionSplits::Table)
+            .value(TransactionSplits::CategoryId, to_category_id)
+            .and_where(Expr::col(TransactionSplits::CategoryId).eq(from_category_id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    pub(super) fn delete_transaction_splits(&self, transaction_id: &str) -> Result<(), DbError> {
+        let query = Query::delete()
+            .from_table(TransactionSplits::Table)
+            .and_where(Expr::col(TransactionSplits::TransactionId).eq(transaction_id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::AccountHolder,
+            transaction::{TransactionEdit, TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_splits() {
+        let _ = std::fs::remove_file(get_test_db_path("split"));
+
+        let db = Db::new(get_test_db_path("split").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Checking".to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(0, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let groceries = db
+            .create_category(&CategoryRequest {
+                name: "Groceries".to_string(),
+                icon: "🥦".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        let household = db
+            .create_category(&CategoryRequest {
+                name: "Household".to_string(),
+                icon: "🧽".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let receipt = |splits: Vec<SplitRequest>| TransactionRequest {
+            account_id: account.id.clone(),
+            amount: Money::new(6000, DEFAULT_CURRENCY),
+            transaction_type: "debit".to_string(),
+            description: "Supermarket".to_string(),
+            category_id: groceries.id.clone(),
+            posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            status: TransactionStatus::Cleared,
+            payee_id: None,
+            splits,
+            tags: vec![],
+            attachments: vec![],
+        };
+        let split = |category_id: &str, minor: i64| SplitRequest {
+            category_id: category_id.to_string(),
+            amount: Money::new(minor, DEFAULT_CURRENCY),
+            memo: String::new(),
+        };
+
+        // Splits that do not add up are rejected and nothing is written
+        let mismatched = db.create_transaction(&receipt(vec![
+            split(&groceries.id, 3000),
+            split(&household.id, 2000),
+        ]));
+        assert!(matches!(mismatched, Err(DbError::Invalid(_))));
+        assert!(db.get_all_transactions().unwrap().is_empty());
+
+        let transaction = db
+            .create_transaction(&receipt(vec![
+                split(&groceries.id, 3500),
+                split(&household.id, 2500),
+            ]))
+            .unwrap();
+        assert_eq!(db.get_transaction_splits(&transaction.id).unwrap().len(), 2);
+
+        // Each split counts towards its own category
+        let totals = db
+            .get_category_totals(DEFAULT_CURRENCY, None, None)
+            .unwrap();
+        let expenses_of = |category_id: &str| {
+            totals
+                .iter()
+                .find(|total| total.category_id == category_id)
+                .map(|total| total.expenses.minor)
+        };
+        assert_eq!(expenses_of(&groceries.id), Some(3500));
+        assert_eq!(expenses_of(&household.id), Some(2500));
+
+        // The amount cannot drift away from its splits
+        let mut changed = transaction.clone();
+        changed.amount = Money::new(7000, DEFAULT_CURRENCY);
+        assert!(
+            db.update_transaction(&transaction.id, &changed.clone().into())
+                .is_err()
+        );
+        assert_eq!(db.get_transaction_splits(&transaction.id).unwrap().len(), 2);
+
+        // Dropping the splits in the same update files it whole again, an empty
+        // list puts the whole amount under the transaction's own category
+        db.update_transaction(
+            &transaction.id,
+            &TransactionEdit {
+                splits: Some(vec![]),
+                ..changed.into()
+            },
+        )
+        .unwrap();
+        let totals = db
+            .get_category_totals(DEFAULT_CURRENCY, None, None)
+            .unwrap();
+        assert_eq!(totals.len(), 1);
+        assert_eq!(totals[0].expenses.minor, 7000);
+
+        db.delete_transaction(&transaction.id).unwrap();
+        assert!(
+            db.get_transaction_splits(&transaction.id)
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,408 @@
+use chrono::{Datelike, Months, NaiveDate};
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+};
+
+use super::{
+    account::CardTerms,
+    budget::{month_end, month_start},
+    transaction::Transaction,
+};
+
+/// The least that is asked for while a statement balance is owed. Cards in
+/// other currencies are asked for as much in their own currency, at the rate
+/// of the closing date.
+pub fn minimum_payment_floor() -> Money {
+    Money::new(2500, "USD")
+}
+
+/// Share of the statement balance that goes into the minimum payment, in
+/// percent, on top of a month of interest.
+pub const MINIMUM_PAYMENT_PERCENT: i64 = 1;
+
+#[derive(Debug)]
+pub struct NotACreditCardError {
+    pub account_id: String,
+}
+
+impl std::fmt::Display for NotACreditCardError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Account {} is not a credit card with a statement closing and due day",
+            self.account_id
+        )
+    }
+}
+
+impl std::error::Error for NotACreditCardError {}
+
+/// The last closed statement of a credit card and the cycle that runs since.
+/// Amounts are what is owed, so charges count up and payments count down.
+#[derive(Debug, Clone)]
+pub struct CardStatement {
+    /// The day the last statement closed
+    pub closing_date: NaiveDate,
+    pub due_date: NaiveDate,
+    /// The day the current cycle closes
+    pub next_closing_date: NaiveDate,
+    /// What was owed when the last statement closed
+    pub statement_balance: Money,
+    pub minimum_payment: Money,
+    /// Payments and refunds posted since the last statement closed
+    pub paid: Money,
+    /// Charges posted since the last statement closed
+    pub charges: Money,
+    /// What is owed now
+    pub balance: Money,
+    pub available_credit: Option<Money>,
+    /// Everything posted in the current cycle
+    pub transactions: Vec<Transaction>,
+    /// The day the statement was worked out for
+    pub on: NaiveDate,
+}
+
+impl CardStatement {
+    /// What is left of the statement balance after the payments since.
+    pub fn remaining(&self) -> Money {
+        Money::new(
+            (self.statement_balance.minor - self.paid.minor).max(0),
+            &self.statement_balance.currency,
+        )
+    }
+
+    /// What is left of the minimum payment after the payments since.
+    pub fn remaining_minimum(&self) -> Money {
+        Money::new(
+            (self.minimum_payment.minor - self.paid.minor).max(0),
+            &self.minimum_payment.currency,
+        )
+    }
+
+    /// Days left until the payment is due, negative once it is overdue.
+    pub fn days_until_due(&self) -> i64 {
+        (self.due_date - self.on).num_days()
+    }
+}
+
+/// The date `day` of the month starting on `month`, or the last day of the
+/// month when it is shorter.
+fn day_of_month(month: NaiveDate, day: u32) -> NaiveDate {
+    month_start(month)
+        .with_day(day.min(month_end(month_start(month)).day()))
+        .unwrap_or(month)
+}
+
+/// The last closing date that lies before `on`, so that the cycle closing on
+/// `on` itself is still open.
+pub fn last_closing_date(card: &CardTerms, on: NaiveDate) -> NaiveDate {
+    let closing = day_of_month(on, card.closing_day);
+    if closing < on {
+        closing
+    } else {
+        day_of_month(month_start(on) - Months::new(1), card.closing_day)
+    }
+}
+
+/// The first due day that comes after `closing_date`.
+pub fn due_date(card: &CardTerms, closing_date: NaiveDate) -> NaiveDate {
+    let due = day_of_month(closing_date, card.due_day);
+    if due > closing_date {
+        due
+    } else {
+        day_of_month(month_start(closing_date) + Months::new(1), card.due_day)
+    }
+}
+
+/// What has to be paid at least on a statement: a percentage of the balance
+/// plus a month of interest, but no less than `floor`, in the same currency,
+/// and no more than the balance itself.
+pub fn minimum_payment(statement_balance: &Money, apr: Option<f64>, floor: &Money) -> Money {
+    let owed = statement_balance.minor;
+    if owed <= 0 {
+        return Money::new(0, &statement_balance.currency);
+    }
+
+    let interest = (owed as f64 * apr.unwrap_or(0.0) / 100.0 / 12.0).round() as i64;
+    let minimum = (owed * MINIMUM_PAYMENT_PERCENT / 100 + interest)
+        .max(floor.minor)
+        .min(owed);
+
+    Money::new(minimum, &statement_balance.currency)
+}
+
+impl Db {
+    /// [`minimum_payment_floor`] in `currency`. Without a rate to go by there
+    /// is no floor, and the percentage and interest alone make the minimum.
+    fn minimum_payment_floor_in(&self, currency: &str, on: NaiveDate) -> Result<Money, DbError> {
+        let floor = minimum_payment_floor();
+        let rate = self
+            .get_exchange_rate(&floor.currency, currency, on)?
+            .unwrap_or(0.0);
+
+        Ok(floor.exchange(rate, currency))
+    }
+
+    /// Works out the last statement of a credit card as it stands on `on`.
+    pub fn get_card_statement(
+        &self,
+        account_id: &str,
+        on: NaiveDate,
+    ) -> Result<CardStatement, DbError> {
+        let account = self.get_account(account_id)?;
+        let card = account.card.clone().ok_or_else(|| {
+            DbError::invalid(NotACreditCardError {
+                account_id: account.id.clone(),
+            })
+        })?;
+        let currency = account.balance.currency.clone();
+
+        let closing_date = last_closing_date(&card, on);
+        let next_closing_date =
+            day_of_month(month_start(closing_date) + Months::new(1), card.closing_day);
+
+        // Ledger amounts, where charges on a card count down
+        let mut closed = account.opening_balance.minor;
+        let mut charges = 0;
+        let mut paid = 0;
+        let mut transactions = vec![];
+        for transaction in self.get_transactions_by_account(&account.id)? {
+            let amount = transaction.signed_amount();
+            if transaction.posted_on <= closing_date {
+                closed += amount;
+            } else if transaction.posted_on <= next_closing_date {
+                if amount < 0 {
+                    charges -= amount;
+                } else {
+                    paid += amount;
+                }
+                transactions.push(transaction);
+            }
+        }
+        transactions.sort_by_key(|transaction| transaction.posted_on);
+
+        let statement_balance = account
+            .account_type
+            .display_amount(&Money::new(closed, &currency));
+        let balance = account.account_type.display_amount(&account.balance);
+        let floor = self.minimum_payment_floor_in(&currency, closing_date)?;
+
+        Ok(CardStatement {
+            closing_date,
+            due_date: due_date(&card, closing_date),
+            next_closing_date,
+            minimum_payment: minimum_payment(&statement_balance, card.apr, &floor),
+            statement_balance,
+            paid: Money::new(paid, &currency),
+            charges: Money::new(charges, &currency),
+            available_credit: card
+                .credit_limit
+                .map(|limit| Money::new(limit.minor - balance.minor, &currency)),
+            balance,
+            transactions,
+            on,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            exchange_rate::ExchangeRate,
+            holder::AccountHolder,
+            transaction::{TransactionRequest, TransactionStatus},
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_card_statement() {
+        let _ = std::fs::remove_file(get_test_db_path("statement"));
+
+        let db = Db::new(get_test_db_path("statement").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Visa".to_string(),
+                bank: "Chase".to_string(),
+                account_number: None,
+                account_type: AccountType::CreditCard,
+                opening_balance: Money::new(-10000, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Shopping".to_string(),
+                icon: "🛍️".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        let on = NaiveDate::from_ymd_opt(2024, 3, 10).unwrap();
+
+        // Without a billing cycle there is no statement
+        assert!(db.get_card_statement(&account.id, on).is_err());
+
+        let card = CardTerms {
+            closing_day: 31,
+            due_day: 25,
+            credit_limit: Some(Money::new(500000, DEFAULT_CURRENCY)),
+            apr: Some(24.0),
+        };
+        db.set_card_terms(&account.id, Some(&card)).unwrap();
+        assert_eq!(db.get_account(&account.id).unwrap().card, Some(card));
+
+        for (amount, transaction_type, posted_on, status) in [
+            (290000, "debit", "2024-02-12", TransactionStatus::Cleared),
+            (50000, "debit", "2024-02-29", TransactionStatus::Pending),
+            (99900, "debit", "2024-03-01", TransactionStatus::Void),
+            (20000, "debit", "2024-03-02", TransactionStatus::Pending),
+            (100000, "credit", "2024-03-05", TransactionStatus::Cleared),
+        ] {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(amount, DEFAULT_CURRENCY),
+                transaction_type: transaction_type.to_string(),
+                description: "Purchase".to_string(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_str(posted_on).unwrap(),
+                status,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+        }
+
+        // February closes on its last day and is due in March
+        let statement = db.get_card_statement(&account.id, on).unwrap();
+        assert_eq!(
+            statement.closing_date,
+            NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()
+        );
+        assert_eq!(
+            statement.due_date,
+            NaiveDate::from_ymd_opt(2024, 3, 25).unwrap()
+        );
+        assert_eq!(
+            statement.next_closing_date,
+            NaiveDate::from_ymd_opt(2024, 3, 31).unwrap()
+        );
+        assert_eq!(statement.days_until_due(), 15);
+        assert_eq!(statement.statement_balance.minor, 350000);
+        // 1% of the balance plus 2% interest
+        assert_eq!(statement.minimum_payment.minor, 10500);
+        assert_eq!(statement.charges.minor, 20000);
+        assert_eq!(statement.paid.minor, 100000);
+        assert_eq!(statement.remaining().minor, 250000);
+        assert_eq!(statement.remaining_minimum().minor, 0);
+        assert_eq!(statement.balance.minor, 270000);
+        assert_eq!(statement.available_credit.unwrap().minor, 230000);
+        assert_eq!(statement.transactions.len(), 3);
+
+        // On the closing day the cycle is still open
+        let statement = db
+            .get_card_statement(&account.id, NaiveDate::from_ymd_opt(2024, 2, 29).unwrap())
+            .unwrap();
+        assert_eq!(
+            statement.closing_date,
+            NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()
+        );
+        assert_eq!(statement.statement_balance.minor, 10000);
+        assert_eq!(statement.minimum_payment, minimum_payment_floor());
+
+        db.set_card_terms(&account.id, None).unwrap();
+        assert!(db.get_account(&account.id).unwrap().card.is_none());
+
+        // A card in another currency gets the floor at the rate of the closing day
+        let euro_card = db
+            .create_account(&AccountRequest {
+                name: "Euro Visa".to_string(),
+                bank: "Chase".to_string(),
+                account_number: None,
+                account_type: AccountType::CreditCard,
+                opening_balance: Money::new(-10000, "EUR"),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        db.set_card_terms(
+            &euro_card.id,
+            Some(&CardTerms {
+                closing_day: 31,
+                due_day: 25,
+                credit_limit: None,
+                apr: None,
+            }),
+        )
+        .unwrap();
+        // Without a rate only the percentage is asked for
+        let statement = db.get_card_statement(&euro_card.id, on).unwrap();
+        assert_eq!(statement.minimum_payment, Money::new(100, "EUR"));
+
+        db.set_exchange_rates(&[ExchangeRate {
+            date: NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+            from_currency: "USD".to_string(),
+            to_currency: "EUR".to_string(),
+            rate: 0.9,
+        }])
+        .unwrap();
+        let statement = db.get_card_statement(&euro_card.id, on).unwrap();
+        assert_eq!(statement.minimum_payment, Money::new(2250, "EUR"));
+    }
+
+    #[test]
+    fn test_card_dates() {
+        let card = CardTerms {
+            closing_day: 15,
+            due_day: 10,
+            credit_limit: None,
+            apr: None,
+        };
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        assert_eq!(
+            last_closing_date(&card, date(2024, 1, 16)),
+            date(2024, 1, 15)
+        );
+        assert_eq!(
+            last_closing_date(&card, date(2024, 1, 15)),
+            date(2023, 12, 15)
+        );
+        assert_eq!(due_date(&card, date(2023, 12, 15)), date(2024, 1, 10));
+
+        let card = CardTerms {
+            due_day: 28,
+            ..card
+        };
+        assert_eq!(due_date(&card, date(2024, 1, 15)), date(2024, 1, 28));
+
+        // The floor does not ask for more than is owed
+        let floor = minimum_payment_floor();
+        let small = Money::new(1000, DEFAULT_CURRENCY);
+        assert_eq!(minimum_payment(&small, None, &floor).minor, 1000);
+        let credit = Money::new(-1000, DEFAULT_CURRENCY);
+        assert_eq!(minimum_payment(&credit, Some(20.0), &floor).minor, 0);
+    }
+}
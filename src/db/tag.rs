@@ -0,0 +1,525 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Func, Order, Query, SimpleExpr, SqliteQueryBuilder};
+use tabled::Tabled;
+
+use crate::{
+    db::{Db, DbError},
+    money::Money,
+    utils::random_hash,
+};
+
+use super::{
+    Accounts, Tags, TransactionTags, Transactions,
+    holder::{held_by, owned_amount},
+    transaction::not_void,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct Tag {
+    pub id: String,
+    pub name: String,
+    pub created_at: chrono::DateTime<Local>,
+}
+
+/// Money that came in and went out on transactions carrying one tag.
+#[derive(Debug, Clone)]
+pub struct TagTotal {
+    pub tag_id: String,
+    pub count: i64,
+    pub income: Money,
+    pub expenses: Money,
+}
+
+/// Raised when a tag is renamed to a name that is already taken.
+#[derive(Debug)]
+pub struct TagExistsError(pub String);
+
+impl std::fmt::Display for TagExistsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "A tag named '{}' already exists, merge the two instead",
+            self.0
+        )
+    }
+}
+
+impl std::error::Error for TagExistsError {}
+
+/// Normalizes user input such as ` #Vacation2026 ` to `vacation2026`.
+pub fn normalize_tag(name: &str) -> String {
+    name.trim().trim_start_matches('#').trim().to_lowercase()
+}
+
+/// Matches transactions that carry every one of `tags`.
+pub(super) fn tagged_with(tags: &[String]) -> SimpleExpr {
+    let names = tags
+        .iter()
+        .map(|tag| normalize_tag(tag))
+        .collect::<Vec<String>>();
+    let wanted = names.len() as i64;
+
+    Expr::col((Transactions::Table, Transactions::Id)).in_subquery(
+        Query::select()
+            .column((TransactionTags::Table, TransactionTags::TransactionId))
+            .from(TransactionTags::Table)
+            .inner_join(
+                Tags::Table,
+                Expr::col((Tags::Table, Tags::Id))
+                    .equals((TransactionTags::Table, TransactionTags::TagId)),
+            )
+            .and_where(Expr::col((Tags::Table, Tags::Name)).is_in(names))
+            .group_by_col((TransactionTags::Table, TransactionTags::TransactionId))
+            .and_having(
+                Expr::expr(Func::count_distinct(Expr::col((
+                    TransactionTags::Table,
+                    TransactionTags::TagId,
+                ))))
+                .eq(wanted),
+            )
+            .to_owned(),
+    )
+}
+
+fn tag_columns() -> [Tags; 3] {
+    [Tags::Id, Tags::Name, Tags::CreatedAt]
+}
+
+fn tag_from_row(row: &Row) -> Result<Tag, rusqlite::Error> {
+    let created_at = DateTime::from_str(row.get::<_, String>(2)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Tag {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at,
+    })
+}
+
+impl Db {
+    pub fn get_tag_by_name(&self, name: &str) -> Result<Option<Tag>, DbError> {
+        let query = Query::select()
+            .columns(tag_columns())
+            .from(Tags::Table)
+            .and_where(Expr::col(Tags::Name).eq(normalize_tag(name)))
+            .limit(1)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query_map((), tag_from_row)?;
+
+        Ok(rows.next().transpose()?)
+    }
+
+    pub fn get_or_create_tag(&self, name: &str) -> Result<Tag, DbError> {
+        if let Some(tag) = self.get_tag_by_name(name)? {
+            return Ok(tag);
+        }
+
+        let id = random_hash(8);
+        let name = normalize_tag(name);
+
+        let query = Query::insert()
+            .into_table(Tags::Table)
+            .columns(tag_columns())
+            .values_panic(vec![
+                id.clone().into(),
+                name.clone().into(),
+                Local::now().to_string().into(),
+            ])
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(Tag {
+            id,
+            name,
+            created_at: Local::now(),
+        })
+    }
+
+    pub fn get_all_tags(&self) -> Result<Vec<Tag>, DbError> {
+        let query = Query::select()
+            .columns(tag_columns())
+            .from(Tags::Table)
+            .order_by(Tags::Name, Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), tag_from_row)?
+            .map(|tag| tag.unwrap())
+            .collect::<Vec<Tag>>())
+    }
+
+    pub fn get_transaction_tags(&self, transaction_id: &str) -> Result<Vec<Tag>, DbError> {
+        let query = Query::select()
+            .columns(
+                tag_columns()
+                    .into_iter()
+                    .map(|column| (Tags::Table, column)),
+            )
+            .from(Tags::Table)
+            .inner_join(
+                TransactionTags::Table,
+                Expr::col((TransactionTags::Table, TransactionTags::TagId))
+                    .equals((Tags::Table, Tags::Id)),
+            )
+            .and_where(
+                Expr::col((TransactionTags::Table, TransactionTags::TransactionId))
+                    .eq(transaction_id),
+            )
+            .order_by((Tags::Table, Tags::Name), Order::Asc)
+            .to_string(SqliteQueryBuilder);
+
+        let mut stmt = self.conn.prepare(&query)?;
+
+        Ok(stmt
+            .query_map((), tag_from_row)?
+            .map(|tag| tag.unwrap())
+            .collect::<Vec<Tag>>())
+    }
+
+    /// Replaces the tags of a transaction, creating any that do not exist yet.
+    pub(super) fn write_transaction_tags(
+        &self,
+        transaction_id: &str,
+        tags: &[String],
+    ) -> Result<(), DbError> {
+        let delete = Query::delete()
+            .from_table(TransactionTags::Table)
+            .and_where(Expr::col(TransactionTags::TransactionId).eq(transaction_id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&delete, ())?;
+
+        for name in tags {
+            if normalize_tag(name).is_empty() {
+                continue;
+            }
+
+            let tag = self.get_or_create_tag(name)?;
+            let insert = Query::insert()
+                .into_table(TransactionTags::Table)
+                .columns([TransactionTags::TransactionId, TransactionTags::TagId])
+                .values_panic(vec![transaction_id.into(), tag.id.into()])
+                .on_conflict(
+                    sea_query::OnConflict::columns([
+                        TransactionTags::TransactionId,
+                        TransactionTags::TagId,
+                    ])
+                    .do_nothing()
+                    .to_owned(),
+                )
+                .to_string(SqliteQueryBuilder);
+            self.conn.execute(&insert, ())?;
+        }
+
+        Ok(())
+    }
+
+    /// Income and expenses per tag converted into `currency`, excluding transfers
+    /// and void transactions, optionally only on accounts `holder_id` holds,
+    /// weighted by their share of each account. A transaction with several tags
+    /// counts in full towards each of them.
+    pub fn get_tag_totals(
+        &self,
+        currency: &str,
+        holder_id: Option<&str>,
+    ) -> Result<Vec<TagTotal>, DbError> {
+        let mut query = Query::select();
+        query
+            .column((TransactionTags::Table, TransactionTags::TagId))
+            .column((Accounts::Table, Accounts::Currency))
+            .column((Transactions::Table, Transactions::PostedOn))
+            .column((Transactions::Table, Transactions::AccountId))
+            .expr(Func::count(Expr::col((
+                Transactions::Table,
+                Transactions::Id,
+            ))))
+            .expr(Expr::cust(
+                "COALESCE(SUM(CASE WHEN transactions.type = 'credit' THEN transactions.amount ELSE 0 END), 0)",
+            ))
+            .expr(Expr::cust(
+                "COALESCE(SUM(CASE WHEN transactions.type = 'credit' THEN 0 ELSE transactions.amount END), 0)",
+            ))
+            .from(TransactionTags::Table)
+            .inner_join(
+                Transactions::Table,
+                Expr::col((Transactions::Table, Transactions::Id))
+                    .equals((TransactionTags::Table, TransactionTags::TransactionId)),
+            )
+            .left_join(
+                Accounts::Table,
+                Expr::col((Accounts::Table, Accounts::Id))
+                    .equals((Transactions::Table, Transactions::AccountId)),
+            )
+            .and_where(Expr::col((Transactions::Table, Transactions::TransferId)).is_null())
+            .and_where(not_void())
+            .group_by_col((TransactionTags::Table, TransactionTags::TagId))
+            .group_by_col((Accounts::Table, Accounts::Currency))
+            .group_by_col((Transactions::Table, Transactions::PostedOn))
+            .group_by_col((Transactions::Table, Transactions::AccountId));
+
+        if let Some(holder_id) = holder_id {
+            query.and_where(held_by(holder_id));
+        }
+
+        let mut stmt = self.conn.prepare(&query.to_string(SqliteQueryBuilder))?;
+        let rows = stmt
+            .query_map((), |row| {
+                let native = row.get::<_, String>(1)?;
+                let posted_on = NaiveDate::from_str(row.get::<_, String>(2)?.as_str())
+                    .unwrap_or(Local::now().date_naive());
+
+                Ok((
+                    row.get::<_, String>(0)?,
+                    posted_on,
+                    row.get::<_, String>(3)?,
+                    row.get::<_, i64>(4)?,
+                    Money::new(row.get(5)?, &native),
+                    Money::new(row.get(6)?, &native),
+                ))
+            })?
+            .collect::<Result<Vec<(String, NaiveDate, String, i64, Money, Money)>, rusqlite::Error>>(
+            )?;
+
+        let ownership = holder_id
+            .map(|user_id| self.get_ownership_by_user(user_id))
+            .transpose()?;
+
+        let mut totals: Vec<TagTotal> = vec![];
+        for (tag_id, posted_on, account_id, count, income, expenses) in rows {
+            let mut income = self.convert(&income, currency, posted_on)?;
+            let mut expenses = self.convert(&expenses, currency, posted_on)?;
+            income.minor = owned_amount(ownership.as_ref(), &account_id, income.minor);
+            expenses.minor = owned_amount(ownership.as_ref(), &account_id, expenses.minor);
+
+            match totals.iter_mut().find(|total| total.tag_id == tag_id) {
+                Some(total) => {
+                    total.count += count;
+                    total.income.minor += income.minor;
+                    total.expenses.minor += expenses.minor;
+                }
+                None => totals.push(TagTotal {
+                    tag_id,
+                    count,
+                    income,
+                    expenses,
+                }),
+            }
+        }
+
+        Ok(totals)
+    }
+
+    pub fn rename_tag(&self, id: &str, name: &str) -> Result<(), DbError> {
+        if let Some(existing) = self.get_tag_by_name(name)?
+            && existing.id != id
+        {
+            return Err(DbError::invalid(TagExistsError(existing.name)));
+        }
+
+        let query = Query::update()
+            .table(Tags::Table)
+            .value(Tags::Name, normalize_tag(name))
+            .and_where(Expr::col(Tags::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+
+    /// Moves every transaction tagged `from_id` over to `into_id` and deletes `from_id`.
+    pub fn merge_tags(&self, from_id: &str, into_id: &str) -> Result<(), DbError> {
+        if from_id == into_id {
+            return Ok(());
+        }
+
+        let tx = self.conn.unchecked_transaction()?;
+
+        // Transactions that already carry both tags keep a single link
+        let relink = Query::insert()
+            .into_table(TransactionTags::Table)
+            .columns([TransactionTags::TransactionId, TransactionTags::TagId])
+            .select_from(
+                Query::select()
+                    .column(TransactionTags::TransactionId)
+                    .expr(Expr::val(into_id))
+                    .from(TransactionTags::Table)
+                    .and_where(Expr::col(TransactionTags::TagId).eq(from_id))
+                    .to_owned(),
+            )
+            .unwrap()
+            .on_conflict(
+                sea_query::OnConflict::columns([
+                    TransactionTags::TransactionId,
+                    TransactionTags::TagId,
+                ])
+                .do_nothing()
+                .to_owned(),
+            )
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&relink, ())?;
+
+        self.remove_tag(from_id)?;
+
+        Ok(tx.commit()?)
+    }
+
+    /// Deletes a tag and takes it off every transaction. The transactions themselves stay.
+    pub fn delete_tag(&self, id: &str) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.remove_tag(id)?;
+        Ok(tx.commit()?)
+    }
+
+    fn remove_tag(&self, id: &str) -> Result<(), DbError> {
+        let unlink = Query::delete()
+            .from_table(TransactionTags::Table)
+            .and_where(Expr::col(TransactionTags::TagId).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&unlink, ())?;
+
+        let query = Query::delete()
+            .from_table(Tags::Table)
+            .and_where(Expr::col(Tags::Id).eq(id))
+            .to_string(SqliteQueryBuilder);
+        self.conn.execute(&query, ())?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::AccountHolder,
+            transaction::{
+                TransactionEdit, TransactionFilter, TransactionRequest, TransactionStatus,
+            },
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
+        utils::get_test_db_path,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_tags() {
+        let _ = std::fs::remove_file(get_test_db_path("tag"));
+
+        let db = Db::new(get_test_db_path("tag").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Checking".to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(0, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Travel".to_string(),
+                icon: "✈️".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let spend = |minor: i64, tags: &[&str]| {
+            db.create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(minor, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: String::new(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2026, 7, 1).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+                attachments: vec![],
+            })
+            .unwrap()
+        };
+        let flight = spend(40000, &["#Vacation2026", "reimbursable"]);
+        let hotel = spend(25000, &["vacation2026"]);
+        spend(1000, &[]);
+
+        // Names are normalized, so both spellings end up on the same tag
+        assert_eq!(db.get_all_tags().unwrap().len(), 2);
+        assert_eq!(db.get_transaction_tags(&flight.id).unwrap().len(), 2);
+
+        let tagged = |tags: &[&str]| {
+            let tags = tags.iter().map(|tag| tag.to_string()).collect::<Vec<_>>();
+            db.get_filtered_transactions(&TransactionFilter {
+                tags: tags.clone(),
+                ..Default::default()
+            })
+            .unwrap()
+            .len()
+        };
+        assert_eq!(tagged(&["vacation2026"]), 2);
+        assert_eq!(tagged(&["vacation2026", "reimbursable"]), 1);
+
+        let (_, expenses) = db
+            .get_income_and_expenses(
+                &TransactionFilter {
+                    tags: vec!["vacation2026".to_string()],
+                    ..Default::default()
+                },
+                DEFAULT_CURRENCY,
+            )
+            .unwrap();
+        assert_eq!(expenses.minor, 65000);
+
+        let vacation = db.get_tag_by_name("vacation2026").unwrap().unwrap();
+        let reimbursable = db.get_tag_by_name("reimbursable").unwrap().unwrap();
+        let totals = db.get_tag_totals(DEFAULT_CURRENCY, None).unwrap();
+        let total = totals
+            .iter()
+            .find(|total| total.tag_id == vacation.id)
+            .unwrap();
+        assert_eq!((total.count, total.expenses.minor), (2, 65000));
+
+        // Renaming onto an existing name is refused, merging is the way to combine tags
+        assert!(db.rename_tag(&reimbursable.id, "Vacation2026").is_err());
+        db.rename_tag(&reimbursable.id, "work").unwrap();
+        db.merge_tags(&vacation.id, &reimbursable.id).unwrap();
+        assert_eq!(db.get_all_tags().unwrap().len(), 1);
+        assert_eq!(tagged(&["work"]), 2);
+
+        db.update_transaction(
+            &hotel.id,
+            &TransactionEdit {
+                tags: Some(vec![]),
+                ..db.get_transaction(&hotel.id).unwrap().into()
+            },
+        )
+        .unwrap();
+        assert_eq!(tagged(&["work"]), 1);
+
+        db.delete_tag(&reimbursable.id).unwrap();
+        assert!(db.get_all_tags().unwrap().is_empty());
+        assert!(db.get_transaction_tags(&flight.id).unwrap().is_empty());
+        assert_eq!(db.get_all_transactions().unwrap().len(), 3);
+    }
+}
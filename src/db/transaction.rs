@@ -1,154 +1,507 @@
-use std::str::FromStr;
+use std::{fmt, str::FromStr};
 
-use chrono::{DateTime, Local};
-use sea_query::{Expr, Query, SqliteQueryBuilder};
+use chrono::{DateTime, Local, NaiveDate};
+use rusqlite::Row;
+use sea_query::{Expr, Order, Query, SelectStatement, SimpleExpr, SqliteQueryBuilder};
 use tabled::Tabled;
 
-use crate::{db::Db, utils::random_hash};
+use crate::{
+    db::{
+        Db, DbError,
+        attachment::AttachmentRequest,
+        holder::{held_by, owned_amount},
+        split::{SplitRequest, validate_splits},
+        tag::tagged_with,
+    },
+    money::Money,
+    utils::random_hash,
+};
 
-use super::Transactions;
+use super::{Accounts, TransactionSplits, Transactions};
+
+/// Where a transaction is in its life at the bank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransactionStatus {
+    /// Entered, but not seen on a statement yet
+    Pending,
+    /// Seen on a statement
+    Cleared,
+    /// Cleared and locked in by a reconciliation
+    Reconciled,
+    /// Cancelled, kept for the record but has no effect on any balance or total
+    Void,
+}
+
+impl TransactionStatus {
+    pub const ALL: [TransactionStatus; 4] = [
+        TransactionStatus::Pending,
+        TransactionStatus::Cleared,
+        TransactionStatus::Reconciled,
+        TransactionStatus::Void,
+    ];
+
+    /// The statuses a transaction can be given by hand. Only a reconciliation
+    /// marks transactions reconciled.
+    pub const SETTABLE: [TransactionStatus; 3] = [
+        TransactionStatus::Pending,
+        TransactionStatus::Cleared,
+        TransactionStatus::Void,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionStatus::Pending => "pending",
+            TransactionStatus::Cleared => "cleared",
+            TransactionStatus::Reconciled => "reconciled",
+            TransactionStatus::Void => "void",
+        }
+    }
+}
+
+impl fmt::Display for TransactionStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl FromStr for TransactionStatus {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        TransactionStatus::ALL
+            .into_iter()
+            .find(|status| status.as_str() == s.trim().to_lowercase())
+            .ok_or_else(|| format!("'{}' is not a transaction status", s))
+    }
+}
 
 #[derive(Debug, Clone, Tabled)]
 pub struct Transaction {
     pub id: String,
     pub account_id: String,
-    pub amount: i64,
+    pub amount: Money,
     pub transaction_type: String,
     pub description: String,
     pub category_id: String,
+    pub posted_on: NaiveDate,
+    pub status: TransactionStatus,
+    /// Shared by both sides of a transfer between two accounts
+    #[tabled(skip)]
+    pub transfer_id: Option<String>,
+    /// Who the money was paid to or received from
+    #[tabled(skip)]
+    pub payee_id: Option<String>,
     pub created_at: chrono::DateTime<Local>,
     pub updated_at: chrono::DateTime<Local>,
 }
 
 pub struct TransactionRequest {
     pub account_id: String,
-    pub amount: i64,
+    pub amount: Money,
     pub transaction_type: String,
     pub description: String,
     pub category_id: String,
+    pub posted_on: NaiveDate,
+    pub status: TransactionStatus,
+    pub payee_id: Option<String>,
+    /// Optional breakdown of the amount across categories, which must add up to it
+    pub splits: Vec<SplitRequest>,
+    /// Free-form labels, created on first use
+    pub tags: Vec<String>,
+    /// Receipts and other documents stored with the transaction
+    pub attachments: Vec<AttachmentRequest>,
+}
+
+pub struct TransferRequest {
+    pub from_account_id: String,
+    pub to_account_id: String,
+    pub amount: Money,
+    pub description: String,
+    pub posted_on: NaiveDate,
+}
+
+/// Everything an update changes about a transaction, written all at once.
+pub struct TransactionEdit {
+    pub transaction: Transaction,
+    /// New split lines replacing the current ones, `None` keeps them
+    pub splits: Option<Vec<SplitRequest>>,
+    /// New tags replacing the current ones, `None` keeps them
+    pub tags: Option<Vec<String>>,
+    /// Added next to the current attachments
+    pub attachments: Vec<AttachmentRequest>,
+}
+
+impl From<Transaction> for TransactionEdit {
+    /// An edit of the transaction's own fields only.
+    fn from(transaction: Transaction) -> Self {
+        TransactionEdit {
+            transaction,
+            splits: None,
+            tags: None,
+            attachments: vec![],
+        }
+    }
+}
+
+impl Transaction {
+    /// The effect this transaction has on its account's balance, in minor units.
+    pub fn signed_amount(&self) -> i64 {
+        signed_amount(&self.transaction_type, self.status, &self.amount)
+    }
+}
+
+/// Narrows down which transactions are listed and totalled.
+#[derive(Debug, Clone, Default)]
+pub struct TransactionFilter {
+    pub account_id: Option<String>,
+    /// Only transactions filed under this category, as a whole or through one
+    /// of their split lines
+    pub category_id: Option<String>,
+    /// Transactions must carry every one of these
+    pub tags: Vec<String>,
+    pub status: Option<TransactionStatus>,
+    /// Only transactions on accounts this user holds
+    pub holder_id: Option<String>,
+}
+
+impl TransactionFilter {
+    fn conditions(&self) -> Vec<SimpleExpr> {
+        let mut conditions = vec![];
+
+        if let Some(account_id) = &self.account_id {
+            conditions.push(
+                Expr::col((Transactions::Table, Transactions::AccountId)).eq(account_id.as_str()),
+            );
+        }
+        if let Some(category_id) = &self.category_id {
+            conditions.push(
+                Expr::col((Transactions::Table, Transactions::CategoryId))
+                    .eq(category_id.as_str())
+                    .or(
+                        Expr::col((Transactions::Table, Transactions::Id)).in_subquery(
+                            Query::select()
+                                .column(TransactionSplits::TransactionId)
+                                .from(TransactionSplits::Table)
+                                .and_where(
+                                    Expr::col(TransactionSplits::CategoryId)
+                                        .eq(category_id.as_str()),
+                                )
+                                .take(),
+                        ),
+                    ),
+            );
+        }
+        if !self.tags.is_empty() {
+            conditions.push(tagged_with(&self.tags));
+        }
+        if let Some(status) = self.status {
+            conditions
+                .push(Expr::col((Transactions::Table, Transactions::Status)).eq(status.as_str()));
+        }
+        if let Some(holder_id) = &self.holder_id {
+            conditions.push(held_by(holder_id));
+        }
+
+        conditions
+    }
+}
+
+/// Raised when an edit or a delete would change a transaction a reconciliation
+/// has already accounted for.
+#[derive(Debug)]
+pub struct ReconciledTransactionError {
+    pub transaction_id: String,
+}
+
+impl fmt::Display for ReconciledTransactionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Transaction {} is reconciled, set it back to cleared before changing its amount, type, account or date or deleting it",
+            self.transaction_id
+        )
+    }
+}
+
+impl std::error::Error for ReconciledTransactionError {}
+
+/// Raised when a transaction would be moved to an account in another currency
+/// without an amount in that currency.
+#[derive(Debug)]
+pub struct CurrencyMismatchError {
+    pub account_id: String,
+    pub account_currency: String,
+    pub amount_currency: String,
+}
+
+impl fmt::Display for CurrencyMismatchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Account {} is in {}, the amount has to be given in {} rather than {}",
+            self.account_id, self.account_currency, self.account_currency, self.amount_currency
+        )
+    }
+}
+
+impl std::error::Error for CurrencyMismatchError {}
+
+/// Leaves out voided transactions, which count towards nothing.
+pub(super) fn not_void() -> SimpleExpr {
+    Expr::col((Transactions::Table, Transactions::Status)).ne(TransactionStatus::Void.as_str())
+}
+
+/// The effect a transaction has on its account's balance, in minor units.
+fn signed_amount(transaction_type: &str, status: TransactionStatus, amount: &Money) -> i64 {
+    if status == TransactionStatus::Void {
+        0
+    } else if transaction_type == "credit" {
+        amount.minor
+    } else {
+        -amount.minor
+    }
+}
+
+fn opposite_type(transaction_type: &str) -> &'static str {
+    if transaction_type == "credit" {
+        "debit"
+    } else {
+        "credit"
+    }
+}
+
+fn transaction_columns() -> [Transactions; 12] {
+    [
+        Transactions::Id,
+        Transactions::AccountId,
+        Transactions::Amount,
+        Transactions::Type,
+        Transactions::Description,
+        Transactions::CategoryId,
+        Transactions::PostedOn,
+        Transactions::TransferId,
+        Transactions::CreatedAt,
+        Transactions::UpdatedAt,
+        Transactions::PayeeId,
+        Transactions::Status,
+    ]
+}
+
+/// Selects every transaction column along with the currency of its account.
+fn select_transactions() -> SelectStatement {
+    Query::select()
+        .columns(
+            transaction_columns()
+                .into_iter()
+                .map(|column| (Transactions::Table, column)),
+        )
+        .column((Accounts::Table, Accounts::Currency))
+        .from(Transactions::Table)
+        .left_join(
+            Accounts::Table,
+            Expr::col((Accounts::Table, Accounts::Id))
+                .equals((Transactions::Table, Transactions::AccountId)),
+        )
+        .to_owned()
+}
+
+fn transaction_from_row(row: &Row) -> Result<Transaction, rusqlite::Error> {
+    let posted_on =
+        NaiveDate::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now().date_naive());
+    let created_at = DateTime::from_str(row.get::<_, String>(8)?.as_str()).unwrap_or(Local::now());
+    let updated_at = DateTime::from_str(row.get::<_, String>(9)?.as_str()).unwrap_or(Local::now());
+
+    Ok(Transaction {
+        id: row.get(0)?,
+        account_id: row.get(1)?,
+        amount: Money::new(row.get(2)?, &row.get::<_, String>(12)?),
+        transaction_type: row.get(3)?,
+        description: row.get(4)?,
+        category_id: row.get(5)?,
+        posted_on,
+        status: TransactionStatus::from_str(&row.get::<_, String>(11)?)
+            .unwrap_or(TransactionStatus::Cleared),
+        transfer_id: row.get(7)?,
+        payee_id: row.get(10)?,
+        created_at,
+        updated_at,
+    })
 }
 
 impl Db {
     pub fn create_transaction(
         &self,
         transaction: &TransactionRequest,
-    ) -> Result<Transaction, rusqlite::Error> {
+    ) -> Result<Transaction, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let created = self.insert_transaction(transaction, None)?;
+        tx.commit()?;
+
+        Ok(created)
+    }
+
+    /// Records a transfer as a debit on the source account and a credit on the
+    /// destination account, linked by a shared transfer id.
+    pub fn create_transfer(
+        &self,
+        transfer: &TransferRequest,
+    ) -> Result<(Transaction, Transaction), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let created = self.insert_transfer(transfer)?;
+        tx.commit()?;
+
+        Ok(created)
+    }
+
+    pub(super) fn insert_transfer(
+        &self,
+        transfer: &TransferRequest,
+    ) -> Result<(Transaction, Transaction), DbError> {
+        let transfer_id = random_hash(8);
+
+        let category = self.get_transfer_category()?;
+        let destination = self.get_account(&transfer.to_account_id)?;
+        let received = self.convert(
+            &transfer.amount,
+            &destination.balance.currency,
+            transfer.posted_on,
+        )?;
+        let outgoing = self.insert_transaction(
+            &TransactionRequest {
+                account_id: transfer.from_account_id.clone(),
+                amount: transfer.amount.clone(),
+                transaction_type: "debit".to_string(),
+                description: transfer.description.clone(),
+                category_id: category.id.clone(),
+                posted_on: transfer.posted_on,
+                status: TransactionStatus::Pending,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            },
+            Some(&transfer_id),
+        )?;
+        let incoming = self.insert_transaction(
+            &TransactionRequest {
+                account_id: transfer.to_account_id.clone(),
+                amount: received,
+                transaction_type: "credit".to_string(),
+                description: transfer.description.clone(),
+                category_id: category.id,
+                posted_on: transfer.posted_on,
+                status: TransactionStatus::Pending,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            },
+            Some(&transfer_id),
+        )?;
+
+        Ok((outgoing, incoming))
+    }
+
+    pub(super) fn insert_transaction(
+        &self,
+        transaction: &TransactionRequest,
+        transfer_id: Option<&str>,
+    ) -> Result<Transaction, DbError> {
+        validate_splits(&transaction.amount, &transaction.splits)?;
+
         let id = random_hash(8);
         let time_now = Local::now().to_string();
 
         let query = Query::insert()
             .into_table(Transactions::Table)
-            .columns([
-                Transactions::Id,
-                Transactions::AccountId,
-                Transactions::Amount,
-                Transactions::Type,
-                Transactions::Description,
-                Transactions::CategoryId,
-                Transactions::CreatedAt,
-                Transactions::UpdatedAt,
-            ])
+            .columns(transaction_columns())
             .values_panic(vec![
                 id.clone().into(),
                 transaction.account_id.clone().into(),
-                transaction.amount.into(),
+                transaction.amount.minor.into(),
                 transaction.transaction_type.clone().into(),
                 transaction.description.clone().into(),
                 transaction.category_id.clone().into(),
+                transaction.posted_on.to_string().into(),
+                transfer_id.map(|id| id.to_string()).into(),
                 time_now.clone().into(),
                 time_now.into(),
+                transaction.payee_id.clone().into(),
+                transaction.status.as_str().into(),
             ])
             .to_string(SqliteQueryBuilder);
 
         self.conn.execute(&query, ())?;
+        self.insert_transaction_splits(&id, &transaction.splits)?;
+        self.write_transaction_tags(&id, &transaction.tags)?;
+        self.insert_attachments(&id, &transaction.attachments)?;
+        self.adjust_account_balance(
+            &transaction.account_id,
+            signed_amount(
+                &transaction.transaction_type,
+                transaction.status,
+                &transaction.amount,
+            ),
+        )?;
 
         Ok(Transaction {
             id,
             account_id: transaction.account_id.clone(),
-            amount: transaction.amount,
+            amount: transaction.amount.clone(),
             transaction_type: transaction.transaction_type.clone(),
             description: transaction.description.clone(),
             category_id: transaction.category_id.clone(),
+            posted_on: transaction.posted_on,
+            status: transaction.status,
+            transfer_id: transfer_id.map(|id| id.to_string()),
+            payee_id: transaction.payee_id.clone(),
             created_at: Local::now(),
             updated_at: Local::now(),
         })
     }
 
-    pub fn get_transaction(&self, id: &str) -> Result<Transaction, rusqlite::Error> {
-        let query = Query::select()
-            .columns([
-                Transactions::Id,
-                Transactions::AccountId,
-                Transactions::Amount,
-                Transactions::Type,
-                Transactions::Description,
-                Transactions::CategoryId,
-                Transactions::CreatedAt,
-                Transactions::UpdatedAt,
-            ])
-            .from(Transactions::Table)
-            .and_where(Expr::col(Transactions::Id).eq(id))
+    pub fn get_transaction(&self, id: &str) -> Result<Transaction, DbError> {
+        let query = select_transactions()
+            .and_where(Expr::col((Transactions::Table, Transactions::Id)).eq(id))
             .limit(1)
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
 
-        let transaction = stmt.query_row((), |row| {
-            let created_at =
-                DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
-            let updated_at =
-                DateTime::from_str(row.get::<_, String>(7)?.as_str()).unwrap_or(Local::now());
-
-            Ok(Transaction {
-                id: row.get(0)?,
-                account_id: row.get(1)?,
-                amount: row.get(2)?,
-                transaction_type: row.get(3)?,
-                description: row.get(4)?,
-                category_id: row.get(5)?,
-                created_at,
-                updated_at,
-            })
-        })?;
-
-        Ok(transaction)
-    }
-
-    pub fn get_all_transactions(&self) -> Result<Vec<Transaction>, rusqlite::Error> {
-        let query = Query::select()
-            .columns([
-                Transactions::Id,
-                Transactions::AccountId,
-                Transactions::Amount,
-                Transactions::Type,
-                Transactions::Description,
-                Transactions::CategoryId,
-                Transactions::CreatedAt,
-                Transactions::UpdatedAt,
-            ])
-            .from(Transactions::Table)
+        Ok(stmt.query_row((), transaction_from_row)?)
+    }
+
+    pub fn get_all_transactions(&self) -> Result<Vec<Transaction>, DbError> {
+        let query = select_transactions()
+            .order_by((Transactions::Table, Transactions::PostedOn), Order::Asc)
+            .order_by((Transactions::Table, Transactions::CreatedAt), Order::Asc)
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
 
         Ok(stmt
-            .query_map((), |row| -> Result<Transaction, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
-                let updated_at =
-                    DateTime::from_str(row.get::<_, String>(7)?.as_str()).unwrap_or(Local::now());
-
-                Ok(Transaction {
-                    id: row.get(0)?,
-                    account_id: row.get(1)?,
-                    amount: row.get(2)?,
-                    transaction_type: row.get(3)?,
-                    description: row.get(4)?,
-                    category_id: row.get(5)?,
-                    created_at,
-                    updated_at,
-                })
-            })?
+            .query_map((), transaction_from_row)?
+            .map(|transaction| transaction.unwrap())
+            .collect::<Vec<Transaction>>())
+    }
+
+    /// Transactions matching every condition of `filter`.
+    pub fn get_filtered_transactions(
+        &self,
+        filter: &TransactionFilter,
+    ) -> Result<Vec<Transaction>, DbError> {
+        let mut query = select_transactions();
+        for condition in filter.conditions() {
+            query.and_where(condition);
+        }
+        query
+            .order_by((Transactions::Table, Transactions::PostedOn), Order::Asc)
+            .order_by((Transactions::Table, Transactions::CreatedAt), Order::Asc);
+
+        let mut stmt = self.conn.prepare(&query.to_string(SqliteQueryBuilder))?;
+
+        Ok(stmt
+            .query_map((), transaction_from_row)?
             .map(|transaction| transaction.unwrap())
             .collect::<Vec<Transaction>>())
     }
@@ -156,62 +509,170 @@ impl Db {
     pub fn get_transactions_by_account(
         &self,
         account_id: &str,
-    ) -> Result<Vec<Transaction>, rusqlite::Error> {
-        let query = Query::select()
-            .columns([
-                Transactions::Id,
-                Transactions::AccountId,
-                Transactions::Amount,
-                Transactions::Type,
-                Transactions::Description,
-                Transactions::CategoryId,
-                Transactions::CreatedAt,
-                Transactions::UpdatedAt,
-            ])
-            .from(Transactions::Table)
-            .and_where(Expr::col(Transactions::AccountId).eq(account_id))
+    ) -> Result<Vec<Transaction>, DbError> {
+        self.get_filtered_transactions(&TransactionFilter {
+            account_id: Some(account_id.to_string()),
+            ..Default::default()
+        })
+    }
+
+    /// Moves transactions to `status`. Reconciled transactions are locked and
+    /// only change when voided, and void transactions are left void, since
+    /// bringing one back takes an explicit update. Everything else is left as
+    /// it is.
+    pub fn set_transaction_status(
+        &self,
+        ids: &[String],
+        status: TransactionStatus,
+    ) -> Result<Vec<Transaction>, DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let changed = self.write_transaction_status(ids, status)?;
+        tx.commit()?;
+
+        Ok(changed)
+    }
+
+    pub(super) fn write_transaction_status(
+        &self,
+        ids: &[String],
+        status: TransactionStatus,
+    ) -> Result<Vec<Transaction>, DbError> {
+        let mut changed = vec![];
+        for id in ids {
+            let previous = self.get_transaction(id)?;
+            if previous.status == status
+                || previous.status == TransactionStatus::Void
+                || (previous.status == TransactionStatus::Reconciled
+                    && status != TransactionStatus::Void)
+            {
+                continue;
+            }
+
+            let mut transaction = previous.clone();
+            transaction.status = status;
+            self.write_transaction(&previous, &transaction)?;
+            changed.push(transaction);
+        }
+
+        Ok(changed)
+    }
+
+    /// The other side of a transfer, if this transaction is part of one.
+    pub fn get_transfer_counterpart(
+        &self,
+        transaction: &Transaction,
+    ) -> Result<Option<Transaction>, DbError> {
+        let Some(transfer_id) = &transaction.transfer_id else {
+            return Ok(None);
+        };
+
+        let query = select_transactions()
+            .and_where(
+                Expr::col((Transactions::Table, Transactions::TransferId)).eq(transfer_id.as_str()),
+            )
+            .and_where(
+                Expr::col((Transactions::Table, Transactions::Id)).ne(transaction.id.as_str()),
+            )
+            .limit(1)
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
+        let mut rows = stmt.query_map((), transaction_from_row)?;
 
-        Ok(stmt
-            .query_map((), |row| -> Result<Transaction, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(6)?.as_str()).unwrap_or(Local::now());
-                let updated_at =
-                    DateTime::from_str(row.get::<_, String>(7)?.as_str()).unwrap_or(Local::now());
-
-                Ok(Transaction {
-                    id: row.get(0)?,
-                    account_id: row.get(1)?,
-                    amount: row.get(2)?,
-                    transaction_type: row.get(3)?,
-                    description: row.get(4)?,
-                    category_id: row.get(5)?,
-                    created_at,
-                    updated_at,
-                })
-            })?
-            .map(|transaction| transaction.unwrap())
-            .collect::<Vec<Transaction>>())
+        Ok(rows.next().transpose()?)
+    }
+
+    /// Updates a transaction together with its splits, tags and attachments, so
+    /// either all of it is saved or none of it. When it is one side of a
+    /// transfer, the other side follows with the same amount, date and
+    /// description and the opposite type.
+    pub fn update_transaction(&self, id: &str, edit: &TransactionEdit) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        let transaction = &edit.transaction;
+        let previous = self.get_transaction(id)?;
+
+        // New splits go in first, the amount is checked against them below
+        if let Some(splits) = &edit.splits {
+            self.delete_transaction_splits(id)?;
+            self.insert_transaction_splits(id, splits)?;
+        }
+        self.write_transaction(&previous, transaction)?;
+
+        if let Some(counterpart_previous) = self.get_transfer_counterpart(&previous)? {
+            let mut counterpart = counterpart_previous.clone();
+            counterpart.amount = self.convert(
+                &transaction.amount,
+                &counterpart_previous.amount.currency,
+                transaction.posted_on,
+            )?;
+            counterpart.transaction_type = opposite_type(&transaction.transaction_type).to_string();
+            counterpart.description = transaction.description.clone();
+            counterpart.posted_on = transaction.posted_on;
+            // Each bank clears its own side, but a cancelled transfer is cancelled on both
+            if transaction.status == TransactionStatus::Void
+                || previous.status == TransactionStatus::Void
+            {
+                counterpart.status = transaction.status;
+            }
+            self.write_transaction(&counterpart_previous, &counterpart)?;
+        }
+
+        if let Some(tags) = &edit.tags {
+            self.write_transaction_tags(id, tags)?;
+        }
+        self.insert_attachments(id, &edit.attachments)?;
+
+        Ok(tx.commit()?)
     }
 
-    pub fn update_transaction(
+    fn write_transaction(
         &self,
-        id: &str,
+        previous: &Transaction,
         transaction: &Transaction,
-    ) -> Result<(), rusqlite::Error> {
+    ) -> Result<(), DbError> {
+        // The reconciled balance is built from these, so they stay as they are
+        // until the transaction is taken out of the reconciliation
+        if previous.status == TransactionStatus::Reconciled
+            && (transaction.amount != previous.amount
+                || transaction.transaction_type != previous.transaction_type
+                || transaction.account_id != previous.account_id
+                || transaction.posted_on != previous.posted_on)
+        {
+            return Err(DbError::invalid(ReconciledTransactionError {
+                transaction_id: previous.id.clone(),
+            }));
+        }
+
+        // The minor units would mean something else in another currency
+        if transaction.account_id != previous.account_id {
+            let account = self.get_account(&transaction.account_id)?;
+            if account.balance.currency != transaction.amount.currency {
+                return Err(DbError::invalid(CurrencyMismatchError {
+                    account_id: account.id,
+                    account_currency: account.balance.currency,
+                    amount_currency: transaction.amount.currency.clone(),
+                }));
+            }
+        }
+
+        let splits = self
+            .get_transaction_splits(&previous.id)?
+            .into_iter()
+            .map(SplitRequest::from)
+            .collect::<Vec<SplitRequest>>();
+        validate_splits(&transaction.amount, &splits)?;
+
         let updated_at = Local::now().to_string();
 
         let query = Query::update()
             .table(Transactions::Table)
-            .and_where(Expr::col(Transactions::Id).eq(id))
+            .and_where(Expr::col(Transactions::Id).eq(previous.id.as_str()))
             .values(vec![
                 (
                     Transactions::AccountId,
                     transaction.account_id.clone().into(),
                 ),
-                (Transactions::Amount, transaction.amount.into()),
+                (Transactions::Amount, transaction.amount.minor.into()),
                 (
                     Transactions::Type,
                     transaction.transaction_type.clone().into(),
@@ -224,31 +685,213 @@ impl Db {
                     Transactions::CategoryId,
                     transaction.category_id.clone().into(),
                 ),
+                (
+                    Transactions::PostedOn,
+                    transaction.posted_on.to_string().into(),
+                ),
+                (Transactions::PayeeId, transaction.payee_id.clone().into()),
+                (Transactions::Status, transaction.status.as_str().into()),
                 (Transactions::UpdatedAt, updated_at.into()),
             ])
             .to_string(SqliteQueryBuilder);
 
         self.conn.execute(&query, ())?;
+        self.adjust_account_balance(
+            &previous.account_id,
+            -signed_amount(
+                &previous.transaction_type,
+                previous.status,
+                &previous.amount,
+            ),
+        )?;
+        self.adjust_account_balance(
+            &transaction.account_id,
+            signed_amount(
+                &transaction.transaction_type,
+                transaction.status,
+                &transaction.amount,
+            ),
+        )
+    }
 
-        Ok(())
+    /// Deletes a transaction, along with the other side if it is part of a transfer.
+    pub fn delete_transaction(&self, id: &str) -> Result<(), DbError> {
+        let tx = self.conn.unchecked_transaction()?;
+        self.remove_transaction(id)?;
+        Ok(tx.commit()?)
     }
 
-    pub fn delete_transaction(&self, id: &str) -> Result<(), rusqlite::Error> {
-        let query = Query::delete()
-            .from_table(Transactions::Table)
-            .and_where(Expr::col(Transactions::Id).eq(id))
-            .to_string(SqliteQueryBuilder);
+    /// Deletes a transaction and the other side of a transfer, reversing their
+    /// effect on the balance. Reconciled transactions, on either side, are
+    /// refused. Callers are expected to run this inside their own SQL transaction.
+    pub(super) fn remove_transaction(&self, id: &str) -> Result<(), DbError> {
+        let previous = self.get_transaction(id)?;
+        let counterpart = self.get_transfer_counterpart(&previous)?;
+        let transactions = std::iter::once(previous)
+            .chain(counterpart)
+            .collect::<Vec<Transaction>>();
 
-        self.conn.execute(&query, ())?;
+        // The reconciled balance is built from them, like in `write_transaction`
+        if let Some(reconciled) = transactions
+            .iter()
+            .find(|transaction| transaction.status == TransactionStatus::Reconciled)
+        {
+            return Err(DbError::invalid(ReconciledTransactionError {
+                transaction_id: reconciled.id.clone(),
+            }));
+        }
+
+        for transaction in transactions {
+            let query = Query::delete()
+                .from_table(Transactions::Table)
+                .and_where(Expr::col(Transactions::Id).eq(transaction.id.as_str()))
+                .to_string(SqliteQueryBuilder);
+
+            self.conn.execute(&query, ())?;
+            self.delete_transaction_splits(&transaction.id)?;
+            self.write_transaction_tags(&transaction.id, &[])?;
+            self.delete_transaction_attachments(&transaction.id)?;
+            self.adjust_account_balance(
+                &transaction.account_id,
+                -signed_amount(
+                    &transaction.transaction_type,
+                    transaction.status,
+                    &transaction.amount,
+                ),
+            )?;
+        }
 
         Ok(())
     }
+
+    /// Income and expenses of the transactions matching `filter`, converted into
+    /// `currency` at the rate of the day each transaction was posted and, with a
+    /// holder in the filter, weighted by their share of each account. Transfers
+    /// only move money around and void transactions never happened, so they
+    /// count as neither.
+    pub fn get_income_and_expenses(
+        &self,
+        filter: &TransactionFilter,
+        currency: &str,
+    ) -> Result<(Money, Money), DbError> {
+        let mut query = Query::select();
+        query
+            .column((Accounts::Table, Accounts::Currency))
+            .column((Transactions::Table, Transactions::PostedOn))
+            .column((Transactions::Table, Transactions::AccountId))
+            .expr(Expr::cust(
+                "COALESCE(SUM(CASE WHEN transactions.type = 'credit' THEN transactions.amount ELSE 0 END), 0)",
+            ))
+            .expr(Expr::cust(
+                "COALESCE(SUM(CASE WHEN transactions.type = 'credit' THEN 0 ELSE transactions.amount END), 0)",
+            ))
+            .from(Transactions::Table)
+            .left_join(
+                Accounts::Table,
+                Expr::col((Accounts::Table, Accounts::Id))
+                    .equals((Transactions::Table, Transactions::AccountId)),
+            )
+            .and_where(Expr::col((Transactions::Table, Transactions::TransferId)).is_null())
+            .and_where(not_void())
+            .group_by_col((Accounts::Table, Accounts::Currency))
+            .group_by_col((Transactions::Table, Transactions::PostedOn))
+            .group_by_col((Transactions::Table, Transactions::AccountId));
+
+        for condition in filter.conditions() {
+            query.and_where(condition);
+        }
+
+        let mut stmt = self.conn.prepare(&query.to_string(SqliteQueryBuilder))?;
+        let days = stmt
+            .query_map((), |row| {
+                let currency = row.get::<_, String>(0)?;
+                let posted_on = NaiveDate::from_str(row.get::<_, String>(1)?.as_str())
+                    .unwrap_or(Local::now().date_naive());
+
+                Ok((
+                    posted_on,
+                    row.get::<_, String>(2)?,
+                    Money::new(row.get(3)?, &currency),
+                    Money::new(row.get(4)?, &currency),
+                ))
+            })?
+            .collect::<Result<Vec<(NaiveDate, String, Money, Money)>, rusqlite::Error>>()?;
+
+        let ownership = filter
+            .holder_id
+            .as_deref()
+            .map(|user_id| self.get_ownership_by_user(user_id))
+            .transpose()?;
+
+        let mut income = Money::new(0, currency);
+        let mut expenses = Money::new(0, currency);
+        for (posted_on, account_id, day_income, day_expenses) in days {
+            let day_income = self.convert(&day_income, currency, posted_on)?.minor;
+            let day_expenses = self.convert(&day_expenses, currency, posted_on)?.minor;
+            income.minor += owned_amount(ownership.as_ref(), &account_id, day_income);
+            expenses.minor += owned_amount(ownership.as_ref(), &account_id, day_expenses);
+        }
+
+        Ok((income, expenses))
+    }
+
+    /// Sum of credits minus debits recorded against an account, in minor units.
+    /// Void transactions are left out.
+    pub fn get_account_net(&self, account_id: &str) -> Result<i64, DbError> {
+        self.sum_account_net(account_id, &[])
+    }
+
+    /// Like [`Db::get_account_net`], but only counting what the bank has already
+    /// cleared or reconciled.
+    pub fn get_account_cleared_net(&self, account_id: &str) -> Result<i64, DbError> {
+        self.sum_account_net(
+            account_id,
+            &[TransactionStatus::Cleared, TransactionStatus::Reconciled],
+        )
+    }
+
+    /// Like [`Db::get_account_net`], but only counting reconciled transactions.
+    pub fn get_account_reconciled_net(&self, account_id: &str) -> Result<i64, DbError> {
+        self.sum_account_net(account_id, &[TransactionStatus::Reconciled])
+    }
+
+    fn sum_account_net(
+        &self,
+        account_id: &str,
+        statuses: &[TransactionStatus],
+    ) -> Result<i64, DbError> {
+        let mut query = Query::select();
+        query
+            .expr(Expr::cust(
+                "COALESCE(SUM(CASE WHEN type = 'credit' THEN amount ELSE -amount END), 0)",
+            ))
+            .from(Transactions::Table)
+            .and_where(Expr::col((Transactions::Table, Transactions::AccountId)).eq(account_id))
+            .and_where(not_void());
+
+        if !statuses.is_empty() {
+            query.and_where(
+                Expr::col((Transactions::Table, Transactions::Status))
+                    .is_in(statuses.iter().map(|status| status.as_str())),
+            );
+        }
+
+        Ok(self
+            .conn
+            .query_row(&query.to_string(SqliteQueryBuilder), (), |row| row.get(0))?)
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use crate::{
-        db::{account::AccountRequest, category::CategoryRequest, users::UserRequest},
+        db::{
+            account::{AccountRequest, AccountType},
+            category::CategoryRequest,
+            holder::AccountHolder,
+            users::UserRequest,
+        },
+        money::DEFAULT_CURRENCY,
         utils::get_test_db_path,
     };
 
@@ -271,60 +914,469 @@ mod tests {
             name: "John Doe".to_string(),
             bank: "Bank of America".to_string(),
             account_number: None,
-            balance: 0.0,
-            holder_id: user.id.clone(),
+            account_type: AccountType::Checking,
+            opening_balance: Money::new(0, DEFAULT_CURRENCY),
+            holders: vec![AccountHolder::sole(&user.id)],
+            card: None,
+            loan: None,
         };
         let account = db.create_account(&account).unwrap();
 
+        let savings = AccountRequest {
+            name: "Savings".to_string(),
+            bank: "Bank of America".to_string(),
+            account_number: None,
+            account_type: AccountType::Checking,
+            opening_balance: Money::new(5000, DEFAULT_CURRENCY),
+            holders: vec![AccountHolder::sole(&user.id)],
+            card: None,
+            loan: None,
+        };
+        let savings = db.create_account(&savings).unwrap();
+
         let category = CategoryRequest {
             name: "Test category".to_string(),
             icon: "🍔".to_string(),
+            parent_id: None,
         };
         let category = db.create_category(&category).unwrap();
 
         // Create a test transaction
         let transaction_req = TransactionRequest {
             account_id: account.id.clone(),
-            amount: 1000,
+            amount: Money::new(1000, DEFAULT_CURRENCY),
             transaction_type: "credit".to_string(),
             description: "Test transaction".to_string(),
             category_id: category.id.clone(),
+            posted_on: NaiveDate::from_ymd_opt(2024, 3, 12).unwrap(),
+            status: TransactionStatus::Cleared,
+            payee_id: None,
+            splits: vec![],
+            tags: vec![],
+            attachments: vec![],
         };
 
         let created_transaction = db.create_transaction(&transaction_req).unwrap();
-        assert_eq!(created_transaction.amount, 1000);
+        assert_eq!(created_transaction.amount.minor, 1000);
         assert_eq!(created_transaction.description, "Test transaction");
 
         // Get transaction by ID
         let retrieved_transaction = db.get_transaction(&created_transaction.id).unwrap();
         assert_eq!(retrieved_transaction.id, created_transaction.id);
-        assert_eq!(retrieved_transaction.amount, 1000);
+        assert_eq!(retrieved_transaction.amount.minor, 1000);
+        assert_eq!(
+            retrieved_transaction.posted_on,
+            NaiveDate::from_ymd_opt(2024, 3, 12).unwrap()
+        );
+
+        // Backdated transactions are listed by the day they happened, not when entered
+        let earlier_transaction = db
+            .create_transaction(&TransactionRequest {
+                account_id: account.id.clone(),
+                amount: Money::new(500, DEFAULT_CURRENCY),
+                transaction_type: "debit".to_string(),
+                description: "Last week's purchase".to_string(),
+                category_id: category.id.clone(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 5).unwrap(),
+                status: TransactionStatus::Cleared,
+                payee_id: None,
+                splits: vec![],
+                tags: vec![],
+                attachments: vec![],
+            })
+            .unwrap();
+
+        // Credits add to the balance and debits take away from it
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, 500);
 
         // Get all transactions
         let all_transactions = db.get_all_transactions().unwrap();
-        assert_eq!(all_transactions.len(), 1);
+        assert_eq!(all_transactions.len(), 2);
+        assert_eq!(all_transactions[0].id, earlier_transaction.id);
+        assert_eq!(all_transactions[1].id, created_transaction.id);
 
         // Get transactions by account
         let account_transactions = db.get_transactions_by_account(&account.id).unwrap();
-        assert_eq!(account_transactions.len(), 1);
+        assert_eq!(account_transactions.len(), 2);
 
         // Update transaction
         let mut updated_transaction = retrieved_transaction.clone();
-        updated_transaction.amount = 2000;
+        updated_transaction.amount = Money::new(2000, DEFAULT_CURRENCY);
         updated_transaction.description = "Updated transaction".to_string();
+        updated_transaction.posted_on = NaiveDate::from_ymd_opt(2024, 3, 1).unwrap();
 
-        let update_result = db.update_transaction(&created_transaction.id, &updated_transaction);
+        let update_result =
+            db.update_transaction(&created_transaction.id, &updated_transaction.into());
         assert!(update_result.is_ok());
 
         let updated_retrieved = db.get_transaction(&created_transaction.id).unwrap();
-        assert_eq!(updated_retrieved.amount, 2000);
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, 1500);
+
+        // Moving a transaction to another account moves its effect on the balance
+        let mut moved_transaction = updated_retrieved.clone();
+        moved_transaction.account_id = savings.id.clone();
+        moved_transaction.transaction_type = "debit".to_string();
+        db.update_transaction(&created_transaction.id, &moved_transaction.into())
+            .unwrap();
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, -500);
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 3000);
+        assert_eq!(
+            db.recompute_account_balance(&savings.id).unwrap().minor,
+            3000
+        );
+
+        // Moving it to an account in another currency takes an amount in that currency
+        let euros = db
+            .create_account(&AccountRequest {
+                name: "Euros".to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(0, "EUR"),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let mut abroad = db.get_transaction(&created_transaction.id).unwrap();
+        abroad.account_id = euros.id.clone();
+        assert!(matches!(
+            db.update_transaction(&created_transaction.id, &abroad.clone().into()),
+            Err(DbError::Invalid(_))
+        ));
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 3000);
+        assert_eq!(db.get_account(&euros.id).unwrap().balance.minor, 0);
+
+        abroad.amount = Money::new(1800, "EUR");
+        db.update_transaction(&created_transaction.id, &abroad.into())
+            .unwrap();
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 5000);
+        assert_eq!(db.get_account(&euros.id).unwrap().balance.minor, -1800);
+
+        let mut back = db.get_transaction(&created_transaction.id).unwrap();
+        back.account_id = savings.id.clone();
+        back.amount = Money::new(2000, DEFAULT_CURRENCY);
+        db.update_transaction(&created_transaction.id, &back.into())
+            .unwrap();
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 3000);
+
+        assert_eq!(updated_retrieved.amount.minor, 2000);
         assert_eq!(updated_retrieved.description, "Updated transaction");
+        assert_eq!(
+            updated_retrieved.posted_on,
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap()
+        );
 
         // Delete transaction
         let delete_result = db.delete_transaction(&created_transaction.id);
         assert!(delete_result.is_ok());
+        db.delete_transaction(&earlier_transaction.id).unwrap();
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, 0);
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 5000);
 
         let all_transactions_after_delete = db.get_all_transactions().unwrap();
         assert!(all_transactions_after_delete.is_empty());
     }
+
+    #[test]
+    fn test_transfers() {
+        let _ = std::fs::remove_file(get_test_db_path("transfer"));
+
+        let db = Db::new(get_test_db_path("transfer").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+
+        let mut accounts = vec![];
+        for name in ["Checking", "Savings"] {
+            accounts.push(
+                db.create_account(&AccountRequest {
+                    name: name.to_string(),
+                    bank: "Bank of America".to_string(),
+                    account_number: None,
+                    account_type: AccountType::Checking,
+                    opening_balance: Money::new(10000, DEFAULT_CURRENCY),
+                    holders: vec![AccountHolder::sole(&user.id)],
+                    card: None,
+                    loan: None,
+                })
+                .unwrap(),
+            );
+        }
+        let (checking, savings) = (&accounts[0], &accounts[1]);
+
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Salary".to_string(),
+                icon: "💰".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+        db.create_transaction(&TransactionRequest {
+            account_id: checking.id.clone(),
+            amount: Money::new(5000, DEFAULT_CURRENCY),
+            transaction_type: "credit".to_string(),
+            description: "Paycheck".to_string(),
+            category_id: category.id.clone(),
+            posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            status: TransactionStatus::Cleared,
+            payee_id: None,
+            splits: vec![],
+            tags: vec![],
+            attachments: vec![],
+        })
+        .unwrap();
+
+        let (outgoing, incoming) = db
+            .create_transfer(&TransferRequest {
+                from_account_id: checking.id.clone(),
+                to_account_id: savings.id.clone(),
+                amount: Money::new(2500, DEFAULT_CURRENCY),
+                description: "Saving up".to_string(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+            })
+            .unwrap();
+        assert_eq!(outgoing.transfer_id, incoming.transfer_id);
+        assert_eq!(db.get_account(&checking.id).unwrap().balance.minor, 12500);
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 12500);
+
+        // Transfers are neither income nor expenses
+        let (income, expenses) = db
+            .get_income_and_expenses(&TransactionFilter::default(), DEFAULT_CURRENCY)
+            .unwrap();
+        assert_eq!(income.minor, 5000);
+        assert_eq!(expenses.minor, 0);
+
+        // Editing one side keeps the other in step
+        let mut edited = db.get_transaction(&incoming.id).unwrap();
+        edited.amount = Money::new(4000, DEFAULT_CURRENCY);
+        db.update_transaction(&incoming.id, &edited.clone().into())
+            .unwrap();
+
+        let counterpart = db.get_transfer_counterpart(&edited).unwrap().unwrap();
+        assert_eq!(counterpart.id, outgoing.id);
+        assert_eq!(counterpart.amount.minor, 4000);
+        assert_eq!(counterpart.transaction_type, "debit");
+        assert_eq!(db.get_account(&checking.id).unwrap().balance.minor, 11000);
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 14000);
+
+        // Deleting one side removes both
+        db.delete_transaction(&outgoing.id).unwrap();
+        assert_eq!(db.get_all_transactions().unwrap().len(), 1);
+        assert_eq!(db.get_account(&checking.id).unwrap().balance.minor, 15000);
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 10000);
+    }
+    #[test]
+    fn test_transaction_status() {
+        let _ = std::fs::remove_file(get_test_db_path("transaction_status"));
+
+        let db = Db::new(get_test_db_path("transaction_status").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let account = db
+            .create_account(&AccountRequest {
+                name: "Checking".to_string(),
+                bank: "Bank of America".to_string(),
+                account_number: None,
+                account_type: AccountType::Checking,
+                opening_balance: Money::new(10000, DEFAULT_CURRENCY),
+                holders: vec![AccountHolder::sole(&user.id)],
+                card: None,
+                loan: None,
+            })
+            .unwrap();
+        let category = db
+            .create_category(&CategoryRequest {
+                name: "Groceries".to_string(),
+                icon: "🛒".to_string(),
+                parent_id: None,
+            })
+            .unwrap();
+
+        let mut transactions = vec![];
+        for (amount, status) in [
+            (1000, TransactionStatus::Cleared),
+            (2000, TransactionStatus::Pending),
+            (4000, TransactionStatus::Void),
+        ] {
+            transactions.push(
+                db.create_transaction(&TransactionRequest {
+                    account_id: account.id.clone(),
+                    amount: Money::new(amount, DEFAULT_CURRENCY),
+                    transaction_type: "debit".to_string(),
+                    description: "Groceries".to_string(),
+                    category_id: category.id.clone(),
+                    posted_on: NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+                    status,
+                    payee_id: None,
+                    splits: vec![],
+                    tags: vec![],
+                    attachments: vec![],
+                })
+                .unwrap(),
+            );
+        }
+
+        // Void transactions count towards nothing
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, 7000);
+        assert_eq!(db.get_account_net(&account.id).unwrap(), -3000);
+        assert_eq!(db.get_account_cleared_net(&account.id).unwrap(), -1000);
+        let (_, expenses) = db
+            .get_income_and_expenses(&TransactionFilter::default(), DEFAULT_CURRENCY)
+            .unwrap();
+        assert_eq!(expenses.minor, 3000);
+
+        let pending = db
+            .get_filtered_transactions(&TransactionFilter {
+                status: Some(TransactionStatus::Pending),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, transactions[1].id);
+
+        // Clearing only touches what is not cleared yet
+        let ids = transactions
+            .iter()
+            .map(|transaction| transaction.id.clone())
+            .collect::<Vec<String>>();
+        let cleared = db
+            .set_transaction_status(&ids[..2], TransactionStatus::Cleared)
+            .unwrap();
+        assert_eq!(cleared.len(), 1);
+        assert_eq!(db.get_account_cleared_net(&account.id).unwrap(), -3000);
+
+        // Clearing leaves void transactions void
+        let cleared = db
+            .set_transaction_status(&ids[2..], TransactionStatus::Cleared)
+            .unwrap();
+        assert!(cleared.is_empty());
+        assert_eq!(
+            db.get_transaction(&ids[2]).unwrap().status,
+            TransactionStatus::Void
+        );
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, 7000);
+
+        // Un-voiding takes an explicit update and puts the amount back on the balance
+        let mut unvoided = db.get_transaction(&ids[2]).unwrap();
+        unvoided.status = TransactionStatus::Pending;
+        db.update_transaction(&ids[2], &unvoided.into()).unwrap();
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, 3000);
+
+        // Reconciled transactions stay locked unless voided
+        db.set_transaction_status(&ids[..1], TransactionStatus::Reconciled)
+            .unwrap();
+        let cleared = db
+            .set_transaction_status(&ids[..1], TransactionStatus::Pending)
+            .unwrap();
+        assert!(cleared.is_empty());
+        assert_eq!(
+            db.get_transaction(&ids[0]).unwrap().status,
+            TransactionStatus::Reconciled
+        );
+
+        // Their amount, type, account and date cannot be edited either, while
+        // the description can
+        let reconciled = db.get_transaction(&ids[0]).unwrap();
+        let balance = db.get_account(&account.id).unwrap().balance.minor;
+        let mut changed = reconciled.clone();
+        changed.amount = Money::new(9900, DEFAULT_CURRENCY);
+        assert!(matches!(
+            db.update_transaction(&ids[0], &changed.into()),
+            Err(DbError::Invalid(_))
+        ));
+        let mut changed = reconciled.clone();
+        changed.posted_on = NaiveDate::from_ymd_opt(2024, 5, 1).unwrap();
+        assert!(db.update_transaction(&ids[0], &changed.into()).is_err());
+        assert_eq!(db.get_account(&account.id).unwrap().balance.minor, balance);
+
+        let mut renamed = reconciled.clone();
+        renamed.description = "Renamed".to_string();
+        db.update_transaction(&ids[0], &renamed.into()).unwrap();
+
+        // Taking it back to cleared first unlocks it
+        let mut unreconciled = db.get_transaction(&ids[0]).unwrap();
+        unreconciled.status = TransactionStatus::Cleared;
+        db.update_transaction(&ids[0], &unreconciled.clone().into())
+            .unwrap();
+        unreconciled.amount = Money::new(9900, DEFAULT_CURRENCY);
+        db.update_transaction(&ids[0], &unreconciled.into())
+            .unwrap();
+        assert_eq!(db.get_transaction(&ids[0]).unwrap().amount.minor, 9900);
+    }
+
+    #[test]
+    fn test_delete_reconciled_transaction() {
+        let _ = std::fs::remove_file(get_test_db_path("delete_reconciled"));
+
+        let db = Db::new(get_test_db_path("delete_reconciled").as_str()).unwrap();
+        db.create_tables().unwrap();
+
+        let user = db
+            .create_user(&UserRequest {
+                name: "John Doe".to_string(),
+            })
+            .unwrap();
+        let mut accounts = vec![];
+        for name in ["Checking", "Savings"] {
+            accounts.push(
+                db.create_account(&AccountRequest {
+                    name: name.to_string(),
+                    bank: "Bank of America".to_string(),
+                    account_number: None,
+                    account_type: AccountType::Checking,
+                    opening_balance: Money::new(10000, DEFAULT_CURRENCY),
+                    holders: vec![AccountHolder::sole(&user.id)],
+                    card: None,
+                    loan: None,
+                })
+                .unwrap(),
+            );
+        }
+        let (checking, savings) = (&accounts[0], &accounts[1]);
+
+        let (outgoing, incoming) = db
+            .create_transfer(&TransferRequest {
+                from_account_id: checking.id.clone(),
+                to_account_id: savings.id.clone(),
+                amount: Money::new(2500, DEFAULT_CURRENCY),
+                description: "Saving up".to_string(),
+                posted_on: NaiveDate::from_ymd_opt(2024, 3, 2).unwrap(),
+            })
+            .unwrap();
+        db.set_transaction_status(
+            std::slice::from_ref(&incoming.id),
+            TransactionStatus::Reconciled,
+        )
+        .unwrap();
+
+        // Neither side goes while the savings side is reconciled
+        for id in [&incoming.id, &outgoing.id] {
+            assert!(matches!(
+                db.delete_transaction(id),
+                Err(DbError::Invalid(_))
+            ));
+        }
+        assert_eq!(db.get_all_transactions().unwrap().len(), 2);
+        assert_eq!(db.get_account(&checking.id).unwrap().balance.minor, 7500);
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 12500);
+
+        // Once it is taken out of the reconciliation both sides can go
+        let mut cleared = db.get_transaction(&incoming.id).unwrap();
+        cleared.status = TransactionStatus::Cleared;
+        db.update_transaction(&incoming.id, &cleared.into())
+            .unwrap();
+        db.delete_transaction(&outgoing.id).unwrap();
+        assert!(db.get_all_transactions().unwrap().is_empty());
+        assert_eq!(db.get_account(&savings.id).unwrap().balance.minor, 10000);
+    }
 }
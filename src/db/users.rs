@@ -1,10 +1,14 @@
 use std::str::FromStr;
 
 use chrono::{DateTime, Local};
+use rusqlite::Row;
 use sea_query::{Expr, Query, SqliteQueryBuilder};
 use tabled::Tabled;
 
-use crate::{db::Db, utils::random_hash};
+use crate::{
+    db::{Db, DbError, expense::UserHasSharedHistoryError, holder::UserHoldsAccountsError},
+    utils::random_hash,
+};
 
 use super::Users;
 
@@ -13,14 +17,34 @@ pub struct User {
     pub id: String,
     pub name: String,
     pub created_at: chrono::DateTime<Local>,
+    /// Set once the user is archived, hiding them while keeping their history
+    #[tabled(skip)]
+    pub archived_at: Option<DateTime<Local>>,
 }
 
 pub struct UserRequest {
     pub name: String,
 }
 
+fn user_columns() -> [Users; 4] {
+    [Users::Id, Users::Name, Users::CreatedAt, Users::ArchivedAt]
+}
+
+fn user_from_row(row: &Row) -> Result<User, rusqlite::Error> {
+    let created_at = DateTime::from_str(row.get::<_, String>(2)?.as_str()).unwrap_or(Local::now());
+
+    Ok(User {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        created_at,
+        archived_at: row
+            .get::<_, Option<String>>(3)?
+            .and_then(|archived_at| DateTime::from_str(&archived_at).ok()),
+    })
+}
+
 impl Db {
-    pub fn create_user(&self, user: &UserRequest) -> Result<User, rusqlite::Error> {
+    pub fn create_user(&self, user: &UserRequest) -> Result<User, DbError> {
         let id = random_hash(8);
         let time_now = Local::now().to_string();
 
@@ -40,12 +64,13 @@ impl Db {
             id,
             name: user.name.clone(),
             created_at: Local::now(),
+            archived_at: None,
         })
     }
 
-    pub fn get_user(&self, id: &str) -> Result<User, rusqlite::Error> {
+    pub fn get_user(&self, id: &str) -> Result<User, DbError> {
         let query = Query::select()
-            .columns([Users::Id, Users::Name, Users::CreatedAt])
+            .columns(user_columns())
             .from(Users::Table)
             .and_where(Expr::col(Users::Id).eq(id))
             .limit(1)
@@ -53,46 +78,28 @@ impl Db {
 
         let mut stmt = self.conn.prepare(&query)?;
 
-        let user = stmt.query_row((), |row| {
-            let created_at =
-                DateTime::from_str(row.get::<_, String>(2)?.as_str()).unwrap_or(Local::now());
-
-            Ok(User {
-                id: row.get(0)?,
-                name: row.get(1)?,
-                created_at,
-            })
-        })?;
+        let user = stmt.query_row((), user_from_row)?;
 
         Ok(user)
     }
 
-    pub fn get_all_users(&self) -> Result<Vec<User>, rusqlite::Error> {
+    pub fn get_all_users(&self) -> Result<Vec<User>, DbError> {
         let query = Query::select()
-            .columns([Users::Id, Users::Name, Users::CreatedAt])
+            .columns(user_columns())
             .from(Users::Table)
             .to_string(SqliteQueryBuilder);
 
         let mut stmt = self.conn.prepare(&query)?;
 
         Ok(stmt
-            .query_map((), |row| -> Result<User, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(2)?.as_str()).unwrap_or(Local::now());
-
-                Ok(User {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    created_at,
-                })
-            })?
+            .query_map((), user_from_row)?
             .map(|user| user.unwrap())
             .collect::<Vec<User>>())
     }
 
-    pub fn search_users_by_name(&self, name: &str) -> Result<Vec<User>, rusqlite::Error> {
+    pub fn search_users_by_name(&self, name: &str) -> Result<Vec<User>, DbError> {
         let query = Query::select()
-            .columns([Users::Id, Users::Name, Users::CreatedAt])
+            .columns(user_columns())
             .from(Users::Table)
             .and_where(Expr::col(Users::Name).like(format!("%{}%", name).as_str()))
             .to_string(SqliteQueryBuilder);
@@ -100,21 +107,12 @@ impl Db {
         let mut stmt = self.conn.prepare(&query)?;
 
         Ok(stmt
-            .query_map((), |row| -> Result<User, rusqlite::Error> {
-                let created_at =
-                    DateTime::from_str(row.get::<_, String>(2)?.as_str()).unwrap_or(Local::now());
-
-                Ok(User {
-                    id: row.get(0)?,
-                    name: row.get(1)?,
-                    created_at,
-                })
-            })?
+            .query_map((), user_from_row)?
             .map(|user| user.unwrap())
             .collect::<Vec<User>>())
     }
 
-    pub fn update_user(&self, id: &str, user: &User) -> Result<(), rusqlite::Error> {
+    pub fn update_user(&self, id: &str, user: &User) -> Result<(), DbError> {
         let query = Query::update()
             .table(Users::Table)
             .and_where(Expr::col(Users::Id).eq(id))
@@ -129,7 +127,42 @@ impl Db {
         Ok(())
     }
 
-    pub fn delete_user(&self, id: &str) -> Result<(), rusqlite::Error> {
+    /// Archives a user, or brings them back with `archived` set to false.
+    pub fn set_user_archived(&self, id: &str, archived: bool) -> Result<(), DbError> {
+        Ok(self.set_archived(Users::Table, Users::Id, Users::ArchivedAt, id, archived)?)
+    }
+
+    /// Deletes a user who no longer holds any account and was never part of a
+    /// shared expense or settlement, which other people's balances depend on.
+    pub fn delete_user(&self, id: &str) -> Result<(), DbError> {
+        let accounts = self.get_accounts_by_holder(id)?;
+        if !accounts.is_empty() {
+            return Err(DbError::invalid(UserHoldsAccountsError {
+                user_id: id.to_string(),
+                accounts: accounts.into_iter().map(|account| account.name).collect(),
+            }));
+        }
+
+        let expenses = self
+            .get_shared_expenses()?
+            .into_iter()
+            .filter(|expense| {
+                expense.paid_by == id || expense.shares.iter().any(|share| share.user_id == id)
+            })
+            .count();
+        let settlements = self
+            .get_settlements()?
+            .into_iter()
+            .filter(|settlement| settlement.from_user_id == id || settlement.to_user_id == id)
+            .count();
+        if expenses > 0 || settlements > 0 {
+            return Err(DbError::invalid(UserHasSharedHistoryError {
+                user_id: id.to_string(),
+                expenses,
+                settlements,
+            }));
+        }
+
         let query = Query::delete()
             .from_table(Users::Table)
             .and_where(Expr::col(Users::Id).eq(id))
@@ -141,6 +174,7 @@ impl Db {
     }
 }
 
+#[cfg(test)]
 mod tests {
     use crate::utils::get_test_db_path;
 
@@ -173,6 +207,7 @@ mod tests {
                 id: user.id.clone(),
                 name: "Jane Doe".to_string(),
                 created_at: user.created_at,
+                archived_at: None,
             },
         );
         assert!(update_res.is_ok());
@@ -180,6 +215,11 @@ mod tests {
         let user = db.get_user(&user.id).unwrap();
         assert_eq!(user.clone().name, "Jane Doe");
 
+        db.set_user_archived(&user.id, true).unwrap();
+        assert!(db.get_user(&user.id).unwrap().archived_at.is_some());
+        db.set_user_archived(&user.id, false).unwrap();
+        assert!(db.get_user(&user.id).unwrap().archived_at.is_none());
+
         let delete_res = db.delete_user(&user.id);
         assert!(delete_res.is_ok());
     }
@@ -1,58 +1,145 @@
 use std::io::{Error, ErrorKind};
 
+use chrono::{Local, NaiveDate};
 use colored::Colorize;
+use tabled::Tabled;
 
 use crate::{
-    db::account::{Account, AccountRequest, AccountResponse},
-    handler::Handler,
+    db::{
+        account::{Account, AccountRequest, AccountResponse, AccountType, CardTerms, LoanTerms},
+        holder::{AccountHolder, WHOLE_OWNERSHIP, format_ownership, owned_amount},
+        transaction::TransactionFilter,
+    },
+    handler::{Handler, archived_name, statement::due_warning},
+    money::{DEFAULT_CURRENCY, Money, parse_currency, parse_minor},
     utils::print_table,
 };
 
+/// What the bank already shows next to what is still on its way.
+#[derive(Debug, Clone, Tabled)]
+pub struct BalanceResponse {
+    pub cleared: Money,
+    pub pending: Money,
+    pub total: Money,
+}
+
+fn opening_balance_help(account_type: AccountType) -> &'static str {
+    if account_type.is_liability() {
+        "Enter how much was owed on the account before any transactions"
+    } else {
+        "Enter the balance of the account before any transactions"
+    }
+}
+
+/// Splits an account evenly between `count` holders, leftover hundredths of a
+/// percent going to the first ones.
+fn equal_ownership(count: usize) -> Vec<i64> {
+    let count = count.max(1) as i64;
+
+    (0..count)
+        .map(|index| WHOLE_OWNERSHIP / count + i64::from(index < WHOLE_OWNERSHIP % count))
+        .collect()
+}
+
+fn parse_ownership(input: &str) -> Result<i64, Error> {
+    // Hundredths of a percent, the unit of WHOLE_OWNERSHIP
+    parse_minor(input.trim_end_matches('%'), 2).map_err(|_| {
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!("'{}' is not a percentage of ownership", input),
+        )
+    })
+}
+
+fn parse_day(input: &str) -> Result<u32, Error> {
+    match input.trim().parse::<u32>() {
+        Ok(day) if (1..=31).contains(&day) => Ok(day),
+        _ => Err(Error::other(format!(
+            "'{}' is not a day of the month between 1 and 31",
+            input
+        ))),
+    }
+}
+
 impl Handler {
     pub fn add_account(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
-            let name = inquire::Text::new("Name")
+            inquire::Text::new("Name")
                 .with_help_message("Enter the name of the account")
                 .prompt()
-                .unwrap();
-
-            name
+                .unwrap()
         });
 
-        let balance = args.get_one::<f64>("balance").cloned().unwrap_or_else(|| {
-            let balance = inquire::Text::new("Balance")
-                .with_help_message("Enter the balance of the account")
-                .prompt()
-                .unwrap();
+        let currency = match args.get_one::<String>("currency") {
+            Some(currency) => currency.clone(),
+            None => {
+                let currency = inquire::Text::new("Currency")
+                    .with_help_message("Enter the currency code of the account (e.g. EUR)")
+                    .with_default(DEFAULT_CURRENCY)
+                    .prompt()
+                    .unwrap();
 
-            balance.parse::<f64>().unwrap()
-        });
+                parse_currency(&currency).map_err(Error::other)?
+            }
+        };
+
+        let account_type = self.prompt_account_type(args, None)?;
+
+        let opening_balance = match args.get_one::<String>("balance") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let balance = inquire::Text::new("Opening Balance")
+                    .with_help_message(opening_balance_help(account_type))
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&balance, &currency).map_err(Error::other)?
+            }
+        };
+        // Liabilities are entered as what is owed but kept as a ledger amount
+        let opening_balance = account_type.display_amount(&opening_balance);
 
         let bank = args.get_one::<String>("bank").cloned().unwrap_or_else(|| {
-            let bank = inquire::Text::new("Bank")
+            inquire::Text::new("Bank")
                 .with_help_message("Enter the bank of the account")
                 .prompt()
-                .unwrap();
-
-            bank
+                .unwrap()
         });
 
-        let holder_id = self.select_user()?.id;
+        let holders = self.prompt_holders(args, None)?;
 
         let account_number = args.get_one::<String>("account_number").cloned();
 
+        let card = if account_type == AccountType::CreditCard {
+            Some(self.prompt_card_terms(args, &currency, None)?)
+        } else {
+            None
+        };
+        let loan = if account_type == AccountType::Loan {
+            Some(self.prompt_loan_terms(
+                args,
+                &account_type.display_amount(&opening_balance),
+                None,
+            )?)
+        } else {
+            None
+        };
+
         let account_request = AccountRequest {
             name,
-            balance,
+            opening_balance,
             bank,
             account_number,
-            holder_id,
+            account_type,
+            holders,
+            card,
+            loan,
         };
 
         let account = self
             .db
             .create_account(&account_request)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .map_err(Error::from)?;
 
         println!(
             "{} {}",
@@ -63,31 +150,415 @@ impl Handler {
         Ok(())
     }
 
-    pub fn list_accounts(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
-        let accounts = self
+    /// The accounts the active user holds, or every account without one.
+    /// Archived accounts are left out unless `archived` is set.
+    fn profile_accounts(&self, archived: bool) -> Result<Vec<Account>, Error> {
+        Ok(match self.profile_id() {
+            Some(user_id) => self.db.get_accounts_by_holder(user_id),
+            None => self.db.get_all_accounts(),
+        }
+        .map_err(Error::from)?
+        .into_iter()
+        .filter(|account| archived || account.archived_at.is_none())
+        .collect())
+    }
+
+    pub fn list_accounts(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let accounts = self.profile_accounts(args.get_flag("all"))?;
+
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+        let today = Local::now().date_naive();
+        let due_within = *args.get_one::<u32>("due_within").unwrap_or(&7) as i64;
+        // Jointly held accounts only count with the share the active user owns
+        let ownership = self
+            .profile_id()
+            .map(|user_id| self.db.get_ownership_by_user(user_id))
+            .transpose()
+            .map_err(Error::from)?;
+
+        // Ledger balances of liabilities are negative, so summing them nets them out
+        let mut assets = Some(Money::new(0, &currency));
+        let mut liabilities = Some(Money::new(0, &currency));
+        let mut responses = vec![];
+        let mut warnings = vec![];
+        for account in accounts {
+            if account.card.is_some() {
+                let statement = self
+                    .db
+                    .get_card_statement(&account.id, today)
+                    .map_err(Error::from)?;
+                warnings.extend(due_warning(&account.name, &statement, due_within));
+            }
+
+            let converted = self.db.convert(&account.balance, &currency, today).ok();
+            let total = if account.account_type.is_liability() {
+                &mut liabilities
+            } else {
+                &mut assets
+            };
+            *total = total
+                .take()
+                .zip(converted.as_ref())
+                .map(|(mut total, converted)| {
+                    total.minor += owned_amount(ownership.as_ref(), &account.id, converted.minor);
+                    total
+                });
+
+            responses.push(self.account_response(account, converted)?);
+        }
+        let net_worth = assets
+            .clone()
+            .zip(liabilities.clone())
+            .map(|(assets, liabilities)| {
+                Money::new(assets.minor + liabilities.minor, &assets.currency)
+            });
+
+        print_table(responses, &self.profile_title("Accounts"));
+        for (label, total) in [
+            ("Assets:", assets),
+            (
+                "Liabilities:",
+                liabilities.map(|liabilities| Money::new(-liabilities.minor, &currency)),
+            ),
+            ("Net Worth:", net_worth),
+        ] {
+            println!(
+                "{} {}",
+                label.bold(),
+                total.map_or("n/a (missing exchange rates)".to_string(), |total| {
+                    total.to_string()
+                })
+            );
+        }
+
+        if !warnings.is_empty() {
+            println!("\n{}", "Payments Due:".bold());
+            for warning in warnings {
+                println!("{}", warning);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn account_response(
+        &self,
+        account: Account,
+        converted: Option<Money>,
+    ) -> Result<AccountResponse, Error> {
+        let account_type = account.account_type;
+        let holders = self
             .db
-            .get_all_accounts()
-            .map_err(|e| Error::new(ErrorKind::Other, e))?
+            .get_account_holders(&account.id)
+            .map_err(Error::from)?
             .into_iter()
-            .map(|account| AccountResponse {
-                id: account.id,
-                name: account.name,
-                bank: account.bank,
-                balance: account.balance,
-                holder_id: account.holder_id,
+            .map(|holder| {
+                let name = self
+                    .db
+                    .get_user(&holder.user_id)
+                    .map_or(holder.user_id, |user| user.name);
+
+                format!("{} {}", name, format_ownership(holder.share))
             })
-            .collect::<Vec<AccountResponse>>();
+            .collect::<Vec<String>>()
+            .join(", ");
 
-        print_table(accounts, "Accounts");
+        Ok(AccountResponse {
+            name: archived_name(&account.name, &account.archived_at),
+            id: account.id,
+            bank: account.bank,
+            account_type,
+            balance: account_type.display_amount(&account.balance),
+            converted: converted.map_or("n/a".to_string(), |converted| {
+                account_type.display_amount(&converted).to_string()
+            }),
+            holders,
+        })
+    }
 
-        Ok(())
+    /// Builds the holders from `--holder user[=percentage]` arguments, which
+    /// split the account evenly when no percentages are given. Without any,
+    /// the current holders are kept, or asked for a new account.
+    fn prompt_holders(
+        &self,
+        args: &clap::ArgMatches,
+        current: Option<Vec<AccountHolder>>,
+    ) -> Result<Vec<AccountHolder>, Error> {
+        if let Some(entries) = args.get_many::<String>("holders") {
+            let entries = entries
+                .map(|entry| match entry.split_once('=') {
+                    Some((user, share)) => Ok((self.find_user(user.trim())?, Some(share))),
+                    None => Ok((self.find_user(entry.trim())?, None)),
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let shares = if entries.iter().all(|(_, share)| share.is_none()) {
+                equal_ownership(entries.len())
+            } else {
+                entries
+                    .iter()
+                    .map(|(user, share)| match share {
+                        Some(share) => parse_ownership(share),
+                        None => Err(Error::new(
+                            ErrorKind::InvalidInput,
+                            format!("The share of {} is missing", user.name),
+                        )),
+                    })
+                    .collect::<Result<Vec<i64>, Error>>()?
+            };
+
+            return Ok(entries
+                .into_iter()
+                .zip(shares)
+                .map(|((user, _), share)| AccountHolder {
+                    user_id: user.id,
+                    share,
+                })
+                .collect());
+        }
+
+        if let Some(current) = current {
+            return Ok(current);
+        }
+        if let Some(user) = &self.profile {
+            return Ok(vec![AccountHolder::sole(&user.id)]);
+        }
+
+        let users = self.active_users()?;
+        let options = users
+            .iter()
+            .map(|user| user.name.clone())
+            .collect::<Vec<String>>();
+        let selected = inquire::MultiSelect::new("Select the holders", options)
+            .raw_prompt()
+            .unwrap();
+
+        if let [option] = selected.as_slice() {
+            return Ok(vec![AccountHolder::sole(&users[option.index].id)]);
+        }
+
+        let defaults = equal_ownership(selected.len());
+        selected
+            .into_iter()
+            .zip(defaults)
+            .map(|(option, default)| {
+                let user = &users[option.index];
+                let share = inquire::Text::new(&format!("Share of {}", user.name))
+                    .with_help_message("Enter the percentage of the account they own")
+                    .with_default(format_ownership(default).trim_end_matches('%'))
+                    .prompt()
+                    .unwrap();
+                let share = parse_ownership(&share)?;
+
+                Ok(AccountHolder {
+                    user_id: user.id.clone(),
+                    share,
+                })
+            })
+            .collect()
+    }
+
+    fn prompt_account_type(
+        &self,
+        args: &clap::ArgMatches,
+        current: Option<AccountType>,
+    ) -> Result<AccountType, Error> {
+        if let Some(account_type) = args.get_one::<AccountType>("type") {
+            return Ok(*account_type);
+        }
+
+        let starting_cursor = current
+            .and_then(|current| {
+                AccountType::ALL
+                    .iter()
+                    .position(|account_type| *account_type == current)
+            })
+            .unwrap_or(0);
+
+        Ok(
+            inquire::Select::new("Account Type", AccountType::ALL.to_vec())
+                .with_starting_cursor(starting_cursor)
+                .prompt()
+                .unwrap(),
+        )
+    }
+
+    /// Asks for the billing cycle and terms of a credit card, defaulting to
+    /// the current ones.
+    fn prompt_card_terms(
+        &self,
+        args: &clap::ArgMatches,
+        currency: &str,
+        current: Option<&CardTerms>,
+    ) -> Result<CardTerms, Error> {
+        let closing_day = match args.get_one::<u32>("closing_day") {
+            Some(day) => *day,
+            None => {
+                let day = inquire::Text::new("Statement Closing Day")
+                    .with_help_message("Enter the day of the month the statement closes (1-31)")
+                    .with_default(&current.map_or(1, |card| card.closing_day).to_string())
+                    .prompt()
+                    .unwrap();
+
+                parse_day(&day)?
+            }
+        };
+
+        let due_day = match args.get_one::<u32>("due_day") {
+            Some(day) => *day,
+            None => {
+                let day = inquire::Text::new("Payment Due Day")
+                    .with_help_message("Enter the day of the month the payment is due (1-31)")
+                    .with_default(&current.map_or(25, |card| card.due_day).to_string())
+                    .prompt()
+                    .unwrap();
+
+                parse_day(&day)?
+            }
+        };
+
+        let credit_limit = match args.get_one::<String>("limit") {
+            Some(amount) => Some(Money::parse(amount, currency).map_err(Error::other)?),
+            None => {
+                let limit = inquire::Text::new("Credit Limit")
+                    .with_help_message("Enter the credit limit, or leave empty if unknown")
+                    .with_default(
+                        &current
+                            .and_then(|card| card.credit_limit.as_ref())
+                            .map_or(String::new(), |limit| limit.to_plain_string()),
+                    )
+                    .prompt()
+                    .unwrap();
+
+                match limit.trim() {
+                    "" => None,
+                    limit => Some(Money::parse(limit, currency).map_err(Error::other)?),
+                }
+            }
+        };
+
+        let apr = match args.get_one::<f64>("apr") {
+            Some(apr) => Some(*apr),
+            None => {
+                let apr = inquire::Text::new("APR")
+                    .with_help_message("Enter the annual interest rate in percent (e.g. 24.99), or leave empty if unknown")
+                    .with_default(
+                        &current
+                            .and_then(|card| card.apr)
+                            .map_or(String::new(), |apr| apr.to_string()),
+                    )
+                    .prompt()
+                    .unwrap();
+
+                match apr.trim() {
+                    "" => None,
+                    apr => Some(
+                        apr.parse::<f64>()
+                            .ok()
+                            .filter(|apr| *apr >= 0.0)
+                            .ok_or_else(|| {
+                                Error::other(format!("'{}' is not an interest rate", apr))
+                            })?,
+                    ),
+                }
+            }
+        };
+
+        Ok(CardTerms {
+            closing_day,
+            due_day,
+            credit_limit,
+            apr,
+        })
+    }
+
+    /// Asks for what was borrowed on a loan and how it is paid back,
+    /// defaulting to the current terms or else to what is owed.
+    fn prompt_loan_terms(
+        &self,
+        args: &clap::ArgMatches,
+        owed: &Money,
+        current: Option<&LoanTerms>,
+    ) -> Result<LoanTerms, Error> {
+        let principal = match args.get_one::<String>("principal") {
+            Some(amount) => Money::parse(amount, &owed.currency).map_err(Error::other)?,
+            None => {
+                let principal = inquire::Text::new("Principal")
+                    .with_help_message("Enter how much was borrowed")
+                    .with_default(
+                        &current
+                            .map_or(owed, |loan| &loan.principal)
+                            .to_plain_string(),
+                    )
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&principal, &owed.currency).map_err(Error::other)?
+            }
+        };
+
+        let rate = match args.get_one::<f64>("rate") {
+            Some(rate) => *rate,
+            None => {
+                let rate = inquire::Text::new("Interest Rate")
+                    .with_help_message("Enter the annual interest rate in percent (e.g. 6.5)")
+                    .with_default(&current.map_or(0.0, |loan| loan.rate).to_string())
+                    .prompt()
+                    .unwrap();
+
+                rate.trim()
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|rate| *rate >= 0.0)
+                    .ok_or_else(|| Error::other(format!("'{}' is not an interest rate", rate)))?
+            }
+        };
+
+        let term_months = match args.get_one::<u32>("term") {
+            Some(term) => *term,
+            None => {
+                let term = inquire::Text::new("Term")
+                    .with_help_message("Enter the number of monthly payments (e.g. 360)")
+                    .with_default(&current.map_or(12, |loan| loan.term_months).to_string())
+                    .prompt()
+                    .unwrap();
+
+                match term.trim().parse::<u32>() {
+                    Ok(term) if term > 0 => term,
+                    _ => {
+                        return Err(Error::other(format!(
+                            "'{}' is not a positive whole number",
+                            term
+                        )));
+                    }
+                }
+            }
+        };
+
+        let start_date = args
+            .get_one::<NaiveDate>("start")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("Start Date")
+                    .with_help_message("Select the day the money was borrowed")
+                    .with_default(current.map_or(Local::now().date_naive(), |loan| loan.start_date))
+                    .prompt()
+                    .unwrap()
+            });
+
+        Ok(LoanTerms {
+            principal,
+            rate,
+            term_months,
+            start_date,
+        })
     }
 
     pub fn select_account(&self) -> Result<Account, Error> {
-        let accounts = self
-            .db
-            .get_all_accounts()
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        let accounts = self.profile_accounts(false)?;
 
         let options = accounts
             .iter()
@@ -104,22 +575,54 @@ impl Handler {
             .clone())
     }
 
+    /// Looks an account up by id, falling back to a name search.
+    pub fn find_account(&self, id_or_name: &str) -> Result<Account, Error> {
+        if let Ok(account) = self.db.get_account(id_or_name) {
+            return Ok(account);
+        }
+
+        let accounts = self
+            .db
+            .search_accounts_by_name(id_or_name)
+            .map_err(Error::from)?;
+
+        match accounts.len() {
+            0 => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("No accounts found matching '{}'", id_or_name),
+            )),
+            1 => Ok(accounts[0].clone()),
+            _ => {
+                let options = accounts
+                    .iter()
+                    .map(|account| account.name.clone())
+                    .collect::<Vec<String>>();
+                let option = inquire::Select::new("Select an account", options)
+                    .prompt()
+                    .unwrap();
+
+                Ok(accounts
+                    .iter()
+                    .find(|account| account.name == option)
+                    .unwrap()
+                    .clone())
+            }
+        }
+    }
+
     pub fn get_account_by_id_or_name(&self, args: &clap::ArgMatches) -> Result<Account, Error> {
         let id = args.get_one::<String>("id").cloned();
         let name = args.get_one::<String>("name").cloned();
 
         if let Some(id1) = id {
-            let account = self
-                .db
-                .get_account(&id1)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            let account = self.db.get_account(&id1).map_err(Error::from)?;
 
             Ok(account)
         } else if let Some(name1) = name {
             let account = self
                 .db
                 .search_accounts_by_name(&name1)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+                .map_err(Error::from)?;
 
             if account.len() == 1 {
                 Ok(account[0].clone())
@@ -148,15 +651,36 @@ impl Handler {
     pub fn get_account(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let account = self.get_account_by_id_or_name(args)?;
 
-        let account_response = AccountResponse {
-            id: account.id,
-            name: account.name,
-            bank: account.bank,
-            balance: account.balance,
-            holder_id: account.holder_id,
+        let converted = self
+            .db
+            .convert(
+                &account.balance,
+                DEFAULT_CURRENCY,
+                Local::now().date_naive(),
+            )
+            .ok();
+
+        let cleared_net = self
+            .db
+            .get_account_cleared_net(&account.id)
+            .map_err(Error::from)?;
+        let cleared = Money::new(
+            account.opening_balance.minor + cleared_net,
+            &account.balance.currency,
+        );
+        let pending = Money::new(
+            account.balance.minor - cleared.minor,
+            &account.balance.currency,
+        );
+        let account_type = account.account_type;
+        let balance = BalanceResponse {
+            cleared: account_type.display_amount(&cleared),
+            pending: account_type.display_amount(&pending),
+            total: account_type.display_amount(&account.balance),
         };
 
-        print_table(vec![account_response], "Account");
+        print_table(vec![self.account_response(account, converted)?], "Account");
+        print_table(vec![balance], "Balance");
         Ok(())
     }
 
@@ -168,19 +692,52 @@ impl Handler {
             .prompt()
             .unwrap();
 
-        let new_balance = inquire::Text::new("New Balance")
-            .with_help_message("Enter the new balance of the account")
+        let new_account_type = self.prompt_account_type(args, Some(account.account_type))?;
+
+        let new_opening_balance = inquire::Text::new("New Opening Balance")
+            .with_help_message(opening_balance_help(new_account_type))
+            .with_default(
+                &account
+                    .account_type
+                    .display_amount(&account.opening_balance)
+                    .to_plain_string(),
+            )
             .prompt()
             .unwrap();
 
-        let new_balance = new_balance.parse::<f64>().unwrap();
+        let new_opening_balance =
+            Money::parse(&new_opening_balance, &account.opening_balance.currency)
+                .map_err(Error::other)?;
+
+        let card = if new_account_type == AccountType::CreditCard {
+            Some(self.prompt_card_terms(args, &account.balance.currency, account.card.as_ref())?)
+        } else {
+            None
+        };
+        let loan = if new_account_type == AccountType::Loan {
+            Some(self.prompt_loan_terms(args, &new_opening_balance, account.loan.as_ref())?)
+        } else {
+            None
+        };
+
+        let holders = self.prompt_holders(
+            args,
+            Some(
+                self.db
+                    .get_account_holders(&account.id)
+                    .map_err(Error::from)?,
+            ),
+        )?;
 
         account.name = new_name;
-        account.balance = new_balance;
+        account.account_type = new_account_type;
+        account.opening_balance = new_account_type.display_amount(&new_opening_balance);
+        account.card = card;
+        account.loan = loan;
 
         self.db
-            .update_account(&account.id, &account)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .update_account(&account.id, &account, &holders)
+            .map_err(Error::from)?;
 
         println!(
             "{} {}",
@@ -191,12 +748,87 @@ impl Handler {
         Ok(())
     }
 
-    pub fn delete_account(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+    pub fn recompute_accounts(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let accounts = if args.contains_id("id") || args.contains_id("name") {
+            vec![self.get_account_by_id_or_name(args)?]
+        } else {
+            self.db.get_all_accounts().map_err(Error::from)?
+        };
+
+        for account in accounts {
+            let balance = self
+                .db
+                .recompute_account_balance(&account.id)
+                .map_err(Error::from)?;
+
+            println!(
+                "{} {}: {} -> {}",
+                "Recomputed balance of".green(),
+                account.name.green(),
+                account.balance,
+                balance
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn archive_account(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account = self.get_account_by_id_or_name(args)?;
+
+        self.db
+            .set_account_archived(&account.id, true)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully archived account".green(),
+            account.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn unarchive_account(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let account = self.get_account_by_id_or_name(args)?;
 
         self.db
-            .delete_account(&account.id)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .set_account_archived(&account.id, false)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully unarchived account".green(),
+            account.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn delete_account(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account = self.get_account_by_id_or_name(args)?;
+
+        // Deleting an account takes every transaction on it along, and the
+        // other side of its transfers with them
+        let transactions = self
+            .db
+            .get_filtered_transactions(&TransactionFilter {
+                account_id: Some(account.id.clone()),
+                ..Default::default()
+            })
+            .map_err(Error::from)?;
+        if !transactions.is_empty() && !args.get_flag("force") {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} has {} transactions, archive it to keep them or pass --force to delete them and the other side of its transfers too",
+                    account.name,
+                    transactions.len()
+                ),
+            ));
+        }
+
+        self.db.delete_account(&account.id).map_err(Error::from)?;
 
         println!(
             "{} {}",
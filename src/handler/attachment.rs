@@ -0,0 +1,100 @@
+use std::{
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use colored::Colorize;
+
+use crate::{db::attachment::AttachmentRequest, handler::Handler, utils::print_table};
+
+impl Handler {
+    /// Reads the files passed with `--attach`, if any.
+    pub fn read_attachments(
+        &self,
+        args: &clap::ArgMatches,
+    ) -> Result<Vec<AttachmentRequest>, Error> {
+        let Some(paths) = args.get_many::<String>("attach") else {
+            return Ok(vec![]);
+        };
+
+        paths
+            .map(|path| {
+                let content = std::fs::read(path).map_err(|e| {
+                    Error::new(e.kind(), format!("Could not read '{}': {}", path, e))
+                })?;
+                let file_name = Path::new(path)
+                    .file_name()
+                    .map_or(path.clone(), |name| name.to_string_lossy().to_string());
+
+                Ok(AttachmentRequest { file_name, content })
+            })
+            .collect()
+    }
+
+    pub fn list_attachments(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let transaction = self.get_transaction_by_id(args)?;
+
+        let attachments = self
+            .db
+            .get_transaction_attachments(&transaction.id)
+            .map_err(Error::from)?;
+
+        print_table(attachments, "Attachments");
+
+        Ok(())
+    }
+
+    pub fn extract_attachment(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let id = args.get_one::<String>("id").unwrap();
+        let attachment = self.db.get_attachment(id).map_err(Error::from)?;
+
+        // An existing directory gets the original file name, anything else is the file to write
+        let output = match args.get_one::<String>("output") {
+            Some(output) if Path::new(output).is_dir() => {
+                Path::new(output).join(&attachment.file_name)
+            }
+            Some(output) => PathBuf::from(output),
+            None => PathBuf::from(&attachment.file_name),
+        };
+
+        if output.exists() {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("'{}' already exists", output.display()),
+            ));
+        }
+
+        let content = self
+            .db
+            .get_attachment_content(&attachment.id)
+            .map_err(Error::from)?;
+        std::fs::write(&output, content)?;
+
+        println!(
+            "{} {} {} {}",
+            "Successfully extracted".green(),
+            attachment.file_name.green(),
+            "to".green(),
+            output.display()
+        );
+
+        Ok(())
+    }
+
+    pub fn remove_attachment(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let id = args.get_one::<String>("id").unwrap();
+        let attachment = self.db.get_attachment(id).map_err(Error::from)?;
+
+        self.db
+            .delete_attachment(&attachment.id)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully removed attachment".green(),
+            attachment.file_name.green()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use std::io::Error;
+
+use chrono::{Local, Months, NaiveDate};
+use colored::{Color, Colorize};
+use tabled::Tabled;
+
+use crate::{
+    db::{
+        budget::{BudgetRequest, month_start},
+        category::{category_path, category_tree},
+    },
+    handler::Handler,
+    money::{DEFAULT_CURRENCY, Money},
+    utils::{print_colored_table, print_table},
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct BudgetResponse {
+    pub month: String,
+    pub category: String,
+    pub amount: Money,
+    pub rollover: bool,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct BudgetStatusResponse {
+    pub category: String,
+    pub budgeted: Money,
+    pub rolled_over: Money,
+    pub spent: Money,
+    pub remaining: Money,
+    pub used: String,
+}
+
+/// Formats the first day of a month as `YYYY-MM`.
+pub(super) fn month_name(month: NaiveDate) -> String {
+    month.format("%Y-%m").to_string()
+}
+
+pub(super) fn this_month() -> NaiveDate {
+    month_start(Local::now().date_naive())
+}
+
+impl Handler {
+    pub fn set_budget(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let category = match args.get_one::<String>("category_id") {
+            Some(category) => self.find_category(category)?,
+            None => self.select_category(None)?,
+        };
+        let month = args
+            .get_one::<NaiveDate>("month")
+            .cloned()
+            .unwrap_or_else(this_month);
+
+        let existing = self
+            .db
+            .get_budget(&category.id, month)
+            .map_err(Error::from)?;
+
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .or(existing
+                .as_ref()
+                .map(|budget| budget.amount.currency.clone()))
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+
+        let amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let mut prompt = inquire::Text::new("Amount")
+                    .with_help_message("Enter how much may be spent in the month (e.g. 300)");
+                let current = existing
+                    .as_ref()
+                    .map(|budget| budget.amount.to_plain_string());
+                if let Some(current) = &current {
+                    prompt = prompt.with_default(current);
+                }
+
+                Money::parse(&prompt.prompt().unwrap(), &currency).map_err(Error::other)?
+            }
+        };
+
+        let rollover = if args.get_flag("rollover") {
+            true
+        } else {
+            inquire::Confirm::new("Carry what is left over into next month?")
+                .with_default(existing.as_ref().is_some_and(|budget| budget.rollover))
+                .prompt()
+                .unwrap()
+        };
+
+        let budget = self
+            .db
+            .set_budget(&BudgetRequest {
+                category_id: category.id,
+                month,
+                amount,
+                rollover,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {} {} {} {}",
+            "Successfully set the budget of".green(),
+            category.name.green(),
+            "for".green(),
+            month_name(budget.month).green(),
+            "to".green(),
+            budget.amount.to_string().green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_budgets(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let month = args.get_one::<NaiveDate>("month").cloned();
+
+        let budgets = self.db.get_budgets(month).map_err(Error::from)?;
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+
+        let responses = budgets
+            .into_iter()
+            .map(|budget| BudgetResponse {
+                month: month_name(budget.month),
+                category: category_path(&categories, &budget.category_id),
+                amount: budget.amount,
+                rollover: budget.rollover,
+            })
+            .collect::<Vec<BudgetResponse>>();
+
+        print_table(responses, "Budgets");
+
+        Ok(())
+    }
+
+    pub fn copy_budgets(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let to = args
+            .get_one::<NaiveDate>("to")
+            .cloned()
+            .unwrap_or_else(this_month);
+        let from = match args.get_one::<NaiveDate>("from") {
+            Some(from) => *from,
+            None => to
+                .checked_sub_months(Months::new(1))
+                .ok_or_else(|| Error::other("There is no month before that"))?,
+        };
+
+        let copied = self.db.copy_budgets(from, to).map_err(Error::from)?;
+
+        println!(
+            "{} {} {} {} {} {}",
+            "Successfully copied".green(),
+            copied.to_string().green(),
+            "budget(s) from".green(),
+            month_name(from).green(),
+            "to".green(),
+            month_name(to).green()
+        );
+
+        Ok(())
+    }
+
+    pub fn budget_status(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let month = args
+            .get_one::<NaiveDate>("month")
+            .cloned()
+            .unwrap_or_else(this_month);
+
+        let statuses = self.db.get_budget_status(month).map_err(Error::from)?;
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+
+        // Follow the category tree so that parents sit above their children
+        let mut responses = vec![];
+        let mut colors = vec![];
+        for (_, category) in category_tree(&categories) {
+            let Some(status) = statuses
+                .iter()
+                .find(|status| status.category_id == category.id)
+            else {
+                continue;
+            };
+
+            let percent = status.percent_used();
+            responses.push(BudgetStatusResponse {
+                category: format!(
+                    "{} {}",
+                    category.icon,
+                    category_path(&categories, &category.id)
+                ),
+                budgeted: status.budgeted.clone(),
+                rolled_over: status.rolled_over.clone(),
+                spent: status.spent.clone(),
+                remaining: status.remaining(),
+                used: if percent.is_finite() {
+                    format!("{:.0}%", percent)
+                } else {
+                    "n/a".to_string()
+                },
+            });
+            colors.push(if status.is_overspent() {
+                Some(Color::Red)
+            } else if percent >= 90.0 {
+                Some(Color::Yellow)
+            } else {
+                None
+            });
+        }
+
+        print_colored_table(
+            responses,
+            &format!("Budget Status {}", month_name(month)),
+            &colors,
+        );
+
+        Ok(())
+    }
+}
@@ -1,39 +1,78 @@
 use std::io::{Error, ErrorKind};
 
 use colored::Colorize;
+use tabled::Tabled;
 
 use crate::{
-    db::category::{Category, CategoryRequest},
-    handler::Handler,
+    db::{
+        category::{Category, CategoryRequest, category_ancestors, category_path, category_tree},
+        transaction::TransactionFilter,
+    },
+    handler::{Handler, archived_name},
+    money::{DEFAULT_CURRENCY, Money},
     utils::print_table,
 };
 
+#[derive(Debug, Clone, Tabled)]
+pub struct CategoryTreeResponse {
+    pub id: String,
+    pub icon: String,
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct CategoryReportResponse {
+    pub icon: String,
+    pub name: String,
+    pub income: Money,
+    pub expenses: Money,
+}
+
+/// Indents a name to its depth in the category tree.
+fn tree_name(depth: usize, name: &str) -> String {
+    if depth == 0 {
+        name.to_string()
+    } else {
+        format!("{}└─ {}", "   ".repeat(depth - 1), name)
+    }
+}
+
+const NO_PARENT: &str = "(none, top level)";
+
 impl Handler {
     pub fn add_category(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
-            let name = inquire::Text::new("Name")
+            inquire::Text::new("Name")
                 .with_help_message("Enter the name of the category")
                 .prompt()
-                .unwrap();
-
-            name
+                .unwrap()
         });
 
         let icon = args.get_one::<String>("icon").cloned().unwrap_or_else(|| {
-            let icon = inquire::Text::new("Icon")
+            inquire::Text::new("Icon")
                 .with_help_message("Enter the icon of the category (emoji)")
                 .prompt()
-                .unwrap();
-
-            icon
+                .unwrap()
         });
 
-        let category_request = CategoryRequest { name, icon };
+        let parent_id = match args.get_one::<String>("parent") {
+            Some(parent) => Some(self.find_category(parent)?.id),
+            None if args.get_flag("root") => None,
+            None => self
+                .select_parent_category(None, None)?
+                .map(|parent| parent.id),
+        };
+
+        let category_request = CategoryRequest {
+            name,
+            icon,
+            parent_id,
+        };
 
         let category = self
             .db
             .create_category(&category_request)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .map_err(Error::from)?;
 
         println!(
             "{} {}",
@@ -44,39 +83,188 @@ impl Handler {
         Ok(())
     }
 
-    pub fn list_categories(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+    pub fn list_categories(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let all = args.get_flag("all");
         let categories = self
             .db
             .get_all_categories()
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
-
-        print_table(categories, "Categories");
+            .map_err(Error::from)?
+            .into_iter()
+            .filter(|category| all || category.archived_at.is_none())
+            .collect::<Vec<Category>>();
+
+        let tree = category_tree(&categories)
+            .into_iter()
+            .map(|(depth, category)| CategoryTreeResponse {
+                name: tree_name(depth, &archived_name(&category.name, &category.archived_at)),
+                id: category.id,
+                icon: category.icon,
+            })
+            .collect::<Vec<CategoryTreeResponse>>();
+
+        print_table(tree, "Categories");
 
         Ok(())
     }
 
-    pub fn select_category(&self) -> Result<Category, Error> {
-        let categories = self
+    pub fn report_categories(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+
+        // Parents include the spending of their children
+        let totals = self
             .db
-            .get_all_categories()
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .get_category_rollups(&currency, None, self.profile_id())
+            .map_err(Error::from)?;
+
+        let report = category_tree(&categories)
+            .into_iter()
+            .filter_map(|(depth, category)| {
+                let total = totals
+                    .iter()
+                    .find(|total| total.category_id == category.id)?;
 
-        let options = categories
+                Some(CategoryReportResponse {
+                    icon: category.icon,
+                    name: tree_name(depth, &category.name),
+                    income: total.income.clone(),
+                    expenses: total.expenses.clone(),
+                })
+            })
+            .collect::<Vec<CategoryReportResponse>>();
+
+        print_table(report, &self.profile_title("Category Report"));
+
+        Ok(())
+    }
+
+    /// Asks for a category, with the cursor starting on `current` if given.
+    pub fn select_category(&self, current: Option<&str>) -> Result<Category, Error> {
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+
+        // Show the full path so that children with the same name can be told apart.
+        // Archived categories are only offered when they are the current one.
+        let tree = category_tree(&categories)
+            .into_iter()
+            .filter(|(_, category)| {
+                category.archived_at.is_none() || Some(category.id.as_str()) == current
+            })
+            .collect::<Vec<(usize, Category)>>();
+        let options = tree
             .iter()
-            .map(|category| format!("{} {}", category.icon, category.name))
+            .map(|(_, category)| {
+                format!(
+                    "{} {}",
+                    category.icon,
+                    category_path(&categories, &category.id)
+                )
+            })
             .collect::<Vec<String>>();
+        let starting_cursor = current
+            .and_then(|current| tree.iter().position(|(_, category)| category.id == current))
+            .unwrap_or(0);
+
         let option = inquire::Select::new("Select a category", options)
-            .prompt()
+            .with_starting_cursor(starting_cursor)
+            .raw_prompt()
             .unwrap();
 
-        // Extract the name from the selected option (remove icon and space)
-        let selected_name = option.split_whitespace().skip(1).collect::<Vec<&str>>().join(" ");
+        Ok(tree[option.index].1.clone())
+    }
 
-        Ok(categories
-            .iter()
-            .find(|category| category.name == selected_name)
-            .unwrap()
-            .clone())
+    /// Asks for the parent of a category, leaving out `exclude` and everything below it.
+    fn select_parent_category(
+        &self,
+        exclude: Option<&str>,
+        current: Option<&str>,
+    ) -> Result<Option<Category>, Error> {
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+
+        let candidates = category_tree(&categories)
+            .into_iter()
+            .map(|(_, category)| category)
+            .filter(|category| {
+                (category.archived_at.is_none() || Some(category.id.as_str()) == current)
+                    && exclude.is_none_or(|exclude| {
+                        !category_ancestors(&categories, &category.id)
+                            .iter()
+                            .any(|id| id == exclude)
+                    })
+            })
+            .collect::<Vec<Category>>();
+
+        // With nothing to choose from the category can only be top-level
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+
+        let mut options = vec![NO_PARENT.to_string()];
+        options.extend(candidates.iter().map(|category| {
+            format!(
+                "{} {}",
+                category.icon,
+                category_path(&categories, &category.id)
+            )
+        }));
+        let starting_cursor = current
+            .and_then(|current| {
+                candidates
+                    .iter()
+                    .position(|category| category.id == current)
+            })
+            .map_or(0, |position| position + 1);
+
+        let option = inquire::Select::new("Select a parent category", options)
+            .with_starting_cursor(starting_cursor)
+            .raw_prompt()
+            .map_err(Error::other)?;
+
+        Ok(match option.index {
+            0 => None,
+            index => Some(candidates[index - 1].clone()),
+        })
+    }
+
+    /// Looks a category up by id, falling back to a name search.
+    pub fn find_category(&self, id_or_name: &str) -> Result<Category, Error> {
+        if let Ok(category) = self.db.get_category(id_or_name) {
+            return Ok(category);
+        }
+
+        let categories = self
+            .db
+            .get_categories_by_name(id_or_name)
+            .map_err(Error::from)?;
+
+        match categories.len() {
+            0 => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("No categories found matching '{}'", id_or_name),
+            )),
+            1 => Ok(categories[0].clone()),
+            _ => {
+                let all_categories = self.db.get_all_categories().map_err(Error::from)?;
+
+                let options = categories
+                    .iter()
+                    .map(|category| {
+                        format!(
+                            "{} {}",
+                            category.icon,
+                            category_path(&all_categories, &category.id)
+                        )
+                    })
+                    .collect::<Vec<String>>();
+                let option = inquire::Select::new("Select a category", options)
+                    .raw_prompt()
+                    .unwrap();
+
+                Ok(categories[option.index].clone())
+            }
+        }
     }
 
     pub fn get_category_by_id_or_name(&self, args: &clap::ArgMatches) -> Result<Category, Error> {
@@ -84,25 +272,19 @@ impl Handler {
         let name = args.get_one::<String>("name").cloned();
 
         if let Some(id) = id {
-            let category = self
-                .db
-                .get_category(&id)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            let category = self.db.get_category(&id).map_err(Error::from)?;
 
             Ok(category)
         } else if let Some(name) = name {
-            let categories = self
-                .db
-                .get_categories_by_name(&name)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            let categories = self.db.get_categories_by_name(&name).map_err(Error::from)?;
 
             if categories.len() == 1 {
-                return Ok(categories[0].clone());
+                Ok(categories[0].clone())
             } else if categories.is_empty() {
-                return Err(Error::new(
+                Err(Error::new(
                     ErrorKind::NotFound,
                     format!("No categories found with name '{}'", name),
-                ));
+                ))
             } else {
                 let options = categories
                     .iter()
@@ -127,18 +309,15 @@ impl Handler {
                 .prompt()
                 .unwrap();
 
-            let categories = self
-                .db
-                .get_categories_by_name(&name)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            let categories = self.db.get_categories_by_name(&name).map_err(Error::from)?;
 
             if categories.len() == 1 {
-                return Ok(categories[0].clone());
+                Ok(categories[0].clone())
             } else if categories.is_empty() {
-                return Err(Error::new(
+                Err(Error::new(
                     ErrorKind::NotFound,
                     format!("No categories found with name '{}'", name),
-                ));
+                ))
             } else {
                 let options = categories
                     .iter()
@@ -182,12 +361,21 @@ impl Handler {
             .prompt()
             .unwrap();
 
+        let new_parent = match args.get_one::<String>("parent") {
+            Some(parent) => Some(self.find_category(parent)?),
+            None if args.get_flag("root") => None,
+            None => {
+                self.select_parent_category(Some(&category.id), category.parent_id.as_deref())?
+            }
+        };
+
         category.name = new_name;
         category.icon = new_icon;
+        category.parent_id = new_parent.map(|parent| parent.id);
 
         self.db
             .update_category(&category.id, &category)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .map_err(Error::from)?;
 
         println!(
             "{} {} {}",
@@ -199,12 +387,64 @@ impl Handler {
         Ok(())
     }
 
-    pub fn delete_category(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+    pub fn archive_category(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let category = self.get_category_by_id_or_name(args)?;
 
         self.db
-            .delete_category(&category.id)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .set_category_archived(&category.id, true)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {}",
+            "Successfully archived category".green(),
+            category.icon.green(),
+            category.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn unarchive_category(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let category = self.get_category_by_id_or_name(args)?;
+
+        self.db
+            .set_category_archived(&category.id, false)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {}",
+            "Successfully unarchived category".green(),
+            category.icon.green(),
+            category.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn delete_category(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let category = self.get_category_by_id_or_name(args)?;
+
+        // Deleting a category moves every transaction filed under it to its
+        // parent, which is worth a second thought
+        let transactions = self
+            .db
+            .get_filtered_transactions(&TransactionFilter {
+                category_id: Some(category.id.clone()),
+                ..Default::default()
+            })
+            .map_err(Error::from)?;
+        if !transactions.is_empty() && !args.get_flag("force") {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "{} has {} transactions, archive it to keep them where they are or pass --force to move them to its parent category",
+                    category.name,
+                    transactions.len()
+                ),
+            ));
+        }
+
+        self.db.delete_category(&category.id).map_err(Error::from)?;
 
         println!(
             "{} {} {}",
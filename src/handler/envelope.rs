@@ -0,0 +1,256 @@
+use std::io::Error;
+
+use chrono::NaiveDate;
+use colored::{Color, Colorize};
+use tabled::Tabled;
+
+use crate::{
+    db::{
+        category::{category_path, category_tree},
+        envelope::{EnvelopeAssignmentRequest, EnvelopeMoveRequest},
+    },
+    handler::{
+        Handler,
+        budget::{month_name, this_month},
+    },
+    money::{DEFAULT_CURRENCY, Money},
+    utils::{print_colored_table, print_table},
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct EnvelopeMoveResponse {
+    pub id: String,
+    pub month: String,
+    pub from: String,
+    pub to: String,
+    pub amount: Money,
+    pub created_at: String,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct EnvelopeStatusResponse {
+    pub category: String,
+    pub carried: Money,
+    pub assigned: Money,
+    pub moved: Money,
+    pub spent: Money,
+    pub available: Money,
+}
+
+fn print_to_be_assigned(to_be_assigned: &Money) {
+    let line = format!("To be assigned: {}", to_be_assigned);
+    match to_be_assigned.minor {
+        0 => println!("{}", line.green()),
+        minor if minor < 0 => println!("{}", line.red()),
+        _ => println!("{}", line.yellow()),
+    }
+}
+
+impl Handler {
+    pub fn assign_envelope(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let category = match args.get_one::<String>("category_id") {
+            Some(category) => self.find_category(category)?,
+            None => self.select_category(None)?,
+        };
+        let month = args
+            .get_one::<NaiveDate>("month")
+            .cloned()
+            .unwrap_or_else(this_month);
+
+        let existing = self
+            .db
+            .get_envelope_assignment(&category.id, month)
+            .map_err(Error::from)?;
+
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .or(existing
+                .as_ref()
+                .map(|assignment| assignment.amount.currency.clone()))
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+
+        let amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let mut prompt = inquire::Text::new("Amount").with_help_message(
+                    "Enter how much to put in the envelope this month (e.g. 300)",
+                );
+                let current = existing
+                    .as_ref()
+                    .map(|assignment| assignment.amount.to_plain_string());
+                if let Some(current) = &current {
+                    prompt = prompt.with_default(current);
+                }
+
+                Money::parse(&prompt.prompt().unwrap(), &currency).map_err(Error::other)?
+            }
+        };
+
+        let assignment = self
+            .db
+            .assign_envelope(&EnvelopeAssignmentRequest {
+                category_id: category.id,
+                month,
+                amount,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {} {} {} {}",
+            "Successfully assigned".green(),
+            assignment.amount.to_string().green(),
+            "to".green(),
+            category.name.green(),
+            "for".green(),
+            month_name(assignment.month).green()
+        );
+
+        let report = self
+            .db
+            .get_envelope_report(month, &currency)
+            .map_err(Error::from)?;
+        print_to_be_assigned(&report.to_be_assigned());
+
+        Ok(())
+    }
+
+    pub fn move_envelope_money(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let from = match args.get_one::<String>("from") {
+            Some(category) => self.find_category(category)?,
+            None => self.select_category(None)?,
+        };
+        let to = match args.get_one::<String>("to") {
+            Some(category) => self.find_category(category)?,
+            None => self.select_category(None)?,
+        };
+        let month = args
+            .get_one::<NaiveDate>("month")
+            .cloned()
+            .unwrap_or_else(this_month);
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+
+        let amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let amount = inquire::Text::new("Amount")
+                    .with_help_message("Enter how much to move (e.g. 50)")
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &currency).map_err(Error::other)?
+            }
+        };
+        if amount.minor <= 0 {
+            return Err(Error::other("The amount to move must be positive"));
+        }
+
+        let envelope_move = self
+            .db
+            .move_envelope_money(&EnvelopeMoveRequest {
+                from_category_id: from.id,
+                to_category_id: to.id,
+                month,
+                amount,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {} {} {} {}",
+            "Successfully moved".green(),
+            envelope_move.amount.to_string().green(),
+            "from".green(),
+            from.name.green(),
+            "to".green(),
+            to.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_envelope_moves(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let month = args.get_one::<NaiveDate>("month").cloned();
+
+        let moves = self.db.get_envelope_moves(month).map_err(Error::from)?;
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+
+        let responses = moves
+            .into_iter()
+            .filter(|envelope_move| month.is_none_or(|month| envelope_move.month == month))
+            .map(|envelope_move| EnvelopeMoveResponse {
+                id: envelope_move.id,
+                month: month_name(envelope_move.month),
+                from: category_path(&categories, &envelope_move.from_category_id),
+                to: category_path(&categories, &envelope_move.to_category_id),
+                amount: envelope_move.amount,
+                created_at: envelope_move
+                    .created_at
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+            })
+            .collect::<Vec<EnvelopeMoveResponse>>();
+
+        print_table(responses, "Envelope Moves");
+
+        Ok(())
+    }
+
+    pub fn envelope_status(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let month = args
+            .get_one::<NaiveDate>("month")
+            .cloned()
+            .unwrap_or_else(this_month);
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+
+        let report = self
+            .db
+            .get_envelope_report(month, &currency)
+            .map_err(Error::from)?;
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+
+        // Follow the category tree so that parents sit above their children
+        let mut responses = vec![];
+        let mut colors = vec![];
+        for (_, category) in category_tree(&categories) {
+            let Some(envelope) = report
+                .envelopes
+                .iter()
+                .find(|envelope| envelope.category_id == category.id)
+            else {
+                continue;
+            };
+
+            responses.push(EnvelopeStatusResponse {
+                category: format!(
+                    "{} {}",
+                    category.icon,
+                    category_path(&categories, &category.id)
+                ),
+                carried: envelope.carried.clone(),
+                assigned: envelope.assigned.clone(),
+                moved: envelope.moved.clone(),
+                spent: envelope.spent.clone(),
+                available: envelope.available(),
+            });
+            colors.push(envelope.is_overspent().then_some(Color::Red));
+        }
+
+        print_colored_table(
+            responses,
+            &format!("Envelopes {}", month_name(report.month)),
+            &colors,
+        );
+        println!("{} {}", "Income:".bold(), report.income);
+        println!("{} {}", "Assigned:".bold(), report.assigned);
+
+        print_to_be_assigned(&report.to_be_assigned());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    io::{Error, ErrorKind},
+    str::FromStr,
+};
+
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+
+use crate::{
+    db::exchange_rate::ExchangeRate, handler::Handler, money::parse_currency, utils::print_table,
+};
+
+/// Parses one `date,from,to,rate` line of a rates file.
+fn parse_rate_line(line: &str) -> Option<ExchangeRate> {
+    let fields = line.split(',').map(str::trim).collect::<Vec<&str>>();
+    if fields.len() != 4 {
+        return None;
+    }
+
+    Some(ExchangeRate {
+        date: NaiveDate::from_str(fields[0]).ok()?,
+        from_currency: parse_currency(fields[1]).ok()?,
+        to_currency: parse_currency(fields[2]).ok()?,
+        rate: fields[3].parse::<f64>().ok().filter(|rate| *rate > 0.0)?,
+    })
+}
+
+impl Handler {
+    pub fn import_exchange_rates(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let file = args.get_one::<String>("file").unwrap();
+        let contents = std::fs::read_to_string(file)?;
+
+        let mut rates = vec![];
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match parse_rate_line(line) {
+                Some(rate) => rates.push(rate),
+                // A header row is allowed, anything else malformed is not
+                None if number == 0 => continue,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Line {} is not a valid date,from,to,rate row", number + 1),
+                    ));
+                }
+            }
+        }
+
+        self.db.set_exchange_rates(&rates).map_err(Error::from)?;
+
+        println!(
+            "{} {} {}",
+            "Successfully imported".green(),
+            rates.len().to_string().green(),
+            "exchange rates".green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_exchange_rates(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let rates = self.db.get_all_exchange_rates().map_err(Error::from)?;
+
+        print_table(rates, "Exchange Rates");
+
+        Ok(())
+    }
+
+    pub fn set_exchange_rate(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let rate = ExchangeRate {
+            date: args
+                .get_one::<NaiveDate>("date")
+                .cloned()
+                .unwrap_or(Local::now().date_naive()),
+            from_currency: args.get_one::<String>("from").cloned().unwrap(),
+            to_currency: args.get_one::<String>("to").cloned().unwrap(),
+            rate: *args.get_one::<f64>("rate").unwrap(),
+        };
+
+        if rate.rate <= 0.0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The exchange rate must be positive",
+            ));
+        }
+
+        self.db
+            .set_exchange_rates(std::slice::from_ref(&rate))
+            .map_err(Error::from)?;
+
+        println!(
+            "{} 1 {} = {} {}",
+            "Successfully set exchange rate".green(),
+            rate.from_currency,
+            rate.rate,
+            rate.to_currency
+        );
+
+        Ok(())
+    }
+}
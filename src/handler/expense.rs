@@ -0,0 +1,455 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind},
+};
+
+use chrono::{Local, NaiveDate};
+use colored::{Color, Colorize};
+use tabled::Tabled;
+
+use crate::{
+    db::{
+        account::{Account, AccountType},
+        expense::{
+            Payment, SettlementRequest, ShareMethod, ShareRequest, SharedExpenseRequest, settle_up,
+        },
+        users::User,
+    },
+    handler::Handler,
+    money::{DEFAULT_CURRENCY, Money, minor_unit_exponent, parse_minor},
+    utils::{print_colored_table, print_table},
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct SharedExpenseResponse {
+    pub id: String,
+    pub spent_on: NaiveDate,
+    pub description: String,
+    pub paid_by: String,
+    pub amount: Money,
+    pub method: ShareMethod,
+    pub shares: String,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct SharedBalanceResponse {
+    pub user: String,
+    pub balance: Money,
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct SettlementResponse {
+    pub id: String,
+    pub settled_on: NaiveDate,
+    pub from: String,
+    pub to: String,
+    pub amount: Money,
+    pub transfer: String,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct PaymentResponse {
+    pub from: String,
+    pub to: String,
+    pub amount: Money,
+}
+
+impl Handler {
+    fn user_names(&self) -> Result<HashMap<String, String>, Error> {
+        Ok(self
+            .db
+            .get_all_users()
+            .map_err(Error::from)?
+            .into_iter()
+            .map(|user| (user.id, user.name))
+            .collect())
+    }
+
+    /// Reads the value of a share, an amount in `currency` for exact shares
+    /// and a percentage such as `33.33` for percentage shares.
+    fn parse_share_value(method: ShareMethod, value: &str, currency: &str) -> Result<i64, Error> {
+        let exponent = match method {
+            ShareMethod::Exact => minor_unit_exponent(currency),
+            _ => 2,
+        };
+
+        parse_minor(value, exponent).map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                format!("'{}' is not a valid {} share", value, method),
+            )
+        })
+    }
+
+    /// Builds the shares from `--with user[=value]` arguments, or asks who
+    /// takes part and for how much.
+    fn prompt_shares(
+        &self,
+        args: &clap::ArgMatches,
+        method: ShareMethod,
+        currency: &str,
+    ) -> Result<Vec<ShareRequest>, Error> {
+        if let Some(with) = args.get_many::<String>("with") {
+            return with
+                .map(|entry| {
+                    let (user, value) = match entry.split_once('=') {
+                        Some((user, value)) => (user, Some(value)),
+                        None => (entry.as_str(), None),
+                    };
+                    let user = self.find_user(user.trim())?;
+
+                    let value = match (method, value) {
+                        (ShareMethod::Equal, None) => 0,
+                        (ShareMethod::Equal, Some(_)) => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidInput,
+                                "Equal shares take no value, use --split exact or percentage",
+                            ));
+                        }
+                        (_, Some(value)) => Self::parse_share_value(method, value, currency)?,
+                        (_, None) => {
+                            return Err(Error::new(
+                                ErrorKind::InvalidInput,
+                                format!("The {} share of {} is missing", method, user.name),
+                            ));
+                        }
+                    };
+
+                    Ok(ShareRequest {
+                        user_id: user.id,
+                        value,
+                    })
+                })
+                .collect();
+        }
+
+        let users = self.active_users()?;
+        let options = users
+            .iter()
+            .map(|user| user.name.clone())
+            .collect::<Vec<String>>();
+        let selected = inquire::MultiSelect::new("Who shares the expense?", options)
+            .raw_prompt()
+            .unwrap();
+
+        selected
+            .into_iter()
+            .map(|option| {
+                let user = &users[option.index];
+                let value = match method {
+                    ShareMethod::Equal => 0,
+                    _ => {
+                        let help = if method == ShareMethod::Exact {
+                            "Enter the amount they owe (e.g. 30.00)"
+                        } else {
+                            "Enter the percentage they owe (e.g. 33.33)"
+                        };
+                        let value = inquire::Text::new(&format!("Share of {}", user.name))
+                            .with_help_message(help)
+                            .prompt()
+                            .unwrap();
+
+                        Self::parse_share_value(method, &value, currency)?
+                    }
+                };
+
+                Ok(ShareRequest {
+                    user_id: user.id.clone(),
+                    value,
+                })
+            })
+            .collect()
+    }
+
+    pub fn add_shared_expense(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let paid_by = match args.get_one::<String>("paid_by") {
+            Some(paid_by) => self.find_user(paid_by)?,
+            None => match &self.profile {
+                Some(user) => user.clone(),
+                None => self.select_user()?,
+            },
+        };
+
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+        let amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let amount = inquire::Text::new("Amount")
+                    .with_help_message("Enter the total that was paid (e.g. 90.00)")
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &currency).map_err(Error::other)?
+            }
+        };
+
+        let description = args
+            .get_one::<String>("description")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::Text::new("Description")
+                    .with_help_message("Enter what the money was spent on")
+                    .prompt()
+                    .unwrap()
+            });
+
+        let spent_on = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or(Local::now().date_naive());
+
+        let method = args
+            .get_one::<ShareMethod>("method")
+            .cloned()
+            .unwrap_or(ShareMethod::Equal);
+        let shares = self.prompt_shares(args, method, &currency)?;
+
+        let expense = self
+            .db
+            .create_shared_expense(&SharedExpenseRequest {
+                paid_by: paid_by.id,
+                description,
+                amount,
+                method,
+                spent_on,
+                shares,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully created shared expense with id".green(),
+            expense.id.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_shared_expenses(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let names = self.user_names()?;
+        let name = |user_id: &str| names.get(user_id).cloned().unwrap_or(user_id.to_string());
+
+        let expenses = self.db.get_shared_expenses().map_err(Error::from)?;
+        let responses = expenses
+            .into_iter()
+            .map(|expense| SharedExpenseResponse {
+                shares: expense
+                    .shares
+                    .iter()
+                    .map(|share| format!("{} {}", name(&share.user_id), share.amount))
+                    .collect::<Vec<String>>()
+                    .join(", "),
+                paid_by: name(&expense.paid_by),
+                id: expense.id,
+                spent_on: expense.spent_on,
+                description: expense.description,
+                amount: expense.amount,
+                method: expense.method,
+            })
+            .collect::<Vec<SharedExpenseResponse>>();
+
+        print_table(responses, "Shared Expenses");
+
+        Ok(())
+    }
+
+    pub fn delete_shared_expense(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let id = args.get_one::<String>("id").unwrap();
+
+        self.db.delete_shared_expense(id).map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully deleted shared expense".green(),
+            id.green()
+        );
+
+        Ok(())
+    }
+
+    fn payment_responses(
+        &self,
+        payments: &[Payment],
+        names: &HashMap<String, String>,
+    ) -> Vec<PaymentResponse> {
+        let name = |user_id: &str| names.get(user_id).cloned().unwrap_or(user_id.to_string());
+
+        payments
+            .iter()
+            .map(|payment| PaymentResponse {
+                from: name(&payment.from_user_id),
+                to: name(&payment.to_user_id),
+                amount: payment.amount.clone(),
+            })
+            .collect()
+    }
+
+    pub fn shared_balances(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let names = self.user_names()?;
+        let balances = self.db.get_shared_balances().map_err(Error::from)?;
+
+        if balances.is_empty() {
+            println!("{}", "Everyone is square".green());
+            return Ok(());
+        }
+
+        let mut responses = vec![];
+        let mut colors = vec![];
+        for balance in &balances {
+            let owed = balance.balance.minor > 0;
+
+            colors.push(Some(if owed { Color::Green } else { Color::Red }));
+            responses.push(SharedBalanceResponse {
+                user: names
+                    .get(&balance.user_id)
+                    .cloned()
+                    .unwrap_or(balance.user_id.clone()),
+                balance: balance.balance.clone(),
+                status: if owed { "is owed" } else { "owes" }.to_string(),
+            });
+        }
+
+        print_colored_table(responses, "Shared Balances", &colors);
+        print_table(
+            self.payment_responses(&settle_up(&balances), &names),
+            "Payments to Settle Up",
+        );
+
+        Ok(())
+    }
+
+    pub fn list_settlements(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let names = self.user_names()?;
+        let name = |user_id: &str| names.get(user_id).cloned().unwrap_or(user_id.to_string());
+
+        let settlements = self.db.get_settlements().map_err(Error::from)?;
+        let responses = settlements
+            .into_iter()
+            .map(|settlement| SettlementResponse {
+                from: name(&settlement.from_user_id),
+                to: name(&settlement.to_user_id),
+                id: settlement.id,
+                settled_on: settlement.settled_on,
+                amount: settlement.amount,
+                transfer: settlement.transfer_id.unwrap_or_default(),
+            })
+            .collect::<Vec<SettlementResponse>>();
+
+        print_table(responses, "Settlements");
+
+        Ok(())
+    }
+
+    /// The account a user settles from or into in `currency`, asking when
+    /// they hold several. Loans and investment accounts are left out.
+    fn settlement_account(&self, user: &User, currency: &str) -> Result<Account, Error> {
+        let accounts = self
+            .db
+            .get_accounts_by_holder(&user.id)
+            .map_err(Error::from)?
+            .into_iter()
+            .filter(|account| {
+                account.archived_at.is_none()
+                    && account.balance.currency == currency
+                    && !matches!(
+                        account.account_type,
+                        AccountType::Loan | AccountType::Investment
+                    )
+            })
+            .collect::<Vec<Account>>();
+
+        match accounts.len() {
+            0 => Err(Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "{} holds no account in {} to settle up with",
+                    user.name, currency
+                ),
+            )),
+            1 => Ok(accounts[0].clone()),
+            _ => {
+                let options = accounts
+                    .iter()
+                    .map(|account| account.name.clone())
+                    .collect::<Vec<String>>();
+                let option = inquire::Select::new(
+                    &format!("Select the account {} settles up with", user.name),
+                    options,
+                )
+                .raw_prompt()
+                .unwrap();
+
+                Ok(accounts[option.index].clone())
+            }
+        }
+    }
+
+    pub fn settle(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let names = self.user_names()?;
+        let balances = self.db.get_shared_balances().map_err(Error::from)?;
+        let payments = settle_up(&balances);
+
+        if payments.is_empty() {
+            println!("{}", "Everyone is square".green());
+            return Ok(());
+        }
+
+        print_table(
+            self.payment_responses(&payments, &names),
+            "Payments to Settle Up",
+        );
+
+        if args.get_flag("dry_run") {
+            return Ok(());
+        }
+
+        let settled_on = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or(Local::now().date_naive());
+
+        // Every account is picked before anything is recorded, so a user
+        // without one leaves nothing half settled
+        let mut settlements = vec![];
+        for payment in payments {
+            let from_user = self
+                .db
+                .get_user(&payment.from_user_id)
+                .map_err(Error::from)?;
+            let to_user = self.db.get_user(&payment.to_user_id).map_err(Error::from)?;
+            let from_account = self.settlement_account(&from_user, &payment.amount.currency)?;
+            let to_account = self.settlement_account(&to_user, &payment.amount.currency)?;
+
+            settlements.push((
+                from_user,
+                to_user,
+                SettlementRequest {
+                    payment,
+                    settled_on,
+                    from_account_id: from_account.id,
+                    to_account_id: to_account.id,
+                },
+            ));
+        }
+
+        for (from_user, to_user, request) in settlements {
+            let settlement = self.db.create_settlement(&request).map_err(Error::from)?;
+
+            println!(
+                "{} {} {} {} {} (transfer {})",
+                "Successfully recorded".green(),
+                from_user.name,
+                "paying".green(),
+                to_user.name,
+                settlement.amount,
+                settlement.transfer_id.unwrap_or_default()
+            );
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,248 @@
+use std::io::{Error, ErrorKind};
+
+use chrono::{Local, NaiveDate};
+use colored::{Color, Colorize};
+use tabled::Tabled;
+
+use crate::{
+    db::goal::{Goal, GoalProgress, GoalRequest},
+    handler::Handler,
+    money::Money,
+    utils::print_colored_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct GoalResponse {
+    pub id: String,
+    pub name: String,
+    pub account: String,
+    pub saved: Money,
+    pub target: Money,
+    pub progress: String,
+    pub target_date: NaiveDate,
+    /// Needed every month from now on to reach the target on time
+    pub required_monthly: Money,
+    /// The recent monthly growth of the account
+    pub contributing: Money,
+    pub projected: String,
+}
+
+impl Handler {
+    pub fn add_goal(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
+            inquire::Text::new("Name")
+                .with_help_message("Enter what you are saving for")
+                .prompt()
+                .unwrap()
+        });
+
+        let account = match args.get_one::<String>("account_id") {
+            Some(account_id) => self.find_account(account_id)?,
+            None => self.select_account()?,
+        };
+        let currency = account.balance.currency;
+
+        let target = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let amount = inquire::Text::new("Target Amount")
+                    .with_help_message("Enter how much you want to have saved (e.g. 5,000)")
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &currency).map_err(Error::other)?
+            }
+        };
+
+        let target_date = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("Target Date")
+                    .with_help_message("Select the date you want to have the money by")
+                    .with_min_date(Local::now().date_naive())
+                    .prompt()
+                    .unwrap()
+            });
+
+        let goal = self
+            .db
+            .create_goal(&GoalRequest {
+                name,
+                account_id: account.id,
+                target,
+                target_date,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully created goal with id".green(),
+            goal.id.green()
+        );
+
+        Ok(())
+    }
+
+    fn goal_response(&self, progress: GoalProgress) -> Result<GoalResponse, Error> {
+        let account = self
+            .db
+            .get_account(&progress.goal.account_id)
+            .map_err(Error::from)?;
+
+        Ok(GoalResponse {
+            progress: format!("{:.0}%", progress.percent()),
+            required_monthly: progress.required_monthly(),
+            projected: if progress.is_reached() {
+                "reached".to_string()
+            } else {
+                progress
+                    .projected_completion()
+                    .map_or("never".to_string(), |date| date.to_string())
+            },
+            contributing: progress.monthly_contribution,
+            saved: progress.saved,
+            id: progress.goal.id,
+            name: progress.goal.name,
+            account: account.name,
+            target: progress.goal.target,
+            target_date: progress.goal.target_date,
+        })
+    }
+
+    pub fn list_goals(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let today = Local::now().date_naive();
+        let goals = self.db.get_all_goals().map_err(Error::from)?;
+
+        let mut responses = vec![];
+        let mut colors = vec![];
+        for goal in goals {
+            let progress = self
+                .db
+                .get_goal_progress(&goal, today)
+                .map_err(Error::from)?;
+
+            // Behind when the current pace misses the target date
+            colors.push(if progress.is_reached() {
+                Some(Color::Green)
+            } else if progress
+                .projected_completion()
+                .is_none_or(|date| date > goal.target_date)
+            {
+                Some(Color::Yellow)
+            } else {
+                None
+            });
+            responses.push(self.goal_response(progress)?);
+        }
+
+        print_colored_table(responses, "Goals", &colors);
+
+        Ok(())
+    }
+
+    pub fn select_goal(&self, message: &str) -> Result<Goal, Error> {
+        let goals = self.db.get_all_goals().map_err(Error::from)?;
+
+        let options = goals
+            .iter()
+            .map(|goal| format!("{} - {} by {}", goal.name, goal.target, goal.target_date))
+            .collect::<Vec<String>>();
+        let option = inquire::Select::new(message, options).raw_prompt().unwrap();
+
+        Ok(goals[option.index].clone())
+    }
+
+    /// Looks a goal up by id, falling back to its name.
+    pub fn find_goal(&self, id_or_name: &str) -> Result<Goal, Error> {
+        if let Ok(goal) = self.db.get_goal(id_or_name) {
+            return Ok(goal);
+        }
+
+        self.db
+            .get_all_goals()
+            .map_err(Error::from)?
+            .into_iter()
+            .find(|goal| goal.name.eq_ignore_ascii_case(id_or_name))
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("No goal found matching '{}'", id_or_name),
+                )
+            })
+    }
+
+    fn get_goal_by_arg(&self, args: &clap::ArgMatches, message: &str) -> Result<Goal, Error> {
+        match args.get_one::<String>("id") {
+            Some(id_or_name) => self.find_goal(id_or_name),
+            None => self.select_goal(message),
+        }
+    }
+
+    pub fn edit_goal(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let mut goal = self.get_goal_by_arg(args, "Select a goal to edit")?;
+
+        if let Some(account_id) = args.get_one::<String>("account_id") {
+            let account = self.find_account(account_id)?;
+            goal.account_id = account.id;
+            goal.target.currency = account.balance.currency;
+        }
+
+        goal.name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
+            inquire::Text::new("New Name")
+                .with_help_message("Enter what you are saving for")
+                .with_default(&goal.name)
+                .prompt()
+                .unwrap()
+        });
+
+        let currency = goal.target.currency.clone();
+        goal.target = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let amount = inquire::Text::new("New Target Amount")
+                    .with_help_message("Enter how much you want to have saved (e.g. 5,000)")
+                    .with_default(&goal.target.to_plain_string())
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &currency).map_err(Error::other)?
+            }
+        };
+
+        goal.target_date = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("New Target Date")
+                    .with_help_message("Select the date you want to have the money by")
+                    .with_default(goal.target_date)
+                    .prompt()
+                    .unwrap()
+            });
+
+        self.db.update_goal(&goal.id, &goal).map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully updated goal".green(),
+            goal.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn delete_goal(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let goal = self.get_goal_by_arg(args, "Select a goal to delete")?;
+
+        self.db.delete_goal(&goal.id).map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully deleted goal".green(),
+            goal.name.green()
+        );
+
+        Ok(())
+    }
+}
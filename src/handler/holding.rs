@@ -0,0 +1,334 @@
+use std::{
+    collections::BTreeMap,
+    io::{Error, ErrorKind},
+};
+
+use chrono::{Local, NaiveDate};
+use colored::{Color, Colorize};
+use tabled::Tabled;
+
+use crate::{
+    db::{
+        account::AccountType,
+        holding::{CostBasisMethod, TradeKind, TradeRequest},
+    },
+    handler::Handler,
+    money::Money,
+    utils::{print_colored_table, print_table},
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct TradeResponse {
+    pub id: String,
+    pub traded_on: NaiveDate,
+    pub account: String,
+    pub symbol: String,
+    pub kind: TradeKind,
+    pub quantity: String,
+    pub amount: Money,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct HoldingResponse {
+    pub account: String,
+    pub symbol: String,
+    pub quantity: String,
+    pub lots: usize,
+    /// When the oldest lot still held was bought
+    pub held_since: NaiveDate,
+    pub cost: Money,
+    pub price: String,
+    pub market_value: String,
+    pub unrealized: String,
+}
+
+#[derive(Debug, Clone, Tabled)]
+pub struct YearlyGainsResponse {
+    pub year: i32,
+    pub proceeds: Money,
+    pub cost: Money,
+    pub realized: Money,
+    pub dividends: Money,
+}
+
+/// Quantities without trailing zeros, so whole units read as `10`.
+fn format_quantity(quantity: f64) -> String {
+    let formatted = format!("{:.6}", quantity);
+
+    formatted
+        .trim_end_matches('0')
+        .trim_end_matches('.')
+        .to_string()
+}
+
+fn cost_basis_method(args: &clap::ArgMatches) -> CostBasisMethod {
+    args.get_one::<CostBasisMethod>("method")
+        .cloned()
+        .unwrap_or(CostBasisMethod::Fifo)
+}
+
+impl Handler {
+    pub fn add_trade(&self, args: &clap::ArgMatches, kind: TradeKind) -> Result<(), Error> {
+        let account = match args.get_one::<String>("account_id") {
+            Some(account_id) => self.find_account(account_id)?,
+            None => self.select_account()?,
+        };
+        if account.account_type != AccountType::Investment {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Trades can only be recorded on investment accounts, {} is a {} account",
+                    account.name, account.account_type
+                ),
+            ));
+        }
+
+        let security = self.get_security_by_arg(args, "Select a security")?;
+
+        let traded_on = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("Date")
+                    .with_help_message("Select the day of the trade")
+                    .prompt()
+                    .unwrap()
+            });
+
+        let quantity = match kind {
+            TradeKind::Dividend => 0.0,
+            _ => match args.get_one::<f64>("quantity") {
+                Some(quantity) => *quantity,
+                None => {
+                    let help = if kind == TradeKind::Split {
+                        "Enter how many units every unit became (e.g. 2 for a 2-for-1 split)"
+                    } else {
+                        "Enter the number of units (e.g. 10.5)"
+                    };
+                    let quantity = inquire::Text::new("Quantity")
+                        .with_help_message(help)
+                        .prompt()
+                        .unwrap();
+
+                    quantity
+                        .trim()
+                        .parse::<f64>()
+                        .map_err(|_| Error::other(format!("'{}' is not a quantity", quantity)))?
+                }
+            },
+        };
+        if kind != TradeKind::Dividend && quantity <= 0.0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The quantity must be positive",
+            ));
+        }
+
+        let amount = match kind {
+            TradeKind::Split => Money::new(0, &security.currency),
+            _ => match args.get_one::<String>("amount") {
+                Some(amount) => Money::parse(amount, &security.currency).map_err(Error::other)?,
+                None => {
+                    let help = match kind {
+                        TradeKind::Buy => "Enter the total paid, fees included (e.g. 1,250.00)",
+                        TradeKind::Sell => "Enter the total received, after fees (e.g. 1,250.00)",
+                        _ => "Enter the dividend paid out (e.g. 12.50)",
+                    };
+                    let amount = inquire::Text::new("Amount")
+                        .with_help_message(help)
+                        .prompt()
+                        .unwrap();
+
+                    Money::parse(&amount, &security.currency).map_err(Error::other)?
+                }
+            },
+        };
+
+        let trade = self
+            .db
+            .create_trade(&TradeRequest {
+                account_id: account.id,
+                security_id: security.id,
+                kind,
+                traded_on,
+                quantity,
+                amount,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {}",
+            format!("Successfully recorded {}", kind).green(),
+            security.symbol.green(),
+            trade.id.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_trades(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account_id = match args.get_one::<String>("account_id") {
+            Some(account_id) => Some(self.find_account(account_id)?.id),
+            None => None,
+        };
+
+        let trades = self
+            .db
+            .get_trades(account_id.as_deref())
+            .map_err(Error::from)?;
+
+        let mut responses = vec![];
+        for trade in trades {
+            let account = self
+                .db
+                .get_account(&trade.account_id)
+                .map_err(Error::from)?;
+            let security = self
+                .db
+                .get_security(&trade.security_id)
+                .map_err(Error::from)?;
+
+            responses.push(TradeResponse {
+                id: trade.id,
+                traded_on: trade.traded_on,
+                account: account.name,
+                symbol: security.symbol,
+                kind: trade.kind,
+                quantity: format_quantity(trade.quantity),
+                amount: trade.amount,
+            });
+        }
+
+        print_table(responses, "Trades");
+
+        Ok(())
+    }
+
+    pub fn delete_trade(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let id = args.get_one::<String>("id").unwrap();
+
+        self.db.delete_trade(id).map_err(Error::from)?;
+
+        println!("{} {}", "Successfully deleted trade".green(), id.green());
+
+        Ok(())
+    }
+
+    pub fn list_holdings(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account_id = match args.get_one::<String>("account_id") {
+            Some(account_id) => Some(self.find_account(account_id)?.id),
+            None => None,
+        };
+        let method = cost_basis_method(args);
+        let on = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or(Local::now().date_naive());
+
+        let holdings = self
+            .db
+            .get_holdings(account_id.as_deref(), method, on)
+            .map_err(Error::from)?;
+
+        // Totals are kept per currency as securities may trade in several
+        let mut totals = BTreeMap::<String, (i64, i64, bool)>::new();
+        let mut responses = vec![];
+        let mut colors = vec![];
+        for holding in holdings {
+            let account = self
+                .db
+                .get_account(&holding.account_id)
+                .map_err(Error::from)?;
+            let cost = holding.cost();
+            let market_value = holding.market_value();
+            let unrealized = holding.unrealized_gain();
+
+            let total = totals
+                .entry(holding.security.currency.clone())
+                .or_insert((0, 0, true));
+            total.0 += cost.minor;
+            match &market_value {
+                Some(value) => total.1 += value.minor,
+                None => total.2 = false,
+            }
+
+            colors.push(unrealized.as_ref().map(|gain| {
+                if gain.minor < 0 {
+                    Color::Red
+                } else {
+                    Color::Green
+                }
+            }));
+            responses.push(HoldingResponse {
+                account: account.name,
+                symbol: holding.security.symbol.clone(),
+                quantity: format_quantity(holding.quantity()),
+                lots: holding.lots.len(),
+                held_since: holding.lots[0].bought_on,
+                cost,
+                price: holding.price.as_ref().map_or("n/a".to_string(), |price| {
+                    format!("{} ({})", price.price, price.priced_on)
+                }),
+                market_value: market_value.map_or("n/a".to_string(), |value| value.to_string()),
+                unrealized: unrealized.map_or("n/a".to_string(), |gain| gain.to_string()),
+            });
+        }
+
+        print_colored_table(
+            responses,
+            &format!("Holdings on {} ({})", on, method),
+            &colors,
+        );
+
+        for (currency, (cost, value, priced)) in totals {
+            let value = priced.then(|| Money::new(value, &currency));
+
+            println!("{} {}", "Cost:".bold(), Money::new(cost, &currency));
+            println!(
+                "{} {}",
+                "Market Value:".bold(),
+                value
+                    .as_ref()
+                    .map_or("n/a (missing prices)".to_string(), |value| value
+                        .to_string())
+            );
+            println!(
+                "{} {}",
+                "Unrealized Gain:".bold(),
+                value.map_or("n/a (missing prices)".to_string(), |value| {
+                    Money::new(value.minor - cost, &currency).to_string()
+                })
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn list_gains(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account_id = match args.get_one::<String>("account_id") {
+            Some(account_id) => Some(self.find_account(account_id)?.id),
+            None => None,
+        };
+        let method = cost_basis_method(args);
+
+        let gains = self
+            .db
+            .get_yearly_gains(account_id.as_deref(), method)
+            .map_err(Error::from)?;
+
+        let responses = gains
+            .into_iter()
+            .map(|gains| YearlyGainsResponse {
+                year: gains.year,
+                realized: gains.realized(),
+                proceeds: gains.proceeds,
+                cost: gains.cost,
+                dividends: gains.dividends,
+            })
+            .collect::<Vec<YearlyGainsResponse>>();
+
+        print_table(responses, &format!("Realized Gains per Year ({})", method));
+
+        Ok(())
+    }
+}
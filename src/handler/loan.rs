@@ -0,0 +1,128 @@
+use std::io::Error;
+
+use chrono::NaiveDate;
+use colored::{Color, Colorize};
+use tabled::Tabled;
+
+use crate::{
+    db::loan::{AmortizationRow, LoanSchedule},
+    handler::Handler,
+    money::Money,
+    utils::print_colored_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct AmortizationResponse {
+    #[tabled(rename = "#")]
+    pub number: u32,
+    pub date: NaiveDate,
+    pub payment: Money,
+    pub interest: Money,
+    pub principal: Money,
+    pub remaining: Money,
+    pub status: String,
+}
+
+impl From<AmortizationRow> for AmortizationResponse {
+    fn from(row: AmortizationRow) -> Self {
+        Self {
+            number: row.number,
+            date: row.date,
+            payment: row.payment,
+            interest: row.interest,
+            principal: row.principal,
+            remaining: row.remaining,
+            status: if row.paid { "paid" } else { "scheduled" }.to_string(),
+        }
+    }
+}
+
+fn payoff_text(schedule: &LoanSchedule) -> String {
+    schedule
+        .payoff_date()
+        .map_or("never".to_string(), |date| date.to_string())
+}
+
+impl Handler {
+    pub fn account_amortization(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account = self.get_account_by_id_or_name(args)?;
+        let currency = account.balance.currency.clone();
+        let extra = match args.get_one::<String>("extra") {
+            Some(extra) => Money::parse(extra, &currency).map_err(Error::other)?,
+            None => Money::new(0, &currency),
+        };
+
+        let schedule = self
+            .db
+            .get_loan_schedule(&account.id, &extra)
+            .map_err(Error::from)?;
+
+        let colors = schedule
+            .rows
+            .iter()
+            .map(|row| row.paid.then_some(Color::Green))
+            .collect::<Vec<Option<Color>>>();
+        let responses = schedule
+            .rows
+            .iter()
+            .cloned()
+            .map(AmortizationResponse::from)
+            .collect::<Vec<AmortizationResponse>>();
+        print_colored_table(
+            responses,
+            &format!("Amortization Schedule of {}", account.name),
+            &colors,
+        );
+
+        let terms = &schedule.terms;
+        println!(
+            "{} {} at {}% over {} months from {}",
+            "Loan:".bold(),
+            terms.principal,
+            terms.rate,
+            terms.term_months,
+            terms.start_date
+        );
+        println!("{} {}", "Monthly Payment:".bold(), schedule.monthly_payment);
+        if schedule.extra.minor > 0 {
+            println!("{} {}", "Extra Payment:".bold(), schedule.extra);
+        }
+        println!(
+            "{} {}",
+            "Remaining Principal:".bold(),
+            schedule.remaining_principal()
+        );
+        println!("{} {}", "Interest Paid:".bold(), schedule.interest_paid());
+        println!(
+            "{} {}",
+            "Interest Remaining:".bold(),
+            schedule.interest_remaining()
+        );
+        println!("{} {}", "Payoff Date:".bold(), payoff_text(&schedule));
+
+        if extra.minor > 0 {
+            let without_extra = self
+                .db
+                .get_loan_schedule(&account.id, &Money::new(0, &currency))
+                .map_err(Error::from)?;
+            let saved = Money::new(
+                without_extra.interest_remaining().minor - schedule.interest_remaining().minor,
+                &currency,
+            );
+
+            println!(
+                "{}",
+                format!(
+                    "Paying {} extra every month moves the payoff from {} to {} and saves {} in interest",
+                    extra,
+                    payoff_text(&without_extra),
+                    payoff_text(&schedule),
+                    saved
+                )
+                .green()
+            );
+        }
+
+        Ok(())
+    }
+}
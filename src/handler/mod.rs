@@ -1,16 +1,56 @@
-use crate::db::Db;
+use chrono::{DateTime, Local};
+
+use crate::db::{Db, users::User};
 
 mod user;
 mod account;
+mod attachment;
+mod budget;
 mod category;
+mod envelope;
+mod exchange_rate;
+mod expense;
+mod goal;
+mod holding;
+mod loan;
+mod networth;
+mod payee;
+mod reconciliation;
+mod schedule;
+mod security;
+mod statement;
+mod tag;
 mod transaction;
 
+/// A name marked as archived when it is, for listings that include archived rows.
+fn archived_name(name: &str, archived_at: &Option<DateTime<Local>>) -> String {
+    match archived_at {
+        Some(_) => format!("{} (archived)", name),
+        None => name.to_string(),
+    }
+}
+
 pub struct Handler {
     db: Db,
+    /// The user commands act as, narrowing listings and reports down to the
+    /// accounts they hold. `None` covers everyone.
+    profile: Option<User>,
 }
 
 impl Handler {
     pub fn new(db: Db) -> Self {
-        Self { db }
+        Self { db, profile: None }
+    }
+
+    fn profile_id(&self) -> Option<&str> {
+        self.profile.as_ref().map(|user| user.id.as_str())
+    }
+
+    /// A table title naming the active user, if there is one.
+    fn profile_title(&self, title: &str) -> String {
+        match &self.profile {
+            Some(user) => format!("{} of {}", title, user.name),
+            None => title.to_string(),
+        }
     }
 }
@@ -0,0 +1,137 @@
+use std::io::Error;
+
+use chrono::{Local, NaiveDate};
+use colored::{Color, Colorize};
+use tabled::Tabled;
+
+use crate::{
+    db::networth::NetWorth,
+    handler::{Handler, budget::month_name},
+    money::{DEFAULT_CURRENCY, Money},
+    utils::print_colored_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct NetWorthHistoryResponse {
+    pub month: String,
+    pub on: NaiveDate,
+    pub assets: Money,
+    pub liabilities: Money,
+    pub net_worth: Money,
+    /// Against the month before
+    pub change: String,
+    pub source: String,
+}
+
+fn print_net_worth(net_worth: &NetWorth) {
+    println!("{} {}", "Assets:".bold(), net_worth.assets);
+    println!("{} {}", "Liabilities:".bold(), net_worth.liabilities);
+    println!("{} {}", "Net Worth:".bold(), net_worth.net());
+}
+
+impl Handler {
+    pub fn show_net_worth(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+        let on = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or(Local::now().date_naive());
+
+        // Snapshots always cover everyone, whoever is active
+        let user = match args.get_one::<String>("user") {
+            Some(user) => Some(self.find_user(user)?),
+            None if args.get_flag("snapshot") => None,
+            None => self.profile.clone(),
+        };
+
+        let net_worth = if args.get_flag("snapshot") {
+            let net_worth = self
+                .db
+                .record_net_worth_snapshot(&currency, on)
+                .map_err(Error::from)?;
+
+            println!(
+                "{} {}",
+                "Successfully recorded net worth snapshot for".green(),
+                on.to_string().green()
+            );
+            net_worth
+        } else {
+            self.db
+                .get_net_worth(&currency, on, user.as_ref().map(|user| user.id.as_str()))
+                .map_err(Error::from)?
+        };
+
+        if let Some(user) = user {
+            println!("{} {}", "Holder:".bold(), user.name);
+        }
+        print_net_worth(&net_worth);
+
+        Ok(())
+    }
+
+    pub fn net_worth_history(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+        let months = *args.get_one::<u32>("months").unwrap_or(&12);
+        let today = Local::now().date_naive();
+        let user = match args.get_one::<String>("user") {
+            Some(user) => Some(self.find_user(user)?),
+            None => self.profile.clone(),
+        };
+
+        let history = self
+            .db
+            .get_net_worth_history(
+                &currency,
+                months,
+                today,
+                user.as_ref().map(|user| user.id.as_str()),
+            )
+            .map_err(Error::from)?;
+
+        let mut previous: Option<Money> = None;
+        let mut responses = vec![];
+        let mut colors = vec![];
+        for point in history {
+            let net = point.net_worth.net();
+            let change = previous
+                .as_ref()
+                .map(|previous| Money::new(net.minor - previous.minor, &currency));
+
+            colors.push(change.as_ref().and_then(|change| match change.minor {
+                minor if minor > 0 => Some(Color::Green),
+                minor if minor < 0 => Some(Color::Red),
+                _ => None,
+            }));
+            responses.push(NetWorthHistoryResponse {
+                month: month_name(point.month),
+                on: point.net_worth.on,
+                assets: point.net_worth.assets,
+                liabilities: point.net_worth.liabilities,
+                net_worth: net.clone(),
+                change: change.map_or("".to_string(), |change| change.to_string()),
+                source: if point.snapshot {
+                    "snapshot"
+                } else {
+                    "reconstructed"
+                }
+                .to_string(),
+            });
+            previous = Some(net);
+        }
+
+        let title = match user {
+            Some(user) => format!("Net Worth History of {}", user.name),
+            None => "Net Worth History".to_string(),
+        };
+        print_colored_table(responses, &title, &colors);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use std::io::{Error, ErrorKind};
+
+use colored::Colorize;
+use tabled::Tabled;
+
+use crate::{
+    db::{
+        category::category_path,
+        payee::{Payee, PayeeRequest},
+    },
+    handler::Handler,
+    utils::print_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct PayeeResponse {
+    pub id: String,
+    pub name: String,
+    pub default_category: String,
+}
+
+impl Handler {
+    pub fn add_payee(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
+            inquire::Text::new("Name")
+                .with_help_message("Enter the name of the payee")
+                .prompt()
+                .unwrap()
+        });
+
+        if self
+            .db
+            .get_payee_by_name(&name)
+            .map_err(Error::from)?
+            .is_some()
+        {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!("A payee named '{}' already exists", name.trim()),
+            ));
+        }
+
+        let default_category_id = match args.get_one::<String>("category_id") {
+            Some(category) => Some(self.find_category(category)?.id),
+            None => None,
+        };
+
+        let payee = self
+            .db
+            .create_payee(&PayeeRequest {
+                name,
+                default_category_id,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully created payee with id".green(),
+            payee.id.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_payees(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let payees = self.db.get_all_payees().map_err(Error::from)?;
+        let categories = self.db.get_all_categories().map_err(Error::from)?;
+
+        let responses = payees
+            .into_iter()
+            .map(|payee| PayeeResponse {
+                id: payee.id,
+                name: payee.name,
+                default_category: payee
+                    .default_category_id
+                    .map(|id| category_path(&categories, &id))
+                    .unwrap_or_default(),
+            })
+            .collect::<Vec<PayeeResponse>>();
+
+        print_table(responses, "Payees");
+
+        Ok(())
+    }
+
+    pub fn select_payee(&self, message: &str) -> Result<Payee, Error> {
+        let payees = self.db.get_all_payees().map_err(Error::from)?;
+
+        let options = payees
+            .iter()
+            .map(|payee| payee.name.clone())
+            .collect::<Vec<String>>();
+        let option = inquire::Select::new(message, options).raw_prompt().unwrap();
+
+        Ok(payees[option.index].clone())
+    }
+
+    /// Looks a payee up by id, falling back to its name.
+    pub fn find_payee(&self, id_or_name: &str) -> Result<Payee, Error> {
+        if let Ok(payee) = self.db.get_payee(id_or_name) {
+            return Ok(payee);
+        }
+
+        self.db
+            .get_payee_by_name(id_or_name)
+            .map_err(Error::from)?
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("No payee found matching '{}'", id_or_name),
+                )
+            })
+    }
+
+    fn get_payee_by_arg(
+        &self,
+        args: &clap::ArgMatches,
+        arg: &str,
+        message: &str,
+    ) -> Result<Payee, Error> {
+        match args.get_one::<String>(arg) {
+            Some(id_or_name) => self.find_payee(id_or_name),
+            None => self.select_payee(message),
+        }
+    }
+
+    /// Asks who a transaction was with, suggesting known payees as the user types.
+    /// Unknown names become new payees, and an empty answer means no payee.
+    pub fn prompt_payee(&self, current: Option<&Payee>) -> Result<Option<Payee>, Error> {
+        let names = self
+            .db
+            .get_all_payees()
+            .map_err(Error::from)?
+            .into_iter()
+            .map(|payee| payee.name)
+            .collect::<Vec<String>>();
+
+        let suggest = move |input: &str| {
+            let input = input.trim().to_lowercase();
+            Ok(names
+                .iter()
+                .filter(|name| !input.is_empty() && name.to_lowercase().contains(&input))
+                .cloned()
+                .collect::<Vec<String>>())
+        };
+
+        let mut prompt = inquire::Text::new("Payee")
+            .with_help_message("Who was paid or paid you, leave empty for none")
+            .with_autocomplete(suggest);
+        if let Some(current) = current {
+            prompt = prompt.with_default(&current.name);
+        }
+        let name = prompt.prompt().unwrap();
+
+        self.get_or_create_payee(&name)
+    }
+
+    pub fn get_or_create_payee(&self, name: &str) -> Result<Option<Payee>, Error> {
+        if name.trim().is_empty() {
+            return Ok(None);
+        }
+
+        if let Some(payee) = self.db.get_payee_by_name(name).map_err(Error::from)? {
+            return Ok(Some(payee));
+        }
+
+        let payee = self
+            .db
+            .create_payee(&PayeeRequest {
+                name: name.to_string(),
+                default_category_id: None,
+            })
+            .map_err(Error::from)?;
+
+        println!("{} {}", "Created new payee".green(), payee.name.green());
+
+        Ok(Some(payee))
+    }
+
+    pub fn update_payee(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let mut payee = self.get_payee_by_arg(args, "id", "Select a payee to update")?;
+
+        let new_name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
+            inquire::Text::new("New Name")
+                .with_help_message("Enter the new name of the payee")
+                .with_default(&payee.name)
+                .prompt()
+                .unwrap()
+        });
+
+        if let Some(existing) = self.db.get_payee_by_name(&new_name).map_err(Error::from)?
+            && existing.id != payee.id
+        {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                format!(
+                    "A payee named '{}' already exists, merge the two instead",
+                    existing.name
+                ),
+            ));
+        }
+
+        let default_category_id = match args.get_one::<String>("category_id") {
+            Some(category) => Some(self.find_category(category)?.id),
+            None => {
+                let set_default = inquire::Confirm::new("Set a default category?")
+                    .with_default(payee.default_category_id.is_some())
+                    .prompt()
+                    .unwrap();
+
+                if set_default {
+                    Some(
+                        self.select_category(payee.default_category_id.as_deref())?
+                            .id,
+                    )
+                } else {
+                    None
+                }
+            }
+        };
+
+        payee.name = new_name;
+        payee.default_category_id = default_category_id;
+
+        self.db
+            .update_payee(&payee.id, &payee)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully updated payee".green(),
+            payee.name.trim().green()
+        );
+
+        Ok(())
+    }
+
+    pub fn merge_payees(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let from = self.get_payee_by_arg(args, "from", "Select the payee to merge away")?;
+        let into = self.get_payee_by_arg(args, "into", "Select the payee to keep")?;
+
+        if from.id == into.id {
+            return Err(Error::other("Cannot merge a payee into itself"));
+        }
+
+        self.db
+            .merge_payees(&from.id, &into.id)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {} {}",
+            "Successfully merged payee".green(),
+            from.name,
+            "into".green(),
+            into.name
+        );
+
+        Ok(())
+    }
+
+    pub fn delete_payee(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let payee = self.get_payee_by_arg(args, "id", "Select a payee to delete")?;
+
+        self.db.delete_payee(&payee.id).map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully deleted payee".green(),
+            payee.name.green()
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+use std::io::Error;
+
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+
+use crate::{
+    db::{
+        reconciliation::{Reconciliation, ReconciliationRequest},
+        transaction::{Transaction, TransactionStatus},
+    },
+    handler::Handler,
+    money::Money,
+    utils::print_table,
+};
+
+const FINISH: &str = "Finish reconciliation";
+const CANCEL: &str = "Cancel";
+
+impl Handler {
+    /// Walks through the unreconciled transactions of an account up to the
+    /// statement date, letting the user tick off the ones on the statement until
+    /// the ledger matches its closing balance.
+    pub fn reconcile_account(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account = self.get_account_by_id_or_name(args)?;
+        let account_type = account.account_type;
+        let currency = account.balance.currency.clone();
+
+        let statement_date = args
+            .get_one::<NaiveDate>("statement_date")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("Statement Date")
+                    .with_help_message("Select the last day covered by the statement")
+                    .with_default(Local::now().date_naive())
+                    .prompt()
+                    .unwrap()
+            });
+
+        let printed_balance = match args.get_one::<String>("statement_balance") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let balance = inquire::Text::new("Statement Balance")
+                    .with_help_message(if account_type.is_liability() {
+                        "Enter the amount owed at the close of the statement"
+                    } else {
+                        "Enter the closing balance printed on the statement"
+                    })
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&balance, &currency).map_err(Error::other)?
+            }
+        };
+        // Statements show what is owed on a liability as a positive balance,
+        // while the ledger keeps it negative
+        let statement_balance = account_type.display_amount(&printed_balance);
+
+        let reconciled = self
+            .db
+            .get_reconciled_balance(&account.id)
+            .map_err(Error::from)?;
+        let candidates = self
+            .db
+            .get_transactions_by_account(&account.id)
+            .map_err(Error::from)?
+            .into_iter()
+            .filter(|transaction| {
+                transaction.posted_on <= statement_date
+                    && matches!(
+                        transaction.status,
+                        TransactionStatus::Pending | TransactionStatus::Cleared
+                    )
+            })
+            .collect::<Vec<Transaction>>();
+
+        // Whatever the bank already cleared is most likely on the statement
+        let mut selected = candidates
+            .iter()
+            .map(|transaction| transaction.status == TransactionStatus::Cleared)
+            .collect::<Vec<bool>>();
+        let mut cursor = 0;
+
+        loop {
+            let ledger = reconciled.minor
+                + candidates
+                    .iter()
+                    .zip(&selected)
+                    .filter(|(_, selected)| **selected)
+                    .map(|(transaction, _)| transaction.signed_amount())
+                    .sum::<i64>();
+            let difference = account_type
+                .display_amount(&Money::new(statement_balance.minor - ledger, &currency));
+
+            if difference.minor == 0 {
+                println!(
+                    "{} {}",
+                    "Difference:".green(),
+                    difference.to_string().green()
+                );
+            } else {
+                println!(
+                    "{} {}",
+                    "Difference:".yellow(),
+                    difference.to_string().yellow()
+                );
+            }
+
+            let mut options = candidates
+                .iter()
+                .zip(&selected)
+                .map(|(transaction, selected)| {
+                    format!(
+                        "[{}] {} {} {}",
+                        if *selected { "x" } else { " " },
+                        transaction.posted_on,
+                        transaction.description,
+                        account_type
+                            .display_amount(&Money::new(transaction.signed_amount(), &currency))
+                    )
+                })
+                .collect::<Vec<String>>();
+            options.push(FINISH.to_string());
+            options.push(CANCEL.to_string());
+
+            let option = inquire::Select::new("Toggle the transactions on the statement", options)
+                .with_starting_cursor(cursor)
+                .raw_prompt()
+                .unwrap();
+            cursor = option.index;
+
+            if option.value == CANCEL {
+                println!("{}", "Reconciliation cancelled".yellow());
+                return Ok(());
+            } else if option.value == FINISH {
+                if difference.minor == 0 {
+                    break;
+                }
+                println!("{}", "The difference has to be zero before finishing".red());
+            } else {
+                selected[option.index] = !selected[option.index];
+            }
+        }
+
+        let transaction_ids = candidates
+            .iter()
+            .zip(&selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(transaction, _)| transaction.id.clone())
+            .collect::<Vec<String>>();
+
+        let reconciliation = self
+            .db
+            .reconcile_account(&ReconciliationRequest {
+                account_id: account.id.clone(),
+                statement_date,
+                statement_balance,
+                transaction_ids,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {} {}",
+            "Successfully reconciled".green(),
+            reconciliation.transactions.to_string().green(),
+            "transaction(s) of".green(),
+            account.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_reconciliations(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account = self.get_account_by_id_or_name(args)?;
+
+        let reconciliations = self
+            .db
+            .get_account_reconciliations(&account.id)
+            .map_err(Error::from)?
+            .into_iter()
+            .map(|mut reconciliation| {
+                reconciliation.statement_balance = account
+                    .account_type
+                    .display_amount(&reconciliation.statement_balance);
+                reconciliation
+            })
+            .collect::<Vec<Reconciliation>>();
+
+        print_table(reconciliations, "Reconciliations");
+
+        Ok(())
+    }
+}
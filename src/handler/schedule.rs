@@ -0,0 +1,387 @@
+use std::io::Error;
+
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+use tabled::Tabled;
+
+use crate::{
+    db::schedule::{Frequency, Schedule, ScheduleRequest},
+    handler::Handler,
+    money::Money,
+    utils::print_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct ScheduleResponse {
+    pub id: String,
+    pub description: String,
+    pub account_id: String,
+    pub amount: Money,
+    pub transaction_type: String,
+    pub rule: String,
+    pub next: String,
+    pub ends: String,
+    pub skips: String,
+}
+
+impl Handler {
+    pub fn add_schedule(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account = match args.get_one::<String>("account_id") {
+            Some(account_id) => self.find_account(account_id)?,
+            None => self.select_account()?,
+        };
+        let currency = account.balance.currency;
+
+        let amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let amount = inquire::Text::new("Amount")
+                    .with_help_message("Enter the amount posted each time (e.g. 12.34)")
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &currency).map_err(Error::other)?
+            }
+        };
+
+        let transaction_type = args.get_one::<String>("type").cloned().unwrap_or_else(|| {
+            let options = vec!["credit".to_string(), "debit".to_string()];
+            inquire::Select::new("Transaction Type", options)
+                .prompt()
+                .unwrap()
+        });
+
+        let payee = match args.get_one::<String>("payee") {
+            Some(payee) => match self.find_payee(payee) {
+                Ok(payee) => Some(payee),
+                Err(_) => self.get_or_create_payee(payee)?,
+            },
+            None => self.prompt_payee(None)?,
+        };
+
+        let description = args
+            .get_one::<String>("description")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::Text::new("Description")
+                    .with_help_message("Enter the description of the transactions")
+                    .prompt()
+                    .unwrap()
+            });
+
+        let category_id = match args.get_one::<String>("category_id") {
+            Some(category) => self.find_category(category)?.id,
+            None => {
+                let default_category_id = payee
+                    .as_ref()
+                    .and_then(|payee| payee.default_category_id.clone());
+                self.select_category(default_category_id.as_deref())?.id
+            }
+        };
+
+        let frequency = self.prompt_frequency(args, None)?;
+        let interval = self.prompt_interval(args, None)?;
+
+        let start_date = args
+            .get_one::<NaiveDate>("start")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("Start Date")
+                    .with_help_message("Select the date of the first occurrence")
+                    .with_default(Local::now().date_naive())
+                    .prompt()
+                    .unwrap()
+            });
+
+        let end_date = self.prompt_end_date(args, None, start_date)?;
+
+        let schedule = self
+            .db
+            .create_schedule(&ScheduleRequest {
+                account_id: account.id,
+                amount,
+                transaction_type,
+                description,
+                category_id,
+                payee_id: payee.map(|payee| payee.id),
+                frequency,
+                interval,
+                start_date,
+                end_date,
+                skips: args
+                    .get_many::<NaiveDate>("skip")
+                    .map(|skips| skips.cloned().collect())
+                    .unwrap_or_default(),
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully created schedule with id".green(),
+            schedule.id.green()
+        );
+
+        Ok(())
+    }
+
+    fn prompt_frequency(
+        &self,
+        args: &clap::ArgMatches,
+        current: Option<Frequency>,
+    ) -> Result<Frequency, Error> {
+        if let Some(frequency) = args.get_one::<Frequency>("frequency") {
+            return Ok(*frequency);
+        }
+
+        let starting_cursor = current
+            .and_then(|current| {
+                Frequency::ALL
+                    .iter()
+                    .position(|frequency| *frequency == current)
+            })
+            .unwrap_or(2);
+
+        Ok(inquire::Select::new("Frequency", Frequency::ALL.to_vec())
+            .with_starting_cursor(starting_cursor)
+            .prompt()
+            .unwrap())
+    }
+
+    fn prompt_interval(&self, args: &clap::ArgMatches, current: Option<u32>) -> Result<u32, Error> {
+        if let Some(interval) = args.get_one::<u32>("every") {
+            return Ok(*interval);
+        }
+
+        let interval = inquire::Text::new("Every")
+            .with_help_message("Repeat every this many days, weeks, months or years")
+            .with_default(&current.unwrap_or(1).to_string())
+            .prompt()
+            .unwrap();
+
+        match interval.trim().parse::<u32>() {
+            Ok(interval) if interval > 0 => Ok(interval),
+            _ => Err(Error::other(format!(
+                "'{}' is not a positive whole number",
+                interval
+            ))),
+        }
+    }
+
+    fn prompt_end_date(
+        &self,
+        args: &clap::ArgMatches,
+        current: Option<NaiveDate>,
+        start_date: NaiveDate,
+    ) -> Result<Option<NaiveDate>, Error> {
+        if let Some(end_date) = args.get_one::<NaiveDate>("until") {
+            return Ok(Some(*end_date));
+        }
+
+        let ends = inquire::Confirm::new("Does the schedule end?")
+            .with_default(current.is_some())
+            .prompt()
+            .unwrap();
+        if !ends {
+            return Ok(None);
+        }
+
+        Ok(Some(
+            inquire::DateSelect::new("End Date")
+                .with_help_message("Select the last date an occurrence may fall on")
+                .with_default(current.unwrap_or(start_date))
+                .with_min_date(start_date)
+                .prompt()
+                .unwrap(),
+        ))
+    }
+
+    fn schedule_response(&self, schedule: Schedule) -> Result<ScheduleResponse, Error> {
+        let skips = self
+            .db
+            .get_schedule_skips(&schedule.id)
+            .map_err(Error::from)?;
+
+        Ok(ScheduleResponse {
+            next: schedule
+                .next_occurrence(&skips)
+                .map_or("ended".to_string(), |date| date.to_string()),
+            ends: schedule
+                .end_date
+                .map_or("never".to_string(), |date| date.to_string()),
+            skips: skips
+                .iter()
+                .map(|date| date.to_string())
+                .collect::<Vec<String>>()
+                .join(", "),
+            rule: schedule.rule(),
+            id: schedule.id,
+            description: schedule.description,
+            account_id: schedule.account_id,
+            amount: schedule.amount,
+            transaction_type: schedule.transaction_type,
+        })
+    }
+
+    pub fn list_schedules(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let schedules = self.db.get_all_schedules().map_err(Error::from)?;
+
+        let responses = schedules
+            .into_iter()
+            .map(|schedule| self.schedule_response(schedule))
+            .collect::<Result<Vec<ScheduleResponse>, Error>>()?;
+
+        print_table(responses, "Schedules");
+
+        Ok(())
+    }
+
+    pub fn select_schedule(&self, message: &str) -> Result<Schedule, Error> {
+        let schedules = self.db.get_all_schedules().map_err(Error::from)?;
+
+        let options = schedules
+            .iter()
+            .map(|schedule| {
+                format!(
+                    "{} - {} ({})",
+                    schedule.description,
+                    schedule.amount,
+                    schedule.rule()
+                )
+            })
+            .collect::<Vec<String>>();
+        let option = inquire::Select::new(message, options).raw_prompt().unwrap();
+
+        Ok(schedules[option.index].clone())
+    }
+
+    fn get_schedule_by_arg(
+        &self,
+        args: &clap::ArgMatches,
+        message: &str,
+    ) -> Result<Schedule, Error> {
+        match args.get_one::<String>("id") {
+            Some(id) => self.db.get_schedule(id).map_err(Error::from),
+            None => self.select_schedule(message),
+        }
+    }
+
+    pub fn edit_schedule(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let mut schedule = self.get_schedule_by_arg(args, "Select a schedule to edit")?;
+
+        if let Some(account_id) = args.get_one::<String>("account_id") {
+            let account = self.find_account(account_id)?;
+            schedule.account_id = account.id;
+            schedule.amount.currency = account.balance.currency;
+        }
+
+        let currency = schedule.amount.currency.clone();
+        schedule.amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let amount = inquire::Text::new("New Amount")
+                    .with_help_message("Enter the amount posted each time (e.g. 12.34)")
+                    .with_default(&schedule.amount.to_plain_string())
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &currency).map_err(Error::other)?
+            }
+        };
+
+        schedule.description = args
+            .get_one::<String>("description")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::Text::new("New Description")
+                    .with_help_message("Enter the description of the transactions")
+                    .with_default(&schedule.description)
+                    .prompt()
+                    .unwrap()
+            });
+
+        if let Some(transaction_type) = args.get_one::<String>("type") {
+            schedule.transaction_type = transaction_type.clone();
+        }
+        if let Some(category) = args.get_one::<String>("category_id") {
+            schedule.category_id = self.find_category(category)?.id;
+        }
+        if let Some(payee) = args.get_one::<String>("payee") {
+            schedule.payee_id = match self.find_payee(payee) {
+                Ok(payee) => Some(payee.id),
+                Err(_) => self.get_or_create_payee(payee)?.map(|payee| payee.id),
+            };
+        }
+        if let Some(start_date) = args.get_one::<NaiveDate>("start") {
+            schedule.start_date = *start_date;
+        }
+
+        schedule.frequency = self.prompt_frequency(args, Some(schedule.frequency))?;
+        schedule.interval = self.prompt_interval(args, Some(schedule.interval))?;
+        schedule.end_date = self.prompt_end_date(args, schedule.end_date, schedule.start_date)?;
+
+        let mut skips = self
+            .db
+            .get_schedule_skips(&schedule.id)
+            .map_err(Error::from)?;
+        if let Some(added) = args.get_many::<NaiveDate>("skip") {
+            skips.extend(added.cloned());
+        }
+        if let Some(removed) = args.get_many::<NaiveDate>("unskip") {
+            let removed = removed.cloned().collect::<Vec<NaiveDate>>();
+            skips.retain(|date| !removed.contains(date));
+        }
+
+        self.db
+            .update_schedule(&schedule.id, &schedule)
+            .map_err(Error::from)?;
+        self.db
+            .set_schedule_skips(&schedule.id, &skips)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully updated schedule".green(),
+            schedule.id.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn delete_schedule(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let schedule = self.get_schedule_by_arg(args, "Select a schedule to delete")?;
+
+        self.db.delete_schedule(&schedule.id).map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully deleted schedule".green(),
+            schedule.description.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn run_schedules(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let until = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or(Local::now().date_naive());
+
+        let posted = self.db.run_schedules(until).map_err(Error::from)?;
+
+        if posted.is_empty() {
+            println!("{}", "Nothing is due".yellow());
+            return Ok(());
+        }
+
+        println!(
+            "{} {} {}",
+            "Successfully posted".green(),
+            posted.len().to_string().green(),
+            "scheduled transaction(s)".green()
+        );
+        print_table(posted, "Posted Transactions");
+
+        Ok(())
+    }
+}
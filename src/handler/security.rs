@@ -0,0 +1,250 @@
+use std::{
+    io::{Error, ErrorKind},
+    str::FromStr,
+};
+
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+use tabled::Tabled;
+
+use crate::{
+    db::security::{Security, SecurityPrice, SecurityRequest},
+    handler::Handler,
+    money::{DEFAULT_CURRENCY, Money, parse_currency},
+    utils::print_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct SecurityResponse {
+    pub id: String,
+    pub symbol: String,
+    pub name: String,
+    pub currency: String,
+    /// The latest imported price, `n/a` when there is none
+    pub price: String,
+    pub priced_on: String,
+}
+
+impl Handler {
+    pub fn add_security(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let symbol = args
+            .get_one::<String>("symbol")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::Text::new("Symbol")
+                    .with_help_message("Enter the ticker symbol (e.g. VTI)")
+                    .prompt()
+                    .unwrap()
+            });
+
+        let name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
+            inquire::Text::new("Name")
+                .with_help_message("Enter the name of the security")
+                .prompt()
+                .unwrap()
+        });
+
+        let currency = match args.get_one::<String>("currency") {
+            Some(currency) => currency.clone(),
+            None => {
+                let currency = inquire::Text::new("Currency")
+                    .with_help_message("Enter the currency it trades in (e.g. EUR)")
+                    .with_default(DEFAULT_CURRENCY)
+                    .prompt()
+                    .unwrap();
+
+                parse_currency(&currency).map_err(Error::other)?
+            }
+        };
+
+        let security = self
+            .db
+            .create_security(&SecurityRequest {
+                symbol,
+                name,
+                currency,
+            })
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully created security".green(),
+            security.symbol.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn list_securities(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let today = Local::now().date_naive();
+        let securities = self.db.get_all_securities().map_err(Error::from)?;
+
+        let mut responses = vec![];
+        for security in securities {
+            let price = self
+                .db
+                .get_security_price(&security, today)
+                .map_err(Error::from)?;
+
+            responses.push(SecurityResponse {
+                price: price
+                    .as_ref()
+                    .map_or("n/a".to_string(), |price| price.price.to_string()),
+                priced_on: price.map_or("n/a".to_string(), |price| price.priced_on.to_string()),
+                id: security.id,
+                symbol: security.symbol,
+                name: security.name,
+                currency: security.currency,
+            });
+        }
+
+        print_table(responses, "Securities");
+
+        Ok(())
+    }
+
+    pub fn select_security(&self, message: &str) -> Result<Security, Error> {
+        let securities = self.db.get_all_securities().map_err(Error::from)?;
+
+        let options = securities
+            .iter()
+            .map(|security| format!("{} - {}", security.symbol, security.name))
+            .collect::<Vec<String>>();
+        let option = inquire::Select::new(message, options).raw_prompt().unwrap();
+
+        Ok(securities[option.index].clone())
+    }
+
+    /// Looks a security up by id, falling back to its symbol.
+    pub fn find_security(&self, id_or_symbol: &str) -> Result<Security, Error> {
+        if let Ok(security) = self.db.get_security(id_or_symbol) {
+            return Ok(security);
+        }
+
+        self.db.get_security_by_symbol(id_or_symbol).map_err(|_| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("No security found matching '{}'", id_or_symbol),
+            )
+        })
+    }
+
+    pub(super) fn get_security_by_arg(
+        &self,
+        args: &clap::ArgMatches,
+        message: &str,
+    ) -> Result<Security, Error> {
+        match args.get_one::<String>("security") {
+            Some(id_or_symbol) => self.find_security(id_or_symbol),
+            None => self.select_security(message),
+        }
+    }
+
+    pub fn delete_security(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let security = self.get_security_by_arg(args, "Select a security to delete")?;
+
+        self.db.delete_security(&security.id).map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully deleted security".green(),
+            security.symbol.green()
+        );
+
+        Ok(())
+    }
+
+    /// Imports a CSV file of `date,symbol,price` lines. Symbols must already
+    /// exist as securities.
+    pub fn import_security_prices(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let file = args.get_one::<String>("file").unwrap();
+        let contents = std::fs::read_to_string(file)?;
+        let securities = self.db.get_all_securities().map_err(Error::from)?;
+
+        let mut prices = vec![];
+        for (number, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields = line.split(',').map(str::trim).collect::<Vec<&str>>();
+            let price = match fields[..] {
+                [date, symbol, price] => NaiveDate::from_str(date).ok().and_then(|priced_on| {
+                    let security = securities
+                        .iter()
+                        .find(|security| security.symbol.eq_ignore_ascii_case(symbol))?;
+
+                    Some(SecurityPrice {
+                        security_id: security.id.clone(),
+                        priced_on,
+                        price: Money::parse(price, &security.currency)
+                            .ok()
+                            .filter(|price| price.minor > 0)?,
+                    })
+                }),
+                _ => None,
+            };
+
+            match price {
+                Some(price) => prices.push(price),
+                // A header row is allowed, anything else malformed is not
+                None if number == 0 => continue,
+                None => {
+                    return Err(Error::new(
+                        ErrorKind::InvalidData,
+                        format!(
+                            "Line {} is not a valid date,symbol,price row of a known security",
+                            number + 1
+                        ),
+                    ));
+                }
+            }
+        }
+
+        self.db.set_security_prices(&prices).map_err(Error::from)?;
+
+        println!(
+            "{} {} {}",
+            "Successfully imported".green(),
+            prices.len().to_string().green(),
+            "prices".green()
+        );
+
+        Ok(())
+    }
+
+    pub fn set_security_price(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let security = self.get_security_by_arg(args, "Select a security to price")?;
+        let price = Money::parse(args.get_one::<String>("price").unwrap(), &security.currency)
+            .map_err(Error::other)?;
+
+        if price.minor <= 0 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The price must be positive",
+            ));
+        }
+
+        let price = SecurityPrice {
+            security_id: security.id.clone(),
+            priced_on: args
+                .get_one::<NaiveDate>("date")
+                .cloned()
+                .unwrap_or(Local::now().date_naive()),
+            price,
+        };
+        self.db
+            .set_security_prices(std::slice::from_ref(&price))
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} = {} on {}",
+            "Successfully set price".green(),
+            security.symbol,
+            price.price,
+            price.priced_on
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,107 @@
+use std::io::Error;
+
+use chrono::{Local, NaiveDate};
+use colored::Colorize;
+use tabled::Tabled;
+
+use crate::{
+    db::statement::CardStatement,
+    handler::{Handler, transaction::TransactionResponse},
+    money::Money,
+    utils::print_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct StatementResponse {
+    pub closed_on: NaiveDate,
+    pub statement_balance: Money,
+    pub minimum_payment: Money,
+    pub due_on: NaiveDate,
+    /// Payments and refunds since the statement closed
+    pub paid: Money,
+    /// What is left of the statement balance
+    pub remaining: Money,
+}
+
+/// The line shown for a card whose payment is coming up, or `None` when
+/// nothing is left to pay or the due date is further out than `within` days.
+pub(super) fn due_warning(name: &str, statement: &CardStatement, within: i64) -> Option<String> {
+    let remaining = statement.remaining();
+    let days = statement.days_until_due();
+    if remaining.minor == 0 || days > within {
+        return None;
+    }
+
+    let minimum = statement.remaining_minimum();
+    Some(if days < 0 {
+        format!(
+            "{}: {} was due on {} ({} days ago), at least {}",
+            name, remaining, statement.due_date, -days, minimum
+        )
+        .red()
+        .to_string()
+    } else {
+        format!(
+            "{}: {} due on {} (in {} days), at least {}",
+            name, remaining, statement.due_date, days, minimum
+        )
+        .yellow()
+        .to_string()
+    })
+}
+
+impl Handler {
+    pub fn account_statement(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let account = self.get_account_by_id_or_name(args)?;
+        let on = args
+            .get_one::<NaiveDate>("date")
+            .cloned()
+            .unwrap_or(Local::now().date_naive());
+
+        let statement = self
+            .db
+            .get_card_statement(&account.id, on)
+            .map_err(Error::from)?;
+
+        print_table(
+            vec![StatementResponse {
+                closed_on: statement.closing_date,
+                statement_balance: statement.statement_balance.clone(),
+                minimum_payment: statement.minimum_payment.clone(),
+                due_on: statement.due_date,
+                paid: statement.paid.clone(),
+                remaining: statement.remaining(),
+            }],
+            &format!("Statement of {}", account.name),
+        );
+
+        let transactions = statement
+            .transactions
+            .iter()
+            .cloned()
+            .map(|transaction| self.transaction_response(transaction))
+            .collect::<Result<Vec<TransactionResponse>, Error>>()?;
+        print_table(
+            transactions,
+            &format!(
+                "Current Cycle ({} to {})",
+                statement
+                    .closing_date
+                    .succ_opt()
+                    .unwrap_or(statement.closing_date),
+                statement.next_closing_date
+            ),
+        );
+
+        println!("{} {}", "Charges:".bold(), statement.charges);
+        println!("{} {}", "Balance:".bold(), statement.balance);
+        if let Some(available_credit) = &statement.available_credit {
+            println!("{} {}", "Available Credit:".bold(), available_credit);
+        }
+        if let Some(warning) = due_warning(&account.name, &statement, i64::MAX) {
+            println!("{}", warning);
+        }
+
+        Ok(())
+    }
+}
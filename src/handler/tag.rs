@@ -0,0 +1,157 @@
+use std::io::{Error, ErrorKind};
+
+use colored::Colorize;
+use tabled::Tabled;
+
+use crate::{
+    db::tag::{Tag, normalize_tag},
+    handler::Handler,
+    money::{DEFAULT_CURRENCY, Money},
+    utils::print_table,
+};
+
+#[derive(Debug, Clone, Tabled)]
+pub struct TagReportResponse {
+    pub tag: String,
+    pub transactions: i64,
+    pub income: Money,
+    pub expenses: Money,
+}
+
+impl Handler {
+    pub fn list_tags(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
+        let tags = self.db.get_all_tags().map_err(Error::from)?;
+
+        print_table(tags, "Tags");
+
+        Ok(())
+    }
+
+    pub fn report_tags(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let tags = self.db.get_all_tags().map_err(Error::from)?;
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+
+        let totals = self
+            .db
+            .get_tag_totals(&currency, self.profile_id())
+            .map_err(Error::from)?;
+
+        let report = tags
+            .into_iter()
+            .filter_map(|tag| {
+                let total = totals.iter().find(|total| total.tag_id == tag.id)?;
+
+                Some(TagReportResponse {
+                    tag: format!("#{}", tag.name),
+                    transactions: total.count,
+                    income: total.income.clone(),
+                    expenses: total.expenses.clone(),
+                })
+            })
+            .collect::<Vec<TagReportResponse>>();
+
+        print_table(
+            report,
+            &format!("{} (excluding transfers)", self.profile_title("Tag Report")),
+        );
+
+        Ok(())
+    }
+
+    pub fn select_tag(&self, message: &str) -> Result<Tag, Error> {
+        let tags = self.db.get_all_tags().map_err(Error::from)?;
+
+        let options = tags
+            .iter()
+            .map(|tag| format!("#{}", tag.name))
+            .collect::<Vec<String>>();
+        let option = inquire::Select::new(message, options).raw_prompt().unwrap();
+
+        Ok(tags[option.index].clone())
+    }
+
+    pub fn find_tag(&self, name: &str) -> Result<Tag, Error> {
+        self.db
+            .get_tag_by_name(name)
+            .map_err(Error::from)?
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("No tag named '{}' found", name),
+                )
+            })
+    }
+
+    fn get_tag_by_arg(
+        &self,
+        args: &clap::ArgMatches,
+        arg: &str,
+        message: &str,
+    ) -> Result<Tag, Error> {
+        match args.get_one::<String>(arg) {
+            Some(name) => self.find_tag(name),
+            None => self.select_tag(message),
+        }
+    }
+
+    pub fn rename_tag(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let tag = self.get_tag_by_arg(args, "name", "Select a tag to rename")?;
+
+        let new_name = args.get_one::<String>("to").cloned().unwrap_or_else(|| {
+            inquire::Text::new("New Name")
+                .with_help_message("Enter the new name of the tag")
+                .with_default(&tag.name)
+                .prompt()
+                .unwrap()
+        });
+
+        self.db
+            .rename_tag(&tag.id, &new_name)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} #{} -> #{}",
+            "Successfully renamed tag".green(),
+            tag.name,
+            normalize_tag(&new_name)
+        );
+
+        Ok(())
+    }
+
+    pub fn merge_tags(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let from = self.get_tag_by_arg(args, "from", "Select the tag to merge away")?;
+        let into = self.get_tag_by_arg(args, "into", "Select the tag to keep")?;
+
+        if from.id == into.id {
+            return Err(Error::other("Cannot merge a tag into itself"));
+        }
+
+        self.db
+            .merge_tags(&from.id, &into.id)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} #{} {} #{}",
+            "Successfully merged tag".green(),
+            from.name,
+            "into".green(),
+            into.name
+        );
+
+        Ok(())
+    }
+
+    pub fn delete_tag(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let tag = self.get_tag_by_arg(args, "name", "Select a tag to delete")?;
+
+        self.db.delete_tag(&tag.id).map_err(Error::from)?;
+
+        println!("{} #{}", "Successfully deleted tag".green(), tag.name);
+
+        Ok(())
+    }
+}
@@ -1,39 +1,104 @@
 use std::io::{Error, ErrorKind};
 
+use chrono::{Local, NaiveDate};
 use colored::Colorize;
 use tabled::Tabled;
 
 use crate::{
-    db::transaction::{Transaction, TransactionRequest},
+    db::{
+        split::SplitRequest,
+        transaction::{
+            Transaction, TransactionEdit, TransactionFilter, TransactionRequest, TransactionStatus,
+            TransferRequest,
+        },
+    },
     handler::Handler,
+    money::{DEFAULT_CURRENCY, Money},
     utils::print_table,
 };
 
 #[derive(Debug, Clone, Tabled)]
 pub struct TransactionResponse {
     pub id: String,
+    pub posted_on: NaiveDate,
     pub account_id: String,
-    pub amount: i64,
+    pub amount: Money,
     pub transaction_type: String,
+    pub status: TransactionStatus,
+    pub payee: String,
     pub description: String,
     pub category_id: String,
+    pub tags: String,
+    pub attachments: i64,
+    pub transfer_id: String,
 }
 
-impl Handler {
-    pub fn add_transaction(&self, args: &clap::ArgMatches) -> Result<(), Error> {
-        let account_id = args.get_one::<String>("account_id").cloned().unwrap_or_else(|| {
-            let account = self.select_account().unwrap();
-            account.id
-        });
+impl From<Transaction> for TransactionResponse {
+    fn from(transaction: Transaction) -> Self {
+        Self {
+            id: transaction.id,
+            posted_on: transaction.posted_on,
+            account_id: transaction.account_id,
+            amount: transaction.amount,
+            transaction_type: transaction.transaction_type,
+            status: transaction.status,
+            payee: String::new(),
+            description: transaction.description,
+            category_id: transaction.category_id,
+            tags: String::new(),
+            attachments: 0,
+            transfer_id: transaction.transfer_id.unwrap_or_default(),
+        }
+    }
+}
 
-        let amount = args.get_one::<i64>("amount").cloned().unwrap_or_else(|| {
-            let amount = inquire::Text::new("Amount")
-                .with_help_message("Enter the amount (in cents)")
-                .prompt()
-                .unwrap();
+#[derive(Debug, Clone, Tabled)]
+pub struct TotalsResponse {
+    pub income: Money,
+    pub expenses: Money,
+}
 
-            amount.parse::<i64>().unwrap()
-        });
+/// Splits input such as `#vacation2026, reimbursable` into separate tags.
+fn parse_tags(input: &str) -> Vec<String> {
+    input
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|tag| !tag.trim_start_matches('#').is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+impl Handler {
+    pub fn add_transaction(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        // Optional fields are only asked for when the required ones are prompted too
+        let prompted = [
+            "account_id",
+            "amount",
+            "type",
+            "description",
+            "category_id",
+            "posted_on",
+        ]
+        .into_iter()
+        .any(|id| !args.contains_id(id));
+
+        let account = match args.get_one::<String>("account_id") {
+            Some(account_id) => self.find_account(account_id)?,
+            None => self.select_account()?,
+        };
+        let account_id = account.id;
+        let currency = account.balance.currency;
+
+        let amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None => {
+                let amount = inquire::Text::new("Amount")
+                    .with_help_message("Enter the amount (e.g. 12.34)")
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &currency).map_err(Error::other)?
+            }
+        };
 
         let transaction_type = args.get_one::<String>("type").cloned().unwrap_or_else(|| {
             let options = vec!["credit".to_string(), "debit".to_string()];
@@ -42,17 +107,75 @@ impl Handler {
                 .unwrap()
         });
 
-        let description = args.get_one::<String>("description").cloned().unwrap_or_else(|| {
-            inquire::Text::new("Description")
-                .with_help_message("Enter the description of the transaction")
-                .prompt()
-                .unwrap()
-        });
+        let payee = match args.get_one::<String>("payee") {
+            Some(payee) => match self.find_payee(payee) {
+                Ok(payee) => Some(payee),
+                Err(_) => self.get_or_create_payee(payee)?,
+            },
+            None if !prompted => None,
+            None => self.prompt_payee(None)?,
+        };
 
-        let category_id = args.get_one::<String>("category_id").cloned().unwrap_or_else(|| {
-            let category = self.select_category().unwrap();
-            category.id
-        });
+        let description = args
+            .get_one::<String>("description")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::Text::new("Description")
+                    .with_help_message("Enter the description of the transaction")
+                    .prompt()
+                    .unwrap()
+            });
+
+        // The payee's usual category is pre-selected
+        let default_category_id = payee
+            .as_ref()
+            .and_then(|payee| payee.default_category_id.clone());
+
+        let (category_id, splits) = match args.get_one::<String>("category_id") {
+            Some(category_id) => (category_id.clone(), vec![]),
+            None => {
+                let split = inquire::Confirm::new("Split across multiple categories?")
+                    .with_default(false)
+                    .prompt()
+                    .unwrap();
+
+                if split {
+                    let splits = self.prompt_splits(&amount)?;
+                    (splits[0].category_id.clone(), splits)
+                } else {
+                    (
+                        self.select_category(default_category_id.as_deref())?.id,
+                        vec![],
+                    )
+                }
+            }
+        };
+
+        let posted_on = args
+            .get_one::<NaiveDate>("posted_on")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("Date")
+                    .with_help_message("Select the date the transaction happened")
+                    .with_default(Local::now().date_naive())
+                    .prompt()
+                    .unwrap()
+            });
+
+        let tags = match args.get_many::<String>("tag") {
+            Some(tags) => tags.cloned().collect(),
+            None if !prompted => vec![],
+            None => {
+                let tags = inquire::Text::new("Tags")
+                    .with_help_message(
+                        "Optional, separated by commas (e.g. #vacation2026, #reimbursable)",
+                    )
+                    .prompt()
+                    .unwrap();
+
+                parse_tags(&tags)
+            }
+        };
 
         let transaction_request = TransactionRequest {
             account_id,
@@ -60,12 +183,32 @@ impl Handler {
             transaction_type,
             description,
             category_id,
+            posted_on,
+            status: args
+                .get_one::<TransactionStatus>("status")
+                .cloned()
+                .unwrap_or(TransactionStatus::Pending),
+            payee_id: payee.as_ref().map(|payee| payee.id.clone()),
+            splits,
+            tags,
+            attachments: self.read_attachments(args)?,
         };
 
         let transaction = self
             .db
             .create_transaction(&transaction_request)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .map_err(Error::from)?;
+
+        // Payees remember the first category they were filed under
+        if let Some(mut payee) = payee
+            && payee.default_category_id.is_none()
+            && transaction_request.splits.is_empty()
+        {
+            payee.default_category_id = Some(transaction.category_id.clone());
+            self.db
+                .update_payee(&payee.id, &payee)
+                .map_err(Error::from)?;
+        }
 
         println!(
             "{} {}",
@@ -76,45 +219,128 @@ impl Handler {
         Ok(())
     }
 
-    pub fn list_transactions(&self, args: &clap::ArgMatches) -> Result<(), Error> {
-        let account_id = args.get_one::<String>("account_id").cloned();
+    /// Asks for split lines until they add up to `amount`.
+    fn prompt_splits(&self, amount: &Money) -> Result<Vec<SplitRequest>, Error> {
+        let mut splits = vec![];
+        let mut remaining = amount.clone();
 
-        let transactions = if let Some(account_id) = account_id {
-            self.db
-                .get_transactions_by_account(&account_id)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?
-        } else {
-            self.db
-                .get_all_transactions()
-                .map_err(|e| Error::new(ErrorKind::Other, e))?
+        while remaining.minor != 0 {
+            println!("{} {}", "Left to split:".yellow(), remaining);
+
+            let category = self.select_category(None)?;
+
+            let split_amount = inquire::Text::new("Amount")
+                .with_help_message("Enter the amount for this category")
+                .with_default(&remaining.to_plain_string())
+                .prompt()
+                .unwrap();
+            let split_amount =
+                Money::parse(&split_amount, &amount.currency).map_err(Error::other)?;
+
+            if split_amount.minor.abs() > remaining.minor.abs() {
+                println!("{} {}", "Amount can be at most".red(), remaining);
+                continue;
+            }
+
+            let memo = inquire::Text::new("Memo")
+                .with_help_message("Optional note for this line")
+                .prompt()
+                .unwrap();
+
+            remaining.minor -= split_amount.minor;
+            splits.push(SplitRequest {
+                category_id: category.id,
+                amount: split_amount,
+                memo,
+            });
+        }
+
+        Ok(splits)
+    }
+
+    pub(super) fn transaction_response(
+        &self,
+        transaction: Transaction,
+    ) -> Result<TransactionResponse, Error> {
+        let tags = self
+            .db
+            .get_transaction_tags(&transaction.id)
+            .map_err(Error::from)?;
+
+        let payee = match &transaction.payee_id {
+            Some(payee_id) => self.db.get_payee(payee_id).ok(),
+            None => None,
+        };
+
+        let attachments = self
+            .db
+            .count_transaction_attachments(&transaction.id)
+            .map_err(Error::from)?;
+
+        let mut response = TransactionResponse::from(transaction);
+        response.attachments = attachments;
+        response.payee = payee.map(|payee| payee.name).unwrap_or_default();
+        response.tags = tags
+            .iter()
+            .map(|tag| format!("#{}", tag.name))
+            .collect::<Vec<String>>()
+            .join(" ");
+
+        Ok(response)
+    }
+
+    pub fn list_transactions(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let filter = TransactionFilter {
+            account_id: args.get_one::<String>("account_id").cloned(),
+            category_id: None,
+            tags: args
+                .get_many::<String>("tag")
+                .map(|tags| tags.cloned().collect::<Vec<String>>())
+                .unwrap_or_default(),
+            status: args.get_one::<TransactionStatus>("status").cloned(),
+            holder_id: self.profile_id().map(str::to_string),
         };
+        let currency = args
+            .get_one::<String>("currency")
+            .cloned()
+            .unwrap_or(DEFAULT_CURRENCY.to_string());
+
+        let transactions = self
+            .db
+            .get_filtered_transactions(&filter)
+            .map_err(Error::from)?;
 
         let transaction_responses = transactions
             .into_iter()
-            .map(|transaction| TransactionResponse {
-                id: transaction.id,
-                account_id: transaction.account_id,
-                amount: transaction.amount,
-                transaction_type: transaction.transaction_type,
-                description: transaction.description,
-                category_id: transaction.category_id,
-            })
-            .collect::<Vec<TransactionResponse>>();
+            .map(|transaction| self.transaction_response(transaction))
+            .collect::<Result<Vec<TransactionResponse>, Error>>()?;
 
-        print_table(transaction_responses, "Transactions");
+        print_table(transaction_responses, &self.profile_title("Transactions"));
+
+        let (income, expenses) = self
+            .db
+            .get_income_and_expenses(&filter, &currency)
+            .map_err(Error::from)?;
+
+        print_table(
+            vec![TotalsResponse { income, expenses }],
+            "Totals (excluding transfers)",
+        );
 
         Ok(())
     }
 
     pub fn select_transaction(&self) -> Result<Transaction, Error> {
-        let transactions = self
-            .db
-            .get_all_transactions()
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        let transactions = self.db.get_all_transactions().map_err(Error::from)?;
 
         let options = transactions
             .iter()
-            .map(|transaction| format!("{} - {}", transaction.description, transaction.amount))
+            .map(|transaction| {
+                format!(
+                    "{} {} - {}",
+                    transaction.posted_on, transaction.description, transaction.amount
+                )
+            })
             .collect::<Vec<String>>();
         let option = inquire::Select::new("Select a transaction", options.clone())
             .prompt()
@@ -129,10 +355,7 @@ impl Handler {
         let id = args.get_one::<String>("id").cloned();
 
         if let Some(id) = id {
-            let transaction = self
-                .db
-                .get_transaction(&id)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            let transaction = self.db.get_transaction(&id).map_err(Error::from)?;
 
             Ok(transaction)
         } else {
@@ -144,52 +367,274 @@ impl Handler {
     pub fn get_transaction(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let transaction = self.get_transaction_by_id(args)?;
 
-        let transaction_response = TransactionResponse {
-            id: transaction.id,
-            account_id: transaction.account_id,
-            amount: transaction.amount,
-            transaction_type: transaction.transaction_type,
-            description: transaction.description,
-            category_id: transaction.category_id,
-        };
+        let splits = self
+            .db
+            .get_transaction_splits(&transaction.id)
+            .map_err(Error::from)?;
+
+        print_table(vec![self.transaction_response(transaction)?], "Transaction");
+
+        if !splits.is_empty() {
+            print_table(splits, "Splits");
+        }
 
-        print_table(vec![transaction_response], "Transaction");
         Ok(())
     }
 
     pub fn update_transaction(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let mut transaction = self.get_transaction_by_id(args)?;
 
-        let new_amount = inquire::Text::new("New Amount")
-            .with_help_message("Enter the new amount (in cents)")
-            .with_default(&transaction.amount.to_string())
-            .prompt()
-            .unwrap();
+        // The amount is in the currency of the account it ends up on
+        let currency = match args.get_one::<String>("account_id") {
+            Some(account_id) => {
+                self.db
+                    .get_account(account_id)
+                    .map_err(Error::from)?
+                    .balance
+                    .currency
+            }
+            None => transaction.amount.currency.clone(),
+        };
+        let new_amount = match args.get_one::<String>("amount") {
+            Some(amount) => Money::parse(amount, &currency).map_err(Error::other)?,
+            None if currency != transaction.amount.currency => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    format!(
+                        "The new account is in {}, give the amount in {} with --amount",
+                        currency, currency
+                    ),
+                ));
+            }
+            None => {
+                let new_amount = inquire::Text::new("New Amount")
+                    .with_help_message("Enter the new amount (e.g. 12.34)")
+                    .with_default(&transaction.amount.to_plain_string())
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&new_amount, &currency).map_err(Error::other)?
+            }
+        };
+
+        let new_type = args.get_one::<String>("type").cloned().unwrap_or_else(|| {
+            inquire::Select::new("New Transaction Type", vec!["credit", "debit"])
+                .prompt()
+                .unwrap()
+                .to_string()
+        });
 
-        let new_amount = new_amount.parse::<i64>().unwrap();
+        let new_description = args
+            .get_one::<String>("description")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::Text::new("New Description")
+                    .with_help_message("Enter the new description of the transaction")
+                    .with_default(&transaction.description)
+                    .prompt()
+                    .unwrap()
+            });
+
+        let new_posted_on = args
+            .get_one::<NaiveDate>("posted_on")
+            .cloned()
+            .unwrap_or_else(|| {
+                inquire::DateSelect::new("New Date")
+                    .with_help_message("Select the date the transaction happened")
+                    .with_default(transaction.posted_on)
+                    .prompt()
+                    .unwrap()
+            });
+
+        let new_status = match args.get_one::<TransactionStatus>("status") {
+            Some(status) => *status,
+            None => {
+                // Reconciled is only offered to keep a transaction that already is
+                let mut statuses = TransactionStatus::SETTABLE.to_vec();
+                if transaction.status == TransactionStatus::Reconciled {
+                    statuses.push(TransactionStatus::Reconciled);
+                }
+                let starting_cursor = statuses
+                    .iter()
+                    .position(|status| *status == transaction.status)
+                    .unwrap_or(0);
+
+                inquire::Select::new("New Status", statuses)
+                    .with_starting_cursor(starting_cursor)
+                    .prompt()
+                    .unwrap()
+            }
+        };
 
-        let new_type = inquire::Select::new("New Transaction Type", vec!["credit", "debit"])
-            .prompt()
-            .unwrap();
+        let new_attachments = self.read_attachments(args)?;
+
+        let new_payee = match args.get_one::<String>("payee") {
+            Some(payee) => match self.find_payee(payee) {
+                Ok(payee) => Some(payee),
+                Err(_) => self.get_or_create_payee(payee)?,
+            },
+            None => {
+                let current = match &transaction.payee_id {
+                    Some(payee_id) => self.db.get_payee(payee_id).ok(),
+                    None => None,
+                };
+                self.prompt_payee(current.as_ref())?
+            }
+        };
 
-        let new_description = inquire::Text::new("New Description")
-            .with_help_message("Enter the new description of the transaction")
-            .with_default(&transaction.description)
-            .prompt()
-            .unwrap();
+        let new_tags = match args.get_many::<String>("tag") {
+            Some(tags) => tags.cloned().collect(),
+            None => {
+                let tags = self
+                    .db
+                    .get_transaction_tags(&transaction.id)
+                    .map_err(Error::from)?
+                    .iter()
+                    .map(|tag| format!("#{}", tag.name))
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                let tags = inquire::Text::new("New Tags")
+                    .with_help_message("Separated by commas, leave empty to remove all tags")
+                    .with_default(&tags)
+                    .prompt()
+                    .unwrap();
+
+                parse_tags(&tags)
+            }
+        };
+
+        if let Some(account_id) = args.get_one::<String>("account_id") {
+            transaction.account_id = account_id.clone();
+        }
+        if let Some(category_id) = args.get_one::<String>("category_id") {
+            transaction.category_id = category_id.clone();
+        }
+
+        let splits = self
+            .db
+            .get_transaction_splits(&transaction.id)
+            .map_err(Error::from)?;
+
+        // Splits that no longer add up are entered again before anything is saved
+        let new_splits = if !splits.is_empty() && new_amount != transaction.amount {
+            println!("{}", "The amount changed, please split it again".yellow());
+            Some(self.prompt_splits(&new_amount)?)
+        } else {
+            None
+        };
 
         transaction.amount = new_amount;
-        transaction.transaction_type = new_type.to_string();
+        transaction.transaction_type = new_type;
+        transaction.payee_id = new_payee.map(|payee| payee.id);
         transaction.description = new_description;
-
+        transaction.posted_on = new_posted_on;
+        transaction.status = new_status;
+
+        let edit = TransactionEdit {
+            transaction,
+            splits: new_splits,
+            tags: Some(new_tags),
+            attachments: new_attachments,
+        };
         self.db
-            .update_transaction(&transaction.id, &transaction)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .update_transaction(&edit.transaction.id, &edit)
+            .map_err(Error::from)?;
 
         println!(
             "{} {}",
             "Successfully updated transaction".green(),
-            transaction.id.green()
+            edit.transaction.id.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn clear_transactions(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let ids = args
+            .get_many::<String>("ids")
+            .unwrap()
+            .cloned()
+            .collect::<Vec<String>>();
+
+        let cleared = self
+            .db
+            .set_transaction_status(&ids, TransactionStatus::Cleared)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {} {}",
+            "Successfully cleared".green(),
+            cleared.len().to_string().green(),
+            "transaction(s)".green()
+        );
+        if cleared.len() < ids.len() {
+            println!(
+                "{}",
+                "Transactions that were already cleared, reconciled or void were left as they are"
+                    .yellow()
+            );
+        }
+
+        Ok(())
+    }
+
+    pub fn transfer(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let from_account = match args.get_one::<String>("from") {
+            Some(from) => self.find_account(from)?,
+            None => self.select_account()?,
+        };
+
+        let to_account = match args.get_one::<String>("to") {
+            Some(to) => self.find_account(to)?,
+            None => self.select_account()?,
+        };
+
+        if from_account.id == to_account.id {
+            return Err(Error::other("Cannot transfer money to the same account"));
+        }
+
+        let amount = match args.get_one::<String>("amount") {
+            Some(amount) => {
+                Money::parse(amount, &from_account.balance.currency).map_err(Error::other)?
+            }
+            None => {
+                let amount = inquire::Text::new("Amount")
+                    .with_help_message("Enter the amount to transfer (e.g. 12.34)")
+                    .prompt()
+                    .unwrap();
+
+                Money::parse(&amount, &from_account.balance.currency).map_err(Error::other)?
+            }
+        };
+
+        let description = args
+            .get_one::<String>("description")
+            .cloned()
+            .unwrap_or_else(|| format!("Transfer to {}", to_account.name));
+
+        let posted_on = args
+            .get_one::<NaiveDate>("posted_on")
+            .cloned()
+            .unwrap_or_else(|| Local::now().date_naive());
+
+        let transfer_request = TransferRequest {
+            from_account_id: from_account.id,
+            to_account_id: to_account.id,
+            amount,
+            description,
+            posted_on,
+        };
+
+        let (outgoing, _) = self
+            .db
+            .create_transfer(&transfer_request)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully created transfer with id".green(),
+            outgoing.transfer_id.unwrap_or_default().green()
         );
 
         Ok(())
@@ -200,7 +645,7 @@ impl Handler {
 
         self.db
             .delete_transaction(&transaction.id)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .map_err(Error::from)?;
 
         println!(
             "{} {}",
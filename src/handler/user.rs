@@ -4,27 +4,22 @@ use colored::Colorize;
 
 use crate::{
     db::users::{User, UserRequest},
-    handler::Handler,
+    handler::{Handler, archived_name},
     utils::print_table,
 };
 
 impl Handler {
     pub fn add_user(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let name = args.get_one::<String>("name").cloned().unwrap_or_else(|| {
-            let name = inquire::Text::new("Name")
+            inquire::Text::new("Name")
                 .with_help_message("Enter the name of the user")
                 .prompt()
-                .unwrap();
-
-            name
+                .unwrap()
         });
 
         let user_request = UserRequest { name };
 
-        let user = self
-            .db
-            .create_user(&user_request)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        let user = self.db.create_user(&user_request).map_err(Error::from)?;
 
         println!(
             "{} {}",
@@ -35,22 +30,79 @@ impl Handler {
         Ok(())
     }
 
-    pub fn list_users(&self, _args: &clap::ArgMatches) -> Result<(), Error> {
-        let users = self
+    /// Every user who is not archived.
+    pub(super) fn active_users(&self) -> Result<Vec<User>, Error> {
+        Ok(self
             .db
             .get_all_users()
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .map_err(Error::from)?
+            .into_iter()
+            .filter(|user| user.archived_at.is_none())
+            .collect())
+    }
+
+    pub fn list_users(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let users = match args.get_flag("all") {
+            true => self
+                .db
+                .get_all_users()
+                .map_err(Error::from)?
+                .into_iter()
+                .map(|mut user| {
+                    user.name = archived_name(&user.name, &user.archived_at);
+                    user
+                })
+                .collect(),
+            false => self.active_users()?,
+        };
 
         print_table(users, "Users");
 
+        if let Some(user) = &self.profile {
+            println!("Active user: {}", user.name);
+        }
+
+        Ok(())
+    }
+
+    /// Acts as `as_user` for this command, by id or name, or else as the user
+    /// last switched to.
+    pub fn use_profile(&mut self, as_user: Option<&String>) -> Result<(), Error> {
+        self.profile = match as_user {
+            Some(as_user) => Some(self.find_user(as_user)?),
+            None => self.db.get_active_user().map_err(Error::from)?,
+        };
+
+        Ok(())
+    }
+
+    pub fn switch_user(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        if args.get_flag("clear") {
+            self.db.set_active_user(None).map_err(Error::from)?;
+
+            println!(
+                "{}",
+                "Cleared the active user, commands cover everyone again".green()
+            );
+            return Ok(());
+        }
+
+        let user = match args.get_one::<String>("user") {
+            Some(user) => self.find_user(user)?,
+            None => self.select_user()?,
+        };
+
+        self.db
+            .set_active_user(Some(&user.id))
+            .map_err(Error::from)?;
+
+        println!("{} {}", "Switched to".green(), user.name.green());
+
         Ok(())
     }
 
     pub fn select_user(&self) -> Result<User, Error> {
-        let users = self
-            .db
-            .get_all_users()
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        let users = self.active_users()?;
 
         let options = users
             .iter()
@@ -67,27 +119,63 @@ impl Handler {
             .clone())
     }
 
+    /// Looks a user up by id, falling back to a name search.
+    pub fn find_user(&self, id_or_name: &str) -> Result<User, Error> {
+        if let Ok(user) = self.db.get_user(id_or_name) {
+            return Ok(user);
+        }
+
+        let users = self
+            .db
+            .search_users_by_name(id_or_name)
+            .map_err(Error::from)?;
+
+        match users.len() {
+            0 => Err(Error::new(
+                ErrorKind::NotFound,
+                format!("No users found matching '{}'", id_or_name),
+            )),
+            1 => Ok(users[0].clone()),
+            _ => {
+                if let Some(user) = users
+                    .iter()
+                    .find(|user| user.name.eq_ignore_ascii_case(id_or_name))
+                {
+                    return Ok(user.clone());
+                }
+
+                let options = users
+                    .iter()
+                    .map(|user| user.name.clone())
+                    .collect::<Vec<String>>();
+                let option = inquire::Select::new("Select a user", options)
+                    .prompt()
+                    .unwrap();
+
+                Ok(users
+                    .iter()
+                    .find(|user| user.name == option)
+                    .unwrap()
+                    .clone())
+            }
+        }
+    }
+
     pub fn get_user_by_id_or_name(&self, args: &clap::ArgMatches) -> Result<User, Error> {
         // check if id is present with the --id flag, if not check for --name flag or finally ask the user for a name
 
         let id = args.get_one::<String>("id").cloned();
         let name = args.get_one::<String>("name").cloned();
 
-        if id.is_some() {
-            let user = self
-                .db
-                .get_user(&id.unwrap())
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        if let Some(id) = id {
+            let user = self.db.get_user(&id).map_err(Error::from)?;
 
             Ok(user)
-        } else if name.is_some() {
-            let users = self
-                .db
-                .search_users_by_name(&name.unwrap())
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        } else if let Some(name) = name {
+            let users = self.db.search_users_by_name(&name).map_err(Error::from)?;
 
             if users.len() == 1 {
-                return Ok(users[0].clone());
+                Ok(users[0].clone())
             } else {
                 let options = users
                     .iter()
@@ -109,13 +197,10 @@ impl Handler {
                 .prompt()
                 .unwrap();
 
-            let users = self
-                .db
-                .search_users_by_name(&name)
-                .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            let users = self.db.search_users_by_name(&name).map_err(Error::from)?;
 
             if users.len() == 1 {
-                return Ok(users[0].clone());
+                Ok(users[0].clone())
             } else {
                 let options = users
                     .iter()
@@ -151,9 +236,7 @@ impl Handler {
 
         user.name = new_name;
 
-        self.db
-            .update_user(&user.id, &user)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+        self.db.update_user(&user.id, &user).map_err(Error::from)?;
 
         println!(
             "{} {}",
@@ -164,12 +247,42 @@ impl Handler {
         Ok(())
     }
 
-    pub fn delete_user(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+    pub fn archive_user(&self, args: &clap::ArgMatches) -> Result<(), Error> {
         let user = self.get_user_by_id_or_name(args)?;
 
         self.db
-            .delete_user(&user.id)
-            .map_err(|e| Error::new(ErrorKind::Other, e))?;
+            .set_user_archived(&user.id, true)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully archived user".green(),
+            user.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn unarchive_user(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let user = self.get_user_by_id_or_name(args)?;
+
+        self.db
+            .set_user_archived(&user.id, false)
+            .map_err(Error::from)?;
+
+        println!(
+            "{} {}",
+            "Successfully unarchived user".green(),
+            user.name.green()
+        );
+
+        Ok(())
+    }
+
+    pub fn delete_user(&self, args: &clap::ArgMatches) -> Result<(), Error> {
+        let user = self.get_user_by_id_or_name(args)?;
+
+        self.db.delete_user(&user.id).map_err(Error::from)?;
 
         println!(
             "{} {}",
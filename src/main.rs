@@ -1,9 +1,10 @@
-use db::Db;
+use db::{Db, holding::TradeKind};
 use handler::Handler;
 
 mod cli;
 mod db;
 mod handler;
+mod money;
 mod utils;
 
 fn main() {
@@ -13,7 +14,10 @@ fn main() {
     let db = Db::new("tmp/test.db").unwrap();
     db.create_tables().unwrap();
 
-    let handler = Handler::new(db);
+    let mut handler = Handler::new(db);
+    handler
+        .use_profile(matches.get_one::<String>("as"))
+        .unwrap();
 
     match matches.subcommand() {
         Some(("user", sub_matches)) => match sub_matches.subcommand() {
@@ -29,9 +33,18 @@ fn main() {
             Some(("delete", sub_matches)) => {
                 handler.delete_user(sub_matches).unwrap();
             }
+            Some(("archive", sub_matches)) => {
+                handler.archive_user(sub_matches).unwrap();
+            }
+            Some(("unarchive", sub_matches)) => {
+                handler.unarchive_user(sub_matches).unwrap();
+            }
             Some(("update", sub_matches)) => {
                 handler.update_user(sub_matches).unwrap();
             }
+            Some(("switch", sub_matches)) => {
+                handler.switch_user(sub_matches).unwrap();
+            }
             _ => {}
         },
 
@@ -49,9 +62,30 @@ fn main() {
             Some(("delete", sub_matches)) => {
                 handler.delete_account(sub_matches).unwrap();
             }
+            Some(("archive", sub_matches)) => {
+                handler.archive_account(sub_matches).unwrap();
+            }
+            Some(("unarchive", sub_matches)) => {
+                handler.unarchive_account(sub_matches).unwrap();
+            }
             Some(("update", sub_matches)) => {
                 handler.update_account(sub_matches).unwrap();
             }
+            Some(("statement", sub_matches)) => {
+                handler.account_statement(sub_matches).unwrap();
+            }
+            Some(("amortization", sub_matches)) => {
+                handler.account_amortization(sub_matches).unwrap();
+            }
+            Some(("recompute", sub_matches)) => {
+                handler.recompute_accounts(sub_matches).unwrap();
+            }
+            Some(("reconcile", sub_matches)) => {
+                handler.reconcile_account(sub_matches).unwrap();
+            }
+            Some(("reconciliations", sub_matches)) => {
+                handler.list_reconciliations(sub_matches).unwrap();
+            }
             _ => {}
         },
 
@@ -68,9 +102,18 @@ fn main() {
             Some(("delete", sub_matches)) => {
                 handler.delete_category(sub_matches).unwrap();
             }
+            Some(("archive", sub_matches)) => {
+                handler.archive_category(sub_matches).unwrap();
+            }
+            Some(("unarchive", sub_matches)) => {
+                handler.unarchive_category(sub_matches).unwrap();
+            }
             Some(("update", sub_matches)) => {
                 handler.update_category(sub_matches).unwrap();
             }
+            Some(("report", sub_matches)) => {
+                handler.report_categories(sub_matches).unwrap();
+            }
             _ => {}
         },
 
@@ -90,6 +133,223 @@ fn main() {
             Some(("update", sub_matches)) => {
                 handler.update_transaction(sub_matches).unwrap();
             }
+            Some(("clear", sub_matches)) => {
+                handler.clear_transactions(sub_matches).unwrap();
+            }
+            Some(("transfer", sub_matches)) => {
+                handler.transfer(sub_matches).unwrap();
+            }
+            Some(("attachments", sub_matches)) => match sub_matches.subcommand() {
+                Some(("list", sub_matches)) => {
+                    handler.list_attachments(sub_matches).unwrap();
+                }
+                Some(("extract", sub_matches)) => {
+                    handler.extract_attachment(sub_matches).unwrap();
+                }
+                Some(("remove", sub_matches)) => {
+                    handler.remove_attachment(sub_matches).unwrap();
+                }
+                _ => {}
+            },
+            _ => {}
+        },
+
+        Some(("payee", sub_matches)) => match sub_matches.subcommand() {
+            Some(("create", sub_matches)) => {
+                handler.add_payee(sub_matches).unwrap();
+            }
+            Some(("list", sub_matches)) => {
+                handler.list_payees(sub_matches).unwrap();
+            }
+            Some(("update", sub_matches)) => {
+                handler.update_payee(sub_matches).unwrap();
+            }
+            Some(("merge", sub_matches)) => {
+                handler.merge_payees(sub_matches).unwrap();
+            }
+            Some(("delete", sub_matches)) => {
+                handler.delete_payee(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("schedule", sub_matches)) => match sub_matches.subcommand() {
+            Some(("create", sub_matches)) => {
+                handler.add_schedule(sub_matches).unwrap();
+            }
+            Some(("list", sub_matches)) => {
+                handler.list_schedules(sub_matches).unwrap();
+            }
+            Some(("edit", sub_matches)) => {
+                handler.edit_schedule(sub_matches).unwrap();
+            }
+            Some(("delete", sub_matches)) => {
+                handler.delete_schedule(sub_matches).unwrap();
+            }
+            Some(("run", sub_matches)) => {
+                handler.run_schedules(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("budget", sub_matches)) => match sub_matches.subcommand() {
+            Some(("set", sub_matches)) => {
+                handler.set_budget(sub_matches).unwrap();
+            }
+            Some(("list", sub_matches)) => {
+                handler.list_budgets(sub_matches).unwrap();
+            }
+            Some(("copy-month", sub_matches)) => {
+                handler.copy_budgets(sub_matches).unwrap();
+            }
+            Some(("status", sub_matches)) => {
+                handler.budget_status(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("goal", sub_matches)) => match sub_matches.subcommand() {
+            Some(("create", sub_matches)) => {
+                handler.add_goal(sub_matches).unwrap();
+            }
+            Some(("list", sub_matches)) => {
+                handler.list_goals(sub_matches).unwrap();
+            }
+            Some(("edit", sub_matches)) => {
+                handler.edit_goal(sub_matches).unwrap();
+            }
+            Some(("delete", sub_matches)) => {
+                handler.delete_goal(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("envelope", sub_matches)) => match sub_matches.subcommand() {
+            Some(("assign", sub_matches)) => {
+                handler.assign_envelope(sub_matches).unwrap();
+            }
+            Some(("move", sub_matches)) => {
+                handler.move_envelope_money(sub_matches).unwrap();
+            }
+            Some(("moves", sub_matches)) => {
+                handler.list_envelope_moves(sub_matches).unwrap();
+            }
+            Some(("status", sub_matches)) => {
+                handler.envelope_status(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("tag", sub_matches)) => match sub_matches.subcommand() {
+            Some(("list", sub_matches)) => {
+                handler.list_tags(sub_matches).unwrap();
+            }
+            Some(("report", sub_matches)) => {
+                handler.report_tags(sub_matches).unwrap();
+            }
+            Some(("rename", sub_matches)) => {
+                handler.rename_tag(sub_matches).unwrap();
+            }
+            Some(("merge", sub_matches)) => {
+                handler.merge_tags(sub_matches).unwrap();
+            }
+            Some(("delete", sub_matches)) => {
+                handler.delete_tag(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("security", sub_matches)) => match sub_matches.subcommand() {
+            Some(("create", sub_matches)) => {
+                handler.add_security(sub_matches).unwrap();
+            }
+            Some(("list", sub_matches)) => {
+                handler.list_securities(sub_matches).unwrap();
+            }
+            Some(("delete", sub_matches)) => {
+                handler.delete_security(sub_matches).unwrap();
+            }
+            Some(("price", sub_matches)) => {
+                handler.set_security_price(sub_matches).unwrap();
+            }
+            Some(("import", sub_matches)) => {
+                handler.import_security_prices(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("trade", sub_matches)) => match sub_matches.subcommand() {
+            Some(("list", sub_matches)) => {
+                handler.list_trades(sub_matches).unwrap();
+            }
+            Some(("delete", sub_matches)) => {
+                handler.delete_trade(sub_matches).unwrap();
+            }
+            Some(("buy", sub_matches)) => {
+                handler.add_trade(sub_matches, TradeKind::Buy).unwrap();
+            }
+            Some(("sell", sub_matches)) => {
+                handler.add_trade(sub_matches, TradeKind::Sell).unwrap();
+            }
+            Some(("dividend", sub_matches)) => {
+                handler.add_trade(sub_matches, TradeKind::Dividend).unwrap();
+            }
+            Some(("split", sub_matches)) => {
+                handler.add_trade(sub_matches, TradeKind::Split).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("holdings", sub_matches)) => {
+            handler.list_holdings(sub_matches).unwrap();
+        }
+
+        Some(("gains", sub_matches)) => {
+            handler.list_gains(sub_matches).unwrap();
+        }
+
+        Some(("networth", sub_matches)) => match sub_matches.subcommand() {
+            Some(("history", sub_matches)) => {
+                handler.net_worth_history(sub_matches).unwrap();
+            }
+            _ => {
+                handler.show_net_worth(sub_matches).unwrap();
+            }
+        },
+
+        Some(("expense", sub_matches)) => match sub_matches.subcommand() {
+            Some(("create", sub_matches)) => {
+                handler.add_shared_expense(sub_matches).unwrap();
+            }
+            Some(("list", sub_matches)) => {
+                handler.list_shared_expenses(sub_matches).unwrap();
+            }
+            Some(("delete", sub_matches)) => {
+                handler.delete_shared_expense(sub_matches).unwrap();
+            }
+            Some(("settlements", sub_matches)) => {
+                handler.list_settlements(sub_matches).unwrap();
+            }
+            Some(("balances", sub_matches)) => {
+                handler.shared_balances(sub_matches).unwrap();
+            }
+            _ => {}
+        },
+
+        Some(("settle", sub_matches)) => {
+            handler.settle(sub_matches).unwrap();
+        }
+
+        Some(("rate", sub_matches)) => match sub_matches.subcommand() {
+            Some(("import", sub_matches)) => {
+                handler.import_exchange_rates(sub_matches).unwrap();
+            }
+            Some(("list", sub_matches)) => {
+                handler.list_exchange_rates(sub_matches).unwrap();
+            }
+            Some(("set", sub_matches)) => {
+                handler.set_exchange_rate(sub_matches).unwrap();
+            }
             _ => {}
         },
 
@@ -0,0 +1,252 @@
+use std::fmt;
+
+/// Currency assumed for amounts that do not carry their own.
+pub const DEFAULT_CURRENCY: &str = "USD";
+
+/// Decimal places of the minor unit of currencies that do not use cents.
+/// Every other currency has two.
+const MINOR_UNIT_EXPONENTS: [(&str, u32); 24] = [
+    ("BHD", 3),
+    ("BIF", 0),
+    ("CLP", 0),
+    ("DJF", 0),
+    ("GNF", 0),
+    ("IQD", 3),
+    ("ISK", 0),
+    ("JOD", 3),
+    ("JPY", 0),
+    ("KMF", 0),
+    ("KRW", 0),
+    ("KWD", 3),
+    ("LYD", 3),
+    ("OMR", 3),
+    ("PYG", 0),
+    ("RWF", 0),
+    ("TND", 3),
+    ("UGX", 0),
+    ("UYI", 0),
+    ("VND", 0),
+    ("VUV", 0),
+    ("XAF", 0),
+    ("XOF", 0),
+    ("XPF", 0),
+];
+
+/// The most decimal places any currency has.
+const MAX_MINOR_UNIT_EXPONENT: u32 = 3;
+
+/// How many decimal places the minor unit of `currency` has, so `2` for cents.
+pub fn minor_unit_exponent(currency: &str) -> u32 {
+    MINOR_UNIT_EXPONENTS
+        .iter()
+        .find(|(code, _)| *code == currency)
+        .map_or(2, |(_, exponent)| *exponent)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Money {
+    /// The amount in minor units (cents for most currencies), so sums never
+    /// pick up floating point error
+    pub minor: i64,
+    pub currency: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseMoneyError(String);
+
+impl fmt::Display for ParseMoneyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a valid amount", self.0)
+    }
+}
+
+impl std::error::Error for ParseMoneyError {}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseCurrencyError(String);
+
+impl fmt::Display for ParseCurrencyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}' is not a three letter currency code", self.0)
+    }
+}
+
+impl std::error::Error for ParseCurrencyError {}
+
+impl Money {
+    pub fn new(minor: i64, currency: &str) -> Self {
+        Self {
+            minor,
+            currency: currency.to_string(),
+        }
+    }
+
+    pub fn parse(input: &str, currency: &str) -> Result<Self, ParseMoneyError> {
+        Ok(Self::new(
+            parse_minor(input, minor_unit_exponent(currency))?,
+            currency,
+        ))
+    }
+
+    /// This amount in `currency`, given how many units of it one unit of ours
+    /// buys.
+    pub fn exchange(&self, rate: f64, currency: &str) -> Money {
+        let exponent =
+            minor_unit_exponent(currency) as i32 - minor_unit_exponent(&self.currency) as i32;
+
+        Money::new(
+            (self.minor as f64 * rate * 10f64.powi(exponent)).round() as i64,
+            currency,
+        )
+    }
+
+    /// The whole and fractional parts of the amount, the fraction padded to
+    /// the decimal places of the currency, or empty when it has none.
+    fn parts(&self) -> (u64, String) {
+        let exponent = minor_unit_exponent(&self.currency);
+        let abs = self.minor.unsigned_abs();
+        let scale = 10u64.pow(exponent);
+
+        let fraction = match exponent {
+            0 => String::new(),
+            _ => format!(".{:0width$}", abs % scale, width = exponent as usize),
+        };
+
+        (abs / scale, fraction)
+    }
+
+    /// The amount in major units without grouping or currency, suitable for prompt defaults.
+    pub fn to_plain_string(&self) -> String {
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let (whole, fraction) = self.parts();
+        format!("{}{}{}", sign, whole, fraction)
+    }
+}
+
+impl fmt::Display for Money {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.minor < 0 { "-" } else { "" };
+        let (whole, fraction) = self.parts();
+
+        let whole = whole.to_string();
+        let mut grouped = String::new();
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i).is_multiple_of(3) {
+                grouped.push(',');
+            }
+            grouped.push(digit);
+        }
+
+        write!(f, "{}{}{} {}", sign, grouped, fraction, self.currency)
+    }
+}
+
+/// Parses human input such as `12.34`, `-5` or `$1,200.00` into minor units
+/// with `exponent` decimal places.
+pub fn parse_minor(input: &str, exponent: u32) -> Result<i64, ParseMoneyError> {
+    let error = || ParseMoneyError(input.to_string());
+
+    let mut negative = false;
+    let mut number = String::new();
+    for c in input.trim().chars() {
+        match c {
+            '-' if number.is_empty() && !negative => negative = true,
+            '+' if number.is_empty() => {}
+            '$' | '€' | '£' | '₹' | '¥' if number.is_empty() => {}
+            ',' | '_' | ' ' => {}
+            '0'..='9' | '.' => number.push(c),
+            _ => return Err(error()),
+        }
+    }
+
+    let (whole, fraction) = number.split_once('.').unwrap_or((&number, ""));
+    if (whole.is_empty() && fraction.is_empty())
+        || fraction.len() > exponent as usize
+        || fraction.contains('.')
+    {
+        return Err(error());
+    }
+
+    let whole = if whole.is_empty() {
+        0
+    } else {
+        whole.parse::<i64>().map_err(|_| error())?
+    };
+    let fraction = format!("{:0<width$}", fraction, width = exponent as usize)
+        .parse::<i64>()
+        .unwrap_or(0);
+
+    let minor = whole
+        .checked_mul(10i64.pow(exponent))
+        .and_then(|whole| whole.checked_add(fraction))
+        .ok_or_else(error)?;
+
+    Ok(if negative { -minor } else { minor })
+}
+
+/// Checks that `input` is an amount in at least some currency, for arguments
+/// read before it is known which currency they are in. The input is kept as
+/// it is, to be parsed with [`Money::parse`] once the currency is known.
+pub fn parse_amount(input: &str) -> Result<String, ParseMoneyError> {
+    parse_minor(input, MAX_MINOR_UNIT_EXPONENT)?;
+
+    Ok(input.to_string())
+}
+
+/// Normalizes an ISO 4217 style code such as `eur` to `EUR`.
+pub fn parse_currency(input: &str) -> Result<String, ParseCurrencyError> {
+    let code = input.trim().to_uppercase();
+
+    if code.len() != 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return Err(ParseCurrencyError(input.to_string()));
+    }
+
+    Ok(code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_money() {
+        assert_eq!(parse_minor("12.34", 2), Ok(1234));
+        assert_eq!(parse_minor("-5", 2), Ok(-500));
+        assert_eq!(parse_minor("$1,200.00", 2), Ok(120000));
+        assert_eq!(parse_minor("-$3.5", 2), Ok(-350));
+        assert_eq!(parse_minor(" .99 ", 2), Ok(99));
+        assert!(parse_minor("12.345", 2).is_err());
+        assert!(parse_minor("1.2.3", 2).is_err());
+        assert!(parse_minor("abc", 2).is_err());
+        assert!(parse_minor("", 2).is_err());
+        assert!(parse_minor("-", 2).is_err());
+
+        let money = Money::parse("$1,234,567.8", "USD").unwrap();
+        assert_eq!(money.minor, 123456780);
+        assert_eq!(money.to_string(), "1,234,567.80 USD");
+        assert_eq!(money.to_plain_string(), "1234567.80");
+        assert_eq!(Money::new(-5, "EUR").to_string(), "-0.05 EUR");
+        assert_eq!(Money::new(-5, "EUR").to_plain_string(), "-0.05");
+
+        // Yen have no minor unit and dinars have three decimal places
+        let yen = Money::parse("¥1,500", "JPY").unwrap();
+        assert_eq!(yen.minor, 1500);
+        assert_eq!(yen.to_string(), "1,500 JPY");
+        assert_eq!(yen.to_plain_string(), "1500");
+        assert!(Money::parse("1.5", "JPY").is_err());
+        let dinars = Money::parse("12.345", "KWD").unwrap();
+        assert_eq!(dinars.minor, 12345);
+        assert_eq!(dinars.to_string(), "12.345 KWD");
+        assert_eq!(Money::new(-5, "KWD").to_plain_string(), "-0.005");
+        assert_eq!(parse_amount("12.345"), Ok("12.345".to_string()));
+        assert!(parse_amount("12.3456").is_err());
+
+        // 10.00 USD at 150 yen to the dollar
+        assert_eq!(Money::new(1000, "USD").exchange(150.0, "JPY").minor, 1500);
+        assert_eq!(Money::new(1500, "JPY").exchange(0.01, "USD").minor, 1500);
+
+        assert_eq!(parse_currency(" inr "), Ok("INR".to_string()));
+        assert!(parse_currency("EURO").is_err());
+        assert!(parse_currency("U$D").is_err());
+    }
+}
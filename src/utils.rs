@@ -1,9 +1,21 @@
+use colored::{Color, Colorize};
 use rand::Rng;
 use tabled::{
     Table, Tabled,
     settings::{Panel, Style},
 };
 
+#[cfg(test)]
+use crate::{
+    db::{
+        Db,
+        account::{Account, AccountRequest, AccountType},
+        holder::AccountHolder,
+        users::{User, UserRequest},
+    },
+    money::{DEFAULT_CURRENCY, Money},
+};
+
 pub fn random_hash(length: usize) -> String {
     let mut rng = rand::thread_rng();
     let mut hash = String::new();
@@ -13,10 +25,46 @@ pub fn random_hash(length: usize) -> String {
     hash
 }
 
+#[cfg(test)]
 pub fn get_test_db_path(name: &str) -> String {
     format!("tmp/test_{}.db", name)
 }
 
+/// A fresh database for the test called `name`, with its tables created.
+#[cfg(test)]
+pub fn get_test_db(name: &str) -> Db {
+    let _ = std::fs::remove_file(get_test_db_path(name));
+
+    let db = Db::new(get_test_db_path(name).as_str()).unwrap();
+    db.create_tables().unwrap();
+    db
+}
+
+#[cfg(test)]
+pub fn create_test_user(db: &Db, name: &str) -> User {
+    db.create_user(&UserRequest {
+        name: name.to_string(),
+    })
+    .unwrap()
+}
+
+/// A checking account in the default currency that `user_id` holds alone,
+/// opened with `opening_balance` minor units.
+#[cfg(test)]
+pub fn create_test_account(db: &Db, user_id: &str, name: &str, opening_balance: i64) -> Account {
+    db.create_account(&AccountRequest {
+        name: name.to_string(),
+        bank: "Bank of America".to_string(),
+        account_number: None,
+        account_type: AccountType::Checking,
+        opening_balance: Money::new(opening_balance, DEFAULT_CURRENCY),
+        holders: vec![AccountHolder::sole(user_id)],
+        card: None,
+        loan: None,
+    })
+    .unwrap()
+}
+
 pub fn print_table<T: Tabled>(data: Vec<T>, title: &str) {
     println!(
         "{}",
@@ -25,3 +73,25 @@ pub fn print_table<T: Tabled>(data: Vec<T>, title: &str) {
             .with(Panel::header(title))
     );
 }
+
+/// Like [`print_table`], but paints the rows that have a color. Whole lines are
+/// painted after rendering so that the escape codes never throw off the widths.
+pub fn print_colored_table<T: Tabled>(data: Vec<T>, title: &str, colors: &[Option<Color>]) {
+    let table = Table::new(data)
+        .with(Style::modern())
+        .with(Panel::header(title))
+        .to_string();
+
+    // Title, header and data rows each sit between two border lines
+    for (index, line) in table.lines().enumerate() {
+        let color = index
+            .checked_sub(5)
+            .filter(|offset| offset % 2 == 0)
+            .and_then(|offset| colors.get(offset / 2).copied().flatten());
+
+        match color {
+            Some(color) => println!("{}", line.color(color)),
+            None => println!("{}", line),
+        }
+    }
+}